@@ -0,0 +1,165 @@
+//! A small load generator driving a `Storage` directly, without the HTTP layer.
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use rand::random;
+
+use crate::storages::{storage::Storage, value::StorageValue};
+
+/// The number of distinct keys each operation kind is spread over
+const KEYSPACE: u32 = 1000;
+
+/// Options of a benchmark run
+pub struct Options {
+    /// Total number of operations to perform
+    pub ops: u32,
+    /// Number of concurrent workers
+    pub concurrency: u32,
+}
+
+/// The outcome of a benchmark run
+pub struct Report {
+    pub ops: u32,
+    pub errors: u32,
+    pub elapsed: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Report {
+    /// Completed operations per second
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        return f64::from(self.ops) / elapsed;
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "ops: {}, errors: {}, elapsed: {:?}, throughput: {:.2} ops/s",
+            self.ops,
+            self.errors,
+            self.elapsed,
+            self.throughput()
+        )?;
+        write!(
+            f,
+            "latency p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+            self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+/// Run a benchmark of random set/get/increment operations
+///
+/// # Arguments
+/// * `db` - The storage to benchmark
+/// * `options` - The number of operations and workers
+///
+/// # Returns
+/// The throughput and latency report
+pub async fn run(db: Arc<Box<dyn Storage>>, options: &Options) -> Report {
+    let concurrency = options.concurrency.max(1);
+    let started_at = Instant::now();
+
+    let mut workers = Vec::new();
+    for worker in 0..concurrency {
+        // Spread the remainder over the first workers
+        let ops = options.ops / concurrency + u32::from(worker < options.ops % concurrency);
+        let db = db.clone();
+        workers.push(tokio::spawn(run_worker(db, ops)));
+    }
+
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+    for worker in workers {
+        let (worker_latencies, worker_errors) = worker.await.unwrap();
+        latencies.extend(worker_latencies);
+        errors += worker_errors;
+    }
+    let elapsed = started_at.elapsed();
+
+    latencies.sort_unstable();
+    return Report {
+        ops: options.ops,
+        errors,
+        elapsed,
+        p50: percentile(&latencies, 50),
+        p90: percentile(&latencies, 90),
+        p99: percentile(&latencies, 99),
+        max: latencies.last().copied().unwrap_or_default(),
+    };
+}
+
+async fn run_worker(db: Arc<Box<dyn Storage>>, ops: u32) -> (Vec<Duration>, u32) {
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+
+    for _ in 0..ops {
+        let id = random::<u32>() % KEYSPACE;
+        let started_at = Instant::now();
+        let failed = match random::<u32>() % 3 {
+            0 => {
+                let value = StorageValue::string(&random::<u64>().to_string());
+                db.set(format!("bench:str:{id}").as_bytes(), &value)
+                    .await
+                    .is_err()
+            }
+            1 => db.get(format!("bench:str:{id}").as_bytes()).await.is_err(),
+            _ => db
+                .increment(format!("bench:num:{id}").as_bytes(), 1, Some(0), 10)
+                .await
+                .is_err(),
+        };
+        latencies.push(started_at.elapsed());
+        if failed {
+            errors += 1;
+        }
+    }
+
+    return (latencies, errors);
+}
+
+/// Pick a percentile from sorted latencies
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    return sorted[(sorted.len() - 1) * percent / 100];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::bredis::Bredis;
+
+    #[tokio::test]
+    async fn test_run() {
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(Bredis::open()));
+        let report = run(
+            db,
+            &Options {
+                ops: 100,
+                concurrency: 4,
+            },
+        )
+        .await;
+
+        assert_eq!(report.ops, 100);
+        assert_eq!(report.errors, 0);
+        assert!(report.throughput() > 0.0);
+        assert!(report.p50 <= report.p99);
+        assert!(report.p99 <= report.max);
+    }
+}
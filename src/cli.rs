@@ -1,8 +1,23 @@
-use clap::{crate_authors, crate_name, Arg, Command};
+use std::io::Write;
+
+use clap::builder::RangedU64ValueParser;
+use clap::{crate_authors, crate_name, value_parser, Arg, ArgAction, ArgMatches, Command};
 
 use crate::info::Info;
+use crate::storages::backend::DEFAULT_DATA_DIR;
+use crate::storages::rocksdb::Rocksdb;
+use crate::storages::storage::Storage;
+use crate::storages::value::{StorageValue, ValueType};
+
+/// Exit code of a successful one-shot command
+pub const EXIT_OK: i32 = 0;
+/// Exit code of a one-shot command whose key does not exist
+pub const EXIT_NOT_FOUND: i32 = 1;
+/// Exit code of a one-shot command whose storage operation failed
+pub const EXIT_ERROR: i32 = 2;
 
 #[allow(clippy::module_name_repetitions)]
+#[allow(clippy::too_many_lines)]
 pub fn make_cli() -> Command {
     let info = Info::default();
 
@@ -22,12 +37,820 @@ pub fn make_cli() -> Command {
                         .help("Address to bind to")
                         .default_value("[::1]:4123"),
                 )
+                .args(backend_args())
+                .arg(
+                    Arg::new("unhealthy-error-rate")
+                        .long("unhealthy-error-rate")
+                        .value_name("RATE")
+                        .help("Backend error rate (0.0 - 1.0) above which /ready reports 503")
+                        .value_parser(value_parser!(f64))
+                        .default_value("0.5"),
+                )
+                .arg(
+                    Arg::new("error-rate-window")
+                        .long("error-rate-window")
+                        .value_name("SECONDS")
+                        .help("Time window the backend error rate is computed over")
+                        .value_parser(value_parser!(u64))
+                        .default_value("30"),
+                )
+                .arg(
+                    Arg::new("no-store-prefix")
+                        .long("no-store-prefix")
+                        .value_name("PREFIX")
+                        .help("Key prefix whose reads must never be cached, may be repeated")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("int-as-string")
+                        .long("int-as-string")
+                        .help(
+                            "Write integers in responses as JSON strings to keep 64-bit precision",
+                        )
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("admin-token")
+                        .long("admin-token")
+                        .value_name("TOKEN")
+                        .help("Bearer token for the /admin routes, which are disabled without it"),
+                )
+                .arg(
+                    Arg::new("auth-max-attempts")
+                        .long("auth-max-attempts")
+                        .value_name("N")
+                        .help("Answer an address with 429 after N invalid admin tokens in a window")
+                        .value_parser(value_parser!(u32).range(1..)),
+                )
+                .arg(
+                    Arg::new("auth-window")
+                        .long("auth-window")
+                        .value_name("SECONDS")
+                        .help("Window invalid admin tokens are counted over, and the block length")
+                        .value_parser(value_parser!(u64).range(1..))
+                        .default_value("60"),
+                )
+                .arg(
+                    Arg::new("op-timeout")
+                        .long("op-timeout")
+                        .value_name("MILLISECONDS")
+                        .help("Answer key requests running longer than this with 504")
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("slow-op-threshold-ms")
+                        .long("slow-op-threshold-ms")
+                        .value_name("MILLISECONDS")
+                        .help("Log storage operations running longer than this as warnings")
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("breaker-threshold")
+                        .long("breaker-threshold")
+                        .value_name("N")
+                        .help("Answer requests with 503 after N consecutive backend failures")
+                        .value_parser(value_parser!(u32).range(1..)),
+                )
+                .arg(
+                    Arg::new("breaker-cooldown")
+                        .long("breaker-cooldown")
+                        .value_name("SECONDS")
+                        .help("How long requests are rejected before the backend is probed again")
+                        .value_parser(value_parser!(u64))
+                        .default_value("30"),
+                )
+                .arg(
+                    Arg::new("sweep-interval")
+                        .long("sweep-interval")
+                        .value_name("SECONDS")
+                        .help("Delete expired keys in the background and publish expiry events")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("memtable-flush-interval-secs")
+                        .long("memtable-flush-interval-secs")
+                        .value_name("SECONDS")
+                        .help("Flush the RocksDB memtables to disk on this schedule")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("expiry-webhook")
+                        .long("expiry-webhook")
+                        .value_name("URL")
+                        .help("POST every key the sweeper deletes to this http:// URL")
+                        .requires("sweep-interval")
+                        .value_parser(crate::http_server::WebhookUrl::parse),
+                )
+                .arg(
+                    Arg::new("max-value-bytes")
+                        .long("max-value-bytes")
+                        .value_name("BYTES")
+                        .help("The largest JSON body of a single write")
+                        .default_value("2097152")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("max-batch-bytes")
+                        .long("max-batch-bytes")
+                        .value_name("BYTES")
+                        .help("The largest JSON body of a batch request")
+                        .default_value("16777216")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("max-connections")
+                        .long("max-connections")
+                        .value_name("N")
+                        .help("Stop accepting connections while a worker has N open ones")
+                        .default_value("25000")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..)),
+                )
+                .arg(
+                    Arg::new("backend-name")
+                        .long("backend-name")
+                        .value_name("NAME")
+                        .help(
+                            "Name of the --backend store, the default of --named-backend requests",
+                        )
+                        .default_value("primary"),
+                )
+                .arg(
+                    Arg::new("named-backend")
+                        .long("named-backend")
+                        .value_name("NAME=BACKEND[:PATH]")
+                        .help(
+                            "Another backend requests select with X-Bredis-Backend: NAME, \
+                             may be repeated",
+                        )
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("mirror-to")
+                        .long("mirror-to")
+                        .value_name("BACKEND[:PATH]")
+                        .help("Mirror every write to a second backend, e.g. rocksdb:/var/lib/new"),
+                )
+                .arg(
+                    Arg::new("mirror-failures-fatal")
+                        .long("mirror-failures-fatal")
+                        .help("Fail writes that can't be mirrored instead of logging them")
+                        .requires("mirror-to")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("max-collection-elements")
+                        .long("max-collection-elements")
+                        .value_name("N")
+                        .help("Reject writes that would grow a list or a hash past N elements")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("max-response-bytes")
+                        .long("max-response-bytes")
+                        .value_name("BYTES")
+                        .help(
+                            "Cut key listings short once their keys take BYTES of JSON, \
+                             with a cursor to resume from",
+                        )
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("require-delimiter-on-prefix-delete")
+                        .long("require-delimiter-on-prefix-delete")
+                        .value_name("DELIMITER")
+                        .help(
+                            "Reject deletes by a prefix that doesn't end in DELIMITER, \
+                             unless the request passes force=true",
+                        ),
+                )
+                .arg(
+                    Arg::new("snapshot-on-exit")
+                        .long("snapshot-on-exit")
+                        .value_name("PATH")
+                        .help(
+                            "Load the bredis backend from PATH at startup and write it back \
+                             there on a clean shutdown only",
+                        )
+                        .conflicts_with_all(["data-dir", "persistent"]),
+                )
+                .arg(
+                    Arg::new("strict-types")
+                        .long("strict-types")
+                        .help("Reject writes that change the type of an existing key")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("ttl-on-missing-key")
+                        .long("ttl-on-missing-key")
+                        .value_name("MODE")
+                        .help(
+                            "What setting the TTL of a missing key does: error answers with \
+                             404, ignore answers with success",
+                        )
+                        .value_parser(["error", "ignore"])
+                        .default_value("error"),
+                )
+                .arg(
+                    Arg::new("ttl-rounding")
+                        .long("ttl-rounding")
+                        .value_name("POLICY")
+                        .help("How remaining TTLs are rounded to whole seconds")
+                        .value_parser(["floor", "ceil", "round"])
+                        .default_value("ceil"),
+                )
+                .arg(
+                    Arg::new("type-case")
+                        .long("type-case")
+                        .value_name("CASE")
+                        .help("The case value type names are written in, e.g. Integer or integer")
+                        .value_parser(["title", "lower"])
+                        .default_value("title"),
+                )
+                .arg(
+                    Arg::new("debug-bodies")
+                        .long("debug-bodies")
+                        .help("Log the bodies of key requests and responses at debug level")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("tenant-header")
+                        .long("tenant-header")
+                        .value_name("HEADER")
+                        .help("Keep the keys of each tenant, named by this header, apart"),
+                )
+                .arg(
+                    Arg::new("enable-ui")
+                        .long("enable-ui")
+                        .help("Serve a web UI for browsing and editing keys at /ui")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Print the value of a key and exit")
+                .arg(Arg::new("key").value_name("KEY").required(true))
+                .args(backend_args()),
+        )
+        .subcommand(
+            Command::new("set")
+                .about("Set the value of a key and exit")
+                .arg(Arg::new("key").value_name("KEY").required(true))
+                .arg(Arg::new("value").value_name("VALUE").required(true))
+                .arg(
+                    Arg::new("ttl")
+                        .long("ttl")
+                        .value_name("SECONDS")
+                        .help("Time-to-live of the key, -1 to never expire")
+                        .value_parser(value_parser!(i64))
+                        .allow_negative_numbers(true)
+                        .default_value("-1"),
+                )
+                .args(backend_args()),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Benchmark random set/get/increment operations against a backend")
+                .arg(
+                    Arg::new("ops")
+                        .long("ops")
+                        .value_name("N")
+                        .help("Total number of operations")
+                        .value_parser(value_parser!(u32))
+                        .default_value("100000"),
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .long("concurrency")
+                        .value_name("C")
+                        .help("Number of concurrent workers")
+                        .value_parser(value_parser!(u32))
+                        .default_value("8"),
+                )
+                .args(backend_args()),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Copy all keys from one backend into another")
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("BACKEND[:PATH]")
+                        .help("Source backend, e.g. surrealkv or rocksdb:/var/lib/bredis")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("BACKEND[:PATH]")
+                        .help("Destination backend, e.g. rocksdb:/var/lib/bredis")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("batch-size")
+                        .long("batch-size")
+                        .value_name("N")
+                        .help("Number of keys copied between progress reports")
+                        .value_parser(value_parser!(usize))
+                        .default_value("1000"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write every key of a backend to an NDJSON file")
                 .arg(
                     Arg::new("backend")
                         .long("backend")
-                        .value_name("BACKEND")
-                        .help("Backend to use. Supported backends: rocksdb, bredis, and surrealkv")
-                        .default_value("surrealkv"),
+                        .value_name("BACKEND[:PATH]")
+                        .help("Backend to export, e.g. rocksdb:/var/lib/bredis")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("FILE")
+                        .help("The NDJSON file to write")
+                        .required(true),
                 ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Set the keys of an NDJSON file written by export in a backend")
+                .arg(
+                    Arg::new("backend")
+                        .long("backend")
+                        .value_name("BACKEND[:PATH]")
+                        .help("Backend to import into, e.g. rocksdb:/var/lib/bredis")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("in")
+                        .long("in")
+                        .value_name("FILE")
+                        .help("The NDJSON file to read")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("flush")
+                        .long("flush")
+                        .help("Delete every key of the backend before importing")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("openapi").about("Print the OpenAPI document of the HTTP API and exit"),
+        )
+        .subcommand(
+            Command::new("fsck")
+                .about("Check that every record of a RocksDB store can be decoded")
+                .arg(
+                    Arg::new("data-dir")
+                        .long("data-dir")
+                        .value_name("PATH")
+                        .help("Data directory of the RocksDB store")
+                        .required(true),
+                ),
+        );
+}
+
+/// Arguments selecting the storage backend, shared by all subcommands
+fn backend_args() -> [Arg; 7] {
+    return [
+        Arg::new("backend")
+            .long("backend")
+            .value_name("BACKEND")
+            .help("Backend to use. Supported backends: rocksdb, bredis, and surrealkv")
+            .default_value("surrealkv"),
+        Arg::new("data-dir")
+            .long("data-dir")
+            .value_name("PATH")
+            .help(
+                "Keep the data in this directory across restarts; without it the data is \
+                 thrown away when the server stops",
+            ),
+        Arg::new("hash-keys-over")
+            .long("hash-keys-over")
+            .value_name("N")
+            .help("Store keys longer than N bytes under their hash to bound the key length")
+            .value_parser(value_parser!(usize)),
+        Arg::new("sync-writes")
+            .long("sync-writes")
+            .help("Fsync every rocksdb write before acknowledging it, at a cost in throughput")
+            .action(ArgAction::SetTrue),
+        Arg::new("skip-read-checksums")
+            .long("skip-read-checksums")
+            .help("Don't verify the checksums of the blocks rocksdb reads, saving some CPU")
+            .action(ArgAction::SetTrue),
+        Arg::new("persistent")
+            .long("persistent")
+            .help(format!(
+                "Keep the data on disk, in {DEFAULT_DATA_DIR} unless --data-dir is given, \
+                 and fsync every write"
+            ))
+            .action(ArgAction::SetTrue),
+        Arg::new("open-timeout-secs")
+            .long("open-timeout-secs")
+            .value_name("SECONDS")
+            .help("Fail instead of waiting longer than this for the backend to open")
+            .value_parser(value_parser!(u64).range(1..)),
+    ];
+}
+
+/// Resolve where the backend keeps its data and whether it fsyncs every write
+///
+/// `--persistent` selects the durable settings: a data directory, `DEFAULT_DATA_DIR`
+/// unless `--data-dir` is given, and synchronous writes.
+///
+/// # Arguments
+/// * `args` - The parsed arguments of a subcommand taking the backend arguments
+///
+/// # Returns
+/// The data directory, if any, and whether every write is fsync'd
+pub fn storage_location(args: &ArgMatches) -> (Option<String>, bool) {
+    let persistent = args.get_flag("persistent");
+    let data_dir = args
+        .get_one::<String>("data-dir")
+        .cloned()
+        .or_else(|| persistent.then(|| DEFAULT_DATA_DIR.to_string()));
+    return (data_dir, persistent || args.get_flag("sync-writes"));
+}
+
+/// Print the value of a key
+///
+/// # Arguments
+/// * `db` - The storage to read from
+/// * `key` - The key to print
+/// * `out` - Where to print the value
+///
+/// # Returns
+/// The process exit code
+pub async fn get(db: &dyn Storage, key: &str, out: &mut impl Write) -> i32 {
+    let value = match db.get_no_reap(key.as_bytes()).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            eprintln!("Key not found: {key}");
+            return EXIT_NOT_FOUND;
+        }
+        Err(err) => {
+            eprintln!("Error getting key: {err}");
+            return EXIT_ERROR;
+        }
+    };
+
+    let rendered = match value.value_type {
+        ValueType::Integer => value.get_integer_value().map(|value| value.to_string()),
+        ValueType::String | ValueType::Json | ValueType::Blob => {
+            Ok(String::from_utf8_lossy(&value.value).to_string())
+        }
+        ValueType::List => value.get_list_value().map(|values| values.join("\n")),
+        ValueType::Hash => value.get_hash_value().map(|fields| {
+            fields
+                .iter()
+                .map(|(field, value)| format!("{field}={}", String::from_utf8_lossy(value)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }),
+    };
+    let rendered = match rendered {
+        Ok(rendered) => rendered,
+        Err(err) => {
+            eprintln!("Error reading value: {err}");
+            return EXIT_ERROR;
+        }
+    };
+    if writeln!(out, "{rendered}").is_err() {
+        return EXIT_ERROR;
+    }
+    return EXIT_OK;
+}
+
+/// Set the value of a key
+///
+/// # Arguments
+/// * `db` - The storage to write to
+/// * `key` - The key to set
+/// * `value` - The string value to store
+/// * `ttl` - The time-to-live of the key, -1 to never expire
+/// * `out` - Where to print the result
+///
+/// # Returns
+/// The process exit code
+pub async fn set(db: &dyn Storage, key: &str, value: &str, ttl: i64, out: &mut impl Write) -> i32 {
+    let value = StorageValue {
+        ttl,
+        ..StorageValue::string(value)
+    };
+
+    if let Err(err) = db.set(key.as_bytes(), &value).await {
+        eprintln!("Error setting key: {err}");
+        return EXIT_ERROR;
+    }
+    if writeln!(out, "OK").is_err() {
+        return EXIT_ERROR;
+    }
+    return EXIT_OK;
+}
+
+/// Export every key of a storage to an NDJSON file
+///
+/// # Arguments
+/// * `db` - The storage to read from
+/// * `path` - The file to write, replaced if it exists
+/// * `out` - Where to print the result
+///
+/// # Returns
+/// The process exit code
+pub async fn export(db: &dyn Storage, path: &str, out: &mut impl Write) -> i32 {
+    let mut file = match std::fs::File::create(path) {
+        Ok(file) => std::io::BufWriter::new(file),
+        Err(err) => {
+            eprintln!("Error creating {path}: {err}");
+            return EXIT_ERROR;
+        }
+    };
+    let exported = match crate::migrate::export(db, &mut file).await {
+        Ok(exported) => exported,
+        Err(err) => {
+            eprintln!("Error exporting keys: {err}");
+            return EXIT_ERROR;
+        }
+    };
+    if writeln!(out, "exported {exported} keys to {path}").is_err() {
+        return EXIT_ERROR;
+    }
+    return EXIT_OK;
+}
+
+/// Import the keys of an NDJSON file written by `export`
+///
+/// # Arguments
+/// * `db` - The storage to write to
+/// * `path` - The file to read
+/// * `flush` - Delete every key of the storage before importing
+/// * `out` - Where to print the result
+///
+/// # Returns
+/// The process exit code
+pub async fn import(db: &dyn Storage, path: &str, flush: bool, out: &mut impl Write) -> i32 {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => std::io::BufReader::new(file),
+        Err(err) => {
+            eprintln!("Error opening {path}: {err}");
+            return EXIT_ERROR;
+        }
+    };
+    let imported = match crate::migrate::import(db, file, flush).await {
+        Ok(imported) => imported,
+        Err(err) => {
+            eprintln!("Error importing keys: {err}");
+            return EXIT_ERROR;
+        }
+    };
+    if writeln!(out, "imported {imported} keys from {path}").is_err() {
+        return EXIT_ERROR;
+    }
+    return EXIT_OK;
+}
+
+/// Print the `OpenAPI` document of the HTTP API as JSON
+///
+/// # Arguments
+/// * `out` - Where to print the document
+///
+/// # Returns
+/// The process exit code
+pub fn openapi(out: &mut impl Write) -> i32 {
+    let document = match crate::http_server::openapi().to_pretty_json() {
+        Ok(document) => document,
+        Err(err) => {
+            eprintln!("Error rendering the OpenAPI document: {err}");
+            return EXIT_ERROR;
+        }
+    };
+    if writeln!(out, "{document}").is_err() {
+        return EXIT_ERROR;
+    }
+    return EXIT_OK;
+}
+
+/// Check the integrity of a `RocksDB` store and print a report
+///
+/// # Arguments
+/// * `path` - The data directory of the store
+/// * `out` - Where to print the report
+///
+/// # Returns
+/// The process exit code, `EXIT_ERROR` if any record is corrupt
+pub fn fsck(path: &str, out: &mut impl Write) -> i32 {
+    let report = match Rocksdb::fsck(path) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("Error reading database: {err}");
+            return EXIT_ERROR;
+        }
+    };
+
+    for key in &report.corrupted {
+        if writeln!(out, "corrupted: {key}").is_err() {
+            return EXIT_ERROR;
+        }
+    }
+    let summary = writeln!(
+        out,
+        "checked {} records, {} corrupted",
+        report.checked,
+        report.corrupted.len()
+    );
+    if summary.is_err() || !report.corrupted.is_empty() {
+        return EXIT_ERROR;
+    }
+    return EXIT_OK;
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::storages::{backend::Backend, bredis::Bredis, mock::TempPath};
+
+    #[tokio::test]
+    async fn test_set_then_get() {
+        let db = Bredis::open();
+
+        let mut out = Vec::new();
+        let code = set(&db, "my_key", "my_value", -1, &mut out).await;
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "OK\n");
+
+        let mut out = Vec::new();
+        let code = get(&db, "my_key", &mut out).await;
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "my_value\n");
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_key() {
+        let db = Bredis::open();
+
+        let mut out = Vec::new();
+        let code = get(&db, "missing_key", &mut out).await;
+        assert_eq!(code, EXIT_NOT_FOUND);
+        assert!(out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_integer() {
+        let db = Bredis::open();
+        db.increment(b"counter", 1, Some(41), 10).await.unwrap();
+
+        let mut out = Vec::new();
+        let code = get(&db, "counter", &mut out).await;
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "42\n");
+    }
+
+    #[tokio::test]
+    async fn test_export_then_import() {
+        let source = Bredis::open();
+        set(&source, "key1", "value1", -1, &mut Vec::new()).await;
+        set(&source, "key2", "value2", 100, &mut Vec::new()).await;
+
+        let path = TempPath::new("test_export");
+        let mut out = Vec::new();
+        assert_eq!(export(&source, &path, &mut out).await, EXIT_OK);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("exported 2 keys to {}\n", &*path)
         );
+
+        let destination = Bredis::open();
+        let mut out = Vec::new();
+        assert_eq!(import(&destination, &path, false, &mut out).await, EXIT_OK);
+        let mut out = Vec::new();
+        assert_eq!(get(&destination, "key2", &mut out).await, EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "value2\n");
+
+        let missing = TempPath::new("test_missing");
+        assert_eq!(
+            import(&destination, &missing, false, &mut Vec::new()).await,
+            EXIT_ERROR
+        );
+    }
+
+    #[test]
+    fn test_fsck() {
+        let db_path = TempPath::new("test_db");
+        let value = StorageValue::string("value1");
+        let store = rocksdb::DB::open_default(&*db_path).unwrap();
+        store.put(b"key1", value.to_binary()).unwrap();
+        store.put(b"corrupt_key", b"\xff\xff").unwrap();
+        drop(store);
+
+        let mut out = Vec::new();
+        let code = fsck(&db_path, &mut out);
+
+        assert_eq!(code, EXIT_ERROR);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("corrupted: corrupt_key"), "{out}");
+        assert!(out.contains("checked 2 records, 1 corrupted"), "{out}");
+    }
+
+    #[test]
+    fn test_openapi() {
+        let matches = make_cli()
+            .try_get_matches_from(["bredis", "openapi"])
+            .unwrap();
+        assert_eq!(matches.subcommand_name(), Some("openapi"));
+
+        let mut out = Vec::new();
+        assert_eq!(openapi(&mut out), EXIT_OK);
+
+        let document: utoipa::openapi::OpenApi = serde_json::from_slice(&out).unwrap();
+        for path in [
+            "/keys",
+            "/keys/{key_name}",
+            "/keys/{key_name}/ttl",
+            "/admin/sync",
+        ] {
+            assert!(document.paths.paths.contains_key(path), "{path} is missing");
+        }
+    }
+
+    #[test]
+    fn test_snapshot_on_exit_conflicts_with_data_dir() {
+        let run = |extra: &[&str]| {
+            let mut args = vec!["bredis", "run", "--snapshot-on-exit", "/tmp/exit.snapshot"];
+            args.extend_from_slice(extra);
+            return make_cli().try_get_matches_from(args);
+        };
+        assert!(run(&[]).is_ok());
+        assert!(run(&["--data-dir", "/var/lib/bredis"]).is_err());
+        assert!(run(&["--persistent"]).is_err());
+    }
+
+    #[rstest]
+    #[case::surrealkv("surrealkv")]
+    #[case::rocksdb("rocksdb")]
+    fn test_persistent(#[case] backend: &str) {
+        let matches = make_cli()
+            .try_get_matches_from(["bredis", "run", "--backend", backend])
+            .unwrap();
+        let args = matches.subcommand_matches("run").unwrap();
+        assert_eq!(storage_location(args), (None, false));
+
+        let matches = make_cli()
+            .try_get_matches_from(["bredis", "run", "--backend", backend, "--persistent"])
+            .unwrap();
+        let args = matches.subcommand_matches("run").unwrap();
+        assert_eq!(
+            storage_location(args),
+            (Some(DEFAULT_DATA_DIR.to_string()), true)
+        );
+
+        let matches = make_cli()
+            .try_get_matches_from([
+                "bredis",
+                "run",
+                "--backend",
+                backend,
+                "--persistent",
+                "--data-dir",
+                "/var/lib/bredis",
+            ])
+            .unwrap();
+        let args = matches.subcommand_matches("run").unwrap();
+        assert_eq!(
+            storage_location(args),
+            (Some("/var/lib/bredis".to_string()), true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_persistent_surrealkv_survives_reopen() {
+        let path = TempPath::new("test_surrealkv");
+        let db = Backend::SurrealKV.open(Some(&path), true, true).unwrap();
+        db.set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+        db.close().await;
+        drop(db);
+
+        let reopened = Backend::SurrealKV.open(Some(&path), true, true).unwrap();
+        let value = reopened.get(b"key1").await.unwrap();
+        reopened.close().await;
+        assert_eq!(value.unwrap().value, b"value1");
+    }
+
+    #[test]
+    fn test_parse_set_args() {
+        let matches = make_cli()
+            .try_get_matches_from(["bredis", "set", "my_key", "my_value", "--ttl", "10"])
+            .unwrap();
+        let (name, args) = matches.subcommand().unwrap();
+        assert_eq!(name, "set");
+        assert_eq!(args.get_one::<String>("key").unwrap(), "my_key");
+        assert_eq!(args.get_one::<String>("value").unwrap(), "my_value");
+        assert_eq!(*args.get_one::<i64>("ttl").unwrap(), 10);
+        assert_eq!(args.get_one::<String>("backend").unwrap(), "surrealkv");
+    }
 }
@@ -0,0 +1,241 @@
+use crate::errors::DatabaseError;
+use crate::storages::storage::Storage;
+use crate::storages::value::{StorageValue, ValueType};
+
+/// Typed reads and writes over a storage, for using bredis as a library
+///
+/// Integers are stored as decimal text, the way the increment routes of the server write them.
+///
+/// # Example
+/// ```
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), bredis::errors::DatabaseError> {
+/// use bredis::client::BredisClient;
+/// use bredis::storages::bredis::Bredis;
+///
+/// let client = BredisClient::new(Box::new(Bredis::open()));
+/// client.set_string("greeting", "hello").await?;
+/// assert_eq!(client.get_string("greeting").await?.as_deref(), Some("hello"));
+/// assert_eq!(client.get_string("missing").await?, None);
+///
+/// client.set_int_ttl("session:1", 1, 60).await?;
+/// assert_eq!(client.incr("session:1", 2).await?, 3);
+/// assert!(client.ttl("session:1").await? > 0);
+/// # Ok(())
+/// # }
+/// ```
+#[allow(clippy::module_name_repetitions)]
+pub struct BredisClient {
+    db: Box<dyn Storage>,
+}
+
+impl BredisClient {
+    /// Wrap a storage
+    ///
+    /// # Arguments
+    /// * `db` - The storage to read and write, e.g. `Bredis::open()`
+    #[must_use]
+    pub const fn new(db: Box<dyn Storage>) -> Self {
+        return Self { db };
+    }
+
+    /// The wrapped storage, for the operations the client has no typed method for
+    pub fn storage(&self) -> &dyn Storage {
+        return self.db.as_ref();
+    }
+
+    /// Get a string value
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    ///
+    /// # Returns
+    /// The string, or None if the key doesn't exist or has expired
+    ///
+    /// # Errors
+    /// `DatabaseError::InvalidValueType` if the key holds another type
+    pub async fn get_string(&self, key: &str) -> Result<Option<String>, DatabaseError> {
+        let Some(value) = self.db.get(key.as_bytes()).await? else {
+            return Ok(None);
+        };
+        expect_type(&value, &ValueType::String)?;
+        return String::from_utf8(value.value)
+            .map(Some)
+            .map_err(|err| DatabaseError::InternalError(err.to_string()));
+    }
+
+    /// Get an integer value
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    ///
+    /// # Returns
+    /// The integer, or None if the key doesn't exist or has expired
+    ///
+    /// # Errors
+    /// `DatabaseError::InvalidValueType` if the key holds another type
+    pub async fn get_int(&self, key: &str) -> Result<Option<i64>, DatabaseError> {
+        return match self.db.get(key.as_bytes()).await? {
+            Some(value) => value.get_integer_value().map(Some),
+            None => Ok(None),
+        };
+    }
+
+    /// Set a string value that never expires
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    /// * `value` - The string to store
+    pub async fn set_string(&self, key: &str, value: &str) -> Result<(), DatabaseError> {
+        return self.set_string_ttl(key, value, -1).await;
+    }
+
+    /// Set a string value that expires
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    /// * `value` - The string to store
+    /// * `ttl` - The seconds until the value expires, -1 to never expire
+    pub async fn set_string_ttl(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: i64,
+    ) -> Result<(), DatabaseError> {
+        let value = StorageValue {
+            ttl,
+            ..StorageValue::string(value)
+        };
+        return self.db.set(key.as_bytes(), &value).await;
+    }
+
+    /// Set an integer value that never expires
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    /// * `value` - The integer to store
+    pub async fn set_int(&self, key: &str, value: i64) -> Result<(), DatabaseError> {
+        return self.set_int_ttl(key, value, -1).await;
+    }
+
+    /// Set an integer value that expires
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    /// * `value` - The integer to store
+    /// * `ttl` - The seconds until the value expires, -1 to never expire
+    pub async fn set_int_ttl(&self, key: &str, value: i64, ttl: i64) -> Result<(), DatabaseError> {
+        let value = StorageValue {
+            value_type: ValueType::Integer,
+            ttl,
+            value: value.to_string().into_bytes(),
+            mtime: 0,
+        };
+        return self.db.set(key.as_bytes(), &value).await;
+    }
+
+    /// Add to an integer, starting from 0 if the key doesn't exist
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `by` - The amount to add
+    ///
+    /// # Returns
+    /// The integer after the addition
+    ///
+    /// # Errors
+    /// `DatabaseError::InvalidValueType` if the key holds another type
+    pub async fn incr(&self, key: &str, by: i64) -> Result<i64, DatabaseError> {
+        let value = self.db.increment(key.as_bytes(), by, Some(0), 10).await?;
+        return value.get_integer_value();
+    }
+
+    /// Subtract from an integer, starting from 0 if the key doesn't exist
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `by` - The amount to subtract
+    ///
+    /// # Returns
+    /// The integer after the subtraction
+    ///
+    /// # Errors
+    /// `DatabaseError::InvalidValueType` if the key holds another type
+    pub async fn decr(&self, key: &str, by: i64) -> Result<i64, DatabaseError> {
+        let value = self.db.decrement(key.as_bytes(), by, Some(0), 10).await?;
+        return value.get_integer_value();
+    }
+
+    /// Get the seconds until a key expires
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value
+    ///
+    /// # Returns
+    /// The remaining seconds, -1 if the key never expires
+    ///
+    /// # Errors
+    /// `DatabaseError::ValueNotFound` if the key doesn't exist
+    pub async fn ttl(&self, key: &str) -> Result<i64, DatabaseError> {
+        return self.db.get_ttl(key.as_bytes()).await;
+    }
+
+    /// Delete a key
+    ///
+    /// # Arguments
+    /// * `key` - The key to delete
+    pub async fn delete(&self, key: &str) -> Result<(), DatabaseError> {
+        return self.db.delete(key.as_bytes()).await;
+    }
+
+    /// Close the storage, flushing what it buffers
+    pub async fn close(self) {
+        self.db.close().await;
+    }
+}
+
+/// Fail with `InvalidValueType` unless the value is of the expected type
+fn expect_type(value: &StorageValue, expected: &ValueType) -> Result<(), DatabaseError> {
+    if value.value_type != *expected {
+        return Err(DatabaseError::InvalidValueType(format!(
+            "expected {expected}, found {}",
+            value.value_type
+        )));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::bredis::Bredis;
+
+    #[tokio::test]
+    async fn test_typed_values() {
+        let client = BredisClient::new(Box::new(Bredis::open()));
+
+        client.set_string("name", "bredis").await.unwrap();
+        client.set_int("count", 5).await.unwrap();
+        assert_eq!(
+            client.get_string("name").await.unwrap().as_deref(),
+            Some("bredis")
+        );
+        assert_eq!(client.get_int("count").await.unwrap(), Some(5));
+        assert_eq!(client.get_int("missing").await.unwrap(), None);
+
+        assert!(matches!(
+            client.get_string("count").await,
+            Err(DatabaseError::InvalidValueType(_))
+        ));
+        assert!(matches!(
+            client.get_int("name").await,
+            Err(DatabaseError::InvalidValueType(_))
+        ));
+
+        assert_eq!(client.decr("count", 7).await.unwrap(), -2);
+        assert_eq!(client.incr("new", 3).await.unwrap(), 3);
+
+        client.delete("name").await.unwrap();
+        assert_eq!(client.get_string("name").await.unwrap(), None);
+    }
+}
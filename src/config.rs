@@ -0,0 +1,486 @@
+use std::time::Duration;
+
+use clap::ArgMatches;
+
+use crate::http_server::{
+    AuthThrottle, BackendNames, BodyLimits, CachePolicy, CollectionLimit, DeleteDelimiter,
+    IntEncoding, MaxResponseBytes, MissingKeyTtl, OpTimeout, TenantHeader, WebhookUrl,
+};
+use crate::info::Info;
+use crate::storages::{clock::TtlRounding, value::TypeCase};
+
+/// The resolved configuration of the `run` subcommand
+///
+/// # Fields
+/// * `bind` - The address the server listens on
+/// * `backend` - The name of the storage backend
+/// * `data_dir` - The data directory of the backend, if any
+/// * `hash_keys_over` - Keys longer than this are stored under their hash
+/// * `sync_writes` - Whether every write is fsync'd before it is acknowledged
+/// * `skip_read_checksums` - Whether rocksdb reads skip verifying the checksums of blocks
+/// * `persistent` - Whether the durable settings of the backend were requested
+/// * `snapshot_on_exit` - The snapshot file the bredis backend is written to on shutdown
+/// * `unhealthy_error_rate` - The backend error rate above which `/ready` reports 503
+/// * `error_rate_window` - The window the error rate is computed over, in seconds
+/// * `no_store_prefixes` - Key prefixes whose reads are never cached
+/// * `int_as_string` - Whether integers are written as JSON strings
+/// * `admin_token` - The bearer token of the admin routes, a secret
+/// * `auth_max_attempts` - Invalid admin tokens after which an address is blocked
+/// * `auth_window` - The window invalid admin tokens are counted over, in seconds
+/// * `op_timeout` - The longest a key request may run, in milliseconds
+/// * `slow_op_threshold` - Storage operations slower than this are logged, in milliseconds
+/// * `sweep_interval` - How often expired keys are deleted in the background, in seconds
+/// * `expiry_webhook` - The URL every key the sweeper deletes is posted to
+/// * `memtable_flush_interval_secs` - How often the memtables are flushed to disk, in seconds
+/// * `max_value_bytes` - The largest JSON body of a single write, in bytes
+/// * `max_batch_bytes` - The largest JSON body of a batch route, in bytes
+/// * `max_connections` - The most open connections of a single worker
+/// * `backend_name` - The name of the default backend, selected by requests without a header
+/// * `named_backends` - Further `name=backend[:path]` backends requests may select
+/// * `mirror_to` - The `backend[:path]` every write is mirrored to, if any
+/// * `mirror_failures_fatal` - Whether a failed mirrored write fails the request
+/// * `max_collection_elements` - The most elements a list or fields a hash may hold
+/// * `max_response_bytes` - The most bytes the keys of one listing page may take
+/// * `require_delimiter_on_prefix_delete` - The delimiter the prefixes of deletes must end in
+/// * `strict_types` - Whether writes may change the type of an existing key
+/// * `ttl_on_missing_key` - Whether setting the TTL of a missing key fails or is ignored
+/// * `ttl_rounding` - How remaining TTLs are rounded to whole seconds
+/// * `type_case` - The case value type names are written in
+/// * `breaker_threshold` - Consecutive backend failures after which requests are rejected
+/// * `breaker_cooldown` - How long requests are rejected before the backend is probed, in seconds
+/// * `debug_bodies` - Whether the bodies of key requests and responses are logged
+/// * `open_timeout_secs` - The longest the backends may take to open, in seconds
+/// * `tenant_header` - The header naming the tenant whose keys a request sees
+/// * `enable_ui` - Whether the web UI is served at `/ui`
+#[derive(Clone, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    pub bind: String,
+    pub backend: String,
+    pub data_dir: Option<String>,
+    pub hash_keys_over: Option<usize>,
+    pub sync_writes: bool,
+    pub skip_read_checksums: bool,
+    pub persistent: bool,
+    pub snapshot_on_exit: Option<String>,
+    pub unhealthy_error_rate: f64,
+    pub error_rate_window: u64,
+    pub no_store_prefixes: Vec<String>,
+    pub int_as_string: bool,
+    pub admin_token: Option<String>,
+    pub auth_max_attempts: Option<u32>,
+    pub auth_window: u64,
+    pub op_timeout: Option<u64>,
+    pub slow_op_threshold: Option<u64>,
+    pub sweep_interval: Option<u64>,
+    pub expiry_webhook: Option<WebhookUrl>,
+    pub memtable_flush_interval_secs: Option<u64>,
+    pub max_value_bytes: usize,
+    pub max_batch_bytes: usize,
+    pub max_connections: usize,
+    pub backend_name: String,
+    pub named_backends: Vec<String>,
+    pub mirror_to: Option<String>,
+    pub mirror_failures_fatal: bool,
+    pub max_collection_elements: Option<usize>,
+    pub max_response_bytes: Option<usize>,
+    pub require_delimiter_on_prefix_delete: Option<String>,
+    pub strict_types: bool,
+    pub ttl_on_missing_key: MissingKeyTtl,
+    pub ttl_rounding: TtlRounding,
+    pub type_case: TypeCase,
+    pub breaker_threshold: Option<u32>,
+    pub breaker_cooldown: u64,
+    pub debug_bodies: bool,
+    pub open_timeout_secs: Option<u64>,
+    pub tenant_header: Option<String>,
+    pub enable_ui: bool,
+}
+
+impl Config {
+    /// Read the configuration from the arguments of the `run` subcommand
+    ///
+    /// # Arguments
+    /// * `args` - The parsed arguments of the `run` subcommand
+    pub fn from_args(args: &ArgMatches) -> Self {
+        let (data_dir, sync_writes) = crate::cli::storage_location(args);
+        return Self {
+            bind: args.get_one::<String>("bind").unwrap().clone(),
+            backend: args.get_one::<String>("backend").unwrap().clone(),
+            data_dir,
+            hash_keys_over: args.get_one::<usize>("hash-keys-over").copied(),
+            sync_writes,
+            skip_read_checksums: args.get_flag("skip-read-checksums"),
+            persistent: args.get_flag("persistent"),
+            snapshot_on_exit: args.get_one::<String>("snapshot-on-exit").cloned(),
+            unhealthy_error_rate: *args.get_one("unhealthy-error-rate").unwrap(),
+            error_rate_window: *args.get_one("error-rate-window").unwrap(),
+            no_store_prefixes: args
+                .get_many::<String>("no-store-prefix")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            int_as_string: args.get_flag("int-as-string"),
+            admin_token: args.get_one::<String>("admin-token").cloned(),
+            auth_max_attempts: args.get_one::<u32>("auth-max-attempts").copied(),
+            auth_window: *args.get_one("auth-window").unwrap(),
+            op_timeout: args.get_one::<u64>("op-timeout").copied(),
+            slow_op_threshold: args.get_one::<u64>("slow-op-threshold-ms").copied(),
+            sweep_interval: args.get_one::<u64>("sweep-interval").copied(),
+            expiry_webhook: args.get_one::<WebhookUrl>("expiry-webhook").cloned(),
+            memtable_flush_interval_secs: args
+                .get_one::<u64>("memtable-flush-interval-secs")
+                .copied(),
+            max_value_bytes: *args.get_one("max-value-bytes").unwrap(),
+            max_batch_bytes: *args.get_one("max-batch-bytes").unwrap(),
+            max_connections: *args.get_one("max-connections").unwrap(),
+            backend_name: args.get_one::<String>("backend-name").unwrap().clone(),
+            named_backends: args
+                .get_many::<String>("named-backend")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            mirror_to: args.get_one::<String>("mirror-to").cloned(),
+            mirror_failures_fatal: args.get_flag("mirror-failures-fatal"),
+            max_collection_elements: args.get_one::<usize>("max-collection-elements").copied(),
+            max_response_bytes: args.get_one::<usize>("max-response-bytes").copied(),
+            require_delimiter_on_prefix_delete: args
+                .get_one::<String>("require-delimiter-on-prefix-delete")
+                .cloned(),
+            strict_types: args.get_flag("strict-types"),
+            ttl_on_missing_key: match args
+                .get_one::<String>("ttl-on-missing-key")
+                .map(String::as_str)
+            {
+                Some("ignore") => MissingKeyTtl::Ignore,
+                _ => MissingKeyTtl::Error,
+            },
+            ttl_rounding: match args.get_one::<String>("ttl-rounding").map(String::as_str) {
+                Some("floor") => TtlRounding::Floor,
+                Some("round") => TtlRounding::Round,
+                _ => TtlRounding::Ceil,
+            },
+            type_case: match args.get_one::<String>("type-case").map(String::as_str) {
+                Some("lower") => TypeCase::Lower,
+                _ => TypeCase::Title,
+            },
+            breaker_threshold: args.get_one::<u32>("breaker-threshold").copied(),
+            breaker_cooldown: *args.get_one("breaker-cooldown").unwrap(),
+            debug_bodies: args.get_flag("debug-bodies"),
+            open_timeout_secs: args.get_one::<u64>("open-timeout-secs").copied(),
+            tenant_header: args.get_one::<String>("tenant-header").cloned(),
+            enable_ui: args.get_flag("enable-ui"),
+        };
+    }
+
+    /// The HTTP caching policy of key reads
+    pub fn cache_policy(&self) -> CachePolicy {
+        return CachePolicy {
+            no_store_prefixes: self.no_store_prefixes.clone(),
+        };
+    }
+
+    /// How integers are written in responses
+    pub const fn int_encoding(&self) -> IntEncoding {
+        if self.int_as_string {
+            return IntEncoding::String;
+        }
+        return IntEncoding::Number;
+    }
+
+    /// The limit of a single key request
+    pub fn op_timeout(&self) -> OpTimeout {
+        return OpTimeout(self.op_timeout.map(Duration::from_millis));
+    }
+
+    /// The body size limits of the key routes
+    pub const fn body_limits(&self) -> BodyLimits {
+        return BodyLimits {
+            value: self.max_value_bytes,
+            batch: self.max_batch_bytes,
+        };
+    }
+
+    /// The most elements a list may hold
+    pub const fn collection_limit(&self) -> CollectionLimit {
+        return CollectionLimit(self.max_collection_elements);
+    }
+
+    /// The most bytes the keys of one listing page may take
+    pub const fn max_response_bytes(&self) -> MaxResponseBytes {
+        return MaxResponseBytes(self.max_response_bytes);
+    }
+
+    /// The delimiter the prefixes of deletes must end in
+    pub fn delete_delimiter(&self) -> DeleteDelimiter {
+        return DeleteDelimiter(self.require_delimiter_on_prefix_delete.clone());
+    }
+
+    /// What setting the TTL of a missing key does
+    pub const fn missing_key_ttl(&self) -> MissingKeyTtl {
+        return self.ttl_on_missing_key;
+    }
+
+    /// The backends requests may select, none while the server runs a single one
+    pub fn backend_names(&self) -> BackendNames {
+        if self.named_backends.is_empty() {
+            return BackendNames::default();
+        }
+        let mut names = vec![self.backend_name.clone()];
+        names.extend(
+            self.named_backends
+                .iter()
+                .filter_map(|named| named.split_once('='))
+                .map(|(name, _)| name.to_string()),
+        );
+        return BackendNames(names);
+    }
+
+    /// The throttle of invalid admin tokens, `None` if it is disabled
+    pub fn auth_throttle(&self) -> Option<AuthThrottle> {
+        return self.auth_max_attempts.map(|max_attempts| {
+            AuthThrottle::new(max_attempts, Duration::from_secs(self.auth_window))
+        });
+    }
+
+    /// The longest the backends may take to open, `None` to wait forever
+    pub fn open_timeout(&self) -> Option<Duration> {
+        return self.open_timeout_secs.map(Duration::from_secs);
+    }
+
+    /// The header naming the tenant of a request
+    pub fn tenant_header(&self) -> TenantHeader {
+        return TenantHeader(self.tenant_header.clone());
+    }
+
+    /// The period of the memtable flushes, `None` if they are left to the backend
+    pub fn memtable_flush_interval(&self) -> Option<Duration> {
+        return self.memtable_flush_interval_secs.map(Duration::from_secs);
+    }
+
+    /// The period of the expired keys sweeper, `None` if it is disabled
+    pub fn sweep_interval(&self) -> Option<Duration> {
+        return self.sweep_interval.map(Duration::from_secs);
+    }
+
+    /// The optional features this configuration turns on
+    fn features(&self) -> Vec<&'static str> {
+        let mut features = Vec::new();
+        if self.admin_token.is_some() {
+            features.push("auth");
+        }
+        if self.auth_max_attempts.is_some() {
+            features.push("auth-throttle");
+        }
+        if self.persistent {
+            features.push("persistent");
+        }
+        if self.snapshot_on_exit.is_some() {
+            features.push("snapshot-on-exit");
+        }
+        if self.sync_writes {
+            features.push("sync-writes");
+        }
+        if self.skip_read_checksums {
+            features.push("skip-read-checksums");
+        }
+        if self.int_as_string {
+            features.push("int-as-string");
+        }
+        if self.hash_keys_over.is_some() {
+            features.push("hash-keys");
+        }
+        if self.op_timeout.is_some() {
+            features.push("op-timeout");
+        }
+        if self.slow_op_threshold.is_some() {
+            features.push("slow-op-log");
+        }
+        if self.sweep_interval.is_some() {
+            features.push("ttl-sweeper");
+        }
+        if self.expiry_webhook.is_some() {
+            features.push("expiry-webhook");
+        }
+        if self.memtable_flush_interval_secs.is_some() {
+            features.push("memtable-flush");
+        }
+        if self.max_collection_elements.is_some() {
+            features.push("collection-limit");
+        }
+        if self.max_response_bytes.is_some() {
+            features.push("response-size-limit");
+        }
+        if self.require_delimiter_on_prefix_delete.is_some() {
+            features.push("prefix-delete-guard");
+        }
+        if self.strict_types {
+            features.push("strict-types");
+        }
+        if !self.named_backends.is_empty() {
+            features.push("multi-backend");
+        }
+        if self.mirror_to.is_some() {
+            features.push("mirror");
+        }
+        if self.breaker_threshold.is_some() {
+            features.push("circuit-breaker");
+        }
+        if self.ttl_on_missing_key == MissingKeyTtl::Ignore {
+            features.push("ttl-ignore-missing");
+        }
+        if self.debug_bodies {
+            features.push("debug-bodies");
+        }
+        if self.type_case == TypeCase::Lower {
+            features.push("lowercase-types");
+        }
+        if self.open_timeout_secs.is_some() {
+            features.push("open-timeout");
+        }
+        if self.tenant_header.is_some() {
+            features.push("multi-tenant");
+        }
+        if self.enable_ui {
+            features.push("ui");
+        }
+        return features;
+    }
+
+    /// A one-line `key=value` summary of the configuration, secrets are redacted
+    ///
+    /// # Arguments
+    /// * `version` - The version of the server
+    pub fn summary(&self, version: &str) -> String {
+        let features = self.features();
+        return format!(
+            "version={version} backend={} bind={} data_dir={} features={} admin_token={}",
+            self.backend,
+            self.bind,
+            self.data_dir.as_deref().unwrap_or("none"),
+            if features.is_empty() {
+                "none".to_string()
+            } else {
+                features.join(",")
+            },
+            if self.admin_token.is_some() {
+                "<redacted>"
+            } else {
+                "none"
+            },
+        );
+    }
+}
+
+/// Log the configuration the server starts with, so operators can check it at a glance
+///
+/// # Arguments
+/// * `config` - The configuration of the `run` subcommand
+pub fn log_startup(config: &Config) {
+    log::info!(
+        "Starting bredis: {}",
+        config.summary(&Info::default().version)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let matches = crate::cli::make_cli()
+            .try_get_matches_from([
+                "bredis",
+                "run",
+                "--backend",
+                "rocksdb",
+                "--data-dir",
+                "/var/lib/bredis",
+                "--admin-token",
+                "s3cr3t-token",
+                "--sync-writes",
+            ])
+            .unwrap();
+        let config = Config::from_args(matches.subcommand_matches("run").unwrap());
+
+        let summary = config.summary("1.2.3");
+        assert_eq!(
+            summary,
+            "version=1.2.3 backend=rocksdb bind=[::1]:4123 data_dir=/var/lib/bredis \
+             features=auth,sync-writes admin_token=<redacted>"
+        );
+        assert!(
+            !summary.contains("s3cr3t-token"),
+            "The admin token is leaked"
+        );
+
+        let matches = crate::cli::make_cli()
+            .try_get_matches_from(["bredis", "run", "--persistent"])
+            .unwrap();
+        let config = Config::from_args(matches.subcommand_matches("run").unwrap());
+        assert_eq!(
+            config.summary("1.2.3"),
+            "version=1.2.3 backend=surrealkv bind=[::1]:4123 data_dir=bredis-data \
+             features=persistent,sync-writes admin_token=none"
+        );
+
+        assert_eq!(config.max_connections, 25_000);
+
+        let summary = Config::default().summary("1.2.3");
+        assert!(
+            summary.contains("features=none admin_token=none"),
+            "{summary}"
+        );
+    }
+
+    #[test]
+    fn test_max_connections() {
+        let matches = crate::cli::make_cli()
+            .try_get_matches_from(["bredis", "run", "--max-connections", "100"])
+            .unwrap();
+        let config = Config::from_args(matches.subcommand_matches("run").unwrap());
+        assert_eq!(config.max_connections, 100);
+
+        assert!(crate::cli::make_cli()
+            .try_get_matches_from(["bredis", "run", "--max-connections", "0"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_expiry_webhook() {
+        let matches = crate::cli::make_cli()
+            .try_get_matches_from([
+                "bredis",
+                "run",
+                "--sweep-interval",
+                "5",
+                "--expiry-webhook",
+                "http://localhost:8080/expired",
+            ])
+            .unwrap();
+        let config = Config::from_args(matches.subcommand_matches("run").unwrap());
+        assert!(config.expiry_webhook.is_some());
+        assert!(config.features().contains(&"expiry-webhook"));
+
+        // Nothing would fire the webhook without the sweeper
+        assert!(crate::cli::make_cli()
+            .try_get_matches_from([
+                "bredis",
+                "run",
+                "--expiry-webhook",
+                "http://localhost:8080/expired",
+            ])
+            .is_err());
+        assert!(crate::cli::make_cli()
+            .try_get_matches_from([
+                "bredis",
+                "run",
+                "--sweep-interval",
+                "5",
+                "--expiry-webhook",
+                "https://localhost/expired",
+            ])
+            .is_err());
+    }
+}
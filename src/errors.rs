@@ -12,10 +12,74 @@ pub enum DatabaseError {
     InvalidValueType(String),
     /// Value not found in the database.
     ValueNotFound(String),
+    /// Invalid JSON document or JSON path.
+    InvalidJson(String),
+    /// The result of the operation would be outside the allowed bounds.
+    OutOfRange(String),
+    /// The operation conflicted with a concurrent one or the backend was busy; retrying may succeed.
+    Conflict(String),
+    /// The operation is not supported by the storage backend.
+    Unsupported(String),
     /// Internal error occurred in the database.
     InternalError(String),
 }
 
+impl DatabaseError {
+    /// Whether the failure is transient, so the same request may succeed when retried.
+    ///
+    /// Transaction conflicts, busy backends and timeouts are retryable; invalid requests,
+    /// missing keys and internal errors are not.
+    pub const fn is_retryable(&self) -> bool {
+        return matches!(self, Self::Conflict(_));
+    }
+
+    /// A stable, machine-readable name of the kind of error, sent as `code` in error responses.
+    pub const fn code(&self) -> &'static str {
+        return match self {
+            Self::InitialFailed(_) => "initial_failed",
+            Self::InvalidValueType(_) => "invalid_value_type",
+            Self::ValueNotFound(_) => "value_not_found",
+            Self::InvalidJson(_) => "invalid_json",
+            Self::OutOfRange(_) => "out_of_range",
+            Self::Conflict(_) => "conflict",
+            Self::Unsupported(_) => "unsupported",
+            Self::InternalError(_) => "internal_error",
+        };
+    }
+
+    /// What the kind of error means, whatever operation failed.
+    pub const fn description(&self) -> &'static str {
+        return match self {
+            Self::InitialFailed(_) => "The database failed to initialize",
+            Self::InvalidValueType(_) => {
+                "The value has another type than the operation expects, or is malformed"
+            }
+            Self::ValueNotFound(_) => "The key doesn't exist or has expired",
+            Self::InvalidJson(_) => "The JSON document or JSON path is invalid",
+            Self::OutOfRange(_) => "The result would be outside the allowed bounds",
+            Self::Conflict(_) => {
+                "The operation conflicted with a concurrent one or the backend was busy"
+            }
+            Self::Unsupported(_) => "The storage backend doesn't support the operation",
+            Self::InternalError(_) => "The backend failed",
+        };
+    }
+
+    /// One error of every kind, with an empty message, to document them.
+    pub fn kinds() -> Vec<Self> {
+        return vec![
+            Self::InitialFailed(String::new()),
+            Self::InvalidValueType(String::new()),
+            Self::ValueNotFound(String::new()),
+            Self::InvalidJson(String::new()),
+            Self::OutOfRange(String::new()),
+            Self::Conflict(String::new()),
+            Self::Unsupported(String::new()),
+            Self::InternalError(String::new()),
+        ];
+    }
+}
+
 // Implement the Display trait for the DatabaseError enum.
 impl fmt::Display for DatabaseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -25,6 +89,10 @@ impl fmt::Display for DatabaseError {
                 write!(f, "Invalid value type: {type_}")
             }
             Self::ValueNotFound(key) => write!(f, "Value not found for key: {key}"),
+            Self::InvalidJson(err) => write!(f, "Invalid JSON: {err}"),
+            Self::OutOfRange(err) => write!(f, "Out of range: {err}"),
+            Self::Conflict(err) => write!(f, "Conflict: {err}"),
+            Self::Unsupported(err) => write!(f, "Unsupported operation: {err}"),
             Self::InternalError(err) => write!(f, "Internal error: {err}"),
         }
     }
@@ -34,9 +102,15 @@ impl fmt::Display for DatabaseError {
 impl std::error::Error for DatabaseError {}
 
 // Implement the From trait for converting a rocksdb::Error to a DatabaseError.
+// Conflicting optimistic transactions fail with `Busy` on commit.
 impl From<rocksdb::Error> for DatabaseError {
     fn from(err: rocksdb::Error) -> Self {
-        Self::InternalError(err.to_string())
+        match err.kind() {
+            rocksdb::ErrorKind::Busy
+            | rocksdb::ErrorKind::TryAgain
+            | rocksdb::ErrorKind::TimedOut => Self::Conflict(err.to_string()),
+            _ => Self::InternalError(err.to_string()),
+        }
     }
 }
 
@@ -53,3 +127,46 @@ impl From<String> for DatabaseError {
         Self::InternalError(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(DatabaseError::Conflict("Resource busy".to_string()).is_retryable());
+        assert!(!DatabaseError::InvalidValueType("not an integer".to_string()).is_retryable());
+        assert!(!DatabaseError::ValueNotFound("key1".to_string()).is_retryable());
+        assert!(!DatabaseError::OutOfRange("11 exceeds 10".to_string()).is_retryable());
+        assert!(!DatabaseError::Unsupported("checkpoints".to_string()).is_retryable());
+        assert!(!DatabaseError::InternalError("corruption".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_kinds_cover_every_variant() {
+        let mut seen = [false; 8];
+        for err in DatabaseError::kinds() {
+            // Adding a variant breaks this match, so it can't be left out of the catalog
+            let index = match err {
+                DatabaseError::InitialFailed(_) => 0,
+                DatabaseError::InvalidValueType(_) => 1,
+                DatabaseError::ValueNotFound(_) => 2,
+                DatabaseError::InvalidJson(_) => 3,
+                DatabaseError::OutOfRange(_) => 4,
+                DatabaseError::Conflict(_) => 5,
+                DatabaseError::Unsupported(_) => 6,
+                DatabaseError::InternalError(_) => 7,
+            };
+            seen[index] = true;
+        }
+        assert!(seen.iter().all(|seen| *seen), "{seen:?}");
+
+        let mut codes: Vec<&str> = DatabaseError::kinds()
+            .iter()
+            .map(DatabaseError::code)
+            .collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), seen.len());
+    }
+}
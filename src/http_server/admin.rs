@@ -0,0 +1,541 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
+
+use crate::errors::DatabaseError;
+use crate::storages::storage::Storage;
+
+use super::{
+    backoff::{self, RateLimit},
+    models,
+};
+
+/// Failed admin authorizations of one client address
+struct Failures {
+    count: u32,
+    since: Instant,
+}
+
+/// Throttles clients that keep presenting wrong admin tokens, to resist token guessing
+///
+/// After `max_attempts` failures within `window`, a client address is answered with 429
+/// until `window` has passed since its last counted failure. A successful authorization
+/// clears the failures of its address.
+pub struct AuthThrottle {
+    max_attempts: u32,
+    window: Duration,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl AuthThrottle {
+    /// Creates a throttle shared by all workers of the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_attempts` - The failures after which an address is blocked.
+    /// * `window` - The time failures are counted over, and the time an address stays blocked.
+    #[must_use]
+    pub fn new(max_attempts: u32, window: Duration) -> Self {
+        return Self {
+            max_attempts,
+            window,
+            failures: Mutex::new(HashMap::new()),
+        };
+    }
+
+    /// The limit the address has run into, if it has failed too often to be let in right now
+    fn blocked(&self, ip: IpAddr) -> Option<RateLimit> {
+        let mut failures = self.failures.lock().unwrap();
+        match failures.get(&ip) {
+            Some(entry) if entry.since.elapsed() >= self.window => {
+                failures.remove(&ip);
+                return None;
+            }
+            Some(entry) if entry.count >= self.max_attempts => {
+                return Some(RateLimit {
+                    limit: self.max_attempts,
+                    remaining: 0,
+                    reset: self.window.saturating_sub(entry.since.elapsed()),
+                });
+            }
+            Some(_) | None => return None,
+        }
+    }
+
+    /// Count a failed authorization of the address
+    fn fail(&self, ip: IpAddr) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.retain(|_, entry| entry.since.elapsed() < self.window);
+        let entry = failures.entry(ip).or_insert(Failures {
+            count: 0,
+            since: Instant::now(),
+        });
+        entry.count += 1;
+        if entry.count >= self.max_attempts {
+            // The block lasts a full window from the failure that triggered it
+            entry.since = Instant::now();
+            log::warn!(
+                "Blocking admin requests from {ip} after {} failures",
+                entry.count
+            );
+        }
+    }
+
+    /// Clear the failures of an address that authorized successfully
+    fn succeed(&self, ip: IpAddr) {
+        self.failures.lock().unwrap().remove(&ip);
+    }
+}
+
+/// Represents the admin service.
+///
+/// Admin routes operate on the whole database, so every request must carry
+/// `Authorization: Bearer <token>` matching the token the server was started with.
+/// Without a configured token the admin routes are disabled.
+pub struct Service {
+    db: Arc<Box<dyn Storage>>,
+    token: Option<String>,
+    throttle: Option<Arc<AuthThrottle>>,
+}
+
+impl Service {
+    /// Creates a new instance of the admin service.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database to administer.
+    /// * `token` - The bearer token admin requests must present, `None` disables the routes.
+    #[must_use]
+    pub fn new(db: Arc<Box<dyn Storage>>, token: Option<String>) -> Self {
+        return Self {
+            db,
+            token,
+            throttle: None,
+        };
+    }
+
+    /// Throttles clients presenting wrong tokens, `None` lets them retry without limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `throttle` - The throttle, shared by all workers of the server.
+    #[must_use]
+    pub fn with_throttle(mut self, throttle: Option<Arc<AuthThrottle>>) -> Self {
+        self.throttle = throttle;
+        return self;
+    }
+
+    /// Configures the admin service with the given `ServiceConfig`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The `ServiceConfig` to configure.
+    pub fn config(self, cfg: &mut web::ServiceConfig) {
+        let self_clone = Arc::new(self);
+        let sync_self = self_clone.clone();
+        let evict_self = self_clone.clone();
+        cfg.service(web::resource("/admin/checkpoint").route(web::post().to(
+            move |req: HttpRequest, body: web::Json<models::CheckpointRequest>| {
+                let self_clone = self_clone.clone();
+                async move { self_clone.checkpoint(&req, body).await }
+            },
+        )))
+        .service(
+            web::resource("/admin/sync").route(web::post().to(move |req: HttpRequest| {
+                let self_clone = sync_self.clone();
+                async move { self_clone.sync(&req).await }
+            })),
+        )
+        .service(web::resource("/admin/evict").route(web::post().to(
+            move |req: HttpRequest, body: web::Json<models::EvictRequest>| {
+                let self_clone = evict_self.clone();
+                async move { self_clone.evict(&req, body).await }
+            },
+        )));
+    }
+
+    /// Checks the bearer token of an admin request.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The incoming request.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the request is authorized, otherwise the response to reject it with.
+    fn authorize(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        let Some(token) = &self.token else {
+            return Some(HttpResponse::Forbidden().json(models::ErrorResponse::new(
+                StatusCode::FORBIDDEN,
+                "Admin routes are disabled, start the server with --admin-token",
+            )));
+        };
+
+        let throttle = self
+            .throttle
+            .as_ref()
+            .zip(req.peer_addr().map(|addr| addr.ip()));
+        if let Some((throttle, ip)) = throttle {
+            if let Some(rate_limit) = throttle.blocked(ip) {
+                let mut builder = HttpResponse::TooManyRequests();
+                backoff::insert_backoff_headers(&mut builder, rate_limit.reset, Some(rate_limit));
+                return Some(builder.json(models::ErrorResponse::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too many invalid admin tokens, try again later",
+                )));
+            }
+        }
+
+        let presented = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if presented == Some(token.as_str()) {
+            if let Some((throttle, ip)) = throttle {
+                throttle.succeed(ip);
+            }
+            return None;
+        }
+        if let Some((throttle, ip)) = throttle {
+            throttle.fail(ip);
+        }
+        return Some(
+            HttpResponse::Unauthorized().json(models::ErrorResponse::new(
+                StatusCode::UNAUTHORIZED,
+                "Invalid admin token",
+            )),
+        );
+    }
+
+    /// Writes a point-in-time copy of the database into a directory on the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The incoming request, used for authorization.
+    /// * `body` - The directory to write the copy into.
+    ///
+    /// # Returns
+    ///
+    /// 200 once the copy is written, 401/403 if the request is not authorized,
+    /// 501 if the backend can't write checkpoints.
+    pub async fn checkpoint(
+        &self,
+        req: &HttpRequest,
+        body: web::Json<models::CheckpointRequest>,
+    ) -> HttpResponse {
+        if let Some(rejection) = self.authorize(req) {
+            return rejection;
+        }
+
+        // A backend without checkpoints is answered with 501 before it is asked for one
+        let result = match self.db.capabilities().require_checkpoint() {
+            Ok(()) => self.db.checkpoint(&body.path).await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(()) => {
+                log::info!("Checkpoint written to {}", body.path);
+                return HttpResponse::Ok().json(models::OperationSuccessResponse { success: true });
+            }
+            Err(err) => {
+                return backoff::error_response(&models::ErrorResponse::from(&err));
+            }
+        }
+    }
+
+    /// Persists every acknowledged write durably.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The incoming request, used for authorization.
+    ///
+    /// # Returns
+    ///
+    /// 200 once the data is on disk, 401/403 if the request is not authorized.
+    pub async fn sync(&self, req: &HttpRequest) -> HttpResponse {
+        if let Some(rejection) = self.authorize(req) {
+            return rejection;
+        }
+
+        match self.db.sync().await {
+            Ok(()) => {
+                return HttpResponse::Ok().json(models::OperationSuccessResponse { success: true })
+            }
+            Err(err) => {
+                return backoff::error_response(&models::ErrorResponse::from(&err));
+            }
+        }
+    }
+
+    /// Deletes the keys that expire within a threshold, to relieve memory pressure by hand.
+    ///
+    /// Keys without a TTL are never evicted. A key written again between the scan and its
+    /// deletion is deleted with its new value.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The incoming request, used for authorization.
+    /// * `body` - The largest remaining TTL of an evicted key, every key with a TTL without it.
+    ///
+    /// # Returns
+    ///
+    /// 200 with the number of deleted keys, 401/403 if the request is not authorized.
+    pub async fn evict(
+        &self,
+        req: &HttpRequest,
+        body: web::Json<models::EvictRequest>,
+    ) -> HttpResponse {
+        if let Some(rejection) = self.authorize(req) {
+            return rejection;
+        }
+
+        match self.evict_keys(body.max_ttl).await {
+            Ok(evicted) => {
+                log::info!("Evicted {evicted} keys");
+                return HttpResponse::Ok().json(models::EvictResponse { evicted });
+            }
+            Err(err) => {
+                return backoff::error_response(&models::ErrorResponse::from(&err));
+            }
+        }
+    }
+
+    async fn evict_keys(&self, max_ttl: Option<i64>) -> Result<usize, DatabaseError> {
+        let mut evicted = 0;
+        for key in self.db.get_all_keys(b"").await? {
+            let Some(value) = self.db.get_no_reap(key.as_bytes()).await? else {
+                continue;
+            };
+            let volatile = value.ttl >= 0;
+            if volatile && max_ttl.is_none_or(|max_ttl| value.ttl <= max_ttl) {
+                self.db.delete(key.as_bytes()).await?;
+                evicted += 1;
+            }
+        }
+        return Ok(evicted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, App};
+
+    use super::*;
+    use crate::storages::mock::TempPath;
+    use crate::storages::rocksdb::Rocksdb;
+    use crate::storages::value::StorageValue;
+
+    #[actix_web::test]
+    async fn test_checkpoint() {
+        let checkpoint_path = TempPath::new("test_checkpoint");
+        let db = Rocksdb::open_ephemeral().unwrap();
+        let value = StorageValue::string("value1");
+        db.set(b"key1", &value).await.unwrap();
+
+        let db: Box<dyn Storage> = Box::new(db);
+        let service = Service::new(Arc::new(db), Some("secret".to_string()));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        let body = models::CheckpointRequest {
+            path: checkpoint_path.to_string(),
+        };
+        let req = test::TestRequest::post()
+            .uri("/admin/checkpoint")
+            .insert_header((header::AUTHORIZATION, "Bearer wrong"))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/checkpoint")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .set_json(&body)
+            .to_request();
+        let resp: models::OperationSuccessResponse = test::call_and_read_body_json(&app, req).await;
+        assert!(resp.success);
+
+        let copy =
+            rocksdb::DB::open_for_read_only(&rocksdb::Options::default(), &*checkpoint_path, false)
+                .unwrap();
+        assert!(copy.get(b"key1").unwrap().is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_checkpoint_unsupported() {
+        let db: Box<dyn Storage> = Box::new(crate::storages::bredis::Bredis::open());
+        let service = Service::new(Arc::new(db), Some("secret".to_string()));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/admin/checkpoint")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .set_json(models::CheckpointRequest {
+                path: TempPath::new("test_checkpoint_unsupported_backend").to_string(),
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+        let body: models::ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code.as_deref(), Some("unsupported"));
+        assert_eq!(
+            body.error,
+            "Unsupported operation: checkpoints are not supported by the bredis backend"
+        );
+        assert!(!body.retryable);
+    }
+
+    #[actix_web::test]
+    async fn test_sync() {
+        let db = Rocksdb::open_ephemeral().unwrap();
+        db.set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+
+        let db: Box<dyn Storage> = Box::new(db);
+        let service = Service::new(Arc::new(db), Some("secret".to_string()));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        let req = test::TestRequest::post().uri("/admin/sync").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/sync")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_request();
+        let resp: models::OperationSuccessResponse = test::call_and_read_body_json(&app, req).await;
+        assert!(resp.success);
+    }
+
+    #[actix_web::test]
+    async fn test_evict() {
+        let db = crate::storages::bredis::Bredis::open();
+        for (key, ttl) in [
+            ("short", 10),
+            ("edge", 60),
+            ("long", 3600),
+            ("persistent", -1),
+        ] {
+            let value = StorageValue {
+                ttl,
+                ..StorageValue::string("value")
+            };
+            db.set(key.as_bytes(), &value).await.unwrap();
+        }
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(db));
+        let service = Service::new(db.clone(), Some("secret".to_string()));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        let evict = |max_ttl: Option<i64>| {
+            test::TestRequest::post()
+                .uri("/admin/evict")
+                .insert_header((header::AUTHORIZATION, "Bearer secret"))
+                .set_json(models::EvictRequest { max_ttl })
+                .to_request()
+        };
+
+        let resp: models::EvictResponse =
+            test::call_and_read_body_json(&app, evict(Some(60))).await;
+        assert_eq!(resp.evicted, 2);
+        let mut keys = db.get_all_keys(b"").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["long", "persistent"]);
+
+        let resp: models::EvictResponse = test::call_and_read_body_json(&app, evict(None)).await;
+        assert_eq!(resp.evicted, 1);
+        assert_eq!(db.get_all_keys(b"").await.unwrap(), vec!["persistent"]);
+    }
+
+    #[actix_web::test]
+    async fn test_throttle_invalid_tokens() {
+        let db: Box<dyn Storage> = Box::new(crate::storages::bredis::Bredis::open());
+        let throttle = Arc::new(AuthThrottle::new(3, Duration::from_millis(200)));
+        let service =
+            Service::new(Arc::new(db), Some("secret".to_string())).with_throttle(Some(throttle));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+        let sync = |token: &str, peer: &str| {
+            test::TestRequest::post()
+                .uri("/admin/sync")
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+                .to_request()
+        };
+
+        for _ in 0..3 {
+            let resp = test::call_service(&app, sync("wrong", "10.0.0.1:1000")).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // Even the right token is refused while the address is blocked
+        let resp = test::call_service(&app, sync("secret", "10.0.0.1:1001")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let header_value = |name: &str| -> u64 {
+            return resp
+                .headers()
+                .get(name)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+        };
+        // The block lasts at most 200ms, jitter may only round it up
+        assert_eq!(header_value("retry-after"), 1);
+        assert_eq!(header_value(backoff::RATE_LIMIT_LIMIT_HEADER), 3);
+        assert_eq!(header_value(backoff::RATE_LIMIT_REMAINING_HEADER), 0);
+        assert_eq!(header_value(backoff::RATE_LIMIT_RESET_HEADER), 1);
+
+        // Other clients are not affected
+        let resp = test::call_service(&app, sync("secret", "10.0.0.2:1000")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let resp = test::call_service(&app, sync("secret", "10.0.0.1:1002")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_successful_auth_resets_failures() {
+        let db: Box<dyn Storage> = Box::new(crate::storages::bredis::Bredis::open());
+        let throttle = Arc::new(AuthThrottle::new(2, Duration::from_mins(1)));
+        let service =
+            Service::new(Arc::new(db), Some("secret".to_string())).with_throttle(Some(throttle));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        for token in ["wrong", "secret", "wrong", "secret"] {
+            let req = test::TestRequest::post()
+                .uri("/admin/sync")
+                .peer_addr("10.0.0.1:1000".parse().unwrap())
+                .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS, "{token}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_disabled_without_token() {
+        let db: Box<dyn Storage> = Box::new(crate::storages::bredis::Bredis::open());
+        let service = Service::new(Arc::new(db), None);
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/admin/checkpoint")
+            .set_json(models::CheckpointRequest {
+                path: TempPath::new("test_checkpoint_disabled").to_string(),
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
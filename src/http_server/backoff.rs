@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, HttpResponseBuilder,
+};
+use rand::Rng;
+
+use super::models;
+
+/// The header with the requests allowed per window
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "X-RateLimit-Limit";
+/// The header with the requests left in the current window
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "X-RateLimit-Remaining";
+/// The header with the seconds until the current window resets
+pub const RATE_LIMIT_RESET_HEADER: &str = "X-RateLimit-Reset";
+
+/// The wait suggested by a 503 when the server doesn't know when it recovers
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// The largest share of the wait added as jitter, so shed clients don't retry in lockstep
+const JITTER_RATIO: f64 = 0.5;
+
+/// A limit a client has run into, advertised with the `X-RateLimit-*` headers
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    /// The requests allowed per window
+    pub limit: u32,
+    /// The requests left in the current window
+    pub remaining: u32,
+    /// The time until the current window resets
+    pub reset: Duration,
+}
+
+/// The `Retry-After` seconds for a wait, with jitter
+///
+/// Jitter only lengthens the wait, so a client never retries before it may succeed.
+///
+/// # Arguments
+/// * `wait` - The shortest time the client has to wait
+///
+/// # Returns
+/// The wait plus up to `JITTER_RATIO` of it, rounded up to whole seconds and at least 1
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    let jitter = wait.mul_f64(rand::thread_rng().gen_range(0.0..=JITTER_RATIO));
+    return ceil_secs(wait + jitter).max(1);
+}
+
+/// Add the backoff headers to a response that sheds load
+///
+/// # Arguments
+/// * `builder` - The response being built
+/// * `wait` - The shortest time the client has to wait before retrying
+/// * `rate_limit` - The limit the client has run into, if the response is a rate limit
+pub fn insert_backoff_headers(
+    builder: &mut HttpResponseBuilder,
+    wait: Duration,
+    rate_limit: Option<RateLimit>,
+) {
+    builder.insert_header((header::RETRY_AFTER, retry_after_secs(wait)));
+    if let Some(rate_limit) = rate_limit {
+        builder
+            .insert_header((RATE_LIMIT_LIMIT_HEADER, rate_limit.limit))
+            .insert_header((RATE_LIMIT_REMAINING_HEADER, rate_limit.remaining))
+            .insert_header((RATE_LIMIT_RESET_HEADER, ceil_secs(rate_limit.reset)));
+    }
+}
+
+/// Start a response with the status of an error, with `Retry-After` if the error sheds load
+///
+/// # Arguments
+/// * `status` - The status of the response
+pub fn error_builder(status: StatusCode) -> HttpResponseBuilder {
+    let mut builder = HttpResponse::build(status);
+    if status == StatusCode::SERVICE_UNAVAILABLE {
+        insert_backoff_headers(&mut builder, DEFAULT_RETRY_AFTER, None);
+    }
+    return builder;
+}
+
+/// The JSON response of an error, see `error_builder`
+///
+/// # Arguments
+/// * `error` - The error to answer with
+pub fn error_response(error: &models::ErrorResponse) -> HttpResponse {
+    return error_builder(error.status).json(error);
+}
+
+/// A duration in whole seconds, rounded up
+fn ceil_secs(duration: Duration) -> u64 {
+    if duration.subsec_nanos() > 0 {
+        return duration.as_secs().saturating_add(1);
+    }
+    return duration.as_secs();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::http_server::queries::service::DatabaseQueries;
+    use crate::storages::{breaker::CircuitBreaker, mock::FailingStorage, storage::Storage};
+
+    #[actix_web::test]
+    async fn test_retry_after_secs() {
+        for _ in 0..100 {
+            let secs = retry_after_secs(Duration::from_secs(10));
+            assert!((10..=15).contains(&secs), "{secs}");
+        }
+        assert_eq!(retry_after_secs(Duration::ZERO), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(200)), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_error_builder() {
+        let response = error_builder(StatusCode::SERVICE_UNAVAILABLE).finish();
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        let response = error_builder(StatusCode::NOT_FOUND).finish();
+        assert!(!response.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[actix_web::test]
+    async fn test_circuit_open_response() {
+        let db: Box<dyn Storage> = Box::new(CircuitBreaker::new(
+            Box::new(FailingStorage),
+            1,
+            Duration::from_mins(1),
+        ));
+        let query_service = DatabaseQueries::new(Arc::new(db));
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+        let get = || test::TestRequest::get().uri("/keys/key1").to_request();
+
+        // The first failure opens the breaker, the next request is shed
+        let resp = test::call_service(&app, get()).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!resp.headers().contains_key(header::RETRY_AFTER));
+
+        let resp = test::call_service(&app, get()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let retry_after: u64 = resp
+            .headers()
+            .get(header::RETRY_AFTER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=2).contains(&retry_after), "{retry_after}");
+        assert!(!resp.headers().contains_key(RATE_LIMIT_LIMIT_HEADER));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use actix_web::{web, HttpResponse, Responder};
+use tokio::sync::broadcast;
+
+use super::models;
+
+/// The number of messages a slow subscriber may fall behind before it starts missing them
+const CHANNEL_CAPACITY: usize = 128;
+
+/// Publish/subscribe channels, created lazily on the first subscription
+///
+/// Messages are only delivered to the subscribers connected at publish time; nothing is stored.
+#[derive(Default)]
+pub struct Channels {
+    senders: Mutex<HashMap<String, broadcast::Sender<String>>>,
+}
+
+impl Channels {
+    /// Subscribe to a channel, creating it if needed
+    ///
+    /// # Arguments
+    /// * `name` - The name of the channel
+    ///
+    /// # Returns
+    /// A receiver of the messages published after this call
+    pub fn subscribe(&self, name: &str) -> broadcast::Receiver<String> {
+        let mut senders = self.senders.lock().unwrap();
+        return senders
+            .entry(name.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe();
+    }
+
+    /// Publish a message to the current subscribers of a channel
+    ///
+    /// # Arguments
+    /// * `name` - The name of the channel
+    /// * `message` - The message to deliver
+    ///
+    /// # Returns
+    /// The number of subscribers the message was delivered to
+    pub fn publish(&self, name: &str, message: String) -> usize {
+        let mut senders = self.senders.lock().unwrap();
+        let Some(sender) = senders.get(name) else {
+            return 0;
+        };
+
+        let Ok(receivers) = sender.send(message) else {
+            // Every subscriber is gone, the channel is created again on the next subscription
+            senders.remove(name);
+            return 0;
+        };
+        return receivers;
+    }
+}
+
+/// Represents the pub/sub service.
+///
+/// Subscribers receive messages as server-sent events, one `data:` event per message.
+pub struct Service {
+    channels: Arc<Channels>,
+}
+
+impl Service {
+    /// Creates a new instance of the pub/sub service.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - The channels shared by all workers of the server.
+    #[must_use]
+    pub const fn new(channels: Arc<Channels>) -> Self {
+        return Self { channels };
+    }
+
+    /// Configures the pub/sub service with the given `ServiceConfig`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The `ServiceConfig` to configure.
+    pub fn config(self, cfg: &mut web::ServiceConfig) {
+        let self_clone = Arc::new(self);
+        cfg.service(
+            web::scope("/channels/{name}")
+                .route("/publish", {
+                    let self_clone = self_clone.clone();
+                    web::post().to(
+                        move |name: web::Path<String>, body: web::Json<models::PublishRequest>| {
+                            let self_clone = self_clone.clone();
+                            async move { self_clone.publish(name, body).await }
+                        },
+                    )
+                })
+                .route("/subscribe", {
+                    let self_clone = self_clone.clone();
+                    web::get().to(move |name: web::Path<String>| {
+                        let self_clone = self_clone.clone();
+                        async move { self_clone.subscribe(name).await }
+                    })
+                }),
+        );
+    }
+
+    /// Publishes a message to a channel.
+    ///
+    /// # Returns
+    ///
+    /// The number of subscribers the message was delivered to.
+    pub async fn publish(
+        &self,
+        name: web::Path<String>,
+        body: web::Json<models::PublishRequest>,
+    ) -> impl Responder {
+        let receivers = self.channels.publish(&name, body.into_inner().message);
+        return web::Json(models::PublishResponse { receivers });
+    }
+
+    /// Subscribes to a channel.
+    ///
+    /// # Returns
+    ///
+    /// A `text/event-stream` response that stays open and carries every published message.
+    pub async fn subscribe(&self, name: web::Path<String>) -> HttpResponse {
+        let receiver = self.channels.subscribe(&name);
+        let events = futures::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => {
+                        let event = web::Bytes::from(format_event(&message));
+                        return Some((Ok::<_, actix_web::Error>(event), receiver));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Subscriber lagged behind, {skipped} messages skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        return HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header(("Cache-Control", "no-cache"))
+            .streaming(events);
+    }
+}
+
+/// Encode a message as a server-sent event, one `data:` field per line
+fn format_event(message: &str) -> String {
+    let mut event = String::new();
+    for line in message.split('\n') {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    return event;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::pin::pin;
+
+    use actix_web::body::MessageBody;
+    use actix_web::{test, App};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_publish_to_subscriber() {
+        let service = Service::new(Arc::new(Channels::default()));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+
+        let req = test::TestRequest::get()
+            .uri("/channels/news/subscribe")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/event-stream"
+        );
+
+        let req = test::TestRequest::post()
+            .uri("/channels/news/publish")
+            .set_json(models::PublishRequest {
+                message: "hello\nworld".to_string(),
+            })
+            .to_request();
+        let published: models::PublishResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(published.receivers, 1);
+
+        let mut body = pin!(resp.into_body());
+        let event = poll_fn(|cx| body.as_mut().poll_next(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event, "data: hello\ndata: world\n\n");
+    }
+
+    #[actix_web::test]
+    async fn test_publish_without_subscribers() {
+        let channels = Channels::default();
+        assert_eq!(channels.publish("empty", "lost".to_string()), 0);
+
+        let receiver = channels.subscribe("empty");
+        drop(receiver);
+        assert_eq!(channels.publish("empty", "lost".to_string()), 0);
+        assert!(channels.senders.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::ops::Deref;
+
+use actix_web::{
+    dev::Payload,
+    error::{ErrorBadRequest, ErrorUnsupportedMediaType},
+    http::header::Accept,
+    web, Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures::future::LocalBoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The serialization formats of request and response bodies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    MsgPack,
+    Cbor,
+}
+
+impl Format {
+    /// The media type of the format
+    pub const fn mime(self) -> &'static str {
+        return match self {
+            Self::Json => "application/json",
+            Self::MsgPack => "application/msgpack",
+            Self::Cbor => "application/cbor",
+        };
+    }
+
+    /// The format of a media type, without its parameters
+    fn from_mime(essence: &str) -> Option<Self> {
+        return match essence {
+            "application/json" => Some(Self::Json),
+            "application/msgpack" | "application/x-msgpack" => Some(Self::MsgPack),
+            "application/cbor" => Some(Self::Cbor),
+            _ => None,
+        };
+    }
+
+    /// The format of a request body, given by its `Content-Type`
+    ///
+    /// # Returns
+    /// JSON if the request has no `Content-Type`, None if its type is not supported
+    pub fn of_request(req: &HttpRequest) -> Option<Self> {
+        return match req.mime_type() {
+            Ok(Some(mime)) => Self::from_mime(mime.essence_str()),
+            Ok(None) => Some(Self::Json),
+            Err(_) => None,
+        };
+    }
+
+    /// The format a response is written in: the most preferred supported type of `Accept`
+    ///
+    /// # Returns
+    /// JSON if the request has no `Accept` or accepts none of the supported types
+    pub fn accepted(req: &HttpRequest) -> Self {
+        return req
+            .get_header::<Accept>()
+            .and_then(|accept| {
+                accept
+                    .ranked()
+                    .iter()
+                    .find_map(|mime| Self::from_mime(mime.essence_str()))
+            })
+            .unwrap_or_default();
+    }
+
+    /// Serialize a value in the format
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        return match self {
+            Self::Json => serde_json::to_vec(value).map_err(|err| err.to_string()),
+            Self::MsgPack => rmp_serde::to_vec_named(value).map_err(|err| err.to_string()),
+            Self::Cbor => {
+                let mut body = Vec::new();
+                ciborium::into_writer(value, &mut body).map_err(|err| err.to_string())?;
+                Ok(body)
+            }
+        };
+    }
+
+    /// Deserialize a value from the format
+    pub fn decode<T: DeserializeOwned>(self, body: &[u8]) -> Result<T, String> {
+        return match self {
+            Self::Json => serde_json::from_slice(body).map_err(|err| err.to_string()),
+            Self::MsgPack => rmp_serde::from_slice(body).map_err(|err| err.to_string()),
+            Self::Cbor => ciborium::from_reader(body).map_err(|err| err.to_string()),
+        };
+    }
+}
+
+/// A request body decoded from the format of its `Content-Type`
+///
+/// The size of the body is bounded by the `web::PayloadConfig` of the route.
+#[derive(Debug)]
+pub struct Body<T>(pub T);
+
+impl<T> Body<T> {
+    pub fn into_inner(self) -> T {
+        return self.0;
+    }
+}
+
+impl<T> Deref for Body<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return &self.0;
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Body<T> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let format = Format::of_request(req);
+        let body = web::Bytes::from_request(req, payload);
+        return Box::pin(async move {
+            let Some(format) = format else {
+                return Err(ErrorUnsupportedMediaType(
+                    "The body must be application/json, application/msgpack or application/cbor",
+                ));
+            };
+            let body = body.await?;
+            return format.decode(&body).map(Self).map_err(ErrorBadRequest);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{
+        http::{header, StatusCode},
+        test, App,
+    };
+
+    use super::*;
+    use crate::http_server::{models, queries::service::DatabaseQueries};
+    use crate::storages::{bredis::Bredis, storage::Storage};
+
+    #[actix_web::test]
+    async fn test_round_trip_in_every_format() {
+        let db: Box<dyn Storage> = Box::new(Bredis::open());
+        let query_service = DatabaseQueries::new(Arc::new(db));
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        let mut results = Vec::new();
+        for format in [Format::Json, Format::MsgPack, Format::Cbor] {
+            // The MIME type has a slash, which would route the read elsewhere
+            let key = format!("key_{format:?}");
+            let request = models::SetRequest {
+                key: key.clone(),
+                value: models::IntOrString::Json(serde_json::json!({"a": [1, "b"]})),
+                ttl: -1,
+            };
+            let req = test::TestRequest::post()
+                .uri("/keys")
+                .insert_header((header::CONTENT_TYPE, format.mime()))
+                .insert_header((header::ACCEPT, format.mime()))
+                .set_payload(format.encode(&request).unwrap())
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{format:?}");
+            assert_eq!(
+                resp.headers().get(header::CONTENT_TYPE).unwrap(),
+                format.mime()
+            );
+            let body = test::read_body(resp).await;
+            let set: models::OperationSuccessResponse = format.decode(&body).unwrap();
+            assert!(set.success, "{format:?}");
+
+            let req = test::TestRequest::get()
+                .uri(&format!("/keys/{key}"))
+                .insert_header((header::ACCEPT, format.mime()))
+                .to_request();
+            let body = test::call_and_read_body(&app, req).await;
+            let get: models::GetResponse = format.decode(&body).unwrap();
+            results.push(serde_json::to_value(get).unwrap());
+        }
+
+        assert_eq!(
+            results[0],
+            serde_json::json!({"value": {"a": [1, "b"]}, "type": "Json"})
+        );
+        assert!(
+            results.iter().all(|result| *result == results[0]),
+            "{results:?}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_unsupported_content_type() {
+        let db: Box<dyn Storage> = Box::new(Bredis::open());
+        let query_service = DatabaseQueries::new(Arc::new(db));
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/keys")
+            .insert_header((header::CONTENT_TYPE, "application/xml"))
+            .set_payload("<key>key1</key>")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[actix_web::test]
+    async fn test_accepted_format() {
+        let req = test::TestRequest::default()
+            .insert_header((
+                header::ACCEPT,
+                "text/html, application/cbor;q=0.5, application/msgpack;q=0.9",
+            ))
+            .to_http_request();
+        assert_eq!(Format::accepted(&req), Format::MsgPack);
+
+        let req = test::TestRequest::default()
+            .insert_header((header::ACCEPT, "*/*"))
+            .to_http_request();
+        assert_eq!(Format::accepted(&req), Format::Json);
+        assert_eq!(
+            Format::accepted(&test::TestRequest::default().to_http_request()),
+            Format::Json
+        );
+    }
+}
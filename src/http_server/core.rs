@@ -5,41 +5,140 @@ use std::sync::Arc;
 
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
-use actix_web::middleware::Logger;
+use actix_web::middleware::{from_fn, Logger};
 use actix_web::{web, App, HttpServer};
 
+use crate::config::Config;
 use crate::errors::Error;
-use crate::http_server::{docs, info, queries};
+use crate::http_server::admin::AuthThrottle;
+use crate::http_server::channels::Channels;
+use crate::http_server::debug_bodies::DebugBodies;
+use crate::http_server::metrics::{self, Metrics};
+use crate::http_server::webhook::{self, ExpiryWebhook};
+use crate::http_server::{
+    admin, channels, docs, expiry, flush, health, info, queries, recovery, stats, ui,
+};
+use crate::storages::monitored::ErrorRate;
 use crate::storages::storage::Storage;
 
 #[derive(Clone)]
 pub struct Server {
     db: Arc<Box<dyn Storage>>,
+    error_rate: Arc<ErrorRate>,
+    config: Config,
+    channels: Arc<Channels>,
+    metrics: Arc<Metrics>,
+    auth_throttle: Option<Arc<AuthThrottle>>,
 }
 
 impl Server {
-    pub const fn new(db: Arc<Box<dyn Storage>>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Box<dyn Storage>>, error_rate: Arc<ErrorRate>, config: Config) -> Self {
+        let auth_throttle = config.auth_throttle().map(Arc::new);
+        Self {
+            db,
+            error_rate,
+            config,
+            channels: Arc::new(Channels::default()),
+            metrics: Arc::new(Metrics::default()),
+            auth_throttle,
+        }
     }
 
+    /// Serve until the server stops, then close the storage
+    ///
+    /// On SIGINT or SIGTERM, actix stops accepting connections and drains the requests in
+    /// flight before the server stops, so the storage is closed exactly once, after its last
+    /// use. It is closed even if the server fails to start.
     #[allow(clippy::future_not_send)]
-    pub async fn serve(self, addr: String) -> Result<(), Error> {
+    pub async fn serve(self) -> Result<(), Error> {
+        let db = self.db.clone();
+        let server = match self.start() {
+            Ok(server) => server,
+            Err(err) => {
+                db.close().await;
+                return Err(err);
+            }
+        };
+        return Self::close_when_stopped(server, db).await;
+    }
+
+    /// Spawn the background tasks and bind the HTTP server, without running it yet
+    fn start(self) -> Result<actix_web::dev::Server, Error> {
+        let addr = self.config.bind.clone();
         log::info!("Starting server on: {addr}");
-        HttpServer::new(move || self.clone().make_app())
+        if let Some(url) = &self.config.expiry_webhook {
+            // Subscribed before the sweeper starts, so no expiry event is missed
+            tokio::spawn(webhook::forward_expiries(
+                Arc::new(ExpiryWebhook::new(url.clone())),
+                self.channels.subscribe(expiry::EXPIRED_CHANNEL),
+            ));
+        }
+        if let Some(interval) = self.config.memtable_flush_interval() {
+            if self.config.backend != "rocksdb" {
+                log::warn!("--memtable-flush-interval-secs only applies to the rocksdb backend");
+            }
+            tokio::spawn(flush::flush_periodically(self.db.clone(), interval));
+        }
+        if let Some(interval) = self.config.sweep_interval() {
+            tokio::spawn(expiry::sweep_periodically(
+                self.db.clone(),
+                self.channels.clone(),
+                interval,
+            ));
+        }
+        let metrics = self.metrics.clone();
+        let max_connections = self.config.max_connections;
+        let server = HttpServer::new(move || self.clone().make_app())
+            .max_connections(max_connections)
+            .on_connect(move |_, extensions| metrics::track_connection(&metrics, extensions))
             .bind(addr)?
-            .run()
-            .await?;
+            .run();
+        return Ok(server);
+    }
 
-        Ok(())
+    /// Wait for a started server to stop, gracefully or not, then close the storage
+    #[allow(clippy::future_not_send)]
+    async fn close_when_stopped(
+        server: actix_web::dev::Server,
+        db: Arc<Box<dyn Storage>>,
+    ) -> Result<(), Error> {
+        let result = server.await;
+        db.close().await;
+        return Ok(result?);
     }
 
     fn config(self, cfg: &mut web::ServiceConfig) {
-        cfg.configure(move |cfg| info::Service::new().config(cfg));
-        cfg.configure(move |cfg| {
-            let query_service = queries::service::DatabaseQueries::new(self.db);
-            query_service.config(cfg);
-        });
+        let info_service = info::Service::new()
+            .with_config(&self.config)
+            .with_db(self.db.clone());
+        cfg.configure(move |cfg| info_service.config(cfg));
+        cfg.configure(move |cfg| health::Service::new(self.error_rate).config(cfg));
+        let stats_service = stats::Service::new(self.db.clone());
+        cfg.configure(move |cfg| stats_service.config(cfg));
+        let admin_service = admin::Service::new(self.db.clone(), self.config.admin_token.clone())
+            .with_throttle(self.auth_throttle);
+        cfg.configure(move |cfg| admin_service.config(cfg));
+        cfg.configure(move |cfg| channels::Service::new(self.channels).config(cfg));
+        cfg.configure(move |cfg| metrics::Service::new(self.metrics).config(cfg));
+        let query_service = queries::service::DatabaseQueries::new(self.db)
+            .with_cache_policy(self.config.cache_policy())
+            .with_int_encoding(self.config.int_encoding())
+            .with_op_timeout(self.config.op_timeout())
+            .with_body_limits(self.config.body_limits())
+            .with_collection_limit(self.config.collection_limit())
+            .with_max_response_bytes(self.config.max_response_bytes())
+            .with_delete_delimiter(self.config.delete_delimiter())
+            .with_missing_key_ttl(self.config.missing_key_ttl())
+            .with_backend_names(self.config.backend_names())
+            .with_ttl_rounding(self.config.ttl_rounding)
+            .with_type_case(self.config.type_case)
+            .with_tenant_header(self.config.tenant_header())
+            .with_debug_bodies(DebugBodies(self.config.debug_bodies));
+        cfg.configure(move |cfg| query_service.config(cfg));
         cfg.configure(move |cfg| docs::Service::new().config(cfg));
+        if self.config.enable_ui {
+            cfg.configure(move |cfg| ui::Service::new().config(cfg));
+        }
     }
 
     fn make_app(
@@ -55,6 +154,72 @@ impl Server {
     > {
         return App::new()
             .configure(|cfg: &mut web::ServiceConfig| self.config(cfg))
+            .wrap(from_fn(recovery::catch_panic))
+            .wrap(from_fn(metrics::track_in_flight))
             .wrap(Logger::default());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test;
+
+    use super::*;
+    use crate::storages::{bredis::Bredis, mock::SlowStorage};
+
+    #[actix_web::test]
+    async fn test_close_on_graceful_shutdown() {
+        let storage = SlowStorage::new(Box::new(Bredis::open()), Duration::ZERO);
+        let closes = storage.closes();
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(storage));
+        let config = Config {
+            bind: "127.0.0.1:0".to_string(),
+            max_connections: 16,
+            ..Config::default()
+        };
+        let error_rate = Arc::new(ErrorRate::new(Duration::from_mins(1), 1.0));
+        let server = Server::new(db.clone(), error_rate, config).start().unwrap();
+        let handle = server.handle();
+        let serving = actix_web::rt::spawn(Server::close_when_stopped(server, db.clone()));
+        assert_eq!(closes.load(Ordering::SeqCst), 0, "Closed while serving");
+
+        handle.stop(true).await;
+        serving.await.unwrap().unwrap();
+        assert_eq!(closes.load(Ordering::SeqCst), 1);
+
+        // Dropping the last handle doesn't close the storage again
+        drop(db);
+        assert_eq!(closes.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_ui_is_gated() {
+        for enable_ui in [true, false] {
+            let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(Bredis::open()));
+            let config = Config {
+                enable_ui,
+                ..Config::default()
+            };
+            let error_rate = Arc::new(ErrorRate::new(Duration::from_mins(1), 1.0));
+            let app = test::init_service(Server::new(db, error_rate, config).make_app()).await;
+
+            let req = test::TestRequest::get().uri("/ui").to_request();
+            let resp = test::call_service(&app, req).await;
+            if !enable_ui {
+                assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+                continue;
+            }
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_TYPE).unwrap(),
+                "text/html; charset=utf-8"
+            );
+            let body = test::read_body(resp).await;
+            assert!(body.starts_with(b"<!DOCTYPE html>"));
+        }
+    }
+}
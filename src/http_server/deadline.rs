@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::StatusCode,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+
+use super::models;
+
+/// The header carrying the client deadline, in milliseconds since the Unix epoch
+pub const DEADLINE_HEADER: &str = "X-Deadline-Ms";
+
+/// The longest a request may run, whatever deadline the client sends; `None` means no limit
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpTimeout(pub Option<Duration>);
+
+/// Resolve the time a request may still run
+///
+/// # Arguments
+/// * `deadline_ms` - The client deadline in milliseconds since the Unix epoch, if any
+/// * `now_ms` - The current time in milliseconds since the Unix epoch
+/// * `op_timeout` - The server limit of a single request, if any
+///
+/// # Returns
+/// The shorter of the two limits, zero if the deadline has already passed
+pub fn time_left(deadline_ms: Option<i64>, now_ms: i64, op_timeout: OpTimeout) -> Option<Duration> {
+    let client_timeout = deadline_ms
+        .map(|deadline_ms| Duration::from_millis(u64::try_from(deadline_ms - now_ms).unwrap_or(0)));
+
+    return match (client_timeout, op_timeout.0) {
+        (Some(client_timeout), Some(op_timeout)) => Some(client_timeout.min(op_timeout)),
+        (client_timeout, op_timeout) => client_timeout.or(op_timeout),
+    };
+}
+
+/// Stop handling a request once its deadline passes and answer it with 504
+///
+/// Dropping the handler future cancels the storage operation at its next await point,
+/// so no more work is spent on a request the client has given up on.
+pub async fn enforce(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let op_timeout = req
+        .app_data::<web::Data<OpTimeout>>()
+        .map_or_else(OpTimeout::default, |op_timeout| *op_timeout.get_ref());
+    let deadline_ms = req
+        .headers()
+        .get(DEADLINE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok());
+
+    let Some(time_left) = time_left(
+        deadline_ms,
+        chrono::Utc::now().timestamp_millis(),
+        op_timeout,
+    ) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    // The request is dropped with the handler, so the response travels as an error
+    let Ok(response) = tokio::time::timeout(time_left, next.call(req)).await else {
+        let error = models::ErrorResponse::new(
+            StatusCode::GATEWAY_TIMEOUT,
+            "The deadline of the request has passed",
+        );
+        let message = error.error.clone();
+        let response = HttpResponse::build(error.status).json(error);
+        return Err(InternalError::from_response(message, response).into());
+    };
+    return Ok(response?.map_into_boxed_body());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{body::to_bytes, test, App};
+
+    use super::*;
+    use crate::http_server::queries::service::DatabaseQueries;
+    use crate::storages::{bredis::Bredis, mock::SlowStorage, storage::Storage};
+
+    #[actix_web::test]
+    async fn test_time_left() {
+        let op_timeout = OpTimeout(Some(Duration::from_millis(500)));
+        assert_eq!(time_left(None, 1000, OpTimeout(None)), None);
+        assert_eq!(
+            time_left(None, 1000, op_timeout),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            time_left(Some(1100), 1000, op_timeout),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            time_left(Some(5000), 1000, op_timeout),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(time_left(Some(900), 1000, op_timeout), Some(Duration::ZERO));
+    }
+
+    #[actix_web::test]
+    async fn test_deadline_exceeded() {
+        let db: Box<dyn Storage> = Box::new(SlowStorage::new(
+            Box::new(Bredis::open()),
+            Duration::from_secs(5),
+        ));
+        let query_service = DatabaseQueries::new(Arc::new(db));
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        let deadline = chrono::Utc::now().timestamp_millis() + 50;
+        let req = test::TestRequest::get()
+            .uri("/keys/key1")
+            .insert_header((DEADLINE_HEADER, deadline.to_string()))
+            .to_request();
+        let resp = test::try_call_service(&app, req)
+            .await
+            .unwrap_err()
+            .error_response();
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let body: models::ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert!(!body.retryable);
+    }
+}
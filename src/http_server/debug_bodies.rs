@@ -0,0 +1,208 @@
+use std::pin::Pin;
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::{ErrorInternalServerError, PayloadError},
+    http::header::{self, HeaderMap},
+    middleware::Next,
+    web, Error, HttpMessage,
+};
+use futures::{Stream, StreamExt};
+
+/// Keys starting with this prefix are used by the server itself, e.g. `__expired__`
+pub const RESERVED_KEY_PREFIX: &str = "__";
+
+/// The most bytes of a body written to the log, the rest is only counted
+const LOGGED_BODY_BYTES: usize = 1024;
+
+/// Whether the bodies of key requests and responses are logged, off by default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugBodies(pub bool);
+
+/// Log the request and response bodies of key routes at debug level, when `--debug-bodies` is set
+///
+/// Bodies are cut to `LOGGED_BODY_BYTES` and the `Authorization` header is redacted.
+/// The bodies of requests naming a reserved key are never logged.
+pub async fn log_bodies(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let enabled = req
+        .app_data::<web::Data<DebugBodies>>()
+        .is_some_and(|debug_bodies| debug_bodies.0);
+    // Buffering the bodies is only worth it when the records are written
+    if !enabled || !log::log_enabled!(log::Level::Debug) {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    let mut payload = req.take_payload();
+    let mut request_body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        request_body.extend_from_slice(&chunk?);
+    }
+    let request_body = request_body.freeze();
+    let replayed: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> = Box::pin(
+        futures::stream::once(futures::future::ready(Ok(request_body.clone()))),
+    );
+    req.set_payload(Payload::from(replayed));
+
+    let target = req.uri().to_string();
+    let method = req.method().clone();
+    let headers = redacted_headers(req.headers());
+
+    let (http_req, response) = next.call(req).await?.map_into_boxed_body().into_parts();
+    let (response, response_body) = response.into_parts();
+    let response_body = body::to_bytes(response_body)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let reserved = mentions_reserved_key(target.as_bytes())
+        || mentions_reserved_key(&request_body)
+        || mentions_reserved_key(&response_body);
+    log::debug!(
+        "{method} {target} headers={headers:?} request={} response={} {}",
+        describe_body(&request_body, reserved),
+        response.status().as_u16(),
+        describe_body(&response_body, reserved),
+    );
+
+    let response = response.set_body(response_body).map_into_boxed_body();
+    return Ok(ServiceResponse::new(http_req, response));
+}
+
+/// The headers of a request as `name: value` pairs, with the bearer token replaced
+fn redacted_headers(headers: &HeaderMap) -> Vec<String> {
+    return headers
+        .iter()
+        .map(|(name, value)| {
+            if *name == header::AUTHORIZATION {
+                return format!("{name}: <redacted>");
+            }
+            return format!("{name}: {}", value.to_str().unwrap_or("<binary>"));
+        })
+        .collect();
+}
+
+/// Whether a request target or a JSON body names a key of the reserved namespace,
+/// as a path segment, a query value or a JSON string
+fn mentions_reserved_key(bytes: &[u8]) -> bool {
+    return ['/', '=', '"'].iter().any(|separator| {
+        let marker = format!("{separator}{RESERVED_KEY_PREFIX}");
+        return bytes
+            .windows(marker.len())
+            .any(|window| window == marker.as_bytes());
+    });
+}
+
+/// The text of a body cut to `LOGGED_BODY_BYTES`, or a placeholder if it must not be logged
+fn describe_body(bytes: &[u8], reserved: bool) -> String {
+    if reserved {
+        return format!("<{} bytes, reserved key>", bytes.len());
+    }
+    if bytes.len() <= LOGGED_BODY_BYTES {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    return format!(
+        "{}... <{} bytes>",
+        String::from_utf8_lossy(&bytes[..LOGGED_BODY_BYTES]),
+        bytes.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::http_server::{models, queries::service::DatabaseQueries};
+    use crate::storages::{bredis::Bredis, storage::Storage};
+
+    /// Keeps the debug records logged by the tests of this module
+    struct CapturingLogger {
+        records: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            return metadata.level() <= log::Level::Debug;
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.records.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    async fn set_key(debug_bodies: bool, key: &str, value: &str) {
+        let db: Box<dyn Storage> = Box::new(Bredis::open());
+        let query_service =
+            DatabaseQueries::new(Arc::new(db)).with_debug_bodies(DebugBodies(debug_bodies));
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/keys")
+            .insert_header((header::AUTHORIZATION, "Bearer s3cr3t-token"))
+            .set_json(models::SetRequest {
+                key: key.to_string(),
+                value: models::IntOrString::String(value.to_string()),
+                ttl: -1,
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn test_log_bodies() {
+        // Another test may have installed the logger already
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Debug);
+
+        set_key(true, "logged_key", "logged_value").await;
+        set_key(false, "quiet_key", "quiet_value").await;
+        set_key(true, "__reserved__", "reserved_value").await;
+
+        let records = LOGGER.records.lock().unwrap();
+        let logged = records
+            .iter()
+            .find(|record| record.contains("logged_value"))
+            .unwrap_or_else(|| panic!("The body is not logged: {records:?}"));
+        assert!(logged.starts_with("POST /keys"), "{logged}");
+        assert!(
+            logged.contains("response=200 {\"success\":true}"),
+            "{logged}"
+        );
+        assert!(logged.contains("authorization: <redacted>"), "{logged}");
+        assert!(!logged.contains("s3cr3t-token"), "{logged}");
+
+        for hidden in ["quiet_value", "reserved_value", "s3cr3t-token"] {
+            assert!(
+                !records.iter().any(|record| record.contains(hidden)),
+                "{hidden} is logged: {records:?}"
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_describe_body() {
+        let body = vec![b'a'; LOGGED_BODY_BYTES + 10];
+        let described = describe_body(&body, false);
+        assert!(described.ends_with(&format!("... <{} bytes>", body.len())));
+        assert_eq!(describe_body(b"{}", true), "<2 bytes, reserved key>");
+
+        assert!(mentions_reserved_key(b"/keys/__hashed__:abc"));
+        assert!(mentions_reserved_key(b"/keys?prefix=__"));
+        assert!(mentions_reserved_key(b"{\"key\":\"__expired__\"}"));
+        assert!(!mentions_reserved_key(b"/keys/my__key"));
+    }
+}
@@ -3,33 +3,587 @@ use utoipa::OpenApi;
 use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::SwaggerUi;
 
+use super::models;
+
+/// The `OpenAPI` document of every route served by the HTTP server.
+///
+/// The handlers are associated functions of their services, which `utoipa::path` can't annotate,
+/// so each route is described by a stub function in `routes`. Keep them in sync with the services.
 #[derive(OpenApi)]
-#[openapi()]
+#[openapi(
+    paths(
+        routes::info,
+        routes::ready,
+        routes::metrics,
+        routes::errors,
+        routes::keyspace_stats,
+        routes::get_all_keys,
+        routes::set_key,
+        routes::set_many_with_ttl,
+        routes::delete_keys,
+        routes::get_keys_changed_since,
+        routes::get_keys_range,
+        routes::get_by_key,
+        routes::get_ex,
+        routes::get_raw,
+        routes::set_raw,
+        routes::delete_key,
+        routes::increment,
+        routes::get_many,
+        routes::increment_many,
+        routes::decrement,
+        routes::decrement_and_delete,
+        routes::set_max,
+        routes::set_min,
+        routes::compare_and_increment,
+        routes::rename_nx,
+        routes::lock,
+        routes::unlock,
+        routes::get_json,
+        routes::merge_json,
+        routes::get_list,
+        routes::list_push,
+        routes::append,
+        routes::get_hash,
+        routes::hset_many,
+        routes::get_type,
+        routes::get_object,
+        routes::get_ttl,
+        routes::set_ttl,
+        routes::adjust_ttl,
+        routes::checkpoint,
+        routes::sync,
+        routes::evict,
+        routes::publish,
+        routes::subscribe,
+    ),
+    components(schemas(models::KeysWithTtlResponse))
+)]
 struct ApiDoc;
+/// Build the `OpenAPI` document served by the docs service
+///
+/// # Returns
+/// The document of every route of the HTTP server
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    return ApiDoc::openapi();
+}
+
 pub struct Service;
 
 impl Service {
-    /// Creates a new instance of the `InfoService`.
+    /// Creates a new instance of the docs service.
     ///
     /// # Returns
     ///
-    /// A new instance of the `InfoService`.
+    /// A new instance of the docs service.
     #[must_use]
     pub const fn new() -> Self {
         return Self;
     }
 
-    /// Configures the `InfoService` with the given `ServiceConfig`.
+    /// Configures the docs service with the given `ServiceConfig`.
+    ///
+    /// The `OpenAPI` document is served at `/openapi.json` and rendered by Redoc at `/docs`
+    /// and by Swagger UI at `/swagger-ui/`.
     ///
     /// # Arguments
     ///
     /// * `cfg` - The `ServiceConfig` to configure.
     #[allow(clippy::unused_self)]
     pub fn config(self, cfg: &mut web::ServiceConfig) {
-        let openapi = ApiDoc::openapi();
-        cfg.service(
-            SwaggerUi::new("/swagger-ui/{_:.*}").url("/docs/openapi.json", openapi.clone()),
-        )
-        .service(Redoc::with_url("/docs", openapi));
+        let openapi = openapi();
+        cfg.service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", openapi.clone()))
+            .service(Redoc::with_url("/docs", openapi))
+            .service(web::resource("/errors").route(
+                web::get().to(|| async { web::Json(models::ErrorCatalogResponse::build()) }),
+            ));
+    }
+}
+
+#[allow(dead_code)]
+mod routes {
+    use crate::http_server::models;
+
+    /// Get the server version
+    #[utoipa::path(get, path = "/info", tag = "server", responses(
+        (status = 200, description = "Server version", body = models::InfoResponse),
+    ))]
+    fn info() {}
+
+    /// Check whether the server is ready to receive traffic
+    #[utoipa::path(get, path = "/ready", tag = "server", responses(
+        (status = 200, description = "The backend error rate is below the threshold", body = models::ReadyResponse),
+        (status = 503, description = "The backend error rate is above the threshold", body = models::ReadyResponse),
+    ))]
+    fn ready() {}
+
+    /// Get the server metrics
+    #[utoipa::path(get, path = "/metrics", tag = "server", responses(
+        (status = 200, description = "Requests in flight and open connections, in the Prometheus text format", content_type = "text/plain", body = String),
+    ))]
+    fn metrics() {}
+
+    /// List every kind of error the storage can fail with
+    #[utoipa::path(get, path = "/errors", tag = "server", responses(
+        (status = 200, description = "The code, HTTP status, description and retryability of every error", body = models::ErrorCatalogResponse),
+    ))]
+    fn errors() {}
+
+    /// Count the keys and their sizes by value type
+    #[utoipa::path(get, path = "/stats/keyspace", tag = "server",
+        params(("sample" = Option<usize>, Query, description = "Read only this many keys and extrapolate from them")),
+        responses((status = 200, description = "The number of keys and their size in bytes, by value type", body = models::KeyspaceResponse)),
+    )]
+    fn keyspace_stats() {}
+
+    /// Get all keys starting with a prefix
+    #[utoipa::path(get, path = "/keys", tag = "keys",
+        params(
+            ("prefix" = String, Query, description = "Prefix of the keys"),
+            ("delimiter" = Option<String>, Query, description = "Fold the keys with this separator after the prefix into `common_prefixes`"),
+            ("cursor" = Option<String>, Query, description = "The first key of the page, the `next_cursor` of the previous page"),
+            ("limit" = Option<usize>, Query, description = "Maximum number of keys of the page"),
+            ("with_ttl" = Option<bool>, Query, description = "List every key as `{key, ttl}`, see `KeysWithTtlResponse`"),
+        ),
+        responses((status = 200, description = "Matching keys", body = models::GetAllKeysResponse)),
+    )]
+    fn get_all_keys() {}
+
+    /// Set the value of a key
+    #[utoipa::path(post, path = "/keys", tag = "keys",
+        params(
+            ("coerce" = Option<bool>, Query, description = "Store numeric strings as integers"),
+            ("keep_ttl" = Option<bool>, Query, description = "Keep the expiry of the value being replaced"),
+        ),
+        request_body = models::SetRequest,
+        responses((status = 200, description = "The key is set", body = models::OperationSuccessResponse)),
+    )]
+    fn set_key() {}
+
+    /// Set the values of several keys atomically, all of them with the same TTL
+    #[utoipa::path(post, path = "/keys/mset-ttl", tag = "keys",
+        request_body = models::SetManyWithTtlRequest,
+        responses(
+            (status = 200, description = "Every key is set", body = models::OperationSuccessResponse),
+            (status = 400, description = "A value is invalid, nothing is written", body = models::ErrorResponse),
+        ),
+    )]
+    fn set_many_with_ttl() {}
+
+    /// Delete all keys starting with a prefix
+    #[utoipa::path(delete, path = "/keys", tag = "keys",
+        params(
+            ("confirm_all" = Option<bool>, Query, description = "Allow an empty prefix, which deletes every key"),
+            ("force" = Option<bool>, Query, description = "Allow a prefix that doesn't end in the delimiter the server requires"),
+        ),
+        request_body = models::DeleteKeysRequest,
+        responses(
+            (status = 200, description = "The keys are deleted", body = models::OperationSuccessResponse),
+            (status = 400, description = "The prefix is empty and `confirm_all` is not set, or doesn't end in the required delimiter and `force` is not set", body = models::ErrorResponse),
+        ),
+    )]
+    fn delete_keys() {}
+
+    /// Get the keys written after a point in time
+    #[utoipa::path(get, path = "/keys/changed-since", tag = "keys",
+        params(("ts" = i64, Query, description = "Milliseconds since the Unix epoch")),
+        responses((status = 200, description = "Keys written after `ts`", body = models::GetAllKeysResponse)),
+    )]
+    fn get_keys_changed_since() {}
+
+    /// Get the keys from `start` (inclusive) to `end` (exclusive) in lexicographic order
+    #[utoipa::path(get, path = "/keys/range", tag = "keys",
+        params(
+            ("start" = String, Query, description = "First key of the range, inclusive"),
+            ("end" = String, Query, description = "End of the range, exclusive"),
+            ("limit" = Option<usize>, Query, description = "Maximum number of keys of the page"),
+            ("cursor" = Option<String>, Query, description = "The first key of the page, the `next_cursor` of the previous page"),
+            ("with_ttl" = Option<bool>, Query, description = "List every key as `{key, ttl}`, see `KeysWithTtlResponse`"),
+        ),
+        responses((status = 200, description = "Keys in the range", body = models::GetAllKeysResponse)),
+    )]
+    fn get_keys_range() {}
+
+    /// Get the value of a key
+    #[utoipa::path(get, path = "/keys/{key_name}", tag = "keys",
+        params(
+            ("key_name" = String, Path, description = "Key"),
+            ("If-None-Match" = Option<String>, Header, description = "The `ETag` of a cached copy of the value"),
+        ),
+        responses(
+            (status = 200, description = "The value, null if the key doesn't exist", body = models::GetResponse),
+            (status = 304, description = "The value still matches `If-None-Match`"),
+        ),
+    )]
+    fn get_by_key() {}
+
+    /// Get the value of a key and set or remove its TTL at the same time
+    #[utoipa::path(post, path = "/keys/{key_name}/getex", tag = "ttl",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::GetExRequest,
+        responses((status = 200, description = "The value, null if the key doesn't exist", body = models::GetResponse)),
+    )]
+    fn get_ex() {}
+
+    /// Stream the stored bytes of a value, without wrapping them in JSON
+    #[utoipa::path(get, path = "/keys/{key_name}/raw", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        responses(
+            (status = 200, description = "The stored bytes", content_type = "text/plain", body = String),
+            (status = 404, description = "The key doesn't exist", body = models::ErrorResponse),
+        ),
+    )]
+    fn get_raw() {}
+
+    /// Store the request body as a blob, without JSON wrapping
+    #[utoipa::path(put, path = "/keys/{key_name}/raw", tag = "keys",
+        params(
+            ("key_name" = String, Path, description = "Key"),
+            ("ttl" = Option<String>, Query, description = "Seconds or a duration like `2h`, the value never expires without it"),
+        ),
+        request_body = Vec<u8>,
+        responses((status = 200, description = "The value is stored", body = models::OperationSuccessResponse)),
+    )]
+    fn set_raw() {}
+
+    /// Delete a key
+    #[utoipa::path(delete, path = "/keys/{key_name}", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        responses((status = 200, description = "The key is deleted", body = models::OperationSuccessResponse)),
+    )]
+    fn delete_key() {}
+
+    /// Increment an integer value
+    #[utoipa::path(post, path = "/keys/{key_name}/inc", tag = "integers",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::IncrementRequest,
+        responses((status = 200, description = "The new value", body = models::IncrementResponse)),
+    )]
+    fn increment() {}
+
+    /// Get the values of several keys as of a single point in time
+    #[utoipa::path(post, path = "/keys/mget", tag = "keys",
+        request_body = models::GetManyRequest,
+        responses((status = 200, description = "The values, in the order of the keys", body = models::GetManyResponse)),
+    )]
+    fn get_many() {}
+
+    /// Increment several integers, all-or-nothing unless `lenient` is set
+    #[utoipa::path(post, path = "/keys/inc", tag = "integers",
+        request_body = models::IncrementManyRequest,
+        responses(
+            (status = 200, description = "The new values, and the failed keys in lenient mode", body = models::IncrementManyResponse),
+            (status = 400, description = "A value of a strict batch is not an integer, nothing is written", body = models::ErrorResponse),
+        ),
+    )]
+    fn increment_many() {}
+
+    /// Decrement an integer value
+    #[utoipa::path(post, path = "/keys/{key_name}/dec", tag = "integers",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::IncrementRequest,
+        responses((status = 200, description = "The new value", body = models::IncrementResponse)),
+    )]
+    fn decrement() {}
+
+    /// Decrement an integer and delete the key once it drops to 0 or below
+    #[utoipa::path(post, path = "/keys/{key_name}/decr-del-on-zero", tag = "integers",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::DecrementAndDeleteRequest,
+        responses(
+            (status = 200, description = "The new value and whether the key was deleted", body = models::DecrementAndDeleteResponse),
+            (status = 404, description = "The key doesn't exist", body = models::ErrorResponse),
+        ),
+    )]
+    fn decrement_and_delete() {}
+
+    /// Set an integer only if the value is larger than the stored one
+    #[utoipa::path(post, path = "/keys/{key_name}/setmax", tag = "integers",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::SetExtremeRequest,
+        responses(
+            (status = 200, description = "The stored value and whether it changed", body = models::SetExtremeResponse),
+            (status = 400, description = "The key holds a value of another type", body = models::ErrorResponse),
+        ),
+    )]
+    fn set_max() {}
+
+    /// Set an integer only if the value is smaller than the stored one
+    #[utoipa::path(post, path = "/keys/{key_name}/setmin", tag = "integers",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::SetExtremeRequest,
+        responses(
+            (status = 200, description = "The stored value and whether it changed", body = models::SetExtremeResponse),
+            (status = 400, description = "The key holds a value of another type", body = models::ErrorResponse),
+        ),
+    )]
+    fn set_min() {}
+
+    /// Add to an integer only if it holds the expected value
+    #[utoipa::path(post, path = "/keys/{key_name}/cas-incr", tag = "integers",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::CompareAndIncrementRequest,
+        responses(
+            (status = 200, description = "The stored value and whether it was incremented", body = models::CompareAndIncrementResponse),
+            (status = 400, description = "The key holds a value of another type", body = models::ErrorResponse),
+            (status = 404, description = "The key doesn't exist", body = models::ErrorResponse),
+        ),
+    )]
+    fn compare_and_increment() {}
+
+    /// Rename a key unless the new key already exists
+    #[utoipa::path(post, path = "/keys/{key_name}/renamenx", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::RenameRequest,
+        responses((status = 200, description = "Whether the key was renamed", body = models::RenameResponse)),
+    )]
+    fn rename_nx() {}
+
+    /// Acquire a lease on a key, answering the token it is released with
+    #[utoipa::path(post, path = "/keys/{key_name}/lock", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::LockRequest,
+        responses(
+            (status = 200, description = "The lock is acquired", body = models::LockResponse),
+            (status = 409, description = "The lock is held", body = models::ErrorResponse),
+        ),
+    )]
+    fn lock() {}
+
+    /// Release a lease on a key, only if the token matches
+    #[utoipa::path(post, path = "/keys/{key_name}/unlock", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::UnlockRequest,
+        responses(
+            (status = 200, description = "The lock is released", body = models::OperationSuccessResponse),
+            (status = 409, description = "The lock is not held with this token", body = models::ErrorResponse),
+        ),
+    )]
+    fn unlock() {}
+
+    /// Get a part of a JSON document
+    #[utoipa::path(get, path = "/keys/{key_name}/json", tag = "json",
+        params(
+            ("key_name" = String, Path, description = "Key"),
+            ("path" = Option<String>, Query, description = "JSONPath of the part, `$` by default"),
+        ),
+        responses((status = 200, description = "The part of the document", body = models::JsonQueryResponse)),
+    )]
+    fn get_json() {}
+
+    /// Merge a JSON merge-patch (RFC 7386) into a JSON document
+    #[utoipa::path(patch, path = "/keys/{key_name}/json", tag = "json",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body(content = Object, description = "The patch; a null member removes the member"),
+        responses((status = 200, description = "The patched document", body = models::JsonQueryResponse)),
+    )]
+    fn merge_json() {}
+
+    /// Get the elements of a list
+    #[utoipa::path(get, path = "/keys/{key_name}/list", tag = "lists",
+        params(("key_name" = String, Path, description = "Key")),
+        responses((status = 200, description = "The elements, null if the key doesn't exist", body = models::ListResponse)),
+    )]
+    fn get_list() {}
+
+    /// Append elements to a list, keeping at most `maxlen` of the most recent ones
+    #[utoipa::path(post, path = "/keys/{key_name}/list", tag = "lists",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::ListPushRequest,
+        responses((status = 200, description = "The new length of the list", body = models::ListPushResponse)),
+    )]
+    fn list_push() {}
+
+    /// Append text to a string and optionally refresh its TTL in the same write
+    #[utoipa::path(post, path = "/keys/{key_name}/append", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::AppendRequest,
+        responses((status = 200, description = "The new length of the string in bytes", body = models::AppendResponse)),
+    )]
+    fn append() {}
+
+    /// Get the fields of a hash
+    #[utoipa::path(get, path = "/keys/{key_name}/hash", tag = "hashes",
+        params(("key_name" = String, Path, description = "Key")),
+        responses((status = 200, description = "The fields, null if the key doesn't exist", body = models::HashResponse)),
+    )]
+    fn get_hash() {}
+
+    /// Set several fields of a hash at once, creating the hash if the key doesn't exist
+    #[utoipa::path(post, path = "/keys/{key_name}/hash", tag = "hashes",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::HashSetRequest,
+        responses(
+            (status = 200, description = "The numbers of added and updated fields", body = models::HashSetResponse),
+            (status = 400, description = "The key holds another type", body = models::ErrorResponse),
+            (status = 409, description = "The hash would hold more fields than --max-collection-elements", body = models::ErrorResponse),
+        ),
+    )]
+    fn hset_many() {}
+
+    /// Get the type of the value of a key
+    #[utoipa::path(get, path = "/keys/{key_name}/type", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        responses((status = 200, description = "One of String, Integer, Json, List, Blob and Hash, null if the key doesn't exist", body = models::TypeResponse)),
+    )]
+    fn get_type() {}
+
+    /// Get the metadata of a key without its value
+    #[utoipa::path(get, path = "/keys/{key_name}/object", tag = "keys",
+        params(("key_name" = String, Path, description = "Key")),
+        responses(
+            (status = 200, description = "The type, stored size and expiry of the key", body = models::ObjectResponse),
+            (status = 404, description = "The key doesn't exist", body = models::ErrorResponse),
+        ),
+    )]
+    fn get_object() {}
+
+    /// Get the remaining TTL of a key, or its expiry time with `absolute=true`
+    #[utoipa::path(get, path = "/keys/{key_name}/ttl", tag = "ttl",
+        params(
+            ("key_name" = String, Path, description = "Key"),
+            ("absolute" = Option<bool>, Query, description = "Report the expiry as a Unix timestamp in seconds"),
+        ),
+        responses((status = 200, description = "Remaining TTL in seconds, -1 if the key never expires", body = models::GetTtlResponse)),
+    )]
+    fn get_ttl() {}
+
+    /// Set the TTL of a key
+    #[utoipa::path(post, path = "/keys/{key_name}/ttl", tag = "ttl",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::SetTtlRequest,
+        responses((status = 200, description = "The TTL is set", body = models::OperationSuccessResponse)),
+    )]
+    fn set_ttl() {}
+
+    /// Extend or shorten the TTL of a key
+    #[utoipa::path(patch, path = "/keys/{key_name}/ttl", tag = "ttl",
+        params(("key_name" = String, Path, description = "Key")),
+        request_body = models::AdjustTtlRequest,
+        responses((status = 200, description = "The new TTL", body = models::GetTtlResponse)),
+    )]
+    fn adjust_ttl() {}
+
+    /// Write a point-in-time copy of the database into a directory on the server
+    ///
+    /// Requires `Authorization: Bearer <token>` matching `--admin-token`.
+    #[utoipa::path(post, path = "/admin/checkpoint", tag = "admin",
+        request_body = models::CheckpointRequest,
+        responses(
+            (status = 200, description = "The copy is written", body = models::OperationSuccessResponse),
+            (status = 401, description = "The admin token is missing or wrong", body = models::ErrorResponse),
+            (status = 403, description = "The server was started without an admin token", body = models::ErrorResponse),
+            (status = 501, description = "The backend can't write checkpoints", body = models::ErrorResponse),
+        ),
+    )]
+    fn checkpoint() {}
+
+    /// Persist every acknowledged write durably, a barrier for writes made without `--sync-writes`
+    ///
+    /// Requires `Authorization: Bearer <token>` matching `--admin-token`.
+    #[utoipa::path(post, path = "/admin/sync", tag = "admin",
+        responses(
+            (status = 200, description = "The data is on disk", body = models::OperationSuccessResponse),
+            (status = 401, description = "The admin token is missing or wrong", body = models::ErrorResponse),
+            (status = 403, description = "The server was started without an admin token", body = models::ErrorResponse),
+        ),
+    )]
+    fn sync() {}
+
+    /// Delete every key that expires within `max_ttl` seconds, or every key with a TTL
+    ///
+    /// Requires `Authorization: Bearer <token>` matching `--admin-token`.
+    #[utoipa::path(post, path = "/admin/evict", tag = "admin",
+        request_body = models::EvictRequest,
+        responses(
+            (status = 200, description = "The number of deleted keys", body = models::EvictResponse),
+            (status = 401, description = "The admin token is missing or wrong", body = models::ErrorResponse),
+            (status = 403, description = "The server was started without an admin token", body = models::ErrorResponse),
+        ),
+    )]
+    fn evict() {}
+
+    /// Publish a message to the current subscribers of a channel
+    #[utoipa::path(post, path = "/channels/{name}/publish", tag = "channels",
+        params(("name" = String, Path, description = "Channel")),
+        request_body = models::PublishRequest,
+        responses((status = 200, description = "The message is delivered", body = models::PublishResponse)),
+    )]
+    fn publish() {}
+
+    /// Subscribe to a channel as a stream of server-sent events
+    #[utoipa::path(get, path = "/channels/{name}/subscribe", tag = "channels",
+        params(("name" = String, Path, description = "Channel")),
+        responses((status = 200, description = "One `data:` event per published message", content_type = "text/event-stream", body = String)),
+    )]
+    fn subscribe() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::errors::DatabaseError;
+
+    #[actix_web::test]
+    async fn test_error_catalog() {
+        let app = test::init_service(App::new().configure(|cfg| Service::new().config(cfg))).await;
+        let req = test::TestRequest::get().uri("/errors").to_request();
+        let catalog: models::ErrorCatalogResponse = test::call_and_read_body_json(&app, req).await;
+
+        for err in DatabaseError::kinds() {
+            let entry = catalog
+                .errors
+                .iter()
+                .find(|entry| entry.code == err.code())
+                .unwrap_or_else(|| panic!("{} is missing", err.code()));
+            assert_eq!(
+                entry.status,
+                models::ErrorResponse::from(&err).status.as_u16()
+            );
+            assert_eq!(entry.retryable, err.is_retryable());
+            assert!(!entry.description.is_empty());
+        }
+        let conflict = catalog.errors.iter().find(|entry| entry.code == "conflict");
+        assert!(conflict.is_some_and(|entry| entry.status == 503 && entry.retryable));
+    }
+
+    #[actix_web::test]
+    async fn test_openapi_documents_routes() {
+        let app = test::init_service(App::new().configure(|cfg| Service::new().config(cfg))).await;
+        let req = test::TestRequest::get().uri("/openapi.json").to_request();
+        let openapi: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        for (path, method) in [
+            ("/keys", "get"),
+            ("/keys", "post"),
+            ("/keys/{key_name}", "get"),
+            ("/keys/{key_name}/inc", "post"),
+            ("/keys/{key_name}/ttl", "patch"),
+            ("/keys/range", "get"),
+            ("/ready", "get"),
+            ("/stats/keyspace", "get"),
+            ("/errors", "get"),
+        ] {
+            assert!(
+                openapi["paths"][path][method].is_object(),
+                "{method} {path} is not documented"
+            );
+        }
+
+        let set_body = &openapi["paths"]["/keys"]["post"]["requestBody"];
+        assert!(set_body.to_string().contains("SetRequest"), "{set_body}");
+        for schema in [
+            "SetRequest",
+            "GetResponse",
+            "IncrementRequest",
+            "GetTtlResponse",
+        ] {
+            assert!(
+                openapi["components"]["schemas"][schema].is_object(),
+                "{schema} schema is missing"
+            );
+        }
     }
 }
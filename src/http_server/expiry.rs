@@ -0,0 +1,81 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::MissedTickBehavior;
+
+use super::channels::Channels;
+use crate::errors::DatabaseError;
+use crate::storages::storage::Storage;
+
+/// The channel the sweeper announces every key it deletes on, the message is the key name
+pub const EXPIRED_CHANNEL: &str = "__expired__";
+
+/// Delete the expired keys and announce each of them on `EXPIRED_CHANNEL`
+///
+/// # Arguments
+/// * `db` - The storage to sweep
+/// * `channels` - The channels to publish the expiry events to
+///
+/// # Returns
+/// A Result containing the number of deleted keys or a `DatabaseError`
+pub async fn sweep(db: &dyn Storage, channels: &Channels) -> Result<usize, DatabaseError> {
+    let expired = db.delete_expired().await?;
+    for key in &expired {
+        channels.publish(EXPIRED_CHANNEL, key.clone());
+    }
+    return Ok(expired.len());
+}
+
+/// Sweep the expired keys every `interval` for as long as the server runs
+///
+/// Without the sweeper, expired keys are only hidden from reads and no expiry event is published.
+pub async fn sweep_periodically(
+    db: Arc<Box<dyn Storage>>,
+    channels: Arc<Channels>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        match sweep(&**db, &channels).await {
+            Ok(0) => {}
+            Ok(count) => log::debug!("Swept {count} expired keys"),
+            Err(err) => log::error!("Error sweeping expired keys: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::{bredis::Bredis, value::StorageValue};
+
+    #[actix_web::test]
+    async fn test_expired_key_is_announced() {
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(Bredis::open()));
+        let channels = Arc::new(Channels::default());
+        let mut expired = channels.subscribe(EXPIRED_CHANNEL);
+
+        let value = StorageValue {
+            ttl: 1,
+            ..StorageValue::string("token")
+        };
+        db.set(b"session", &value).await.unwrap();
+        db.set(b"user", &StorageValue::string("alice"))
+            .await
+            .unwrap();
+        tokio::spawn(sweep_periodically(
+            db.clone(),
+            channels.clone(),
+            Duration::from_millis(100),
+        ));
+
+        let key = tokio::time::timeout(Duration::from_secs(5), expired.recv())
+            .await
+            .expect("No expiry event was published")
+            .unwrap();
+        assert_eq!(key, "session");
+        assert!(db.get(b"session").await.unwrap().is_none());
+        assert!(db.get(b"user").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,65 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::MissedTickBehavior;
+
+use crate::storages::storage::Storage;
+
+/// Flush the memtables of the backend every `interval` for as long as the server runs
+///
+/// After a burst of writes followed by a quiet period, the writes stay in memory and in the
+/// WAL until the memtables fill up. A scheduled flush bounds how long they stay there.
+/// Backends without memtables ignore the flush.
+pub async fn flush_periodically(db: Arc<Box<dyn Storage>>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick completes right away, and there is nothing to flush at startup
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        match db.flush_memtables().await {
+            Ok(()) => log::debug!("Flushed the memtables"),
+            Err(err) => log::error!("Error flushing the memtables: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::{rocksdb::Rocksdb, value::StorageValue};
+
+    /// The number of SST files of a `RocksDB` store, which a memtable flush adds to
+    fn sst_files(path: &str) -> usize {
+        return std::fs::read_dir(path)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .is_ok_and(|entry| entry.path().extension().is_some_and(|ext| ext == "sst"))
+            })
+            .count();
+    }
+
+    #[actix_web::test]
+    async fn test_flush_periodically() {
+        let rocksdb = Rocksdb::open_ephemeral().unwrap();
+        let db_path = rocksdb.path().to_string();
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(rocksdb));
+        db.set(b"burst", &StorageValue::string("value"))
+            .await
+            .unwrap();
+        assert_eq!(sst_files(&db_path), 0, "The write is already flushed");
+
+        tokio::spawn(flush_periodically(db.clone(), Duration::from_millis(100)));
+        let flushed = tokio::time::timeout(Duration::from_secs(5), async {
+            while sst_files(&db_path) == 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        assert!(flushed.is_ok(), "The memtables were not flushed");
+
+        let value = db.get(b"burst").await.unwrap().unwrap();
+        assert_eq!(value.value, b"value");
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+
+use crate::storages::monitored::ErrorRate;
+
+use super::models;
+
+/// Represents the readiness service.
+///
+/// The server reports itself as not ready while the backend error rate exceeds the
+/// configured threshold, so a load balancer can take the instance out of rotation.
+pub struct Service {
+    error_rate: Arc<ErrorRate>,
+}
+
+impl Service {
+    /// Creates a new instance of the readiness service.
+    ///
+    /// # Arguments
+    ///
+    /// * `error_rate` - The rolling backend error rate to report on.
+    #[must_use]
+    pub const fn new(error_rate: Arc<ErrorRate>) -> Self {
+        return Self { error_rate };
+    }
+
+    /// Configures the readiness service with the given `ServiceConfig`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The `ServiceConfig` to configure.
+    pub fn config(self, cfg: &mut web::ServiceConfig) {
+        let self_clone = Arc::new(self);
+        cfg.service(web::resource("/ready").route(web::get().to(move || {
+            let self_clone = self_clone.clone();
+            async move { self_clone.ready().await }
+        })));
+    }
+
+    /// Reports whether the server is ready to receive traffic.
+    ///
+    /// # Returns
+    ///
+    /// 200 with the current error rate when healthy, 503 otherwise.
+    pub async fn ready(&self) -> HttpResponse {
+        let ready = self.error_rate.is_healthy();
+        let response = models::ReadyResponse {
+            ready,
+            error_rate: self.error_rate.rate(),
+        };
+
+        if ready {
+            return HttpResponse::Ok().json(response);
+        }
+        return HttpResponse::ServiceUnavailable().json(response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{http::StatusCode, test, App};
+
+    use super::*;
+    use crate::http_server::queries::service::DatabaseQueries;
+    use crate::storages::{mock::FailingStorage, monitored::Monitored, storage::Storage};
+
+    #[actix_web::test]
+    async fn test_ready_flips_on_backend_errors() {
+        let error_rate = Arc::new(ErrorRate::new(Duration::from_mins(1), 0.5));
+        let db: Box<dyn Storage> =
+            Box::new(Monitored::new(Box::new(FailingStorage), error_rate.clone()));
+        let query_service = DatabaseQueries::new(Arc::new(db));
+        let health_service = Service::new(error_rate);
+        let app = test::init_service(App::new().configure(|cfg| {
+            query_service.config(cfg);
+            health_service.config(cfg);
+        }))
+        .await;
+
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        for _ in 0..3 {
+            let req = test::TestRequest::get().uri("/keys/key1").to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body: models::ReadyResponse = test::read_body_json(resp).await;
+        assert!(!body.ready);
+    }
+}
@@ -2,10 +2,15 @@ use std::sync::Arc;
 
 use actix_web::{web, Responder};
 
+use crate::config::Config;
+use crate::storages::storage::Storage;
+
 use super::models;
 
 pub struct Service {
     info: crate::info::Info,
+    config: Option<models::ConfigResponse>,
+    db: Option<Arc<Box<dyn Storage>>>,
 }
 
 /// Represents the Info service.
@@ -21,9 +26,76 @@ impl Service {
     pub fn new() -> Self {
         return Self {
             info: crate::info::Info::default(),
+            config: None,
+            db: None,
         };
     }
 
+    /// Report the effective configuration of the server.
+    ///
+    /// Secrets such as the admin token are left out; only whether they are set is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The resolved configuration the server runs with.
+    #[must_use]
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.config = Some(models::ConfigResponse {
+            bind: config.bind.clone(),
+            backend: config.backend.clone(),
+            data_dir: config.data_dir.clone(),
+            hash_keys_over: config.hash_keys_over,
+            sync_writes: config.sync_writes,
+            skip_read_checksums: config.skip_read_checksums,
+            persistent: config.persistent,
+            snapshot_on_exit: config.snapshot_on_exit.clone(),
+            unhealthy_error_rate: config.unhealthy_error_rate,
+            error_rate_window: config.error_rate_window,
+            no_store_prefixes: config.no_store_prefixes.clone(),
+            int_as_string: config.int_as_string,
+            admin_auth_enabled: config.admin_token.is_some(),
+            auth_max_attempts: config.auth_max_attempts,
+            auth_window: config.auth_window,
+            op_timeout: config.op_timeout,
+            slow_op_threshold: config.slow_op_threshold,
+            sweep_interval: config.sweep_interval,
+            expiry_webhook_enabled: config.expiry_webhook.is_some(),
+            memtable_flush_interval_secs: config.memtable_flush_interval_secs,
+            max_value_bytes: config.max_value_bytes,
+            max_batch_bytes: config.max_batch_bytes,
+            max_connections: config.max_connections,
+            backend_name: config.backend_name.clone(),
+            named_backends: config.named_backends.clone(),
+            mirror_to: config.mirror_to.clone(),
+            mirror_failures_fatal: config.mirror_failures_fatal,
+            max_collection_elements: config.max_collection_elements,
+            max_response_bytes: config.max_response_bytes,
+            require_delimiter_on_prefix_delete: config.require_delimiter_on_prefix_delete.clone(),
+            strict_types: config.strict_types,
+            ttl_on_missing_key: config.ttl_on_missing_key.as_str().to_string(),
+            ttl_rounding: config.ttl_rounding.as_str().to_string(),
+            type_case: config.type_case.as_str().to_string(),
+            breaker_threshold: config.breaker_threshold,
+            breaker_cooldown: config.breaker_cooldown,
+            debug_bodies: config.debug_bodies,
+            open_timeout_secs: config.open_timeout_secs,
+            tenant_header: config.tenant_header.clone(),
+            enable_ui: config.enable_ui,
+        });
+        return self;
+    }
+
+    /// Report the number of keys of the storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The storage the server runs on.
+    #[must_use]
+    pub fn with_db(mut self, db: Arc<Box<dyn Storage>>) -> Self {
+        self.db = Some(db);
+        return self;
+    }
+
     /// Configures the `InfoService` with the given `ServiceConfig`.
     ///
     /// # Arguments
@@ -41,11 +113,76 @@ impl Service {
     ///
     /// # Returns
     ///
-    /// A JSON response containing the server information.
+    /// A JSON response containing the server information, the effective configuration and the
+    /// number of keys, which is left out if the storage fails to count them.
     pub async fn get(&self) -> impl Responder {
+        let key_count = match &self.db {
+            Some(db) => db.len().await.ok(),
+            None => None,
+        };
         web::Json(models::InfoResponse {
             version: self.info.version.clone(),
             rustc: self.info.rustc.clone(),
+            config: self.config.clone(),
+            key_count,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_info_reports_config() {
+        let matches = crate::cli::make_cli()
+            .try_get_matches_from([
+                "bredis",
+                "run",
+                "--backend",
+                "bredis",
+                "--no-store-prefix",
+                "session:",
+                "--int-as-string",
+                "--admin-token",
+                "s3cr3t-token",
+            ])
+            .unwrap();
+        let config = Config::from_args(matches.subcommand_matches("run").unwrap());
+
+        let service = Service::new().with_config(&config);
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+        let req = test::TestRequest::get().uri("/info").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert!(
+            !String::from_utf8_lossy(&body).contains("s3cr3t-token"),
+            "The admin token is leaked"
+        );
+
+        let info: models::InfoResponse = serde_json::from_slice(&body).unwrap();
+        let reported = info.config.unwrap();
+        assert_eq!(reported.bind, "[::1]:4123");
+        assert_eq!(reported.backend, "bredis");
+        assert_eq!(reported.data_dir, None);
+        assert_eq!(reported.no_store_prefixes, vec!["session:"]);
+        assert!(reported.int_as_string);
+        assert!(reported.admin_auth_enabled);
+        assert_eq!(info.key_count, None);
+    }
+
+    #[actix_web::test]
+    async fn test_info_reports_key_count() {
+        let db = crate::storages::bredis::Bredis::open();
+        let value = crate::storages::value::StorageValue::string("value");
+        db.set(b"key1", &value).await.unwrap();
+        db.set(b"key2", &value).await.unwrap();
+
+        let service = Service::new().with_db(Arc::new(Box::new(db)));
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+        let req = test::TestRequest::get().uri("/info").to_request();
+        let info: models::InfoResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(info.key_count, Some(2));
+    }
+}
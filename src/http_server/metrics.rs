@@ -0,0 +1,214 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Extensions, ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error, HttpResponse, Responder,
+};
+
+/// Counters of the server, shared by all workers
+#[derive(Default)]
+pub struct Metrics {
+    in_flight: AtomicUsize,
+    connections: AtomicUsize,
+}
+
+impl Metrics {
+    /// The number of requests being handled right now
+    pub fn in_flight(&self) -> usize {
+        return self.in_flight.load(Ordering::Relaxed);
+    }
+
+    /// The number of client connections open right now, idle keep-alive ones included
+    pub fn active_connections(&self) -> usize {
+        return self.connections.load(Ordering::Relaxed);
+    }
+
+    /// Render the metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        return format!(
+            "# HELP bredis_requests_in_flight Requests being handled.\n\
+             # TYPE bredis_requests_in_flight gauge\n\
+             bredis_requests_in_flight {}\n\
+             # HELP bredis_active_connections Open client connections.\n\
+             # TYPE bredis_active_connections gauge\n\
+             bredis_active_connections {}\n",
+            self.in_flight(),
+            self.active_connections()
+        );
+    }
+}
+
+/// Counts a request as in flight until it is dropped, even if the handler is cancelled
+struct InFlight<'a>(&'a Metrics);
+
+impl<'a> InFlight<'a> {
+    fn start(metrics: &'a Metrics) -> Self {
+        metrics.in_flight.fetch_add(1, Ordering::Relaxed);
+        return Self(metrics);
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts a connection as active until the connection data holding it is dropped
+struct ActiveConnection(Arc<Metrics>);
+
+impl ActiveConnection {
+    fn open(metrics: Arc<Metrics>) -> Self {
+        metrics.connections.fetch_add(1, Ordering::Relaxed);
+        return Self(metrics);
+    }
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Count a new connection as active for as long as it stays open, for `HttpServer::on_connect`
+///
+/// The connection data is dropped with the connection, and the counter with it.
+pub fn track_connection(metrics: &Arc<Metrics>, extensions: &mut Extensions) {
+    extensions.insert(ActiveConnection::open(metrics.clone()));
+}
+
+/// Count the requests in flight, for every route of the app
+pub async fn track_in_flight(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(metrics) = req.app_data::<web::Data<Metrics>>().cloned() else {
+        return next.call(req).await;
+    };
+
+    let _in_flight = InFlight::start(&metrics);
+    return next.call(req).await;
+}
+
+/// Represents the metrics service.
+pub struct Service {
+    metrics: Arc<Metrics>,
+}
+
+impl Service {
+    /// Creates a new instance of the metrics service.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The metrics shared by all workers of the server.
+    #[must_use]
+    pub const fn new(metrics: Arc<Metrics>) -> Self {
+        return Self { metrics };
+    }
+
+    /// Configures the metrics service with the given `ServiceConfig`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The `ServiceConfig` to configure.
+    pub fn config(self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::from(self.metrics))
+            .service(web::resource("/metrics").route(web::get().to(Self::get)));
+    }
+
+    /// Renders the metrics.
+    ///
+    /// # Returns
+    ///
+    /// The metrics in the Prometheus text format.
+    pub async fn get(metrics: web::Data<Metrics>) -> impl Responder {
+        return HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(metrics.render());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{middleware::from_fn, test, App};
+
+    use super::*;
+    use crate::http_server::queries::service::DatabaseQueries;
+    use crate::storages::{bredis::Bredis, mock::SlowStorage, storage::Storage};
+
+    #[actix_web::test]
+    async fn test_in_flight() {
+        let metrics = Arc::new(Metrics::default());
+        let service = Service::new(metrics.clone());
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| service.config(cfg))
+                .wrap(from_fn(track_in_flight)),
+        )
+        .await;
+
+        // The request reading the metrics is in flight itself
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert!(
+            String::from_utf8_lossy(&body).contains("bredis_requests_in_flight 1\n"),
+            "{body:?}"
+        );
+        assert_eq!(metrics.in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_in_flight_during_slow_request() {
+        let db: Box<dyn Storage> = Box::new(SlowStorage::new(
+            Box::new(Bredis::open()),
+            Duration::from_millis(300),
+        ));
+        let query_service = DatabaseQueries::new(Arc::new(db));
+        let metrics = Arc::new(Metrics::default());
+        let service = Service::new(metrics.clone());
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| service.config(cfg))
+                .configure(|cfg| query_service.config(cfg))
+                .wrap(from_fn(track_in_flight)),
+        )
+        .await;
+
+        let slow = test::TestRequest::get().uri("/keys/key1").to_request();
+        let read_metrics = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let req = test::TestRequest::get().uri("/metrics").to_request();
+            return test::call_and_read_body(&app, req).await;
+        };
+        let (resp, body) = tokio::join!(test::call_service(&app, slow), read_metrics);
+
+        // The slow request and the one reading the metrics
+        assert!(
+            String::from_utf8_lossy(&body).contains("bredis_requests_in_flight 2\n"),
+            "{body:?}"
+        );
+        assert!(resp.status().is_success());
+        assert_eq!(metrics.in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_active_connections() {
+        let metrics = Arc::new(Metrics::default());
+        let mut first = Extensions::new();
+        let mut second = Extensions::new();
+        track_connection(&metrics, &mut first);
+        track_connection(&metrics, &mut second);
+        assert_eq!(metrics.active_connections(), 2);
+        assert!(metrics.render().contains("bredis_active_connections 2\n"));
+
+        drop(first);
+        assert_eq!(metrics.active_connections(), 1);
+    }
+}
@@ -1,9 +1,36 @@
 #![allow(clippy::unused_async)]
 
+mod admin;
+mod backoff;
+mod channels;
+mod codec;
 mod core;
+mod deadline;
+mod debug_bodies;
 mod docs;
+mod expiry;
+mod flush;
+mod health;
 mod info;
+mod metrics;
 mod models;
 mod queries;
+mod recovery;
+mod routing;
+mod stats;
+mod tenants;
+mod type_names;
+mod ui;
+mod webhook;
 
+pub use crate::http_server::admin::AuthThrottle;
 pub use crate::http_server::core::Server;
+pub use crate::http_server::deadline::OpTimeout;
+pub use crate::http_server::docs::openapi;
+pub use crate::http_server::models::IntEncoding;
+pub use crate::http_server::queries::service::{
+    BodyLimits, CachePolicy, CollectionLimit, DeleteDelimiter, MaxResponseBytes, MissingKeyTtl,
+};
+pub use crate::http_server::routing::BackendNames;
+pub use crate::http_server::tenants::TenantHeader;
+pub use crate::http_server::webhook::WebhookUrl;
@@ -1,18 +1,109 @@
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(untagged)]
-pub enum IntOrString {
-    Int(i64),
-    String(String),
+use actix_web::{body::BoxBody, http::StatusCode, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::openapi::schema::{Object, ObjectBuilder, SchemaType, Type};
+use utoipa::ToSchema;
+
+use super::{backoff, codec::Format};
+use crate::errors::DatabaseError;
+pub use crate::storages::value::IntOrString;
+
+/// How integers are written in responses
+///
+/// JavaScript clients parse JSON numbers as doubles, so integers beyond 2^53 lose precision
+/// unless they are written as strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntEncoding {
+    #[default]
+    Number,
+    String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// An integer in a response, written as a JSON number or string depending on its encoding
+///
+/// Both forms are accepted when it is read back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JsonInt {
+    pub value: i64,
+    pub encoding: IntEncoding,
+}
+
+impl JsonInt {
+    pub const fn new(value: i64, encoding: IntEncoding) -> Self {
+        return Self { value, encoding };
+    }
+}
+
+impl PartialEq<i64> for JsonInt {
+    fn eq(&self, other: &i64) -> bool {
+        return self.value == *other;
+    }
+}
+
+impl Serialize for JsonInt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return match self.encoding {
+            IntEncoding::Number => serializer.serialize_i64(self.value),
+            IntEncoding::String => serializer.collect_str(&self.value),
+        };
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonInt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(i64),
+            String(String),
+        }
+
+        return match Repr::deserialize(deserializer)? {
+            Repr::Number(value) => Ok(Self::new(value, IntEncoding::Number)),
+            Repr::String(value) => value
+                .parse()
+                .map(|value| Self::new(value, IntEncoding::String))
+                .map_err(serde::de::Error::custom),
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct SetRequest {
     pub key: String,
     pub value: IntOrString,
 
-    #[serde(default = "default_ttl")]
+    /// Seconds, or a duration like `"30s"`, `"5m"`, `"2h"` or `"7d"`; -1 to never expire
+    #[serde(default = "default_ttl", deserialize_with = "deserialize_ttl")]
+    pub ttl: i64,
+}
+
+/// Values of several keys, written atomically with the same TTL
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SetManyWithTtlRequest {
+    /// Seconds, or a duration like `"30s"`, `"5m"`, `"2h"` or `"7d"`; -1 to never expire
+    #[serde(deserialize_with = "deserialize_ttl")]
+    pub ttl: i64,
+    pub entries: BTreeMap<String, IntOrString>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetQuery {
+    /// Store purely numeric string values as integers
+    #[serde(default)]
+    pub coerce: bool,
+    /// Keep the expiry of the value being replaced, the `ttl` of the request only applies
+    /// to a new key
+    #[serde(default)]
+    pub keep_ttl: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetRawQuery {
+    /// Seconds, or a duration like `"30s"` or `"2h"`; the value never expires without it
+    #[serde(default = "default_ttl", deserialize_with = "deserialize_ttl")]
     pub ttl: i64,
 }
 
@@ -20,30 +111,233 @@ const fn default_ttl() -> i64 {
     return -1;
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Parse a duration like `"30s"`, `"5m"`, `"2h"` or `"7d"` into seconds
+///
+/// A number without a unit is taken as seconds.
+///
+/// # Arguments
+/// * `value` - The duration, a non-negative integer optionally followed by `s`, `m`, `h` or `d`
+///
+/// # Returns
+/// The number of seconds or a message describing why the duration is invalid
+///
+/// # Example
+/// ```
+/// assert_eq!(parse_duration("5m"), Ok(300));
+/// ```
+pub fn parse_duration(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Invalid duration unit in {value:?}, expected s, m, h or d"
+            ))
+        }
+    };
+
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid duration: {value:?}"))?;
+    return amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Duration out of range: {value:?}"));
+}
+
+/// Read a TTL given either as integer seconds or as a duration string, see `parse_duration`
+fn deserialize_ttl<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Seconds(i64),
+        Duration(String),
+    }
+
+    return match Repr::deserialize(deserializer)? {
+        Repr::Seconds(seconds) => Ok(seconds),
+        Repr::Duration(duration) => parse_duration(&duration).map_err(serde::de::Error::custom),
+    };
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct DeleteKeysRequest {
     #[serde(default)]
     pub prefix: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteKeysQuery {
+    /// Allow an empty prefix, which deletes every key
+    #[serde(default)]
+    pub confirm_all: bool,
+    /// Allow a prefix that doesn't end in the delimiter the server requires
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct GetResponse {
     pub value: Option<IntOrString>,
+    /// The stored type of the value, which tells a numeric-looking string from an integer;
+    /// null if the key doesn't exist
+    #[serde(rename = "type", default)]
+    pub value_type: Option<String>,
+    /// The number of elements of a list or bytes of a blob, which are read from
+    /// `/keys/{key}/list` and `/keys/{key}/raw` instead of `value`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct OperationSuccessResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct GetAllKeysResponse {
-    pub keys: Vec<String>,
+    #[serde(flatten)]
+    pub page: Page<String>,
+    /// The folded prefixes of deeper keys, only listed with a `delimiter`, never paginated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub common_prefixes: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A listed key with its remaining TTL
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+pub struct KeyWithTtl {
+    pub key: String,
+    /// The remaining TTL in seconds, rounded like `GET /keys/{key}/ttl`; -1 if the key never expires
+    pub ttl: i64,
+}
+
+/// A listing of keys requested with `with_ttl=true`
+///
+/// Keys that expire between the scan and the TTL lookup are left out.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct KeysWithTtlResponse {
+    #[serde(flatten)]
+    pub page: Page<KeyWithTtl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub common_prefixes: Vec<String>,
+}
+
+/// One page of a listing, the envelope shared by the routes listing keys
+///
+/// Pass `next_cursor` as the `cursor` of the next request to read the following page.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The first item of the next page, null on the last page
+    pub next_cursor: Option<String>,
+    /// The number of items on all pages together, null if counting them needs a full scan
+    pub total: Option<usize>,
+    /// Whether more items follow this page
+    pub truncated: bool,
+}
+
+impl<T> Page<T> {
+    /// A page holding every item of a listing
+    pub fn complete(items: Vec<T>) -> Self {
+        return Self {
+            total: Some(items.len()),
+            items,
+            next_cursor: None,
+            truncated: false,
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
+    /// The kind of error, one of the codes listed by `GET /errors`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Whether the same request may succeed when retried
+    #[serde(default)]
+    pub retryable: bool,
+    /// The HTTP status the error is answered with
+    #[serde(skip)]
+    pub status: StatusCode,
+}
+
+impl ErrorResponse {
+    /// An error that is not worth retrying
+    pub fn new(status: StatusCode, error: impl Into<String>) -> Self {
+        return Self {
+            error: error.into(),
+            code: None,
+            retryable: false,
+            status,
+        };
+    }
+}
+
+/// Retryable errors are answered with 503, client errors with 4xx and the rest with 500
+impl From<&DatabaseError> for ErrorResponse {
+    fn from(err: &DatabaseError) -> Self {
+        let status = match err {
+            DatabaseError::Conflict(_) => StatusCode::SERVICE_UNAVAILABLE,
+            DatabaseError::InvalidValueType(_) | DatabaseError::InvalidJson(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            DatabaseError::ValueNotFound(_) => StatusCode::NOT_FOUND,
+            DatabaseError::OutOfRange(_) => StatusCode::CONFLICT,
+            DatabaseError::Unsupported(_) => StatusCode::NOT_IMPLEMENTED,
+            DatabaseError::InitialFailed(_) | DatabaseError::InternalError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        return Self {
+            error: err.to_string(),
+            code: Some(err.code().to_string()),
+            retryable: err.is_retryable(),
+            status,
+        };
+    }
+}
+
+/// A kind of error the server answers storage failures with
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ErrorCatalogEntry {
+    /// The `code` of the error responses
+    pub code: String,
+    /// The HTTP status of the error responses
+    pub status: u16,
+    pub description: String,
+    /// Whether the same request may succeed when retried
+    pub retryable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ErrorCatalogResponse {
+    pub errors: Vec<ErrorCatalogEntry>,
+}
+
+impl ErrorCatalogResponse {
+    /// The catalog of every kind of `DatabaseError`
+    pub fn build() -> Self {
+        let errors = DatabaseError::kinds()
+            .iter()
+            .map(|err| {
+                let response = ErrorResponse::from(err);
+                return ErrorCatalogEntry {
+                    code: err.code().to_string(),
+                    status: response.status.as_u16(),
+                    description: err.description().to_string(),
+                    retryable: response.retryable,
+                };
+            })
+            .collect();
+        return Self { errors };
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -53,47 +347,527 @@ pub enum ApiResponse<T> {
     ErrorResponse(ErrorResponse),
 }
 
+impl<T> From<DatabaseError> for ApiResponse<T> {
+    fn from(err: DatabaseError) -> Self {
+        return Self::ErrorResponse(ErrorResponse::from(&err));
+    }
+}
+
+/// The response is written in the format the request accepts, see `Format::accepted`
+impl<T: Serialize> Responder for ApiResponse<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let mut builder = match &self {
+            Self::Success(_) => HttpResponse::Ok(),
+            Self::ErrorResponse(err) => backoff::error_builder(err.status),
+        };
+        let format = Format::accepted(req);
+        return match format.encode(&self) {
+            Ok(body) => builder.content_type(format.mime()).body(body),
+            Err(err) => HttpResponse::InternalServerError().body(err),
+        };
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GetAllKeysQuery {
     pub prefix: String,
+    /// Fold the keys with this separator after the prefix into common prefixes
+    pub delimiter: Option<String>,
+    /// The first key of the page, the `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// The most keys of the page
+    pub limit: Option<usize>,
+    /// List every key with its remaining TTL, see `KeysWithTtlResponse`
+    #[serde(default)]
+    pub with_ttl: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChangedSinceQuery {
+    /// Time in milliseconds since the Unix epoch
+    pub ts: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct RangeQuery {
+    /// The first key of the range, inclusive
+    pub start: String,
+    /// The end of the range, exclusive
+    pub end: String,
+    /// The most keys of the page
+    pub limit: Option<usize>,
+    /// The first key of the page, the `next_cursor` of the previous page; `start` by default
+    pub cursor: Option<String>,
+    /// List every key with its remaining TTL, see `KeysWithTtlResponse`
+    #[serde(default)]
+    pub with_ttl: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct InfoResponse {
     pub version: String,
     pub rustc: String,
+    /// The effective configuration, without secrets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ConfigResponse>,
+    /// The number of keys, which some backends estimate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_count: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ConfigResponse {
+    pub bind: String,
+    pub backend: String,
+    pub data_dir: Option<String>,
+    pub hash_keys_over: Option<usize>,
+    pub sync_writes: bool,
+    pub skip_read_checksums: bool,
+    pub persistent: bool,
+    pub snapshot_on_exit: Option<String>,
+    pub unhealthy_error_rate: f64,
+    pub error_rate_window: u64,
+    pub no_store_prefixes: Vec<String>,
+    pub int_as_string: bool,
+    /// Whether the admin routes require a token; the token itself is never reported
+    pub admin_auth_enabled: bool,
+    pub auth_max_attempts: Option<u32>,
+    pub auth_window: u64,
+    pub op_timeout: Option<u64>,
+    pub slow_op_threshold: Option<u64>,
+    pub sweep_interval: Option<u64>,
+    /// Whether expired keys are posted to a webhook; the URL itself is never reported
+    pub expiry_webhook_enabled: bool,
+    pub memtable_flush_interval_secs: Option<u64>,
+    pub max_value_bytes: usize,
+    pub max_batch_bytes: usize,
+    pub max_connections: usize,
+    pub backend_name: String,
+    pub named_backends: Vec<String>,
+    pub mirror_to: Option<String>,
+    pub mirror_failures_fatal: bool,
+    pub max_collection_elements: Option<usize>,
+    pub max_response_bytes: Option<usize>,
+    pub require_delimiter_on_prefix_delete: Option<String>,
+    pub strict_types: bool,
+    pub ttl_on_missing_key: String,
+    pub ttl_rounding: String,
+    pub type_case: String,
+    pub breaker_threshold: Option<u32>,
+    pub breaker_cooldown: u64,
+    pub debug_bodies: bool,
+    pub open_timeout_secs: Option<u64>,
+    pub tenant_header: Option<String>,
+    pub enable_ui: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct IncrementRequest {
     pub value: i64,
     #[serde(default)]
     pub default: Option<i64>,
+    /// Treat a missing key as 0 when no explicit `default` is given.
+    #[serde(default)]
+    pub assume_zero: bool,
+    /// The base the integer is stored in, 10 or 16.
+    #[serde(default = "default_base")]
+    pub base: u32,
+    /// The largest value the increment may reach.
+    #[serde(default)]
+    pub max: Option<i64>,
+    /// Cap the result at `max` instead of rejecting the increment with 409.
+    #[serde(default)]
+    pub saturate: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+const fn default_base() -> u32 {
+    return 10;
+}
+
+impl IncrementRequest {
+    /// Resolve the default value passed to the storage.
+    ///
+    /// An explicit `default` always wins; otherwise `assume_zero` makes a missing key count as 0.
+    pub fn default_value(&self) -> Option<i64> {
+        if self.default.is_none() && self.assume_zero {
+            return Some(0);
+        }
+        return self.default;
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct IncrementResponse {
+    /// A string when the server runs with `--int-as-string`.
+    #[schema(value_type = i64)]
+    pub value: JsonInt,
+    /// The new value written in the requested base.
+    pub formatted: String,
+    /// Whether the result was capped at the requested `max`.
+    #[serde(default)]
+    pub capped: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DecrementAndDeleteRequest {
     pub value: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DecrementRequest {
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DecrementAndDeleteResponse {
+    /// A string when the server runs with `--int-as-string`.
+    #[schema(value_type = i64)]
+    pub value: JsonInt,
+    /// Whether the value dropped to 0 or below and the key was deleted.
+    pub deleted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SetExtremeRequest {
     pub value: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SetExtremeResponse {
+    /// The stored value after the request. A string when the server runs with `--int-as-string`.
+    #[schema(value_type = i64)]
+    pub value: JsonInt,
+    /// Whether the value of the request was stored.
+    pub changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CompareAndIncrementRequest {
+    /// The value the key must hold for `delta` to be added.
+    pub expected: i64,
+    pub delta: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CompareAndIncrementResponse {
+    /// The stored value after the request. A string when the server runs with `--int-as-string`.
+    #[schema(value_type = i64)]
+    pub value: JsonInt,
+    /// Whether the key held `expected` and `delta` was added.
+    pub swapped: bool,
+}
+
+/// Keys whose values are read as of a single point in time
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetManyRequest {
+    pub keys: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetManyResponse {
+    /// The value of every key, in the order of the request; nulls for a missing key.
+    pub values: Vec<GetResponse>,
+}
+
+/// Base-10 increments of several keys, missing keys start from 0
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct IncrementManyRequest {
+    pub increments: BTreeMap<String, i64>,
+    /// Apply the valid increments and report the failed ones instead of rejecting the whole batch.
     #[serde(default)]
-    pub default: i64,
+    pub lenient: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DecrementResponse {
-    pub value: i64,
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct IncrementManyResponse {
+    /// The new values of the incremented keys.
+    #[schema(value_type = BTreeMap<String, i64>)]
+    pub results: BTreeMap<String, JsonInt>,
+    /// The keys left untouched in lenient mode, with the reason.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TypeResponse {
+    /// The type of the value, null if the key doesn't exist
+    #[serde(rename = "type")]
+    pub value_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct GetTtlResponse {
+    /// The remaining seconds, or the expiry as a Unix timestamp with `absolute=true`;
+    /// -1 if the key never expires
+    pub ttl: i64,
+}
+
+/// Low-level metadata of a key, like the `OBJECT` command of Redis
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ObjectResponse {
+    #[serde(rename = "type")]
+    pub value_type: String,
+    /// The size of the stored record, in bytes
+    pub encoded_length: usize,
+    /// The remaining seconds, -1 if the key never expires
     pub ttl: i64,
+    /// The expiry as a Unix timestamp, null if the key never expires
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyspaceQuery {
+    /// Read only this many keys and extrapolate from them
+    pub sample: Option<NonZeroUsize>,
+}
+
+/// The number and the size of the keys of one value type
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
+pub struct TypeStats {
+    pub count: u64,
+    /// The size of the keys and their stored records, in bytes
+    pub bytes: u64,
+}
+
+/// The keys by value type, estimated when they were sampled
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct KeyspaceResponse {
+    #[serde(flatten)]
+    pub types: BTreeMap<String, TypeStats>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct GetTtlQuery {
+    /// Report the expiry as a Unix timestamp instead of the remaining seconds
+    #[serde(default)]
+    pub absolute: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct SetTtlRequest {
+    /// Seconds, or a duration like `"30s"`, `"5m"`, `"2h"` or `"7d"`
+    #[serde(deserialize_with = "deserialize_ttl")]
+    pub ttl: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct GetExRequest {
+    /// The new TTL, in seconds or as a duration like `"30s"`; the TTL is unchanged without it
+    #[serde(default, deserialize_with = "deserialize_new_ttl")]
+    pub ttl: Option<i64>,
+    /// Remove the TTL of the key, takes precedence over `ttl`
+    #[serde(default)]
+    pub persist: bool,
+}
+
+impl GetExRequest {
+    /// Resolve the TTL passed to the storage: -1 to persist the key, None to leave it unchanged
+    pub const fn new_ttl(&self) -> Option<i64> {
+        if self.persist {
+            return Some(-1);
+        }
+        return self.ttl;
+    }
+}
+
+fn deserialize_new_ttl<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<i64>, D::Error> {
+    return deserialize_ttl(deserializer).map(Some);
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AdjustTtlRequest {
+    pub delta: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RenameRequest {
+    pub new_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RenameResponse {
+    pub renamed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct LockRequest {
+    /// The lease time, in seconds or as a duration like `"30s"`
+    #[serde(deserialize_with = "deserialize_ttl")]
     pub ttl: i64,
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct LockResponse {
+    /// The token the lock is released with
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UnlockRequest {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonQuery {
+    #[serde(default = "default_json_path")]
+    pub path: String,
+}
+
+fn default_json_path() -> String {
+    return String::from("$");
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct JsonQueryResponse {
+    #[schema(schema_with = any_json_schema)]
+    pub value: Option<serde_json::Value>,
+}
+
+/// The schema of any JSON value, which utoipa would render as `{}`
+fn any_json_schema() -> Object {
+    return ObjectBuilder::new()
+        .schema_type(SchemaType::from_iter([
+            Type::Object,
+            Type::Array,
+            Type::String,
+            Type::Number,
+            Type::Boolean,
+            Type::Null,
+        ]))
+        .build();
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ListPushRequest {
+    pub values: Vec<String>,
+    /// Keep only the last `maxlen` elements after pushing
+    pub maxlen: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ListPushResponse {
+    pub length: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AppendRequest {
+    pub value: String,
+    /// The new TTL, in seconds or as a duration like `"30s"`; the TTL is unchanged without it
+    #[serde(default, deserialize_with = "deserialize_new_ttl")]
+    pub ttl: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AppendResponse {
+    pub length: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ListResponse {
+    pub values: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct HashSetRequest {
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct HashSetResponse {
+    /// The number of fields the hash didn't have before
+    pub added: usize,
+    /// The number of fields whose value was replaced
+    pub updated: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct HashResponse {
+    pub fields: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CheckpointRequest {
+    /// Directory on the server to write the copy into, it must not exist yet
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EvictRequest {
+    /// Evict keys that expire within this many seconds, every key with a TTL without it
+    #[serde(default)]
+    pub max_ttl: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EvictResponse {
+    /// The number of deleted keys
+    pub evicted: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PublishRequest {
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PublishResponse {
+    /// Number of subscribers the message was delivered to
+    pub receivers: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReadyResponse {
+    pub ready: bool,
+    pub error_rate: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_status() {
+        let conflict = ErrorResponse::from(&DatabaseError::Conflict("Resource busy".to_string()));
+        assert_eq!(conflict.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(conflict.retryable);
+
+        let invalid =
+            ErrorResponse::from(&DatabaseError::InvalidValueType("not a list".to_string()));
+        assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+        assert!(!invalid.retryable);
+
+        let missing = ErrorResponse::from(&DatabaseError::ValueNotFound("key1".to_string()));
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        assert_eq!(missing.code.as_deref(), Some("value_not_found"));
+        assert!(!missing.retryable);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5m"), Ok(300));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+        assert_eq!(parse_duration("7d"), Ok(604_800));
+        assert_eq!(parse_duration("30s"), Ok(30));
+        assert_eq!(parse_duration("30"), Ok(30));
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_ttl_accepts_integers_and_durations() {
+        let request: SetTtlRequest = serde_json::from_str(r#"{"ttl": 60}"#).unwrap();
+        assert_eq!(request.ttl, 60);
+        let request: SetTtlRequest = serde_json::from_str(r#"{"ttl": "2h"}"#).unwrap();
+        assert_eq!(request.ttl, 7200);
+        assert!(serde_json::from_str::<SetTtlRequest>(r#"{"ttl": "2x"}"#).is_err());
+
+        let request: SetRequest =
+            serde_json::from_str(r#"{"key": "key1", "value": "value1", "ttl": "5m"}"#).unwrap();
+        assert_eq!(request.ttl, 300);
+        let request: SetRequest =
+            serde_json::from_str(r#"{"key": "key1", "value": "value1"}"#).unwrap();
+        assert_eq!(request.ttl, -1);
+    }
+}
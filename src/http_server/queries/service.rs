@@ -1,36 +1,351 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-use actix_web::web;
+use actix_web::{
+    http::{
+        header::{CacheControl, CacheDirective, ETag, EntityTag, Expires, HttpDate, IfNoneMatch},
+        StatusCode,
+    },
+    middleware::from_fn,
+    web, CustomizeResponder, Either, HttpResponse, Responder,
+};
+use sha2::{Digest, Sha256};
 
 use crate::{
-    http_server::models,
+    errors::DatabaseError,
+    http_server::{
+        codec::Body,
+        deadline::{self, OpTimeout},
+        debug_bodies::{self, DebugBodies},
+        models,
+        routing::{self, BackendNames},
+        tenants::{self, TenantHeader},
+        type_names,
+    },
     storages::{
+        clock::{Clock, SystemClock, TtlRounding},
+        json, listing,
         storage::Storage,
-        value::{StorageValue, ValueType},
+        value::{Extreme, StorageValue, TypeCase, ValueType},
     },
 };
 
 /// A type alias for the storage type
 pub type StorageType = Arc<Box<dyn Storage>>;
 
+/// A listing of keys, with the TTL of every key when it is requested with `with_ttl=true`
+type KeyListing = Either<
+    models::ApiResponse<models::GetAllKeysResponse>,
+    models::ApiResponse<models::KeysWithTtlResponse>,
+>;
+
+/// The size of the chunks raw values are streamed in
+const RAW_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The media type of a value downloaded from the raw route
+///
+/// # Returns
+/// None for lists and hashes, whose stored bytes are an internal encoding
+const fn raw_content_type(value_type: &ValueType) -> Option<&'static str> {
+    return match value_type {
+        ValueType::String | ValueType::Integer => Some("text/plain; charset=utf-8"),
+        ValueType::Json => Some("application/json"),
+        ValueType::Blob => Some("application/octet-stream"),
+        ValueType::List | ValueType::Hash => None,
+    };
+}
+
+/// The entity tag of a stored value, a hash of its type and content
+///
+/// The TTL is left out, so extending the life of a key doesn't invalidate cached copies.
+fn entity_tag(value: &StorageValue) -> EntityTag {
+    let mut hasher = Sha256::new();
+    hasher.update(value.value_type.name(TypeCase::Title).as_bytes());
+    hasher.update(&value.value);
+    let mut tag = String::with_capacity(32);
+    for byte in &hasher.finalize()[..16] {
+        write!(tag, "{byte:02x}").unwrap();
+    }
+    return EntityTag::new_strong(tag);
+}
+
+/// HTTP caching policy of key reads
+///
+/// Reads of keys with a TTL may be cached until the key expires, reads of
+/// persistent keys and keys under one of `no_store_prefixes` are never cached.
+#[derive(Clone, Default)]
+pub struct CachePolicy {
+    pub no_store_prefixes: Vec<String>,
+}
+
+impl CachePolicy {
+    /// Add `Cache-Control` and `Expires` headers to a read of a key
+    ///
+    /// # Arguments
+    /// * `key` - The key that was read
+    /// * `ttl` - The remaining TTL of the key, `None` if it was not found
+    /// * `responder` - The response to add the headers to
+    fn apply<R: Responder>(
+        &self,
+        key: &str,
+        ttl: Option<i64>,
+        responder: R,
+    ) -> CustomizeResponder<R> {
+        let sensitive = self
+            .no_store_prefixes
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str()));
+        let max_age = ttl
+            .and_then(|ttl| u32::try_from(ttl).ok())
+            .filter(|_| !sensitive);
+
+        return match max_age {
+            Some(max_age) => {
+                let expires = SystemTime::now() + Duration::from_secs(u64::from(max_age));
+                responder
+                    .customize()
+                    .insert_header(CacheControl(vec![CacheDirective::MaxAge(max_age)]))
+                    .insert_header(Expires(HttpDate::from(expires)))
+            }
+            None => responder
+                .customize()
+                .insert_header(CacheControl(vec![CacheDirective::NoStore])),
+        };
+    }
+}
+
+/// The largest request bodies the key routes accept, in bytes
+///
+/// Batch routes carry many keys in one request, so they get their own, larger limit
+/// instead of raising the limit of every single write.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimits {
+    pub value: usize,
+    pub batch: usize,
+}
+
+impl BodyLimits {
+    /// 2 MiB for single writes, the default of actix-web, and 16 MiB for batches
+    pub const DEFAULT: Self = Self {
+        value: 2 * 1024 * 1024,
+        batch: 16 * 1024 * 1024,
+    };
+}
+
+/// The most elements a list or fields a hash may hold; `None` means no limit
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollectionLimit(pub Option<usize>);
+
+/// The most bytes of JSON the keys of one listing page may take; `None` means no limit
+///
+/// Pages cut short by the limit end with `truncated` and a `next_cursor` to resume from.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaxResponseBytes(pub Option<usize>);
+
+/// The delimiter every prefix of a delete must end in, so `a` can't delete `ab` when `a:` was
+/// meant; `None` accepts any prefix
+#[derive(Clone, Debug, Default)]
+pub struct DeleteDelimiter(pub Option<String>);
+
+/// What setting the TTL of a missing key does
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissingKeyTtl {
+    /// Answer with 404, as for any other operation on a missing key
+    #[default]
+    Error,
+    /// Answer with success and leave the key missing
+    Ignore,
+}
+
+impl MissingKeyTtl {
+    /// The name of the behavior, as passed to `--ttl-on-missing-key`
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        return match self {
+            Self::Error => "error",
+            Self::Ignore => "ignore",
+        };
+    }
+}
+
 pub struct DatabaseQueries {
     db: StorageType,
+    cache_policy: CachePolicy,
+    int_encoding: models::IntEncoding,
+    op_timeout: OpTimeout,
+    body_limits: BodyLimits,
+    collection_limit: CollectionLimit,
+    max_response_bytes: MaxResponseBytes,
+    delete_delimiter: DeleteDelimiter,
+    missing_key_ttl: MissingKeyTtl,
+    backend_names: BackendNames,
+    ttl_rounding: TtlRounding,
+    debug_bodies: DebugBodies,
+    type_case: TypeCase,
+    tenant_header: TenantHeader,
+    clock: Arc<dyn Clock>,
 }
 
 impl DatabaseQueries {
     #[must_use]
-    pub const fn new(db: StorageType) -> Self {
-        Self { db }
+    pub fn new(db: StorageType) -> Self {
+        Self {
+            db,
+            cache_policy: CachePolicy {
+                no_store_prefixes: Vec::new(),
+            },
+            int_encoding: models::IntEncoding::Number,
+            op_timeout: OpTimeout(None),
+            body_limits: BodyLimits::DEFAULT,
+            collection_limit: CollectionLimit(None),
+            max_response_bytes: MaxResponseBytes(None),
+            delete_delimiter: DeleteDelimiter(None),
+            missing_key_ttl: MissingKeyTtl::Error,
+            backend_names: BackendNames(Vec::new()),
+            ttl_rounding: TtlRounding::Ceil,
+            debug_bodies: DebugBodies(false),
+            type_case: TypeCase::Title,
+            tenant_header: TenantHeader(None),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the HTTP caching policy of key reads
+    #[must_use]
+    pub fn with_cache_policy(mut self, cache_policy: CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        return self;
+    }
+
+    /// Set how integer values are written in responses
+    #[must_use]
+    pub const fn with_int_encoding(mut self, int_encoding: models::IntEncoding) -> Self {
+        self.int_encoding = int_encoding;
+        return self;
+    }
+
+    /// Set the longest a request may run, `X-Deadline-Ms` can only shorten it
+    #[must_use]
+    pub const fn with_op_timeout(mut self, op_timeout: OpTimeout) -> Self {
+        self.op_timeout = op_timeout;
+        return self;
+    }
+
+    /// Set the largest request bodies of single writes and of batch routes
+    #[must_use]
+    pub const fn with_body_limits(mut self, body_limits: BodyLimits) -> Self {
+        self.body_limits = body_limits;
+        return self;
+    }
+
+    /// Set the most elements a list may hold
+    #[must_use]
+    pub const fn with_collection_limit(mut self, collection_limit: CollectionLimit) -> Self {
+        self.collection_limit = collection_limit;
+        return self;
+    }
+
+    /// Set the most bytes the keys of one listing page may take
+    #[must_use]
+    pub const fn with_max_response_bytes(mut self, max_response_bytes: MaxResponseBytes) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        return self;
+    }
+
+    /// Require the prefixes of deletes to end in a delimiter, unless the request forces it
+    #[must_use]
+    pub fn with_delete_delimiter(mut self, delete_delimiter: DeleteDelimiter) -> Self {
+        self.delete_delimiter = delete_delimiter;
+        return self;
+    }
+
+    /// Set what setting the TTL of a missing key does
+    #[must_use]
+    pub const fn with_missing_key_ttl(mut self, missing_key_ttl: MissingKeyTtl) -> Self {
+        self.missing_key_ttl = missing_key_ttl;
+        return self;
+    }
+
+    /// Set the backends requests may select with the `X-Bredis-Backend` header
+    #[must_use]
+    pub fn with_backend_names(mut self, backend_names: BackendNames) -> Self {
+        self.backend_names = backend_names;
+        return self;
+    }
+
+    /// Set how remaining TTLs are rounded to whole seconds
+    #[must_use]
+    pub const fn with_ttl_rounding(mut self, ttl_rounding: TtlRounding) -> Self {
+        self.ttl_rounding = ttl_rounding;
+        return self;
+    }
+
+    /// Log the request and response bodies of key routes at debug level
+    #[must_use]
+    pub const fn with_debug_bodies(mut self, debug_bodies: DebugBodies) -> Self {
+        self.debug_bodies = debug_bodies;
+        return self;
+    }
+
+    /// Set the case value type names are written in
+    #[must_use]
+    pub const fn with_type_case(mut self, type_case: TypeCase) -> Self {
+        self.type_case = type_case;
+        return self;
+    }
+
+    /// Set the header naming the tenant whose keys a request sees
+    #[must_use]
+    pub fn with_tenant_header(mut self, tenant_header: TenantHeader) -> Self {
+        self.tenant_header = tenant_header;
+        return self;
+    }
+
+    /// Read the current time from `clock` instead of the system clock
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        return self;
     }
 
     pub fn config(&self, cfg: &mut web::ServiceConfig) {
         let scoped_services = web::scope("/keys")
+            .wrap(from_fn(deadline::enforce))
+            .wrap(from_fn(tenants::select_tenant))
+            .wrap(from_fn(routing::select_backend))
+            .wrap(from_fn(debug_bodies::log_bodies))
+            .wrap(from_fn(type_names::apply_type_case))
+            .app_data(web::PayloadConfig::new(self.body_limits.value))
             .service(
                 web::resource("")
                     .route(web::get().to(Self::get_all_keys))
                     .route(web::post().to(Self::set_key))
                     .route(web::delete().to(Self::delete_keys)),
             )
+            .service(
+                web::resource("/changed-since").route(web::get().to(Self::get_keys_changed_since)),
+            )
+            .service(web::resource("/range").route(web::get().to(Self::get_keys_range)))
+            .service(
+                web::resource("/inc")
+                    .app_data(web::PayloadConfig::new(self.body_limits.batch))
+                    .route(web::post().to(Self::increment_many)),
+            )
+            .service(
+                web::resource("/mget")
+                    .app_data(web::PayloadConfig::new(self.body_limits.batch))
+                    .route(web::post().to(Self::get_many)),
+            )
+            .service(
+                web::resource("/mset-ttl")
+                    .app_data(web::PayloadConfig::new(self.body_limits.batch))
+                    .route(web::post().to(Self::set_many_with_ttl)),
+            )
             .service(
                 web::resource("/{key_name}")
                     .route(web::get().to(Self::get_by_key))
@@ -38,205 +353,1011 @@ impl DatabaseQueries {
             )
             .service(web::resource("/{key_name}/inc").route(web::post().to(Self::increment)))
             .service(web::resource("/{key_name}/dec").route(web::post().to(Self::decrement)))
+            .service(
+                web::resource("/{key_name}/decr-del-on-zero")
+                    .route(web::post().to(Self::decrement_and_delete)),
+            )
+            .service(web::resource("/{key_name}/setmax").route(web::post().to(Self::set_max)))
+            .service(web::resource("/{key_name}/setmin").route(web::post().to(Self::set_min)))
+            .service(
+                web::resource("/{key_name}/cas-incr")
+                    .route(web::post().to(Self::compare_and_increment)),
+            )
+            .service(web::resource("/{key_name}/renamenx").route(web::post().to(Self::rename_nx)))
+            .service(web::resource("/{key_name}/lock").route(web::post().to(Self::lock)))
+            .service(web::resource("/{key_name}/unlock").route(web::post().to(Self::unlock)))
+            .service(
+                web::resource("/{key_name}/json")
+                    .route(web::get().to(Self::get_json))
+                    .route(web::patch().to(Self::merge_json)),
+            )
+            .service(
+                web::resource("/{key_name}/list")
+                    .route(web::get().to(Self::get_list))
+                    .route(web::post().to(Self::list_push)),
+            )
+            .service(
+                web::resource("/{key_name}/hash")
+                    .route(web::get().to(Self::get_hash))
+                    .route(web::post().to(Self::hset_many)),
+            )
+            .service(web::resource("/{key_name}/type").route(web::get().to(Self::get_type)))
+            .service(web::resource("/{key_name}/object").route(web::get().to(Self::get_object)))
+            .service(web::resource("/{key_name}/getex").route(web::post().to(Self::get_ex)))
+            .service(web::resource("/{key_name}/append").route(web::post().to(Self::append)))
+            .service(
+                web::resource("/{key_name}/raw")
+                    .route(web::get().to(Self::get_raw))
+                    .route(web::put().to(Self::set_raw)),
+            )
             .service(
                 web::resource("/{key_name}/ttl")
                     .route(web::get().to(Self::get_ttl))
-                    .route(web::post().to(Self::set_ttl)),
+                    .route(web::post().to(Self::set_ttl))
+                    .route(web::patch().to(Self::adjust_ttl)),
             );
 
         cfg.app_data(web::Data::new(self.db.clone()))
+            .app_data(web::Data::new(self.cache_policy.clone()))
+            .app_data(web::Data::new(self.int_encoding))
+            .app_data(web::Data::new(self.op_timeout))
+            .app_data(web::Data::new(self.collection_limit))
+            .app_data(web::Data::new(self.max_response_bytes))
+            .app_data(web::Data::new(self.delete_delimiter.clone()))
+            .app_data(web::Data::new(self.missing_key_ttl))
+            .app_data(web::Data::new(self.backend_names.clone()))
+            .app_data(web::Data::new(self.ttl_rounding))
+            .app_data(web::Data::new(self.debug_bodies))
+            .app_data(web::Data::new(self.type_case))
+            .app_data(web::Data::new(self.tenant_header.clone()))
+            .app_data(web::Data::from(self.clock.clone()))
             .service(scoped_services);
     }
 
+    /// Get the value of a key, or 304 if it still matches the `If-None-Match` of the request
     pub async fn get_by_key(
         db: web::Data<StorageType>,
+        cache_policy: web::Data<CachePolicy>,
+        int_encoding: web::Data<models::IntEncoding>,
         key: web::Path<String>,
-    ) -> web::Json<models::ApiResponse<models::GetResponse>> {
+        if_none_match: Option<web::Header<IfNoneMatch>>,
+    ) -> Either<
+        CustomizeResponder<HttpResponse>,
+        CustomizeResponder<models::ApiResponse<models::GetResponse>>,
+    > {
         let possible_value = db.get(key.as_bytes()).await;
-        return match possible_value {
-            Ok(Some(sotre_value)) => match sotre_value.value_type {
-                ValueType::Integer => {
-                    web::Json(models::ApiResponse::Success(models::GetResponse {
-                        value: Some(models::IntOrString::Int(i64::from_be_bytes(
-                            sotre_value.value.as_slice().try_into().unwrap(),
-                        ))),
-                    }))
-                }
-                ValueType::String => web::Json(models::ApiResponse::Success(models::GetResponse {
-                    value: Some(models::IntOrString::String(
-                        String::from_utf8(sotre_value.value).unwrap(),
-                    )),
-                })),
-            },
-            Ok(None) => web::Json(models::ApiResponse::Success(models::GetResponse {
+        let (ttl, etag) = match &possible_value {
+            Ok(Some(store_value)) => (Some(store_value.ttl), Some(entity_tag(store_value))),
+            _ => (None, None),
+        };
+
+        if let (Some(etag), Some(web::Header(if_none_match))) = (&etag, if_none_match) {
+            let unchanged = match if_none_match {
+                IfNoneMatch::Any => true,
+                IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+            };
+            if unchanged {
+                let response = HttpResponse::NotModified()
+                    .insert_header(ETag(etag.clone()))
+                    .finish();
+                return Either::Left(cache_policy.apply(&key, ttl, response));
+            }
+        }
+
+        let response = cache_policy.apply(
+            &key,
+            ttl,
+            Self::get_response(possible_value, **int_encoding),
+        );
+        return Either::Right(match etag {
+            Some(etag) => response.insert_header(ETag(etag)),
+            None => response,
+        });
+    }
+
+    pub async fn get_ex(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        key: web::Path<String>,
+        request: Body<models::GetExRequest>,
+    ) -> models::ApiResponse<models::GetResponse> {
+        let possible_value = db.get_ex(key.as_bytes(), request.new_ttl()).await;
+        return Self::get_response(possible_value, **int_encoding);
+    }
+
+    /// Stream the stored bytes of a value, without wrapping them in JSON
+    pub async fn get_raw(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+    ) -> Either<HttpResponse, models::ApiResponse<()>> {
+        let store_value = match db.get(key.as_bytes()).await {
+            Ok(Some(store_value)) => store_value,
+            Ok(None) => {
+                return Either::Right(models::ApiResponse::from(DatabaseError::ValueNotFound(
+                    key.into_inner(),
+                )))
+            }
+            Err(err) => return Either::Right(models::ApiResponse::from(err)),
+        };
+        let Some(content_type) = raw_content_type(&store_value.value_type) else {
+            let route = match store_value.value_type {
+                ValueType::Hash => "hash",
+                _ => "list",
+            };
+            return Either::Right(models::ApiResponse::from(DatabaseError::InvalidValueType(
+                format!(
+                    "{key} holds a {}, read it from /keys/{key}/{route}",
+                    store_value.value_type
+                ),
+            )));
+        };
+
+        let value = web::Bytes::from(store_value.value);
+        let length = value.len();
+        let chunks = (0..length).step_by(RAW_CHUNK_SIZE).map(move |start| {
+            let end = (start + RAW_CHUNK_SIZE).min(length);
+            return Ok::<_, actix_web::Error>(value.slice(start..end));
+        });
+        return Either::Left(
+            HttpResponse::Ok()
+                .content_type(content_type)
+                .streaming(futures::stream::iter(chunks)),
+        );
+    }
+
+    /// Store the request body as a blob, without JSON wrapping or base64
+    pub async fn set_raw(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        web::Query(models::SetRawQuery { ttl }): web::Query<models::SetRawQuery>,
+        body: web::Bytes,
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
+        let result = db
+            .set(key.as_bytes(), &StorageValue::blob(&body, ttl))
+            .await;
+        return match result {
+            Ok(()) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    /// Render the value of a key read from the storage
+    ///
+    /// # Arguments
+    /// * `possible_value` - The result of the read
+    /// * `int_encoding` - How integers are written in the response
+    fn get_response(
+        possible_value: Result<Option<StorageValue>, DatabaseError>,
+        int_encoding: models::IntEncoding,
+    ) -> models::ApiResponse<models::GetResponse> {
+        return match possible_value.and_then(|value| Self::render_value(value, int_encoding)) {
+            Ok(response) => models::ApiResponse::Success(response),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    /// Render a value read from the storage, with nulls for a missing key
+    ///
+    /// # Arguments
+    /// * `possible_value` - The value, if the key exists
+    /// * `int_encoding` - How integers are written in the response
+    fn render_value(
+        possible_value: Option<StorageValue>,
+        int_encoding: models::IntEncoding,
+    ) -> Result<models::GetResponse, DatabaseError> {
+        let Some(store_value) = possible_value else {
+            return Ok(models::GetResponse {
                 value: None,
-            })),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+                value_type: None,
+                length: None,
+            });
+        };
+        // Collections and blobs are summarized by their length, they are read from their own routes
+        let (value, length) = match store_value.value_type {
+            ValueType::Integer => store_value
+                .get_integer_value()
+                .map(|value| match int_encoding {
+                    models::IntEncoding::Number => (Some(models::IntOrString::Int(value)), None),
+                    models::IntEncoding::String => {
+                        (Some(models::IntOrString::String(value.to_string())), None)
+                    }
+                }),
+            ValueType::String => Ok((
+                Some(models::IntOrString::String(
+                    String::from_utf8_lossy(&store_value.value).to_string(),
+                )),
+                None,
+            )),
+            ValueType::Json => store_value
+                .get_json_value()
+                .map(|value| (Some(models::IntOrString::Json(value)), None)),
+            ValueType::List => store_value
+                .get_list_value()
+                .map(|values| (None, Some(values.len()))),
+            ValueType::Blob => Ok((None, Some(store_value.value.len()))),
+            ValueType::Hash => store_value
+                .get_hash_value()
+                .map(|fields| (None, Some(fields.len()))),
+        }?;
+        return Ok(models::GetResponse {
+            value,
+            value_type: Some(store_value.value_type.to_string()),
+            length,
+        });
+    }
+
+    /// Get the values of several keys as of a single point in time
+    pub async fn get_many(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        request: Body<models::GetManyRequest>,
+    ) -> models::ApiResponse<models::GetManyResponse> {
+        let keys: Vec<Vec<u8>> = request
+            .keys
+            .iter()
+            .map(|key| key.as_bytes().to_vec())
+            .collect();
+        let values = db.get_many(&keys).await.and_then(|values| {
+            values
+                .into_iter()
+                .map(|value| Self::render_value(value, **int_encoding))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        return match values {
+            Ok(values) => models::ApiResponse::Success(models::GetManyResponse { values }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn get_json(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        web::Query(models::JsonQuery { path }): web::Query<models::JsonQuery>,
+    ) -> models::ApiResponse<models::JsonQueryResponse> {
+        let document = match db.get(key.as_bytes()).await {
+            Ok(Some(store_value)) => store_value.get_json_value(),
+            Ok(None) => {
+                return models::ApiResponse::Success(models::JsonQueryResponse { value: None })
+            }
+            Err(err) => Err(err),
+        };
+
+        let result =
+            document.and_then(|document| json::query(&document, &path).map(Option::<&_>::cloned));
+        return match result {
+            Ok(value) => models::ApiResponse::Success(models::JsonQueryResponse { value }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn merge_json(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        patch: Body<serde_json::Value>,
+    ) -> models::ApiResponse<models::JsonQueryResponse> {
+        let result = db
+            .json_merge(key.as_bytes(), &patch)
+            .await
+            .and_then(|store_value| store_value.get_json_value());
+        return match result {
+            Ok(document) => models::ApiResponse::Success(models::JsonQueryResponse {
+                value: Some(document),
+            }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn get_list(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+    ) -> models::ApiResponse<models::ListResponse> {
+        let values = match db.get(key.as_bytes()).await {
+            Ok(Some(store_value)) => store_value.get_list_value().map(Some),
+            Ok(None) => Ok(None),
+            Err(err) => Err(err),
+        };
+        return match values {
+            Ok(values) => models::ApiResponse::Success(models::ListResponse { values }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn list_push(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::ListPushRequest>,
+        collection_limit: web::Data<CollectionLimit>,
+    ) -> models::ApiResponse<models::ListPushResponse> {
+        let result = db
+            .list_push(
+                key.as_bytes(),
+                &request.values,
+                request.maxlen,
+                collection_limit.0,
+            )
+            .await;
+        return match result {
+            Ok(length) => models::ApiResponse::Success(models::ListPushResponse { length }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn append(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::AppendRequest>,
+    ) -> models::ApiResponse<models::AppendResponse> {
+        return match db.append(key.as_bytes(), &request.value, request.ttl).await {
+            Ok(length) => models::ApiResponse::Success(models::AppendResponse { length }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn get_hash(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+    ) -> models::ApiResponse<models::HashResponse> {
+        let fields = match db.get(key.as_bytes()).await {
+            Ok(Some(store_value)) => store_value.get_hash_value().map(Some),
+            Ok(None) => Ok(None),
+            Err(err) => Err(err),
+        };
+        return match fields {
+            Ok(fields) => models::ApiResponse::Success(models::HashResponse {
+                fields: fields.map(|fields| {
+                    fields
+                        .into_iter()
+                        .map(|(field, value)| (field, String::from_utf8_lossy(&value).to_string()))
+                        .collect()
+                }),
+            }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn hset_many(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::HashSetRequest>,
+        collection_limit: web::Data<CollectionLimit>,
+    ) -> models::ApiResponse<models::HashSetResponse> {
+        let fields: HashMap<String, Vec<u8>> = request
+            .into_inner()
+            .fields
+            .into_iter()
+            .map(|(field, value)| (field, value.into_bytes()))
+            .collect();
+        return match db
+            .hset_many(key.as_bytes(), &fields, collection_limit.0)
+            .await
+        {
+            Ok((added, updated)) => {
+                models::ApiResponse::Success(models::HashSetResponse { added, updated })
+            }
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
     pub async fn get_all_keys(
         db: web::Data<StorageType>,
-        web::Query(models::GetAllKeysQuery { prefix }): web::Query<models::GetAllKeysQuery>,
-    ) -> web::Json<models::ApiResponse<models::GetAllKeysResponse>> {
-        let keys = db.get_all_keys(prefix.as_bytes()).await;
-        return match keys {
-            Ok(keys) => web::Json(models::ApiResponse::Success(models::GetAllKeysResponse {
-                keys,
-            })),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+        ttl_rounding: web::Data<TtlRounding>,
+        clock: web::Data<dyn Clock>,
+        max_response_bytes: web::Data<MaxResponseBytes>,
+        web::Query(query): web::Query<models::GetAllKeysQuery>,
+    ) -> KeyListing {
+        let keys = match db.get_all_keys(query.prefix.as_bytes()).await {
+            Ok(keys) => keys,
+            Err(err) => return Either::Left(models::ApiResponse::from(err)),
+        };
+        let (keys, common_prefixes) = match &query.delimiter {
+            Some(delimiter) => listing::fold_by_delimiter(keys, &query.prefix, delimiter),
+            None => (keys, Vec::new()),
+        };
+
+        let total = keys.len();
+        let (mut items, mut next_cursor) =
+            listing::paginate(keys, query.cursor.as_deref(), query.limit);
+        if let Some(cut) = listing::fit_bytes(&mut items, max_response_bytes.0) {
+            next_cursor = Some(cut);
+        }
+        let response = models::GetAllKeysResponse {
+            page: models::Page {
+                items,
+                truncated: next_cursor.is_some(),
+                next_cursor,
+                total: Some(total),
+            },
+            common_prefixes,
+        };
+        if !query.with_ttl {
+            return Either::Left(models::ApiResponse::Success(response));
+        }
+        return Either::Right(
+            Self::with_ttls(
+                &db,
+                **ttl_rounding,
+                &**clock,
+                **max_response_bytes,
+                response,
+            )
+            .await,
+        );
+    }
+
+    /// Add the remaining TTL to every key of a listing, with one storage read per key
+    ///
+    /// Keys that expired since they were listed are left out, and not counted in `total`.
+    /// The entries are larger than the bare keys, so the page may be cut again to fit
+    /// `max_response_bytes`.
+    async fn with_ttls(
+        db: &StorageType,
+        ttl_rounding: TtlRounding,
+        clock: &dyn Clock,
+        max_response_bytes: MaxResponseBytes,
+        listing: models::GetAllKeysResponse,
+    ) -> models::ApiResponse<models::KeysWithTtlResponse> {
+        let listed = listing.page.items.len();
+        let mut items = Vec::with_capacity(listed);
+        for key in listing.page.items {
+            let ttl = match db.get_expire_at(key.as_bytes()).await {
+                Ok(expire_at) => ttl_rounding.remaining(expire_at, clock.now_millis()),
+                Err(DatabaseError::ValueNotFound(_)) => continue,
+                Err(err) => return models::ApiResponse::from(err),
+            };
+            items.push(models::KeyWithTtl { key, ttl });
+        }
+
+        let expired = listed - items.len();
+        let next_cursor = match listing::fit_bytes(&mut items, max_response_bytes.0) {
+            Some(cut) => Some(cut.key),
+            None => listing.page.next_cursor,
+        };
+        return models::ApiResponse::Success(models::KeysWithTtlResponse {
+            page: models::Page {
+                items,
+                truncated: next_cursor.is_some(),
+                next_cursor,
+                total: listing
+                    .page
+                    .total
+                    .map(|total| total.saturating_sub(expired)),
+            },
+            common_prefixes: listing.common_prefixes,
+        });
+    }
+
+    pub async fn get_keys_changed_since(
+        db: web::Data<StorageType>,
+        web::Query(models::ChangedSinceQuery { ts }): web::Query<models::ChangedSinceQuery>,
+    ) -> models::ApiResponse<models::GetAllKeysResponse> {
+        return match db.keys_modified_since(ts).await {
+            Ok(keys) => models::ApiResponse::Success(models::GetAllKeysResponse {
+                page: models::Page::complete(keys),
+                common_prefixes: Vec::new(),
+            }),
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
+    pub async fn get_keys_range(
+        db: web::Data<StorageType>,
+        web::Query(models::RangeQuery {
+            start,
+            end,
+            limit,
+            cursor,
+            with_ttl,
+        }): web::Query<models::RangeQuery>,
+        ttl_rounding: web::Data<TtlRounding>,
+        clock: web::Data<dyn Clock>,
+        max_response_bytes: web::Data<MaxResponseBytes>,
+    ) -> KeyListing {
+        let start = cursor.unwrap_or(start);
+        // One more key than the limit tells whether another page follows, and where it starts
+        let keys = db
+            .range(
+                start.as_bytes(),
+                end.as_bytes(),
+                limit.map(|limit| limit.saturating_add(1)),
+            )
+            .await;
+        let mut keys = match keys {
+            Ok(keys) => keys,
+            Err(err) => return Either::Left(models::ApiResponse::from(err)),
+        };
+
+        let mut next_cursor = match limit {
+            Some(limit) if keys.len() > limit => keys.pop(),
+            _ => None,
+        };
+        // Counting the keys of the whole range would scan it, `limit` is there to avoid that
+        let total = limit.is_none().then_some(keys.len());
+        if let Some(cut) = listing::fit_bytes(&mut keys, max_response_bytes.0) {
+            next_cursor = Some(cut);
+        }
+        let response = models::GetAllKeysResponse {
+            page: models::Page {
+                items: keys,
+                truncated: next_cursor.is_some(),
+                next_cursor,
+                total,
+            },
+            common_prefixes: Vec::new(),
+        };
+        if !with_ttl {
+            return Either::Left(models::ApiResponse::Success(response));
+        }
+        return Either::Right(
+            Self::with_ttls(
+                &db,
+                **ttl_rounding,
+                &**clock,
+                **max_response_bytes,
+                response,
+            )
+            .await,
+        );
+    }
+
     pub async fn set_key(
         db: web::Data<StorageType>,
-        request: web::Json<models::SetRequest>,
-    ) -> web::Json<models::ApiResponse<models::OperationSuccessResponse>> {
+        request: Body<models::SetRequest>,
+        web::Query(models::SetQuery { coerce, keep_ttl }): web::Query<models::SetQuery>,
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
         let store_value = match &request.value {
-            models::IntOrString::Int(i) => StorageValue {
-                value_type: ValueType::Integer,
-                ttl: request.ttl,
-                value: i.to_be_bytes().to_vec(),
-            },
-            models::IntOrString::String(s) => StorageValue {
-                value_type: ValueType::String,
-                ttl: request.ttl,
-                value: s.as_bytes().to_vec(),
+            models::IntOrString::String(s) if coerce => match s.parse::<i64>() {
+                Ok(i) => StorageValue {
+                    ttl: request.ttl,
+                    ..StorageValue::integer(i)
+                },
+                Err(_) => StorageValue::from_int_or_string(&request.value, request.ttl),
             },
+            value => StorageValue::from_int_or_string(value, request.ttl),
         };
 
-        let result = db.set(request.key.as_bytes(), &store_value).await;
+        let result = if keep_ttl {
+            db.set_keep_ttl(request.key.as_bytes(), &store_value).await
+        } else {
+            db.set(request.key.as_bytes(), &store_value).await
+        };
         return match result {
-            Ok(()) => web::Json(models::ApiResponse::Success(
-                models::OperationSuccessResponse { success: true },
-            )),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+            Ok(()) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    /// Write several keys in one atomic batch, every one of them with the TTL of the request
+    pub async fn set_many_with_ttl(
+        db: web::Data<StorageType>,
+        request: Body<models::SetManyWithTtlRequest>,
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
+        let entries: Vec<(Vec<u8>, StorageValue)> = request
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                let value = StorageValue::from_int_or_string(value, request.ttl);
+                return (key.as_bytes().to_vec(), value);
+            })
+            .collect();
+
+        return match db.set_many(&entries).await {
+            Ok(()) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
     pub async fn delete_key(
         db: web::Data<StorageType>,
         key: web::Path<String>,
-    ) -> web::Json<models::ApiResponse<models::OperationSuccessResponse>> {
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
         let result = db.delete(key.as_bytes()).await;
         return match result {
-            Ok(()) => web::Json(models::ApiResponse::Success(
-                models::OperationSuccessResponse { success: true },
-            )),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+            Ok(()) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
     pub async fn delete_keys(
         db: web::Data<StorageType>,
-        request: Option<web::Json<models::DeleteKeysRequest>>,
-    ) -> web::Json<models::ApiResponse<models::OperationSuccessResponse>> {
+        request: Option<Body<models::DeleteKeysRequest>>,
+        web::Query(models::DeleteKeysQuery { confirm_all, force }): web::Query<
+            models::DeleteKeysQuery,
+        >,
+        delete_delimiter: web::Data<DeleteDelimiter>,
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
         let prefix = match request {
             None => String::new(),
             Some(request) => request.prefix.clone(),
         };
 
-        match db.delete_prefix(prefix.as_bytes()).await {
-            Ok(()) => {
-                return web::Json(models::ApiResponse::Success(
-                    models::OperationSuccessResponse { success: true },
-                ))
+        // An empty prefix matches every key, so wiping the database must be explicit
+        if prefix.is_empty() && !confirm_all {
+            return models::ApiResponse::ErrorResponse(models::ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "An empty prefix deletes all keys, pass confirm_all=true to proceed",
+            ));
+        }
+
+        // A prefix cut inside a level of the keys also matches the longer words of that level
+        if let Some(delimiter) = &delete_delimiter.0 {
+            if !prefix.is_empty() && !prefix.ends_with(delimiter.as_str()) && !force {
+                return models::ApiResponse::ErrorResponse(models::ErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "The prefix must end with {delimiter:?}, pass force=true to delete \
+                         every key starting with {prefix:?}"
+                    ),
+                ));
             }
-            Err(err) => {
-                return web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                    error: format!("{err}",),
-                }))
+        }
+
+        return match db.delete_prefix(prefix.as_bytes()).await {
+            Ok(()) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
             }
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn rename_nx(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::RenameRequest>,
+    ) -> models::ApiResponse<models::RenameResponse> {
+        let result = db
+            .rename_nx(key.as_bytes(), request.new_key.as_bytes())
+            .await;
+        return match result {
+            Ok(renamed) => models::ApiResponse::Success(models::RenameResponse { renamed }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn lock(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::LockRequest>,
+    ) -> models::ApiResponse<models::LockResponse> {
+        if request.ttl <= 0 {
+            return models::ApiResponse::ErrorResponse(models::ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "A lock needs a positive TTL, so a crashed holder can't keep it forever",
+            ));
         }
+
+        let token = format!("{:032x}", rand::random::<u128>());
+        let lease = StorageValue::from_int_or_string(
+            &models::IntOrString::String(token.clone()),
+            request.ttl,
+        );
+        return match db.set_nx(key.as_bytes(), &lease).await {
+            Ok(true) => models::ApiResponse::Success(models::LockResponse { token }),
+            Ok(false) => models::ApiResponse::ErrorResponse(models::ErrorResponse::new(
+                StatusCode::CONFLICT,
+                format!("The lock {key} is held"),
+            )),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn unlock(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::UnlockRequest>,
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
+        return match db
+            .delete_if_equal(key.as_bytes(), request.token.as_bytes())
+            .await
+        {
+            Ok(true) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Ok(false) => models::ApiResponse::ErrorResponse(models::ErrorResponse::new(
+                StatusCode::CONFLICT,
+                format!("The lock {key} is not held with this token"),
+            )),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn get_type(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+    ) -> models::ApiResponse<models::TypeResponse> {
+        return match db.get(key.as_bytes()).await {
+            Ok(value) => models::ApiResponse::Success(models::TypeResponse {
+                value_type: value.map(|value| value.value_type.to_string()),
+            }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn get_object(
+        db: web::Data<StorageType>,
+        clock: web::Data<dyn Clock>,
+        key: web::Path<String>,
+    ) -> models::ApiResponse<models::ObjectResponse> {
+        // A single read, so the type, the expiry and the size all describe the same record
+        let record = match db.get_record(key.as_bytes()).await {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                return models::ApiResponse::from(DatabaseError::ValueNotFound(key.into_inner()))
+            }
+            Err(err) => return models::ApiResponse::from(err),
+        };
+        let value = match StorageValue::try_from_binary(&record) {
+            Ok(value) => value,
+            Err(err) => return models::ApiResponse::from(err),
+        };
+        // The record keeps the absolute expiry
+        let expires_at = (value.ttl >= 0).then_some(value.ttl);
+        return models::ApiResponse::Success(models::ObjectResponse {
+            value_type: value.value_type.to_string(),
+            encoded_length: record.len(),
+            ttl: expires_at.map_or(-1, |expire_at| expire_at - clock.now()),
+            expires_at,
+        });
     }
 
+    /// Get the remaining TTL of a key, rounded to whole seconds by the TTL rounding policy
     pub async fn get_ttl(
         db: web::Data<StorageType>,
+        ttl_rounding: web::Data<TtlRounding>,
+        clock: web::Data<dyn Clock>,
         key: web::Path<String>,
-    ) -> web::Json<models::ApiResponse<models::GetTtlResponse>> {
-        let ttl = db.get_ttl(key.as_bytes()).await;
+        web::Query(models::GetTtlQuery { absolute }): web::Query<models::GetTtlQuery>,
+    ) -> models::ApiResponse<models::GetTtlResponse> {
+        let expire_at = db.get_expire_at(key.as_bytes()).await;
+        let ttl = if absolute {
+            expire_at
+        } else {
+            expire_at.map(|expire_at| ttl_rounding.remaining(expire_at, clock.now_millis()))
+        };
         return match ttl {
-            Ok(ttl) => web::Json(models::ApiResponse::Success(models::GetTtlResponse { ttl })),
-            Err(crate::errors::DatabaseError::ValueNotFound(_)) => {
-                web::Json(models::ApiResponse::Success(models::GetTtlResponse {
-                    ttl: -1,
-                }))
+            Ok(ttl) => models::ApiResponse::Success(models::GetTtlResponse { ttl }),
+            Err(DatabaseError::ValueNotFound(_)) => {
+                models::ApiResponse::Success(models::GetTtlResponse { ttl: -1 })
             }
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
     pub async fn set_ttl(
         db: web::Data<StorageType>,
+        missing_key_ttl: web::Data<MissingKeyTtl>,
         key: web::Path<String>,
-        request: web::Json<models::SetTtlRequest>,
-    ) -> web::Json<models::ApiResponse<models::OperationSuccessResponse>> {
+        request: Body<models::SetTtlRequest>,
+    ) -> models::ApiResponse<models::OperationSuccessResponse> {
         let result = db.update_ttl(key.as_bytes(), request.ttl).await;
         return match result {
-            Ok(()) => web::Json(models::ApiResponse::Success(
-                models::OperationSuccessResponse { success: true },
-            )),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+            Ok(()) => {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Err(DatabaseError::ValueNotFound(_))
+                if *missing_key_ttl.get_ref() == MissingKeyTtl::Ignore =>
+            {
+                models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+            }
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn adjust_ttl(
+        db: web::Data<StorageType>,
+        key: web::Path<String>,
+        request: Body<models::AdjustTtlRequest>,
+    ) -> models::ApiResponse<models::GetTtlResponse> {
+        let result = db.adjust_ttl(key.as_bytes(), request.delta).await;
+        return match result {
+            Ok(ttl) => models::ApiResponse::Success(models::GetTtlResponse { ttl }),
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
     pub async fn increment(
         db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
         key: web::Path<String>,
-        request: web::Json<models::IncrementRequest>,
-    ) -> web::Json<models::ApiResponse<models::IncrementResponse>> {
-        let store_value_result = db
-            .increment(key.as_bytes(), request.value, request.default)
-            .await;
+        request: Body<models::IncrementRequest>,
+    ) -> models::ApiResponse<models::IncrementResponse> {
+        let store_value_result = match request.max {
+            Some(max) => {
+                db.increment_bounded(
+                    key.as_bytes(),
+                    request.value,
+                    request.default_value(),
+                    max,
+                    request.saturate,
+                    request.base,
+                )
+                .await
+            }
+            None => db
+                .increment(
+                    key.as_bytes(),
+                    request.value,
+                    request.default_value(),
+                    request.base,
+                )
+                .await
+                .map(|store_value| (store_value, false)),
+        };
         if store_value_result.is_err() {
-            return web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}", err = store_value_result.err().unwrap()),
-            }));
+            return models::ApiResponse::from(store_value_result.err().unwrap());
+        }
+
+        let (store_value, capped) = store_value_result.unwrap();
+        return match store_value.get_integer_value_in_base(request.base) {
+            Ok(value) => models::ApiResponse::Success(models::IncrementResponse {
+                value: models::JsonInt::new(value, **int_encoding),
+                formatted: String::from_utf8_lossy(&store_value.value).to_string(),
+                capped,
+            }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    /// Strict batches are applied atomically and rejected as a whole if any key fails,
+    /// lenient ones increment the keys one by one and report the failures per key.
+    pub async fn increment_many(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        request: Body<models::IncrementManyRequest>,
+    ) -> models::ApiResponse<models::IncrementManyResponse> {
+        let models::IncrementManyRequest {
+            increments,
+            lenient,
+        } = request.into_inner();
+        let mut response = models::IncrementManyResponse {
+            results: BTreeMap::new(),
+            errors: BTreeMap::new(),
+        };
+
+        if lenient {
+            for (key, value) in increments {
+                let result = db
+                    .increment(key.as_bytes(), value, Some(0), 10)
+                    .await
+                    .and_then(|store_value| store_value.get_integer_value());
+                match result {
+                    Ok(new_value) => {
+                        response
+                            .results
+                            .insert(key, models::JsonInt::new(new_value, **int_encoding));
+                    }
+                    Err(err) => {
+                        response.errors.insert(key, err.to_string());
+                    }
+                }
+            }
+            return models::ApiResponse::Success(response);
         }
 
-        return match store_value_result.unwrap().get_integer_value() {
-            Ok(value) => web::Json(models::ApiResponse::Success(models::IncrementResponse {
-                value,
-            })),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+        let batch: Vec<(Vec<u8>, i64)> = increments
+            .iter()
+            .map(|(key, value)| (key.as_bytes().to_vec(), *value))
+            .collect();
+        return match db.increment_many(&batch).await {
+            Ok(new_values) => {
+                response.results = increments
+                    .into_keys()
+                    .zip(new_values)
+                    .map(|(key, value)| (key, models::JsonInt::new(value, **int_encoding)))
+                    .collect();
+                models::ApiResponse::Success(response)
+            }
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 
     pub async fn decrement(
         db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
         key: web::Path<String>,
-        request: web::Json<models::IncrementRequest>,
-    ) -> web::Json<models::ApiResponse<models::IncrementResponse>> {
+        request: Body<models::IncrementRequest>,
+    ) -> models::ApiResponse<models::IncrementResponse> {
         let store_value_result = db
-            .decrement(key.as_bytes(), request.value, request.default)
+            .decrement(
+                key.as_bytes(),
+                request.value,
+                request.default_value(),
+                request.base,
+            )
             .await;
         if store_value_result.is_err() {
-            return web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}", err = store_value_result.err().unwrap()),
-            }));
+            return models::ApiResponse::from(store_value_result.err().unwrap());
         }
 
-        return match store_value_result.unwrap().get_integer_value() {
-            Ok(value) => web::Json(models::ApiResponse::Success(models::IncrementResponse {
-                value,
-            })),
-            Err(err) => web::Json(models::ApiResponse::ErrorResponse(models::ErrorResponse {
-                error: format!("{err}"),
-            })),
+        let store_value = store_value_result.unwrap();
+        return match store_value.get_integer_value_in_base(request.base) {
+            Ok(value) => models::ApiResponse::Success(models::IncrementResponse {
+                value: models::JsonInt::new(value, **int_encoding),
+                formatted: String::from_utf8_lossy(&store_value.value).to_string(),
+                capped: false,
+            }),
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    pub async fn decrement_and_delete(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        key: web::Path<String>,
+        request: Body<models::DecrementAndDeleteRequest>,
+    ) -> models::ApiResponse<models::DecrementAndDeleteResponse> {
+        return match db.decrement_and_delete(key.as_bytes(), request.value).await {
+            Ok((value, deleted)) => {
+                models::ApiResponse::Success(models::DecrementAndDeleteResponse {
+                    value: models::JsonInt::new(value, **int_encoding),
+                    deleted,
+                })
+            }
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    /// Add to an integer only if it holds the expected value, for optimistic counters
+    pub async fn compare_and_increment(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        key: web::Path<String>,
+        request: Body<models::CompareAndIncrementRequest>,
+    ) -> models::ApiResponse<models::CompareAndIncrementResponse> {
+        return match db
+            .compare_and_increment(key.as_bytes(), request.expected, request.delta)
+            .await
+        {
+            Ok((value, swapped)) => {
+                models::ApiResponse::Success(models::CompareAndIncrementResponse {
+                    value: models::JsonInt::new(value, **int_encoding),
+                    swapped,
+                })
+            }
+            Err(err) => models::ApiResponse::from(err),
+        };
+    }
+
+    /// Raise a high-water mark, keeping the stored value if it is already larger
+    pub async fn set_max(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        key: web::Path<String>,
+        request: Body<models::SetExtremeRequest>,
+    ) -> models::ApiResponse<models::SetExtremeResponse> {
+        return Self::set_extreme(&db, **int_encoding, &key, request.value, Extreme::Max).await;
+    }
+
+    /// Lower a low-water mark, keeping the stored value if it is already smaller
+    pub async fn set_min(
+        db: web::Data<StorageType>,
+        int_encoding: web::Data<models::IntEncoding>,
+        key: web::Path<String>,
+        request: Body<models::SetExtremeRequest>,
+    ) -> models::ApiResponse<models::SetExtremeResponse> {
+        return Self::set_extreme(&db, **int_encoding, &key, request.value, Extreme::Min).await;
+    }
+
+    async fn set_extreme(
+        db: &StorageType,
+        int_encoding: models::IntEncoding,
+        key: &str,
+        value: i64,
+        extreme: Extreme,
+    ) -> models::ApiResponse<models::SetExtremeResponse> {
+        return match db.set_extreme(key.as_bytes(), value, extreme).await {
+            Ok((value, changed)) => models::ApiResponse::Success(models::SetExtremeResponse {
+                value: models::JsonInt::new(value, int_encoding),
+                changed,
+            }),
+            Err(err) => models::ApiResponse::from(err),
         };
     }
 }
@@ -1,16 +1,25 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use actix_web::{test, App};
+use actix_web::{
+    http::{header, StatusCode},
+    test, App,
+};
 use rstest::*;
 use rstest_reuse::{apply, template};
 
-use super::service::DatabaseQueries;
+use super::service::{
+    BodyLimits, CachePolicy, CollectionLimit, DatabaseQueries, DeleteDelimiter, MaxResponseBytes,
+    MissingKeyTtl,
+};
 use crate::http_server::models;
 use crate::storages::bredis::Bredis;
+use crate::storages::clock::TtlRounding;
+use crate::storages::mock::MockClock;
 use crate::storages::rocksdb::Rocksdb;
 use crate::storages::storage::Storage;
 use crate::storages::surrealkv::SurrealKV;
-use crate::storages::value::{StorageValue, ValueType};
+use crate::storages::value::{StorageValue, TypeCase, ValueType};
 
 #[template]
 #[rstest]
@@ -67,13 +76,303 @@ async fn test_get_all_keys(
     let body: models::ApiResponse<models::GetAllKeysResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::GetAllKeysResponse { keys }) => {
+        models::ApiResponse::Success(models::GetAllKeysResponse {
+            page: models::Page { items: keys, .. },
+            ..
+        }) => {
             assert_eq!(keys.len(), 2);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
     }
 }
 
+#[apply(test_cases)]
+async fn test_get_all_keys_with_delimiter(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    for key in [
+        "user:1",
+        "user:2",
+        "user:1:posts:1",
+        "user:1:posts:2",
+        "user:2:likes",
+    ] {
+        db.set(key.as_bytes(), &StorageValue::string("value"))
+            .await
+            .unwrap();
+    }
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=user:&delimiter=:")
+        .to_request();
+    let mut body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    body.page.items.sort();
+    assert_eq!(body.page.items, vec!["user:1", "user:2"]);
+    assert_eq!(body.common_prefixes, vec!["user:1:", "user:2:"]);
+
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=user:1:&delimiter=:")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(body.page.items.is_empty(), "{body:?}");
+    assert_eq!(body.common_prefixes, vec!["user:1:posts:"]);
+
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=user:")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.items.len(), 5);
+    assert!(body.common_prefixes.is_empty());
+}
+
+#[apply(test_cases)]
+async fn test_paginate_keys(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=&limit=3")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "items": ["key1", "key2", "prefix_key1"],
+            "next_cursor": "prefix_key2",
+            "total": 5,
+            "truncated": true,
+        })
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=&limit=3&cursor=prefix_key2")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.items, vec!["prefix_key2", "value_num"]);
+    assert_eq!(body.page.next_cursor, None);
+    assert_eq!(body.page.total, Some(5));
+    assert!(!body.page.truncated);
+
+    // A range only knows whether another page follows, not how many keys it holds
+    let req = test::TestRequest::get()
+        .uri("/keys/range?start=key1&end=value_num&limit=2")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.items, vec!["key1", "key2"]);
+    assert_eq!(body.page.next_cursor.as_deref(), Some("prefix_key1"));
+    assert_eq!(body.page.total, None);
+    assert!(body.page.truncated);
+
+    let req = test::TestRequest::get()
+        .uri("/keys/range?start=key1&end=value_num&limit=2&cursor=prefix_key1")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.items, vec!["prefix_key1", "prefix_key2"]);
+    assert_eq!(body.page.next_cursor, None);
+    assert!(!body.page.truncated);
+
+    let req = test::TestRequest::get()
+        .uri("/keys/changed-since?ts=0")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.total, Some(body.page.items.len()));
+    assert!(!body.page.truncated);
+}
+
+#[apply(test_cases)]
+async fn test_get_keys_changed_since(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let since = chrono::Utc::now().timestamp_millis();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(models::SetRequest {
+            key: "changed_key".to_string(),
+            value: models::IntOrString::String("changed".to_string()),
+            ttl: -1,
+        })
+        .to_request();
+    test::call_service(&app, req).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/keys/changed-since?ts={since}"))
+        .to_request();
+    let body: models::ApiResponse<models::GetAllKeysResponse> =
+        test::call_and_read_body_json(&app, req).await;
+
+    match body {
+        models::ApiResponse::Success(models::GetAllKeysResponse {
+            page: models::Page { items: keys, .. },
+            ..
+        }) => {
+            assert_eq!(keys, vec!["changed_key"]);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_get_keys_range(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::get()
+        .uri("/keys/range?start=key2&end=value_num&limit=2")
+        .to_request();
+    let body: models::ApiResponse<models::GetAllKeysResponse> =
+        test::call_and_read_body_json(&app, req).await;
+
+    match body {
+        models::ApiResponse::Success(models::GetAllKeysResponse {
+            page: models::Page { items: keys, .. },
+            ..
+        }) => {
+            assert_eq!(keys, vec!["key2", "prefix_key1"]);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_list_keys_with_ttl(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    for (key, ttl) in [("prefix_ttl", 100), ("prefix_gone", 1)] {
+        let value = StorageValue {
+            ttl,
+            ..StorageValue::string("value")
+        };
+        db.set(key.as_bytes(), &value).await.unwrap();
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=prefix_&with_ttl=true")
+        .to_request();
+    let mut body: models::KeysWithTtlResponse = test::call_and_read_body_json(&app, req).await;
+    body.page.items.sort_by(|a, b| a.key.cmp(&b.key));
+    let keys: Vec<&str> = body
+        .page
+        .items
+        .iter()
+        .map(|item| item.key.as_str())
+        .collect();
+    assert_eq!(keys, vec!["prefix_key1", "prefix_key2", "prefix_ttl"]);
+    assert_eq!(body.page.total, Some(3));
+
+    for item in &body.page.items {
+        let req = test::TestRequest::get()
+            .uri(&format!("/keys/{}/ttl", item.key))
+            .to_request();
+        let ttl: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(item.ttl, ttl.ttl, "{}", item.key);
+    }
+    assert!((98..=100).contains(&body.page.items[2].ttl), "{body:?}");
+
+    let req = test::TestRequest::get()
+        .uri("/keys/range?start=prefix_key2&end=prefix_u&with_ttl=true")
+        .to_request();
+    let body: models::KeysWithTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body.page.items,
+        vec![
+            models::KeyWithTtl {
+                key: "prefix_key2".to_string(),
+                ttl: -1
+            },
+            models::KeyWithTtl {
+                key: "prefix_ttl".to_string(),
+                ttl: body.page.items[1].ttl
+            },
+        ]
+    );
+}
+
+#[apply(test_cases)]
+async fn test_list_keys_byte_budget(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    // Every key takes 1008 bytes of JSON, so 4 of them fit in 5000 bytes
+    let padding = "x".repeat(1000);
+    let mut expected: Vec<String> = (0..10).map(|i| format!("big_{i:02}{padding}")).collect();
+    for key in &expected {
+        db.set(key.as_bytes(), &StorageValue::string("value"))
+            .await
+            .unwrap();
+    }
+    expected.sort();
+    let query_service =
+        DatabaseQueries::new(Arc::new(db)).with_max_response_bytes(MaxResponseBytes(Some(5000)));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let mut listed = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let uri = match &cursor {
+            Some(cursor) => format!("/keys?prefix=big_&cursor={cursor}"),
+            None => "/keys?prefix=big_".to_string(),
+        };
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+        assert!(body.page.items.len() <= 4, "{:?}", body.page.items.len());
+        assert_eq!(body.page.total, Some(10));
+        assert_eq!(body.page.truncated, body.page.next_cursor.is_some());
+        listed.extend(body.page.items);
+        cursor = body.page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(listed, expected);
+
+    let req = test::TestRequest::get()
+        .uri("/keys/range?start=big_&end=big_99&limit=6")
+        .to_request();
+    let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.items, expected[..4]);
+    assert_eq!(body.page.next_cursor.as_ref(), Some(&expected[4]));
+    assert!(body.page.truncated);
+
+    // The entries with a TTL are larger, and are held to the same budget
+    let req = test::TestRequest::get()
+        .uri("/keys?prefix=big_&with_ttl=true")
+        .to_request();
+    let body: models::KeysWithTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.page.items.len(), 4);
+    assert_eq!(body.page.next_cursor.as_ref(), Some(&expected[4]));
+    assert!(body.page.truncated);
+}
+
 #[apply(test_cases)]
 async fn test_set_key(
     #[future]
@@ -100,6 +399,102 @@ async fn test_set_key(
     );
 }
 
+#[apply(test_cases)]
+async fn test_set_key_keep_ttl(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let set = |uri: &str, value: &str, ttl: i64| {
+        test::TestRequest::post()
+            .uri(uri)
+            .set_json(models::SetRequest {
+                key: "session".to_string(),
+                value: models::IntOrString::String(value.to_string()),
+                ttl,
+            })
+            .to_request()
+    };
+    let ttl = || {
+        test::TestRequest::get()
+            .uri("/keys/session/ttl")
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, set("/keys", "first", 100)).await;
+    assert!(resp.status().is_success());
+
+    let resp = test::call_service(&app, set("/keys?keep_ttl=true", "second", -1)).await;
+    assert!(resp.status().is_success());
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, ttl()).await;
+    assert!((99..=100).contains(&body.ttl), "{body:?}");
+    let req = test::TestRequest::get().uri("/keys/session").to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        matches!(&body.value, Some(models::IntOrString::String(value)) if value == "second"),
+        "{body:?}"
+    );
+
+    // Without keep_ttl, the TTL of the request replaces the expiry
+    let resp = test::call_service(&app, set("/keys", "third", 30)).await;
+    assert!(resp.status().is_success());
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, ttl()).await;
+    assert!((29..=30).contains(&body.ttl), "{body:?}");
+}
+
+#[apply(test_cases)]
+async fn test_set_key_coerce(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    for (uri, key, expected_type) in [
+        ("/keys?coerce=true", "coerced", ValueType::Integer),
+        ("/keys", "not_coerced", ValueType::String),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(models::SetRequest {
+                key: key.to_string(),
+                value: models::IntOrString::String("42".to_string()),
+                ttl: -1,
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let value = db_arc.get(key.as_bytes()).await.unwrap().unwrap();
+        assert_eq!(value.value_type, expected_type);
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/keys/coerced/inc")
+        .set_json(models::IncrementRequest {
+            value: 1,
+            default: None,
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let body: models::ApiResponse<models::IncrementResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    match body {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
+            assert_eq!(value, 43);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
 #[apply(test_cases)]
 async fn test_delete_key(
     #[future]
@@ -151,6 +546,84 @@ async fn test_delete_keys(
     assert!(db_arc.get(b"key1").await.unwrap().is_some());
 }
 
+#[apply(test_cases)]
+async fn test_delete_all_keys_requires_confirm(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::delete().uri("/keys").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::delete()
+        .uri("/keys")
+        .set_json(models::DeleteKeysRequest {
+            prefix: String::new(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert!(db_arc.get(b"key1").await.unwrap().is_some());
+
+    let req = test::TestRequest::delete()
+        .uri("/keys?confirm_all=true")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert!(db_arc.get_all_keys(b"").await.unwrap().is_empty());
+}
+
+#[apply(test_cases)]
+async fn test_delete_prefix_requires_delimiter(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    for key in ["a:1", "a:2", "ab", "abc"] {
+        db_arc
+            .set(key.as_bytes(), &StorageValue::string("value"))
+            .await
+            .unwrap();
+    }
+
+    let query_service = DatabaseQueries::new(db_arc.clone())
+        .with_delete_delimiter(DeleteDelimiter(Some(":".to_string())));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let delete = |uri: &str, prefix: &str| {
+        test::TestRequest::delete()
+            .uri(uri)
+            .set_json(models::DeleteKeysRequest {
+                prefix: prefix.to_string(),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, delete("/keys", "a")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert!(db_arc.get(b"ab").await.unwrap().is_some());
+    assert!(db_arc.get(b"a:1").await.unwrap().is_some());
+
+    let resp = test::call_service(&app, delete("/keys", "a:")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(db_arc.get(b"a:1").await.unwrap().is_none());
+    assert!(db_arc.get(b"a:2").await.unwrap().is_none());
+    assert!(db_arc.get(b"ab").await.unwrap().is_some());
+
+    let resp = test::call_service(&app, delete("/keys?force=true", "a")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(db_arc.get(b"ab").await.unwrap().is_none());
+    assert!(db_arc.get(b"abc").await.unwrap().is_none());
+    assert!(db_arc.get(b"key1").await.unwrap().is_some());
+}
+
 #[apply(test_cases)]
 async fn test_ttl(
     #[future]
@@ -222,11 +695,11 @@ async fn test_integer_value(
     let body: models::ApiResponse<models::GetResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::GetResponse { value }) => {
+        models::ApiResponse::Success(models::GetResponse { value, .. }) => {
             let value = value.unwrap();
             match value {
                 models::IntOrString::Int(i) => assert_eq!(i, 123),
-                models::IntOrString::String(_) => panic!("Unexpected value: {value:?}"),
+                _ => panic!("Unexpected value: {value:?}"),
             }
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
@@ -270,11 +743,11 @@ async fn test_string_value(
     let body: models::ApiResponse<models::GetResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::GetResponse { value }) => {
+        models::ApiResponse::Success(models::GetResponse { value, .. }) => {
             let value = value.unwrap();
             match value {
                 models::IntOrString::String(s) => assert_eq!(s, "value3"),
-                models::IntOrString::Int(_) => panic!("Unexpected value: {value:?}"),
+                _ => panic!("Unexpected value: {value:?}"),
             }
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
@@ -295,6 +768,10 @@ async fn test_increment(
         .set_json(models::IncrementRequest {
             value: 1,
             default: None,
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
         })
         .to_request();
     let resp = test::call_service(&app, req).await;
@@ -308,13 +785,98 @@ async fn test_increment(
     let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::IncrementResponse { value }) => {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
             assert_eq!(value, 2);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
     }
 }
 
+#[apply(test_cases)]
+async fn test_increment_integer_set_over_http(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(models::SetRequest {
+            key: "counter".to_string(),
+            value: models::IntOrString::Int(123),
+            ttl: -1,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/keys/counter/inc")
+        .set_json(models::IncrementRequest {
+            value: 7,
+            default: None,
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let body: models::IncrementResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.value, 130);
+
+    let req = test::TestRequest::get().uri("/keys/counter").to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        matches!(body.value, Some(models::IntOrString::Int(130))),
+        "{body:?}"
+    );
+}
+
+#[apply(test_cases)]
+async fn test_increment_with_max(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let increment = |saturate| models::IncrementRequest {
+        value: 5,
+        default: None,
+        assume_zero: true,
+        base: 10,
+        max: Some(8),
+        saturate,
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/keys/requests/inc")
+        .set_json(increment(true))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["value"], 5);
+    assert_eq!(body["capped"], false);
+
+    // Past the max without saturation: rejected and left unchanged
+    let req = test::TestRequest::post()
+        .uri("/keys/requests/inc")
+        .set_json(increment(false))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri("/keys/requests/inc")
+        .set_json(increment(true))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["value"], 8);
+    assert_eq!(body["capped"], true);
+}
+
 #[apply(test_cases)]
 async fn test_default_increment(
     #[future]
@@ -329,6 +891,10 @@ async fn test_default_increment(
         .set_json(models::IncrementRequest {
             value: 1,
             default: Some(10),
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
         })
         .to_request();
     let resp = test::call_service(&app, req).await;
@@ -342,7 +908,7 @@ async fn test_default_increment(
     let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::IncrementResponse { value }) => {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
             assert_eq!(value, 2);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
@@ -363,6 +929,10 @@ async fn test_default_exist_increment(
         .set_json(models::IncrementRequest {
             value: 1,
             default: Some(10),
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
         })
         .to_request();
     let resp = test::call_service(&app, req).await;
@@ -376,7 +946,7 @@ async fn test_default_exist_increment(
     let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::IncrementResponse { value }) => {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
             assert_eq!(value, 11);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
@@ -397,6 +967,10 @@ async fn test_decrement(
         .set_json(models::IncrementRequest {
             value: 1,
             default: None,
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
         })
         .to_request();
     let resp = test::call_service(&app, req).await;
@@ -410,7 +984,7 @@ async fn test_decrement(
     let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::IncrementResponse { value }) => {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
             assert_eq!(value, 0);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
@@ -418,7 +992,157 @@ async fn test_decrement(
 }
 
 #[apply(test_cases)]
-async fn test_default_decrement(
+async fn test_decrement_and_delete(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let decrement = |value: i64| {
+        test::TestRequest::post()
+            .uri("/keys/value_num/decr-del-on-zero")
+            .set_json(models::DecrementAndDeleteRequest { value })
+            .to_request()
+    };
+
+    // Decrementing by a negative value raises the count, above zero the key is kept
+    let body: models::DecrementAndDeleteResponse =
+        test::call_and_read_body_json(&app, decrement(-2)).await;
+    assert_eq!(body.value, 3);
+    assert!(!body.deleted);
+
+    let body: models::DecrementAndDeleteResponse =
+        test::call_and_read_body_json(&app, decrement(3)).await;
+    assert_eq!(body.value, 0);
+    assert!(body.deleted);
+
+    let req = test::TestRequest::get().uri("/keys/value_num").to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(body.value.is_none(), "{body:?}");
+
+    let resp = test::call_service(&app, decrement(1)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[apply(test_cases)]
+async fn test_set_max_min(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let set = |route: &str, value: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/keys/peak/{route}"))
+            .set_json(models::SetExtremeRequest { value })
+            .to_request()
+    };
+
+    for (route, value, expected, changed) in [
+        // A missing key is set to the value
+        ("setmax", 100, 100, true),
+        ("setmax", 250, 250, true),
+        ("setmax", 120, 250, false),
+        ("setmin", 300, 250, false),
+        ("setmin", 50, 50, true),
+    ] {
+        let body: models::SetExtremeResponse =
+            test::call_and_read_body_json(&app, set(route, value)).await;
+        assert_eq!(body.value, expected, "{route} {value}");
+        assert_eq!(body.changed, changed, "{route} {value}");
+    }
+
+    let value = db_arc.get(b"peak").await.unwrap().unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 50);
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/setmax")
+        .set_json(models::SetExtremeRequest { value: 1 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[apply(test_cases)]
+async fn test_get_many(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys/mget")
+        .set_json(models::GetManyRequest {
+            keys: vec![
+                "value_num".to_string(),
+                "missing".to_string(),
+                "key1".to_string(),
+            ],
+        })
+        .to_request();
+    let body: models::GetManyResponse = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body.values.len(), 3);
+    assert!(
+        matches!(body.values[0].value, Some(models::IntOrString::Int(1))),
+        "{body:?}"
+    );
+    assert!(body.values[1].value.is_none(), "{body:?}");
+    assert!(body.values[1].value_type.is_none(), "{body:?}");
+    assert!(
+        matches!(&body.values[2].value, Some(models::IntOrString::String(value)) if value == "value1"),
+        "{body:?}"
+    );
+}
+
+#[apply(test_cases)]
+async fn test_compare_and_increment(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let cas = |key: &str, expected: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/keys/{key}/cas-incr"))
+            .set_json(models::CompareAndIncrementRequest { expected, delta: 5 })
+            .to_request()
+    };
+
+    // value_num holds 1
+    let body: models::CompareAndIncrementResponse =
+        test::call_and_read_body_json(&app, cas("value_num", 1)).await;
+    assert_eq!(body.value, 6);
+    assert!(body.swapped);
+
+    let body: models::CompareAndIncrementResponse =
+        test::call_and_read_body_json(&app, cas("value_num", 1)).await;
+    assert_eq!(body.value, 6);
+    assert!(!body.swapped);
+
+    let req = test::TestRequest::get().uri("/keys/value_num").to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        matches!(body.value, Some(models::IntOrString::Int(6))),
+        "{body:?}"
+    );
+
+    let resp = test::call_service(&app, cas("missing", 0)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = test::call_service(&app, cas("key1", 0)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[apply(test_cases)]
+async fn test_default_decrement(
     #[future]
     #[case]
     db: Box<dyn Storage>,
@@ -431,6 +1155,10 @@ async fn test_default_decrement(
         .set_json(models::IncrementRequest {
             value: 1,
             default: Some(10),
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
         })
         .to_request();
     let resp = test::call_service(&app, req).await;
@@ -444,7 +1172,7 @@ async fn test_default_decrement(
     let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::IncrementResponse { value }) => {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
             assert_eq!(value, 9);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
@@ -465,6 +1193,10 @@ async fn test_default_exist_decrement(
         .set_json(models::IncrementRequest {
             value: 1,
             default: Some(10),
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
         })
         .to_request();
     let resp = test::call_service(&app, req).await;
@@ -478,13 +1210,181 @@ async fn test_default_exist_decrement(
     let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
 
     match body {
-        models::ApiResponse::Success(models::IncrementResponse { value }) => {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
             assert_eq!(value, 0);
         }
         models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
     }
 }
 
+#[apply(test_cases)]
+async fn test_increment_missing_key(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys/missing_num/inc")
+        .set_json(models::IncrementRequest {
+            value: 1,
+            default: None,
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(_) => panic!("Unexpected response: {body:?}"),
+        models::ApiResponse::ErrorResponse(models::ErrorResponse {
+            error, retryable, ..
+        }) => {
+            assert!(error.contains("missing_num"), "Unexpected error: {error}");
+            assert!(!retryable);
+        }
+    }
+}
+
+#[apply(test_cases)]
+async fn test_increment_missing_key_assume_zero(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys/missing_num/inc")
+        .set_json(models::IncrementRequest {
+            value: 3,
+            default: None,
+            assume_zero: true,
+            base: 10,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+
+    let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
+            assert_eq!(value, 3);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_increment_hex(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys/hex_num/inc")
+        .set_json(models::IncrementRequest {
+            value: 1,
+            default: Some(0xfe),
+            assume_zero: false,
+            base: 16,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let body: models::ApiResponse<models::IncrementResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    match body {
+        models::ApiResponse::Success(models::IncrementResponse {
+            value, formatted, ..
+        }) => {
+            assert_eq!(value, 255);
+            assert_eq!(formatted, "ff");
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/keys/hex_num/inc")
+        .set_json(models::IncrementRequest {
+            value: 1,
+            default: None,
+            assume_zero: false,
+            base: 16,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let body: models::ApiResponse<models::IncrementResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    match body {
+        models::ApiResponse::Success(models::IncrementResponse {
+            value, formatted, ..
+        }) => {
+            assert_eq!(value, 256);
+            assert_eq!(formatted, "100");
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_decrement_missing_key_assume_zero(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys/missing_num/dec")
+        .set_json(models::IncrementRequest {
+            value: 3,
+            default: None,
+            assume_zero: true,
+            base: 10,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+
+    let body: models::ApiResponse<models::IncrementResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::IncrementResponse { value, .. }) => {
+            assert_eq!(value, -3);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
 #[apply(test_cases)]
 async fn test_get_ttl(
     #[future]
@@ -513,6 +1413,41 @@ async fn test_get_ttl(
     }
 }
 
+#[apply(test_cases)]
+async fn test_get_ttl_absolute(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(serde_json::json!({ "key": "session", "value": "token", "ttl": 600 }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let now = chrono::Utc::now().timestamp();
+    let req = test::TestRequest::get()
+        .uri("/keys/session/ttl?absolute=true")
+        .to_request();
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        (body.ttl - (now + 600)).abs() <= 2,
+        "{} vs {}",
+        body.ttl,
+        now + 600
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/keys/key1/ttl?absolute=true")
+        .to_request();
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.ttl, -1);
+}
+
 #[apply(test_cases)]
 async fn test_get_ttl_nonexistent_key(
     #[future]
@@ -543,6 +1478,51 @@ async fn test_get_ttl_nonexistent_key(
     }
 }
 
+#[apply(test_cases)]
+async fn test_set_key_rejects_other_json_types(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    for value in [
+        serde_json::json!(true),
+        serde_json::Value::Null,
+        serde_json::json!(1.5),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/keys")
+            .set_json(serde_json::json!({"key": "typed", "value": value, "ttl": -1}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{value}");
+        let body = test::read_body(resp).await;
+        assert!(
+            String::from_utf8_lossy(&body).contains("value must be an integer or string"),
+            "{value}: {body:?}"
+        );
+    }
+    assert!(db_arc.get(b"typed").await.unwrap().is_none());
+
+    for (value, expected_type) in [
+        (serde_json::json!(42), ValueType::Integer),
+        (serde_json::json!("42"), ValueType::String),
+        (serde_json::json!([1, true]), ValueType::Json),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/keys")
+            .set_json(serde_json::json!({"key": "typed", "value": value, "ttl": -1}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{value}");
+        let stored = db_arc.get(b"typed").await.unwrap().unwrap();
+        assert_eq!(stored.value_type, expected_type, "{value}");
+    }
+}
+
 #[apply(test_cases)]
 async fn test_set_key_with_ttl(
     #[future]
@@ -589,6 +1569,95 @@ async fn test_set_key_with_ttl(
     }
 }
 
+#[apply(test_cases)]
+async fn test_if_none_match(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get().uri("/keys/key1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+    let req = test::TestRequest::get()
+        .uri("/keys/key1")
+        .insert_header((header::IF_NONE_MATCH, etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert!(test::read_body(resp).await.is_empty());
+
+    db_arc
+        .set(b"key1", &StorageValue::string("new_value"))
+        .await
+        .unwrap();
+    let req = test::TestRequest::get()
+        .uri("/keys/key1")
+        .insert_header((header::IF_NONE_MATCH, etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_ne!(resp.headers().get(header::ETAG).unwrap(), &etag);
+    let body: models::ApiResponse<models::GetResponse> = test::read_body_json(resp).await;
+    match body {
+        models::ApiResponse::Success(models::GetResponse {
+            value: Some(models::IntOrString::String(value)),
+            ..
+        }) => assert_eq!(value, "new_value"),
+        _ => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_cache_headers(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let value = StorageValue {
+        ttl: 100,
+        ..StorageValue::string("value")
+    };
+    db.set(b"cached_key", &value).await.unwrap();
+    db.set(b"secret_key", &value).await.unwrap();
+
+    let query_service = DatabaseQueries::new(Arc::new(db)).with_cache_policy(CachePolicy {
+        no_store_prefixes: vec!["secret_".to_string()],
+    });
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/keys/cached_key")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let cache_control = resp.headers().get(header::CACHE_CONTROL).unwrap();
+    let max_age: i64 = cache_control
+        .to_str()
+        .unwrap()
+        .strip_prefix("max-age=")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((99..=100).contains(&max_age), "{max_age}");
+    assert!(resp.headers().contains_key(header::EXPIRES));
+
+    for uri in ["/keys/key1", "/keys/secret_key"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        assert!(!resp.headers().contains_key(header::EXPIRES));
+    }
+}
+
 #[apply(test_cases)]
 async fn test_set_ttl(
     #[future]
@@ -629,91 +1698,1261 @@ async fn test_set_ttl(
     }
 }
 
-#[fixture]
-async fn rocksdb() -> Box<dyn Storage> {
-    let db_path = format!("/dev/shm/test_db_{}", rand::random::<i32>());
-    let db = Rocksdb::open(db_path.as_str()).unwrap();
+#[actix_web::test]
+async fn test_get_ttl_rounding() {
+    let clock = Arc::new(MockClock::new(1_700_000_000));
+    let db: Box<dyn Storage> = Box::new(Bredis::open().with_clock(clock.clone()));
+    let db = Arc::new(db);
+    // 900ms into a second, so the key expires 4.1s later
+    clock.advance_millis(900);
+    db.set(
+        b"key1",
+        &StorageValue {
+            ttl: 5,
+            ..StorageValue::string("value1")
+        },
+    )
+    .await
+    .unwrap();
+    clock.advance_millis(50);
+
+    for (ttl_rounding, expected) in [
+        (TtlRounding::Floor, 4),
+        (TtlRounding::Ceil, 5),
+        (TtlRounding::Round, 4),
+    ] {
+        let query_service = DatabaseQueries::new(db.clone())
+            .with_clock(clock.clone())
+            .with_ttl_rounding(ttl_rounding);
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+        let req = test::TestRequest::get().uri("/keys/key1/ttl").to_request();
+        let body: models::ApiResponse<models::GetTtlResponse> =
+            test::call_and_read_body_json(&app, req).await;
+        match body {
+            models::ApiResponse::Success(models::GetTtlResponse { ttl }) => {
+                assert_eq!(ttl, expected, "{ttl_rounding:?}");
+            }
+            models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+        }
+    }
+}
+
+#[apply(test_cases)]
+async fn test_set_ttl_missing_key(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let set_ttl = || {
+        test::TestRequest::post()
+            .uri("/keys/missing_key/ttl")
+            .set_json(models::SetTtlRequest { ttl: 5 })
+            .to_request()
+    };
+
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let resp = test::call_service(&app, set_ttl()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let query_service =
+        DatabaseQueries::new(db_arc.clone()).with_missing_key_ttl(MissingKeyTtl::Ignore);
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let resp = test::call_service(&app, set_ttl()).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+    assert!(db_arc.get(b"missing_key").await.unwrap().is_none());
+}
+
+#[apply(test_cases)]
+async fn test_set_ttl_duration(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    db.set(b"key1", &StorageValue::string("value1"))
+        .await
+        .unwrap();
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/ttl")
+        .set_json(serde_json::json!({"ttl": "2h"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{resp:?}");
+
+    let req = test::TestRequest::get().uri("/keys/key1/ttl").to_request();
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert!((7190..=7200).contains(&body.ttl), "{}", body.ttl);
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/ttl")
+        .set_json(serde_json::json!({"ttl": "2 weeks"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[apply(test_cases)]
+async fn test_get_ex(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let value = StorageValue {
+        ttl: 10,
+        ..StorageValue::string("value1")
+    };
+    db.set(b"key1", &value).await.unwrap();
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/getex")
+        .set_json(serde_json::json!({"ttl": 60}))
+        .to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        matches!(body.value, Some(models::IntOrString::String(ref value)) if value == "value1"),
+        "{body:?}"
+    );
+    let req = test::TestRequest::get().uri("/keys/key1/ttl").to_request();
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert!((59..=60).contains(&body.ttl), "{}", body.ttl);
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/getex")
+        .set_json(serde_json::json!({"persist": true}))
+        .to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(body.value.is_some(), "{body:?}");
+    let req = test::TestRequest::get().uri("/keys/key1/ttl").to_request();
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.ttl, -1);
+
+    let req = test::TestRequest::post()
+        .uri("/keys/missing_key/getex")
+        .set_json(serde_json::json!({}))
+        .to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(body.value.is_none(), "{body:?}");
+}
+
+#[apply(test_cases)]
+async fn test_set_many_with_ttl(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys/mset-ttl")
+        .set_json(serde_json::json!({
+            "ttl": "5m",
+            "entries": {"group:name": "bredis", "group:visits": 3, "key1": "replaced"},
+        }))
+        .to_request();
+    let body: models::ApiResponse<models::OperationSuccessResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    assert!(
+        matches!(
+            body,
+            models::ApiResponse::Success(models::OperationSuccessResponse { success: true })
+        ),
+        "{body:?}"
+    );
+
+    for (key, expected) in [
+        (
+            "group:name",
+            models::IntOrString::String("bredis".to_string()),
+        ),
+        ("group:visits", models::IntOrString::Int(3)),
+        ("key1", models::IntOrString::String("replaced".to_string())),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/keys/{key}"))
+            .to_request();
+        let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+        match (body.value, expected) {
+            (Some(models::IntOrString::String(value)), models::IntOrString::String(expected)) => {
+                assert_eq!(value, expected);
+            }
+            (Some(models::IntOrString::Int(value)), models::IntOrString::Int(expected)) => {
+                assert_eq!(value, expected);
+            }
+            (value, _) => panic!("Unexpected value of {key}: {value:?}"),
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/keys/{key}/ttl"))
+            .to_request();
+        let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+        assert!((299..=300).contains(&body.ttl), "{key}: {}", body.ttl);
+    }
+}
+
+#[apply(test_cases)]
+async fn test_append(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/append")
+        .set_json(serde_json::json!({"value": ";login", "ttl": "1m"}))
+        .to_request();
+    let body: models::ApiResponse<models::AppendResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    match body {
+        models::ApiResponse::Success(models::AppendResponse { length }) => {
+            assert_eq!(length, 12);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::get().uri("/keys/key1").to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        matches!(body.value, Some(models::IntOrString::String(ref value)) if value == "value1;login"),
+        "{body:?}"
+    );
+    let req = test::TestRequest::get().uri("/keys/key1/ttl").to_request();
+    let body: models::GetTtlResponse = test::call_and_read_body_json(&app, req).await;
+    assert!((59..=60).contains(&body.ttl), "{}", body.ttl);
+
+    // Appending to a number is rejected
+    let req = test::TestRequest::post()
+        .uri("/keys/value_num/append")
+        .set_json(serde_json::json!({"value": "1"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_client_error(), "{}", resp.status());
+}
+
+#[apply(test_cases)]
+async fn test_adjust_ttl(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/ttl")
+        .set_json(models::SetTtlRequest { ttl: 100 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+
+    let req = test::TestRequest::patch()
+        .uri("/keys/key1/ttl")
+        .set_json(models::AdjustTtlRequest { delta: 60 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+
+    let body: models::ApiResponse<models::GetTtlResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::GetTtlResponse { ttl }) => {
+            assert!((155..=160).contains(&ttl));
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::patch()
+        .uri("/keys/key1/ttl")
+        .set_json(models::AdjustTtlRequest { delta: -500 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::GetTtlResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::GetTtlResponse { ttl }) => {
+            assert_eq!(ttl, 0);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::get().uri("/keys/key1").to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::GetResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::GetResponse { value, .. }) => {
+            assert!(value.is_none());
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_rename_nx(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/renamenx")
+        .set_json(models::RenameRequest {
+            new_key: "key2".to_string(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::RenameResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::RenameResponse { renamed }) => assert!(!renamed),
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+    assert!(db_arc.get(b"key1").await.unwrap().is_some());
+    assert!(db_arc.get(b"key2").await.unwrap().is_some());
+
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/renamenx")
+        .set_json(models::RenameRequest {
+            new_key: "key3".to_string(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::RenameResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::RenameResponse { renamed }) => assert!(renamed),
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+    assert!(db_arc.get(b"key1").await.unwrap().is_none());
+    assert!(db_arc.get(b"key3").await.unwrap().is_some());
+}
+
+#[apply(test_cases)]
+async fn test_lock(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db_arc = Arc::new(db.await);
+    let query_service = DatabaseQueries::new(db_arc.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/keys/job_lock/lock")
+        .set_json(models::LockRequest { ttl: 30 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: models::ApiResponse<models::LockResponse> = test::read_body_json(resp).await;
+    let token = match body {
+        models::ApiResponse::Success(models::LockResponse { token }) => token,
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    };
+    let lease = db_arc.get(b"job_lock").await.unwrap().unwrap();
+    assert_eq!(lease.value, token.as_bytes());
+    assert!(lease.ttl > 0, "The lease must expire");
+
+    let req = test::TestRequest::post()
+        .uri("/keys/job_lock/lock")
+        .set_json(models::LockRequest { ttl: 30 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri("/keys/job_lock/unlock")
+        .set_json(models::UnlockRequest {
+            token: "wrong-token".to_string(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert!(db_arc.get(b"job_lock").await.unwrap().is_some());
+
+    let req = test::TestRequest::post()
+        .uri("/keys/job_lock/unlock")
+        .set_json(models::UnlockRequest { token })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(db_arc.get(b"job_lock").await.unwrap().is_none());
+
+    let req = test::TestRequest::post()
+        .uri("/keys/job_lock/lock")
+        .set_json(models::LockRequest { ttl: 30 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[apply(test_cases)]
+async fn test_json_path(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(models::SetRequest {
+            key: "config".to_string(),
+            value: models::IntOrString::Json(serde_json::json!({
+                "a": { "b": { "c": 42 }, "d": [1, 2, 3] }
+            })),
+            ttl: -1,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/keys/config/json?path=$.a.b.c")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::JsonQueryResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::JsonQueryResponse { value }) => {
+            assert_eq!(value, Some(serde_json::json!(42)));
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/keys/config/json?path=$.a.d%5B2%5D")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::JsonQueryResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::JsonQueryResponse { value }) => {
+            assert_eq!(value, Some(serde_json::json!(3)));
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/keys/key1/json?path=$.a")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    // An error body would also parse as a response without a value, so check the status
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: models::ErrorResponse = test::read_body_json(resp).await;
+    assert_eq!(body.code.as_deref(), Some("invalid_value_type"));
+}
+
+#[apply(test_cases)]
+async fn test_json_merge(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(models::SetRequest {
+            key: "config".to_string(),
+            value: models::IntOrString::Json(serde_json::json!({
+                "name": "bredis",
+                "limits": { "max": 10, "min": 1 }
+            })),
+            ttl: -1,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "{:?}: {:?}",
+        resp,
+        resp.response().body()
+    );
+
+    let req = test::TestRequest::patch()
+        .uri("/keys/config/json")
+        .set_json(serde_json::json!({ "limits": { "max": 20 } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: models::ApiResponse<models::JsonQueryResponse> = test::read_body_json(resp).await;
+
+    match body {
+        models::ApiResponse::Success(models::JsonQueryResponse { value }) => {
+            assert_eq!(
+                value,
+                Some(serde_json::json!({
+                    "name": "bredis",
+                    "limits": { "max": 20, "min": 1 }
+                }))
+            );
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    let req = test::TestRequest::patch()
+        .uri("/keys/key1/json")
+        .set_json(serde_json::json!({ "a": 1 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    // An error body would also parse as a response without a value, so check the status
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: models::ErrorResponse = test::read_body_json(resp).await;
+    assert_eq!(body.code.as_deref(), Some("invalid_value_type"));
+}
+
+#[fixture]
+async fn rocksdb() -> Box<dyn Storage> {
+    let db = Rocksdb::open_ephemeral().unwrap();
+
+    let value = &mut StorageValue::string("value1");
+    db.set(b"key1", value).await.unwrap();
+
+    value.value = b"value2".to_vec();
+    db.set(b"key2", value).await.unwrap();
+
+    value.value = b"value3".to_vec();
+    db.set(b"prefix_key1", value).await.unwrap();
+
+    value.value = b"value4".to_vec();
+    db.set(b"prefix_key2", value).await.unwrap();
+
+    let value = &StorageValue {
+        value_type: ValueType::Integer,
+        ttl: -1,
+        value: b"1".to_vec(),
+        mtime: 0,
+    };
+    db.set(b"value_num", value).await.unwrap();
+
+    return Box::new(db);
+}
+
+#[fixture]
+async fn bredis() -> Box<dyn Storage> {
+    let db = Bredis::open();
+    let value = &mut StorageValue::string("value1");
+    db.set(b"key1", value).await.unwrap();
+
+    value.value = b"value2".to_vec();
+    db.set(b"key2", value).await.unwrap();
+
+    value.value = b"value3".to_vec();
+    db.set(b"prefix_key1", value).await.unwrap();
+
+    value.value = b"value4".to_vec();
+    db.set(b"prefix_key2", value).await.unwrap();
+
+    let value = &StorageValue {
+        value_type: ValueType::Integer,
+        ttl: -1,
+        value: b"1".to_vec(),
+        mtime: 0,
+    };
+    db.set(b"value_num", value).await.unwrap();
+
+    return Box::new(db);
+}
+
+#[fixture]
+async fn surrealkv() -> Box<dyn Storage> {
+    let db = SurrealKV::open();
+    let value = &mut StorageValue::string("value1");
+    db.set(b"key1", value).await.unwrap();
+
+    value.value = b"value2".to_vec();
+    db.set(b"key2", value).await.unwrap();
+
+    value.value = b"value3".to_vec();
+    db.set(b"prefix_key1", value).await.unwrap();
+
+    value.value = b"value4".to_vec();
+    db.set(b"prefix_key2", value).await.unwrap();
+
+    let value = &StorageValue {
+        value_type: ValueType::Integer,
+        ttl: -1,
+        value: b"1".to_vec(),
+        mtime: 0,
+    };
+    db.set(b"value_num", value).await.unwrap();
+
+    return Box::new(db);
+}
+
+#[apply(test_cases)]
+async fn test_list_push(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    for values in [vec!["a", "b"], vec!["c", "d", "e"]] {
+        let req = test::TestRequest::post()
+            .uri("/keys/events/list")
+            .set_json(models::ListPushRequest {
+                values: values.into_iter().map(String::from).collect(),
+                maxlen: Some(3),
+            })
+            .to_request();
+        let body: models::ApiResponse<models::ListPushResponse> =
+            test::call_and_read_body_json(&app, req).await;
+        match body {
+            models::ApiResponse::Success(models::ListPushResponse { length }) => {
+                assert!(length <= 3);
+            }
+            models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+        }
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/keys/events/list")
+        .to_request();
+    let body: models::ApiResponse<models::ListResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    match body {
+        models::ApiResponse::Success(models::ListResponse { values }) => {
+            assert_eq!(values.unwrap(), vec!["c", "d", "e"]);
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    // Pushing onto a string is rejected
+    let req = test::TestRequest::post()
+        .uri("/keys/key1/list")
+        .set_json(models::ListPushRequest {
+            values: vec!["x".to_string()],
+            maxlen: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["error"].is_string(), "{body}");
+    assert_eq!(body["retryable"], serde_json::json!(false));
+}
+
+#[apply(test_cases)]
+async fn test_get_raw(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    // Larger than a chunk of the stream
+    let large = "abcdefghijklmnopqrstuvwxyz".repeat(8000);
+    db.set(b"large", &StorageValue::string(&large))
+        .await
+        .unwrap();
+    db.list_push(b"events", &["login".to_string()], None, None)
+        .await
+        .unwrap();
+    let fields = HashMap::from([("name".to_string(), b"bredis".to_vec())]);
+    db.hset_many(b"user:1", &fields, None).await.unwrap();
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get().uri("/keys/large/raw").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+    let body = test::read_body(resp).await;
+    assert_eq!(body, large.as_bytes());
 
-    let value = &mut StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"value1".to_vec(),
+    let req = test::TestRequest::get()
+        .uri("/keys/missing/raw")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Collections point to the route that reads them
+    for (key, route) in [
+        ("events", "/keys/events/list"),
+        ("user:1", "/keys/user:1/hash"),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/keys/{key}/raw"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: models::ErrorResponse = test::read_body_json(resp).await;
+        assert!(body.error.ends_with(route), "{}", body.error);
+    }
+}
+
+#[apply(test_cases)]
+async fn test_set_raw(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    // Every byte value, most of which aren't valid UTF-8 on their own
+    let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+    let req = test::TestRequest::put()
+        .uri("/keys/blob/raw?ttl=1h")
+        .insert_header((header::CONTENT_TYPE, "application/octet-stream"))
+        .set_payload(bytes.clone())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get().uri("/keys/blob/raw").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/octet-stream"
+    );
+    assert_eq!(test::read_body(resp).await, bytes);
+
+    let req = test::TestRequest::get().uri("/keys/blob").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        serde_json::json!({"value": null, "type": "Blob", "length": 1000})
+    );
+}
+
+#[apply(test_cases)]
+async fn test_get_object(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let expiring = StorageValue {
+        ttl: 100,
+        ..StorageValue::string("value")
     };
-    db.set(b"key1", value).await.unwrap();
+    db.set(b"expiring", &expiring).await.unwrap();
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
 
-    value.value = b"value2".to_vec();
-    db.set(b"key2", value).await.unwrap();
+    let object = |key: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/keys/{key}/object"))
+            .to_request()
+    };
 
-    value.value = b"value3".to_vec();
-    db.set(b"prefix_key1", value).await.unwrap();
+    let body: models::ObjectResponse = test::call_and_read_body_json(&app, object("key1")).await;
+    assert_eq!(body.value_type, "String");
+    assert_eq!(
+        body.encoded_length,
+        StorageValue::string("value1").to_binary().len()
+    );
+    assert_eq!((body.ttl, body.expires_at), (-1, None));
+
+    let body: models::ObjectResponse =
+        test::call_and_read_body_json(&app, object("value_num")).await;
+    assert_eq!(body.value_type, "Integer");
+    assert!(body.encoded_length > 0);
+    assert_eq!((body.ttl, body.expires_at), (-1, None));
+
+    let body: models::ObjectResponse =
+        test::call_and_read_body_json(&app, object("expiring")).await;
+    assert!((99..=100).contains(&body.ttl), "{body:?}");
+    let expires_at = chrono::Utc::now().timestamp() + 100;
+    assert!(
+        (expires_at - 2..=expires_at).contains(&body.expires_at.unwrap()),
+        "{body:?}"
+    );
 
-    value.value = b"value4".to_vec();
-    db.set(b"prefix_key2", value).await.unwrap();
+    let resp = test::call_service(&app, object("missing")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
 
-    let value = &StorageValue {
-        value_type: ValueType::Integer,
-        ttl: -1,
-        value: b"1".to_vec(),
+#[actix_web::test]
+async fn test_get_object_with_mock_clock() {
+    let clock = Arc::new(MockClock::new(1_700_000_000));
+    let db: Box<dyn Storage> = Box::new(Bredis::open().with_clock(clock.clone()));
+    db.set(
+        b"expiring",
+        &StorageValue {
+            ttl: 100,
+            ..StorageValue::string("value")
+        },
+    )
+    .await
+    .unwrap();
+    clock.advance(40);
+    let record = db.get_record(b"expiring").await.unwrap().unwrap();
+    let query_service = DatabaseQueries::new(Arc::new(db)).with_clock(clock.clone());
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/keys/expiring/object")
+        .to_request();
+    let body: models::ObjectResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.ttl, 60);
+    assert_eq!(body.expires_at, Some(1_700_000_100));
+    assert_eq!(body.encoded_length, record.len());
+}
+
+#[apply(test_cases)]
+async fn test_get_list_by_key(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    db.list_push(b"events", &["a".to_string(), "b".to_string()], None, None)
+        .await
+        .unwrap();
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    // A list is summarized instead of decoded as a scalar
+    let req = test::TestRequest::get().uri("/keys/events").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(
+        body,
+        serde_json::json!({"value": null, "type": "List", "length": 2})
+    );
+}
+
+#[apply(test_cases)]
+async fn test_list_push_collection_limit(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service =
+        DatabaseQueries::new(Arc::new(db)).with_collection_limit(CollectionLimit(Some(3)));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let push = |values: &[&str]| {
+        test::TestRequest::post()
+            .uri("/keys/events/list")
+            .set_json(models::ListPushRequest {
+                values: values.iter().map(ToString::to_string).collect(),
+                maxlen: None,
+            })
+            .to_request()
     };
-    db.set(b"value_num", value).await.unwrap();
 
-    return Box::new(db);
+    let resp = test::call_service(&app, push(&["a", "b", "c"])).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = test::call_service(&app, push(&["d"])).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["error"].is_string(), "{body}");
+
+    let req = test::TestRequest::get()
+        .uri("/keys/events/list")
+        .to_request();
+    let body: models::ListResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.values.unwrap(), vec!["a", "b", "c"]);
 }
 
-#[fixture]
-async fn bredis() -> Box<dyn Storage> {
-    let db = Bredis::open();
-    let value = &mut StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"value1".to_vec(),
+#[apply(test_cases)]
+async fn test_hset_many(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let hset = |key: &str, fields: &[(&str, &str)]| {
+        test::TestRequest::post()
+            .uri(&format!("/keys/{key}/hash"))
+            .set_json(models::HashSetRequest {
+                fields: fields
+                    .iter()
+                    .map(|(field, value)| ((*field).to_string(), (*value).to_string()))
+                    .collect(),
+            })
+            .to_request()
     };
-    db.set(b"key1", value).await.unwrap();
 
-    value.value = b"value2".to_vec();
-    db.set(b"key2", value).await.unwrap();
+    let req = hset("user:1", &[("name", "bredis"), ("lang", "rust")]);
+    let body: models::HashSetResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((body.added, body.updated), (2, 0));
 
-    value.value = b"value3".to_vec();
-    db.set(b"prefix_key1", value).await.unwrap();
+    let req = hset(
+        "user:1",
+        &[("lang", "Rust"), ("stars", "42"), ("forks", "7")],
+    );
+    let body: models::HashSetResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((body.added, body.updated), (2, 1));
 
-    value.value = b"value4".to_vec();
-    db.set(b"prefix_key2", value).await.unwrap();
+    let req = test::TestRequest::get()
+        .uri("/keys/user:1/hash")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        serde_json::json!({"fields": {"forks": "7", "lang": "Rust", "name": "bredis", "stars": "42"}})
+    );
 
-    let value = &StorageValue {
-        value_type: ValueType::Integer,
-        ttl: -1,
-        value: b"1".to_vec(),
+    let req = test::TestRequest::get().uri("/keys/user:1").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        serde_json::json!({"value": null, "type": "Hash", "length": 4})
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/keys/missing/hash")
+        .to_request();
+    let body: models::HashResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(body.fields.is_none());
+
+    let resp = test::call_service(&app, hset("key1", &[("name", "bredis")])).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[apply(test_cases)]
+async fn test_hset_many_collection_limit(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service =
+        DatabaseQueries::new(Arc::new(db)).with_collection_limit(CollectionLimit(Some(2)));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let hset = |fields: &[&str]| {
+        test::TestRequest::post()
+            .uri("/keys/user:1/hash")
+            .set_json(models::HashSetRequest {
+                fields: fields
+                    .iter()
+                    .map(|field| ((*field).to_string(), "value".to_string()))
+                    .collect(),
+            })
+            .to_request()
     };
-    db.set(b"value_num", value).await.unwrap();
 
-    return Box::new(db);
+    let resp = test::call_service(&app, hset(&["name", "lang"])).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = test::call_service(&app, hset(&["stars"])).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["error"].is_string(), "{body}");
+
+    let req = test::TestRequest::get()
+        .uri("/keys/user:1/hash")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        serde_json::json!({"fields": {"lang": "value", "name": "value"}})
+    );
 }
 
-#[fixture]
-async fn surrealkv() -> Box<dyn Storage> {
-    let db = SurrealKV::open();
-    let value = &mut StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"value1".to_vec(),
+#[apply(test_cases)]
+async fn test_int_as_string(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service =
+        DatabaseQueries::new(Arc::new(db)).with_int_encoding(models::IntEncoding::String);
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let big = i64::MAX - 1;
+
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(models::SetRequest {
+            key: "big".to_string(),
+            value: models::IntOrString::Int(big),
+            ttl: -1,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get().uri("/keys/big").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["value"], serde_json::json!(big.to_string()));
+
+    let req = test::TestRequest::post()
+        .uri("/keys/big_counter/inc")
+        .set_json(models::IncrementRequest {
+            value: 1,
+            default: Some(big - 1),
+            assume_zero: false,
+            base: 10,
+            max: None,
+            saturate: false,
+        })
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["value"], serde_json::json!(big.to_string()));
+
+    let response: models::IncrementResponse = serde_json::from_value(body).unwrap();
+    assert_eq!(response.value, big);
+}
+
+#[apply(test_cases)]
+async fn test_increment_many(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+    let batch = |lenient| models::IncrementManyRequest {
+        increments: [
+            ("value_num".to_string(), 2),
+            ("new_num".to_string(), 5),
+            ("key1".to_string(), 1),
+        ]
+        .into(),
+        lenient,
     };
-    db.set(b"key1", value).await.unwrap();
 
-    value.value = b"value2".to_vec();
-    db.set(b"key2", value).await.unwrap();
+    // Strict: the string key1 rejects the whole batch
+    let req = test::TestRequest::post()
+        .uri("/keys/inc")
+        .set_json(batch(false))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-    value.value = b"value3".to_vec();
-    db.set(b"prefix_key1", value).await.unwrap();
+    let req = test::TestRequest::get().uri("/keys/new_num").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["value"].is_null(), "{body}");
 
-    value.value = b"value4".to_vec();
-    db.set(b"prefix_key2", value).await.unwrap();
+    // Lenient: the integers are incremented and key1 is reported
+    let req = test::TestRequest::post()
+        .uri("/keys/inc")
+        .set_json(batch(true))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
 
-    let value = &StorageValue {
-        value_type: ValueType::Integer,
-        ttl: -1,
-        value: b"1".to_vec(),
+    let body: models::ApiResponse<models::IncrementManyResponse> = test::read_body_json(resp).await;
+    match body {
+        models::ApiResponse::Success(models::IncrementManyResponse { results, errors }) => {
+            assert_eq!(results.len(), 2);
+            assert_eq!(results["value_num"], 3);
+            assert_eq!(results["new_num"], 5);
+            assert!(errors["key1"].contains("Invalid value type"), "{errors:?}");
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+
+    // Lenient: a key that would overflow is reported instead of wrapping around
+    let req = test::TestRequest::post()
+        .uri("/keys/inc")
+        .set_json(models::IncrementManyRequest {
+            increments: [("max_num".to_string(), i64::MAX)].into(),
+            lenient: false,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/keys/inc")
+        .set_json(models::IncrementManyRequest {
+            increments: [("max_num".to_string(), 1), ("new_num".to_string(), 1)].into(),
+            lenient: true,
+        })
+        .to_request();
+    let body: models::ApiResponse<models::IncrementManyResponse> =
+        test::call_and_read_body_json(&app, req).await;
+    match body {
+        models::ApiResponse::Success(models::IncrementManyResponse { results, errors }) => {
+            assert_eq!(results.len(), 1);
+            assert_eq!(results["new_num"], 6);
+            assert!(errors["max_num"].contains("overflows"), "{errors:?}");
+        }
+        models::ApiResponse::ErrorResponse(_) => panic!("Unexpected response: {body:?}"),
+    }
+}
+
+#[apply(test_cases)]
+async fn test_batch_body_limit(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db)).with_body_limits(BodyLimits {
+        value: 256,
+        batch: 4096,
+    });
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    let increments = (0..20).map(|i| (format!("counter_{i:02}"), 1)).collect();
+    let batch = models::IncrementManyRequest {
+        increments,
+        lenient: false,
     };
-    db.set(b"value_num", value).await.unwrap();
+    assert!(serde_json::to_vec(&batch).unwrap().len() > 256);
+    let req = test::TestRequest::post()
+        .uri("/keys/inc")
+        .set_json(batch)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{:?}", resp.response().body());
 
-    return Box::new(db);
+    // A single write of the same size is over the value limit
+    let req = test::TestRequest::post()
+        .uri("/keys")
+        .set_json(serde_json::json!({ "key": "big", "value": "x".repeat(300) }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[apply(test_cases)]
+async fn test_get_type(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    for (key, expected) in [
+        ("key1", Some(ValueType::String)),
+        ("value_num", Some(ValueType::Integer)),
+        ("missing", None),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/keys/{key}/type"))
+            .to_request();
+        let body: models::TypeResponse = test::call_and_read_body_json(&app, req).await;
+        let value_type = body
+            .value_type
+            .map(|value_type| value_type.parse::<ValueType>().unwrap());
+        assert_eq!(value_type, expected, "{key}");
+    }
+}
+
+#[apply(test_cases)]
+async fn test_type_case(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db: Arc<Box<dyn Storage>> = Arc::new(db.await);
+    for (type_case, integer, string) in [
+        (TypeCase::Title, "Integer", "String"),
+        (TypeCase::Lower, "integer", "string"),
+    ] {
+        let query_service = DatabaseQueries::new(db.clone()).with_type_case(type_case);
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        let req = test::TestRequest::get()
+            .uri("/keys/value_num/type")
+            .to_request();
+        let body: models::TypeResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body.value_type.as_deref(), Some(integer));
+
+        let req = test::TestRequest::get()
+            .uri("/keys/key1/object")
+            .to_request();
+        let body: models::ObjectResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body.value_type, string);
+
+        let req = test::TestRequest::get().uri("/keys/key1/list").to_request();
+        let body: models::ErrorResponse = test::call_and_read_body_json(&app, req).await;
+        let list = ValueType::List.name(type_case);
+        assert!(
+            body.error
+                .contains(&format!("expected {list}, found {string}")),
+            "{body:?}"
+        );
+    }
+}
+
+#[apply(test_cases)]
+async fn test_get_reports_type(
+    #[future]
+    #[case]
+    db: Box<dyn Storage>,
+) {
+    let db = db.await;
+    let query_service = DatabaseQueries::new(Arc::new(db));
+    let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+    for (key, value, expected) in [
+        (
+            "numeric_string",
+            models::IntOrString::String("42".to_string()),
+            "String",
+        ),
+        ("integer", models::IntOrString::Int(42), "Integer"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/keys")
+            .set_json(models::SetRequest {
+                key: key.to_string(),
+                value,
+                ttl: -1,
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/keys/{key}"))
+            .to_request();
+        let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body.value_type.as_deref(), Some(expected), "{key}");
+    }
+
+    let req = test::TestRequest::get().uri("/keys/missing").to_request();
+    let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(body.value_type.is_none());
 }
@@ -0,0 +1,79 @@
+use std::panic::AssertUnwindSafe;
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::StatusCode,
+    middleware::Next,
+    Error, HttpResponse,
+};
+use futures::FutureExt;
+
+use super::models;
+
+/// Answer a request whose handler panics with 500 instead of dropping the connection
+///
+/// The panic is still reported by the panic hook; only the response is recovered.
+/// The request goes down with the handler, so the response travels as an error that
+/// actix answers with as it is.
+pub async fn catch_panic(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    // Routing panics if the request is cloned, so only what the log needs is kept
+    let method = req.method().clone();
+    let path = req.path().to_string();
+    let Ok(response) = AssertUnwindSafe(next.call(req)).catch_unwind().await else {
+        log::error!("The handler of {method} {path} panicked");
+        let error = models::ErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "The request failed unexpectedly",
+        );
+        let message = error.error.clone();
+        let response = HttpResponse::build(error.status).json(error);
+        return Err(InternalError::from_response(message, response).into());
+    };
+    return Ok(response?.map_into_boxed_body());
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::to_bytes, middleware::from_fn, test, web, App};
+
+    use super::*;
+
+    async fn panicking_handler() -> HttpResponse {
+        panic!("Synthetic handler failure");
+    }
+
+    #[actix_web::test]
+    async fn test_handler_panic() {
+        let app = test::init_service(
+            App::new()
+                .route("/panic", web::get().to(panicking_handler))
+                .route(
+                    "/ok",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                )
+                .wrap(from_fn(catch_panic)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/panic").to_request();
+        let resp = test::try_call_service(&app, req)
+            .await
+            .unwrap_err()
+            .error_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let body: models::ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.error, "The request failed unexpectedly");
+        assert!(!body.retryable);
+
+        // The worker keeps answering after the panic
+        let req = test::TestRequest::get().uri("/ok").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,100 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+
+use crate::storages::multi::MultiBackend;
+
+use super::models;
+
+/// The header naming the backend a request goes to
+pub const BACKEND_HEADER: &str = "X-Bredis-Backend";
+
+/// The names of the backends requests may select; empty when the server runs a single one
+#[derive(Clone, Debug, Default)]
+pub struct BackendNames(pub Vec<String>);
+
+/// Send the storage operations of a request to the backend named by its `X-Bredis-Backend`
+/// header, and answer requests naming an unknown backend with 400
+///
+/// Requests without the header go to the default backend.
+pub async fn select_backend(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(name) = req.headers().get(BACKEND_HEADER) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let name = name.to_str().unwrap_or_default().to_string();
+    let known = req
+        .app_data::<web::Data<BackendNames>>()
+        .is_some_and(|names| names.0.contains(&name));
+    if !known {
+        let error =
+            models::ErrorResponse::new(StatusCode::BAD_REQUEST, format!("Unknown backend: {name}"));
+        let response = HttpResponse::build(error.status).json(error);
+        return Ok(ServiceResponse::new(req.request().clone(), response));
+    }
+
+    let response = MultiBackend::select(name, next.call(req)).await?;
+    return Ok(response.map_into_boxed_body());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::http_server::queries::service::DatabaseQueries;
+    use crate::storages::{bredis::Bredis, storage::Storage};
+
+    #[actix_web::test]
+    async fn test_select_backend() {
+        let db = MultiBackend::new("durable", Box::new(Bredis::open()))
+            .with_backend("cache", Box::new(Bredis::open()));
+        let names = BackendNames(db.names());
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(db));
+        let query_service = DatabaseQueries::new(db.clone()).with_backend_names(names);
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        let req = test::TestRequest::post()
+            .uri("/keys")
+            .insert_header((BACKEND_HEADER, "cache"))
+            .set_json(models::SetRequest {
+                key: "key1".to_string(),
+                value: models::IntOrString::String("value1".to_string()),
+                ttl: -1,
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let cached = MultiBackend::select("cache".to_string(), db.get(b"key1")).await;
+        assert!(cached.unwrap().is_some());
+        let durable = MultiBackend::select("durable".to_string(), db.get(b"key1")).await;
+        assert!(durable.unwrap().is_none());
+
+        let req = test::TestRequest::get()
+            .uri("/keys/key1")
+            .insert_header((BACKEND_HEADER, "cache"))
+            .to_request();
+        let body: models::ApiResponse<models::GetResponse> =
+            test::call_and_read_body_json(&app, req).await;
+        assert!(matches!(
+            body,
+            models::ApiResponse::Success(models::GetResponse { value: Some(_), .. })
+        ));
+
+        let req = test::TestRequest::get()
+            .uri("/keys/key1")
+            .insert_header((BACKEND_HEADER, "archive"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
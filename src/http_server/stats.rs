@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use actix_web::web;
+
+use crate::errors::DatabaseError;
+use crate::storages::storage::Storage;
+
+use super::models;
+
+/// Represents the statistics service.
+///
+/// This service reports how the keyspace is made up, for capacity planning.
+pub struct Service {
+    db: Arc<Box<dyn Storage>>,
+}
+
+impl Service {
+    /// Creates a new instance of the statistics service.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The storage to report on.
+    #[must_use]
+    pub const fn new(db: Arc<Box<dyn Storage>>) -> Self {
+        return Self { db };
+    }
+
+    /// Configures the statistics service with the given `ServiceConfig`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The `ServiceConfig` to configure.
+    pub fn config(self, cfg: &mut web::ServiceConfig) {
+        let self_clone = Arc::new(self);
+        cfg.service(web::resource("/stats/keyspace").route(web::get().to(
+            move |query: web::Query<models::KeyspaceQuery>| {
+                let self_clone = self_clone.clone();
+                async move {
+                    return match self_clone.keyspace(query.into_inner()).await {
+                        Ok(stats) => models::ApiResponse::Success(stats),
+                        Err(err) => models::ApiResponse::from(err),
+                    };
+                }
+            },
+        )));
+    }
+
+    /// Counts the keys and their sizes by value type.
+    ///
+    /// Every key is listed, but with `sample` only that many evenly spaced keys are read and
+    /// the counts and sizes are extrapolated from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The number of keys to sample, all keys are read without it.
+    ///
+    /// # Returns
+    ///
+    /// The number of keys and their size in bytes, key included, by value type.
+    pub async fn keyspace(
+        &self,
+        query: models::KeyspaceQuery,
+    ) -> Result<models::KeyspaceResponse, DatabaseError> {
+        let keys = self.db.get_all_keys(b"").await?;
+        let step = match query.sample {
+            Some(sample) => keys.len().div_ceil(sample.get()).max(1),
+            None => 1,
+        };
+
+        let mut types: BTreeMap<String, models::TypeStats> = BTreeMap::new();
+        let mut sampled: u64 = 0;
+        for key in keys.iter().step_by(step) {
+            sampled += 1;
+            // Keys that expired since they were listed count as sampled but not as stored
+            let Some(value) = self.db.get_no_reap(key.as_bytes()).await? else {
+                continue;
+            };
+            let size = key.len().saturating_add(value.to_binary().len());
+            let stats = types.entry(value.value_type.to_string()).or_default();
+            stats.count += 1;
+            stats.bytes = stats
+                .bytes
+                .saturating_add(u64::try_from(size).unwrap_or(u64::MAX));
+        }
+
+        if step > 1 {
+            let total = u64::try_from(keys.len()).unwrap_or(u64::MAX);
+            for stats in types.values_mut() {
+                stats.count = stats.count.saturating_mul(total) / sampled;
+                stats.bytes = stats.bytes.saturating_mul(total) / sampled;
+            }
+        }
+        return Ok(models::KeyspaceResponse { types });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::storages::bredis::Bredis;
+    use crate::storages::value::StorageValue;
+
+    async fn mixed_db() -> Arc<Box<dyn Storage>> {
+        let db = Bredis::open();
+        for i in 0..6 {
+            let key = format!("string{i}");
+            db.set(key.as_bytes(), &StorageValue::string("value"))
+                .await
+                .unwrap();
+        }
+        for i in 0..3 {
+            let key = format!("int{i}");
+            db.set(key.as_bytes(), &StorageValue::integer(i))
+                .await
+                .unwrap();
+        }
+        db.set(b"blob", &StorageValue::blob(b"\x00\x01\x02", -1))
+            .await
+            .unwrap();
+        return Arc::new(Box::new(db));
+    }
+
+    #[actix_web::test]
+    async fn test_keyspace() {
+        let service = Service::new(mixed_db().await);
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+        let req = test::TestRequest::get().uri("/stats/keyspace").to_request();
+        let stats: models::KeyspaceResponse = test::call_and_read_body_json(&app, req).await;
+
+        let counts: Vec<(&str, u64)> = stats
+            .types
+            .iter()
+            .map(|(value_type, stats)| (value_type.as_str(), stats.count))
+            .collect();
+        assert_eq!(counts, vec![("Blob", 1), ("Integer", 3), ("String", 6)]);
+        // Every string record holds at least its 7-byte key and its 5-byte value
+        assert!(stats.types["String"].bytes >= 6 * 12, "{stats:?}");
+    }
+
+    #[actix_web::test]
+    async fn test_keyspace_sampled() {
+        let service = Service::new(mixed_db().await);
+        let app = test::init_service(App::new().configure(|cfg| service.config(cfg))).await;
+        let req = test::TestRequest::get()
+            .uri("/stats/keyspace?sample=5")
+            .to_request();
+        let stats: models::KeyspaceResponse = test::call_and_read_body_json(&app, req).await;
+        // Every other key is read and counts twice
+        let total: u64 = stats.types.values().map(|stats| stats.count).sum();
+        assert_eq!(total, 10, "{stats:?}");
+
+        let req = test::TestRequest::get()
+            .uri("/stats/keyspace?sample=0")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+}
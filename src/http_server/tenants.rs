@@ -0,0 +1,118 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+
+use crate::storages::tenant::TenantKeys;
+
+use super::models;
+
+/// The header naming the tenant of a request, set with `--tenant-header`
+#[derive(Clone, Debug, Default)]
+pub struct TenantHeader(pub Option<String>);
+
+/// Confine the storage operations of a request to the keys of the tenant named by the
+/// `--tenant-header` header, and answer requests without a valid tenant with 400
+///
+/// Without `--tenant-header`, requests see every key.
+pub async fn select_tenant(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let header = req
+        .app_data::<web::Data<TenantHeader>>()
+        .and_then(|header| header.0.clone());
+    let Some(header) = header else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let tenant = req
+        .headers()
+        .get(&header)
+        .and_then(|tenant| tenant.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !TenantKeys::is_valid_tenant(&tenant) {
+        let error = models::ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!("The {header} header must name a tenant without a colon"),
+        );
+        let response = HttpResponse::build(error.status).json(error);
+        return Ok(ServiceResponse::new(req.request().clone(), response));
+    }
+
+    let response = TenantKeys::scope(tenant, next.call(req)).await?;
+    return Ok(response.map_into_boxed_body());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::http_server::queries::service::DatabaseQueries;
+    use crate::storages::{bredis::Bredis, storage::Storage};
+
+    const HEADER: &str = "X-Tenant";
+
+    #[actix_web::test]
+    async fn test_select_tenant() {
+        let db: Arc<Box<dyn Storage>> =
+            Arc::new(Box::new(TenantKeys::new(Box::new(Bredis::open()))));
+        let query_service = DatabaseQueries::new(db.clone())
+            .with_tenant_header(TenantHeader(Some(HEADER.to_string())));
+        let app = test::init_service(App::new().configure(|cfg| query_service.config(cfg))).await;
+
+        for tenant in ["acme", "globex"] {
+            let req = test::TestRequest::post()
+                .uri("/keys")
+                .insert_header((HEADER, tenant))
+                .set_json(models::SetRequest {
+                    key: "shared".to_string(),
+                    value: models::IntOrString::String(tenant.to_string()),
+                    ttl: -1,
+                })
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert!(resp.status().is_success());
+        }
+
+        // The same logical key of two tenants doesn't collide
+        for tenant in ["acme", "globex"] {
+            let req = test::TestRequest::get()
+                .uri("/keys/shared")
+                .insert_header((HEADER, tenant))
+                .to_request();
+            let body: models::GetResponse = test::call_and_read_body_json(&app, req).await;
+            assert!(
+                matches!(&body.value, Some(models::IntOrString::String(value)) if value == tenant),
+                "{body:?}"
+            );
+        }
+
+        // Listings only show the keys of the tenant, without the tenant prefix
+        let req = test::TestRequest::get()
+            .uri("/keys?prefix=")
+            .insert_header((HEADER, "acme"))
+            .to_request();
+        let body: models::GetAllKeysResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body.page.items, vec!["shared"]);
+
+        let mut keys = db.get_all_keys(b"").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["tenant:acme:shared", "tenant:globex:shared"]);
+
+        for tenant in [None, Some("acme:eu")] {
+            let mut req = test::TestRequest::get().uri("/keys/shared");
+            if let Some(tenant) = tenant {
+                req = req.insert_header((HEADER, tenant));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
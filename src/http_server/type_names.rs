@@ -0,0 +1,23 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error,
+};
+
+use crate::storages::value::TypeCase;
+
+/// Write the value type names of a request in the case set with `--type-case`
+///
+/// This covers the names in responses, e.g. of `/type` and `/object`, as well as in the
+/// messages of errors about mismatched types.
+pub async fn apply_type_case(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let case = req
+        .app_data::<web::Data<TypeCase>>()
+        .map_or(TypeCase::Title, |case| *case.get_ref());
+    let response = case.scope(next.call(req)).await?;
+    return Ok(response.map_into_boxed_body());
+}
@@ -0,0 +1,36 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+
+/// The page of the web UI, embedded so the binary serves it without any files next to it
+const PAGE: &str = include_str!("ui.html");
+
+/// Represents the web UI service.
+///
+/// A single page at `/ui` lists keys by prefix and gets, sets and deletes values through
+/// the `/keys` endpoints.
+pub struct Service;
+
+impl Service {
+    /// Creates a new instance of the web UI service.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the web UI service.
+    #[must_use]
+    pub const fn new() -> Self {
+        return Self;
+    }
+
+    /// Configures the web UI service with the given `ServiceConfig`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The `ServiceConfig` to configure.
+    #[allow(clippy::unused_self)]
+    pub fn config(self, cfg: &mut web::ServiceConfig) {
+        cfg.service(web::resource("/ui").route(web::get().to(|| async {
+            HttpResponse::Ok()
+                .content_type(ContentType::html())
+                .body(PAGE)
+        })));
+    }
+}
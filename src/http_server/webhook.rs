@@ -0,0 +1,288 @@
+use std::{io, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::{broadcast, Semaphore},
+};
+
+/// The most attempts to deliver one expiry event
+const MAX_ATTEMPTS: u32 = 3;
+/// The wait before the first retry, doubled for every further one
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+/// The longest a single attempt may take
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
+/// The most deliveries in flight, further events wait for one of them to finish
+const MAX_IN_FLIGHT: usize = 16;
+
+/// An `http://host[:port][/path]` URL the expiry events are posted to
+///
+/// There is no TLS client in the server, so `https://` URLs are rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebhookUrl {
+    host: String,
+    port: u16,
+    /// The `host[:port]` part of the URL, sent as the `Host` header
+    authority: String,
+    path: String,
+}
+
+impl WebhookUrl {
+    /// Parse a webhook URL, as passed to `--expiry-webhook`
+    ///
+    /// # Arguments
+    /// * `url` - The URL to parse
+    ///
+    /// # Returns
+    /// The URL, or a message explaining why it is not supported
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            return Err(format!("Only http:// webhook URLs are supported: {url}"));
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.ends_with(']') => (
+                host,
+                port.parse::<u16>()
+                    .map_err(|_| format!("Invalid port in the webhook URL: {url}"))?,
+            ),
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("Missing host in the webhook URL: {url}"));
+        }
+        return Ok(Self {
+            host: host.to_string(),
+            port,
+            authority: authority.to_string(),
+            path: path.to_string(),
+        });
+    }
+
+    /// Post a JSON body to the URL
+    ///
+    /// # Returns
+    /// The status code of the response
+    async fn post(&self, body: &[u8]) -> io::Result<u16> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.authority,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body).await?;
+
+        // Only the status line matters, the rest of the response is not read
+        let mut response = Vec::new();
+        let mut buffer = [0; 256];
+        while !response.windows(2).any(|window| window == b"\r\n") {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The webhook closed the connection without a response",
+                ));
+            }
+            response.extend_from_slice(&buffer[..read]);
+        }
+        return String::from_utf8_lossy(&response)
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid webhook response"));
+    }
+}
+
+/// Posts `{"key": ..., "reason": "expired"}` to a URL for every key the sweeper reaps
+pub struct ExpiryWebhook {
+    url: WebhookUrl,
+}
+
+impl ExpiryWebhook {
+    /// Create a webhook posting to a URL
+    ///
+    /// # Arguments
+    /// * `url` - The URL the events are posted to
+    pub const fn new(url: WebhookUrl) -> Self {
+        return Self { url };
+    }
+
+    /// Deliver the expiry event of a key, retrying up to `MAX_ATTEMPTS` times
+    ///
+    /// # Arguments
+    /// * `key` - The expired key
+    ///
+    /// # Returns
+    /// Whether the webhook accepted the event with a 2xx response
+    async fn deliver(&self, key: &str) -> bool {
+        let body = serde_json::json!({ "key": key, "reason": "expired" }).to_string();
+        let mut backoff = RETRY_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            match tokio::time::timeout(ATTEMPT_TIMEOUT, self.url.post(body.as_bytes())).await {
+                Ok(Ok(status)) if (200..300).contains(&status) => return true,
+                Ok(Ok(status)) => log::warn!(
+                    "The expiry webhook answered {status} for {key}, attempt {attempt}/{MAX_ATTEMPTS}"
+                ),
+                Ok(Err(err)) => log::warn!(
+                    "The expiry webhook failed for {key}, attempt {attempt}/{MAX_ATTEMPTS}: {err}"
+                ),
+                Err(_) => log::warn!(
+                    "The expiry webhook timed out for {key}, attempt {attempt}/{MAX_ATTEMPTS}"
+                ),
+            }
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+            }
+        }
+        log::error!("Dropping the expiry event of {key}, the webhook didn't accept it");
+        return false;
+    }
+}
+
+/// Post every expiry event to the webhook for as long as the server runs
+///
+/// Events are read from the expiry channel, so a slow webhook never stalls the sweeper.
+/// If the deliveries fall too far behind, the oldest events are dropped and logged.
+///
+/// # Arguments
+/// * `webhook` - The webhook to post to
+/// * `expired` - A subscription to `expiry::EXPIRED_CHANNEL`
+pub async fn forward_expiries(
+    webhook: Arc<ExpiryWebhook>,
+    mut expired: broadcast::Receiver<String>,
+) {
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    loop {
+        match expired.recv().await {
+            Ok(key) => {
+                // The semaphore is never closed
+                let permit = in_flight.clone().acquire_owned().await.unwrap();
+                let webhook = webhook.clone();
+                tokio::spawn(async move {
+                    webhook.deliver(&key).await;
+                    drop(permit);
+                });
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                log::error!("The expiry webhook fell behind, {missed} expiry events are dropped");
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{net::TcpListener, sync::mpsc};
+
+    use super::*;
+    use crate::http_server::{
+        channels::Channels,
+        expiry::{self, EXPIRED_CHANNEL},
+    };
+    use crate::storages::{bredis::Bredis, storage::Storage, value::StorageValue};
+
+    /// Read one request and answer it with a status, sending its body to the test
+    async fn answer(stream: &mut TcpStream, status: &str, bodies: &mpsc::UnboundedSender<String>) {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        let body_start = loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            if let Some(index) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                break index + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let length: usize = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: "))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        while request.len() < body_start + length {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        bodies
+            .send(String::from_utf8_lossy(&request[body_start..]).into_owned())
+            .unwrap();
+        stream
+            .write_all(format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let url = WebhookUrl::parse("http://localhost:8080/hooks/expired").unwrap();
+        assert_eq!(url.host, "localhost");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.authority, "localhost:8080");
+        assert_eq!(url.path, "/hooks/expired");
+
+        let url = WebhookUrl::parse("http://example.com").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+        let url = WebhookUrl::parse("http://[::1]:9000/").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", 9000));
+
+        assert!(WebhookUrl::parse("https://example.com/").is_err());
+        assert!(WebhookUrl::parse("http://example.com:http/").is_err());
+        assert!(WebhookUrl::parse("http:///path").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_fires_on_expiry() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (bodies, mut received) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // The first attempt fails, so the event is only accepted on a retry
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                answer(&mut stream, status, &bodies).await;
+            }
+        });
+
+        let db: Arc<Box<dyn Storage>> = Arc::new(Box::new(Bredis::open()));
+        let channels = Arc::new(Channels::default());
+        let url = WebhookUrl::parse(&format!("http://127.0.0.1:{port}/expired")).unwrap();
+        tokio::spawn(forward_expiries(
+            Arc::new(ExpiryWebhook::new(url)),
+            channels.subscribe(EXPIRED_CHANNEL),
+        ));
+
+        let value = StorageValue {
+            ttl: 1,
+            ..StorageValue::string("token")
+        };
+        db.set(b"session", &value).await.unwrap();
+        tokio::spawn(expiry::sweep_periodically(
+            db.clone(),
+            channels.clone(),
+            Duration::from_millis(100),
+        ));
+
+        for _ in 0..2 {
+            let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+                .await
+                .expect("The webhook was not called")
+                .unwrap();
+            let event: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(
+                event,
+                serde_json::json!({ "key": "session", "reason": "expired" })
+            );
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! The storage layer of bredis, for embedding it in a Rust program without the HTTP server
+//!
+//! [`client::BredisClient`] offers typed reads and writes over any [`storages::storage::Storage`]:
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), bredis::errors::DatabaseError> {
+//! use bredis::client::BredisClient;
+//! use bredis::storages::bredis::Bredis;
+//!
+//! let client = BredisClient::new(Box::new(Bredis::open()));
+//! client.set_int("visits", 41).await?;
+//! assert_eq!(client.incr("visits", 1).await?, 42);
+//! client.close().await;
+//! # Ok(())
+//! # }
+//! ```
+#![warn(clippy::pedantic)]
+#![warn(clippy::cargo)]
+#![deny(clippy::as_conversions)]
+#![allow(clippy::needless_return)]
+#![allow(clippy::multiple_crate_versions)]
+// The storages were written for the binary, where none of their items were exported
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::must_use_candidate)]
+
+pub mod client;
+pub mod errors;
+pub mod storages;
@@ -5,74 +5,290 @@
 #![allow(clippy::needless_return)]
 #![allow(clippy::multiple_crate_versions)]
 #[allow(clippy::future_not_send)]
+mod bench;
 mod cli;
-mod errors;
+mod config;
 mod http_server;
 pub(crate) mod info;
-mod storages;
+mod migrate;
 
-use log::{debug, error};
-use rand::random;
+use bredis::{errors, storages};
+use clap::ArgMatches;
+use config::Config;
+use log::error;
 use std::sync::Arc;
+use std::time::Duration;
+use storages::backend::{open_with_timeout, Backend};
+use storages::breaker::CircuitBreaker;
+use storages::bredis::Bredis;
+use storages::hashed::HashedKeys;
+use storages::monitored::{ErrorRate, Monitored};
+use storages::multi::MultiBackend;
+use storages::slow_log::SlowOpLog;
 use storages::storage::Storage;
+use storages::strict_types::StrictTypes;
+use storages::tee::TeeStorage;
+use storages::tenant::TenantKeys;
 
-enum Backend {
-    Rocksdb,
-    Bredis,
-    SurrealKV,
-}
 /// The main entry point of the program.
 #[tokio::main]
+#[allow(clippy::too_many_lines)]
 async fn main() {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("debug"));
 
     let matches = cli::make_cli().get_matches();
 
-    if let Some(cmd_args) = matches.subcommand_matches("run") {
-        let bind: &String = cmd_args.get_one("bind").unwrap();
-        let backend: &String = cmd_args.get_one("backend").unwrap();
-        let backend = match backend.as_str() {
-            "rocksdb" => Backend::Rocksdb,
-            "bredis" => Backend::Bredis,
-            "surrealkv" => Backend::SurrealKV,
-            _ => {
-                error!("Invalid backend: {backend}");
+    match matches.subcommand() {
+        Some(("run", cmd_args)) => {
+            let Some(db) = open_backend(cmd_args).await else {
+                return;
+            };
+            let config = Config::from_args(cmd_args);
+            let db: Box<dyn Storage> = match &config.mirror_to {
+                Some(spec) => {
+                    let Some(secondary) = open_spec(spec, config.open_timeout()).await else {
+                        return;
+                    };
+                    log::info!("Mirroring writes to {spec}");
+                    Box::new(TeeStorage::new(db, secondary, config.mirror_failures_fatal))
+                }
+                None => db,
+            };
+            let Some(db) = open_named_backends(&config, db).await else {
                 return;
+            };
+            run(config, db).await;
+        }
+        Some(("get", cmd_args)) => {
+            let Some(db) = open_backend(cmd_args).await else {
+                std::process::exit(cli::EXIT_ERROR);
+            };
+            let key: &String = cmd_args.get_one("key").unwrap();
+            let code = cli::get(db.as_ref(), key, &mut std::io::stdout()).await;
+            db.close().await;
+            drop(db);
+            std::process::exit(code);
+        }
+        Some(("set", cmd_args)) => {
+            let Some(db) = open_backend(cmd_args).await else {
+                std::process::exit(cli::EXIT_ERROR);
+            };
+            let key: &String = cmd_args.get_one("key").unwrap();
+            let value: &String = cmd_args.get_one("value").unwrap();
+            let ttl: i64 = *cmd_args.get_one("ttl").unwrap();
+            let code = cli::set(db.as_ref(), key, value, ttl, &mut std::io::stdout()).await;
+            db.close().await;
+            drop(db);
+            std::process::exit(code);
+        }
+        Some(("bench", cmd_args)) => {
+            let Some(db) = open_backend(cmd_args).await else {
+                std::process::exit(cli::EXIT_ERROR);
+            };
+            let options = bench::Options {
+                ops: *cmd_args.get_one("ops").unwrap(),
+                concurrency: *cmd_args.get_one("concurrency").unwrap(),
+            };
+            let db = Arc::new(db);
+            let report = bench::run(db.clone(), &options).await;
+            db.close().await;
+            println!("{report}");
+        }
+        Some(("migrate", cmd_args)) => {
+            let from: &String = cmd_args.get_one("from").unwrap();
+            let to: &String = cmd_args.get_one("to").unwrap();
+            let batch_size: usize = *cmd_args.get_one("batch-size").unwrap();
+            let (source, destination) =
+                match (open_spec(from, None).await, open_spec(to, None).await) {
+                    (Some(source), Some(destination)) => (source, destination),
+                    (source, destination) => {
+                        // Close whichever side did open before giving up
+                        for db in [source, destination].into_iter().flatten() {
+                            db.close().await;
+                        }
+                        std::process::exit(cli::EXIT_ERROR);
+                    }
+                };
+            let result = migrate::migrate(source.as_ref(), destination.as_ref(), batch_size).await;
+            source.close().await;
+            destination.close().await;
+            drop((source, destination));
+            match result {
+                Ok(copied) => log::info!("Migrated {copied} keys from {from} to {to}"),
+                Err(err) => {
+                    error!("Error migrating data: {err}");
+                    std::process::exit(cli::EXIT_ERROR);
+                }
             }
-        };
-        run(bind, backend).await;
+        }
+        Some(("export", cmd_args)) => {
+            let spec: &String = cmd_args.get_one("backend").unwrap();
+            let path: &String = cmd_args.get_one("out").unwrap();
+            let Some(db) = open_spec(spec, None).await else {
+                std::process::exit(cli::EXIT_ERROR);
+            };
+            let code = cli::export(db.as_ref(), path, &mut std::io::stdout()).await;
+            db.close().await;
+            drop(db);
+            std::process::exit(code);
+        }
+        Some(("import", cmd_args)) => {
+            let spec: &String = cmd_args.get_one("backend").unwrap();
+            let path: &String = cmd_args.get_one("in").unwrap();
+            let Some(db) = open_spec(spec, None).await else {
+                std::process::exit(cli::EXIT_ERROR);
+            };
+            let flush = cmd_args.get_flag("flush");
+            let code = cli::import(db.as_ref(), path, flush, &mut std::io::stdout()).await;
+            db.close().await;
+            drop(db);
+            std::process::exit(code);
+        }
+        Some(("openapi", _)) => {
+            std::process::exit(cli::openapi(&mut std::io::stdout()));
+        }
+        Some(("fsck", cmd_args)) => {
+            let path: &String = cmd_args.get_one("data-dir").unwrap();
+            std::process::exit(cli::fsck(path, &mut std::io::stdout()));
+        }
+        _ => {}
     }
 }
 
-#[allow(clippy::future_not_send)]
-async fn run(bind: &str, backend: Backend) {
-    let db: Arc<Box<dyn Storage>> = match backend {
-        Backend::Rocksdb => {
-            let db_path = format!("/dev/shm/bredis_{}", random::<i32>());
-
-            debug!("Using database path: {db_path}");
+/// Open the backend selected by the `--backend`, `--data-dir` and `--persistent` arguments,
+/// giving up after `--open-timeout-secs`.
+async fn open_backend(cmd_args: &ArgMatches) -> Option<Box<dyn Storage>> {
+    let backend: &String = cmd_args.get_one("backend").unwrap();
+    let backend: Backend = match backend.parse() {
+        Ok(backend) => backend,
+        Err(err) => {
+            error!("{err}");
+            return None;
+        }
+    };
 
-            let db_result = storages::rocksdb::Rocksdb::open(db_path.as_str());
-            if let Err(err) = db_result {
-                error!("Error opening database: {err}");
-                return;
-            }
-            let db = db_result.unwrap();
-            Arc::new(Box::new(db))
+    let (data_dir, sync_writes) = cli::storage_location(cmd_args);
+    if cmd_args.get_flag("sync-writes") && backend != Backend::Rocksdb {
+        log::warn!("--sync-writes only applies to the rocksdb backend");
+    }
+    let verify_checksums = !cmd_args.get_flag("skip-read-checksums");
+    if !verify_checksums && backend != Backend::Rocksdb {
+        log::warn!("--skip-read-checksums only applies to the rocksdb backend");
+    }
+    // Only `run` shuts down gracefully, the other subcommands don't take the argument
+    let exit_snapshot = cmd_args
+        .try_get_one::<String>("snapshot-on-exit")
+        .ok()
+        .flatten();
+    let open_timeout = cmd_args
+        .get_one::<u64>("open-timeout-secs")
+        .map(|secs| Duration::from_secs(*secs));
+    let opened = match exit_snapshot {
+        Some(path) if backend == Backend::Bredis => {
+            log::info!("Opening the {backend} backend with a snapshot on exit in {path}");
+            let path = path.clone();
+            let open = move || {
+                Bredis::open_with_exit_snapshot(&path)
+                    .map(|db| -> Box<dyn Storage> { Box::new(db) })
+            };
+            open_with_timeout(open, open_timeout).await
+        }
+        Some(_) => {
+            error!("--snapshot-on-exit only applies to the bredis backend");
+            return None;
         }
-        Backend::Bredis => {
-            let db = storages::bredis::Bredis::open();
-            Arc::new(Box::new(db))
+        None => {
+            log::info!(
+                "Opening the {backend} backend {}, sync_writes={sync_writes}",
+                data_dir.as_deref().map_or_else(
+                    || "without persistence".to_string(),
+                    |path| format!("in {path}")
+                )
+            );
+            let open = move || backend.open(data_dir.as_deref(), sync_writes, verify_checksums);
+            open_with_timeout(open, open_timeout).await
         }
-        Backend::SurrealKV => {
-            let db = storages::surrealkv::SurrealKV::open();
-            Arc::new(Box::new(db))
+    };
+    match opened {
+        Ok(db) => match cmd_args.get_one::<usize>("hash-keys-over") {
+            Some(threshold) => return Some(Box::new(HashedKeys::new(db, *threshold))),
+            None => return Some(db),
+        },
+        Err(err) => {
+            error!("Error opening database: {err}");
+            return None;
         }
+    }
+}
+
+/// Open the backend described by a `backend[:path]` specification, giving up after `timeout`.
+async fn open_spec(spec: &str, timeout: Option<Duration>) -> Option<Box<dyn Storage>> {
+    let owned_spec = spec.to_string();
+    let open = move || {
+        return Backend::from_spec(&owned_spec)
+            .and_then(|(backend, path)| backend.open(path, false, true));
     };
+    match open_with_timeout(open, timeout).await {
+        Ok(db) => return Some(db),
+        Err(err) => {
+            error!("Error opening {spec}: {err}");
+            return None;
+        }
+    }
+}
 
-    let server = http_server::Server::new(db);
+/// Put the `--named-backend` stores next to the default one, if there are any.
+async fn open_named_backends(config: &Config, db: Box<dyn Storage>) -> Option<Box<dyn Storage>> {
+    if config.named_backends.is_empty() {
+        return Some(db);
+    }
+    let mut multi = MultiBackend::new(config.backend_name.clone(), db);
+    for named in &config.named_backends {
+        let Some((name, spec)) = named.split_once('=') else {
+            error!("Invalid --named-backend {named}, expected NAME=BACKEND[:PATH]");
+            return None;
+        };
+        multi = multi.with_backend(name, open_spec(spec, config.open_timeout()).await?);
+        log::info!("Requests with X-Bredis-Backend: {name} go to {spec}");
+    }
+    return Some(Box::new(multi));
+}
+
+#[allow(clippy::future_not_send)]
+async fn run(config: Config, db: Box<dyn Storage>) {
+    config::log_startup(&config);
+    let error_rate = Arc::new(ErrorRate::new(
+        Duration::from_secs(config.error_rate_window),
+        config.unhealthy_error_rate,
+    ));
+    let db: Box<dyn Storage> = if config.tenant_header.is_some() {
+        Box::new(TenantKeys::new(db))
+    } else {
+        db
+    };
+    let db: Box<dyn Storage> = if config.strict_types {
+        Box::new(StrictTypes::new(db))
+    } else {
+        db
+    };
+    let db: Box<dyn Storage> = match config.slow_op_threshold {
+        Some(threshold) => Box::new(SlowOpLog::new(db, Duration::from_millis(threshold))),
+        None => db,
+    };
+    let db: Box<dyn Storage> = Box::new(Monitored::new(db, error_rate.clone()));
+    // Outside of `Monitored`, so rejected operations don't hide the failures of the backend
+    let db: Box<dyn Storage> = match config.breaker_threshold {
+        Some(threshold) => Box::new(CircuitBreaker::new(
+            db,
+            threshold,
+            Duration::from_secs(config.breaker_cooldown),
+        )),
+        None => db,
+    };
+    let server = http_server::Server::new(Arc::new(db), error_rate, config);
 
-    if let Err(err) = server.serve(bind.to_owned()).await {
+    // The server closes the storage once it has stopped
+    if let Err(err) = server.serve().await {
         error!("Error serving: {err}");
     }
 }
@@ -0,0 +1,266 @@
+//! Copy data between two storages or through an NDJSON file, preserving value types and TTLs.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::DatabaseError;
+use crate::storages::storage::Storage;
+use crate::storages::value::{StorageValue, ValueType};
+
+/// One line of an export file
+///
+/// # Fields
+/// * `key` - The key
+/// * `value_type` - The type of the value, e.g. `String`
+/// * `ttl` - The seconds the key had left at export time, -1 if it never expires
+/// * `value` - The stored bytes of the value in hex, so every type round-trips exactly
+#[derive(Serialize, Deserialize, Debug)]
+struct ExportRecord {
+    key: String,
+    #[serde(rename = "type")]
+    value_type: String,
+    ttl: i64,
+    value: String,
+}
+
+/// Copy every key from one storage into another
+///
+/// Keys are copied in batches and progress is logged after each batch.
+/// Keys that expire while the migration is running are skipped.
+///
+/// # Arguments
+/// * `source` - The storage to read from
+/// * `destination` - The storage to write to
+/// * `batch_size` - The number of keys copied between progress reports
+///
+/// # Returns
+/// A Result containing the number of copied keys or a `DatabaseError`
+pub async fn migrate(
+    source: &dyn Storage,
+    destination: &dyn Storage,
+    batch_size: usize,
+) -> Result<usize, DatabaseError> {
+    let keys = source.get_all_keys(b"").await?;
+    let total = keys.len();
+    let mut copied = 0;
+
+    for (batch_index, batch) in keys.chunks(batch_size.max(1)).enumerate() {
+        for key in batch {
+            // The returned TTL is the remaining time, which `set` turns back into an expiry.
+            // The source is only read, so expired keys are skipped rather than reaped.
+            if let Some(value) = source.get_no_reap(key.as_bytes()).await? {
+                destination.set(key.as_bytes(), &value).await?;
+                copied += 1;
+            }
+        }
+        log::info!("Migrated batch {}: {copied}/{total} keys", batch_index + 1);
+    }
+
+    return Ok(copied);
+}
+
+/// Write every key of a storage as one JSON object per line
+///
+/// # Arguments
+/// * `source` - The storage to read from
+/// * `out` - Where to write the lines
+///
+/// # Returns
+/// A Result containing the number of exported keys or a `DatabaseError`
+pub async fn export(source: &dyn Storage, out: &mut impl Write) -> Result<usize, DatabaseError> {
+    let mut keys = source.get_all_keys(b"").await?;
+    keys.sort_unstable();
+    let mut exported = 0;
+
+    for key in keys {
+        let Some(value) = source.get_no_reap(key.as_bytes()).await? else {
+            continue;
+        };
+        let record = ExportRecord {
+            key,
+            value_type: value.value_type.to_string(),
+            ttl: value.ttl,
+            value: to_hex(&value.value),
+        };
+        serde_json::to_writer(&mut *out, &record)
+            .map_err(|err| DatabaseError::InternalError(err.to_string()))?;
+        writeln!(out)?;
+        exported += 1;
+    }
+
+    out.flush()?;
+    return Ok(exported);
+}
+
+/// Set the keys of an export file in a storage
+///
+/// The TTLs of the file are counted again from the time of the import.
+///
+/// # Arguments
+/// * `destination` - The storage to write to
+/// * `input` - The lines written by `export`
+/// * `flush` - Delete every key of the storage before importing
+///
+/// # Returns
+/// A Result containing the number of imported keys or a `DatabaseError`, which names the
+/// line of a malformed record
+pub async fn import(
+    destination: &dyn Storage,
+    input: impl BufRead,
+    flush: bool,
+) -> Result<usize, DatabaseError> {
+    if flush {
+        destination.delete_prefix(b"").await?;
+    }
+
+    let mut imported = 0;
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid =
+            |err: String| DatabaseError::InvalidValueType(format!("Line {}: {err}", index + 1));
+        let record: ExportRecord =
+            serde_json::from_str(&line).map_err(|err| invalid(err.to_string()))?;
+        let value = StorageValue {
+            value_type: record
+                .value_type
+                .parse::<ValueType>()
+                .map_err(|err| invalid(err.to_string()))?,
+            ttl: record.ttl,
+            value: from_hex(&record.value).map_err(invalid)?,
+            mtime: 0,
+        };
+        destination.set(record.key.as_bytes(), &value).await?;
+        imported += 1;
+    }
+
+    return Ok(imported);
+}
+
+/// Write bytes as lowercase hex
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{byte:02x}").unwrap();
+    }
+    return hex;
+}
+
+/// Read the bytes written by `to_hex`
+fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 == 1 || !hex.is_ascii() {
+        return Err(format!("Invalid hex value: {hex}"));
+    }
+    return (0..hex.len())
+        .step_by(2)
+        .map(|start| {
+            u8::from_str_radix(&hex[start..start + 2], 16)
+                .map_err(|_| format!("Invalid hex value: {hex}"))
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::{bredis::Bredis, mock::TempPath, rocksdb::Rocksdb, value::StorageValue};
+
+    #[tokio::test]
+    async fn test_migrate_bredis_to_rocksdb() {
+        let source = Bredis::open();
+        let value = &mut StorageValue::string("value1");
+        source.set(b"key1", value).await.unwrap();
+
+        value.value = b"value2".to_vec();
+        value.ttl = 1000;
+        source.set(b"key2", value).await.unwrap();
+
+        source.increment(b"counter", 1, Some(41), 10).await.unwrap();
+
+        let destination = Rocksdb::open_ephemeral().unwrap();
+
+        let copied = migrate(&source, &destination, 2).await.unwrap();
+        assert_eq!(copied, 3);
+
+        let mut source_keys = source.get_all_keys(b"").await.unwrap();
+        let mut destination_keys = destination.get_all_keys(b"").await.unwrap();
+        source_keys.sort();
+        destination_keys.sort();
+        assert_eq!(source_keys, destination_keys);
+
+        for key in source_keys {
+            let expected = source.get(key.as_bytes()).await.unwrap().unwrap();
+            let actual = destination.get(key.as_bytes()).await.unwrap().unwrap();
+            assert_eq!(
+                actual.value_type, expected.value_type,
+                "Value type is incorrect"
+            );
+            assert_eq!(actual.value, expected.value, "Value is incorrect");
+            assert!((actual.ttl - expected.ttl).abs() <= 1, "TTL is incorrect");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_then_import() {
+        let source = Bredis::open();
+        source
+            .set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+        let value = StorageValue {
+            ttl: 1000,
+            ..StorageValue::blob(b"\x00\xff", -1)
+        };
+        source.set(b"key2", &value).await.unwrap();
+        source.increment(b"counter", 1, Some(41), 10).await.unwrap();
+        source
+            .list_push(b"events", &["a".to_string(), "b".to_string()], None, None)
+            .await
+            .unwrap();
+
+        let path = TempPath::new("test_export");
+        let mut file = std::fs::File::create(&path).unwrap();
+        assert_eq!(export(&source, &mut file).await.unwrap(), 4);
+
+        let destination = Bredis::open();
+        destination
+            .set(b"stale", &StorageValue::string("stale"))
+            .await
+            .unwrap();
+        let file = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+        assert_eq!(import(&destination, file, true).await.unwrap(), 4);
+
+        let mut source_keys = source.get_all_keys(b"").await.unwrap();
+        let mut destination_keys = destination.get_all_keys(b"").await.unwrap();
+        source_keys.sort();
+        destination_keys.sort();
+        assert_eq!(source_keys, destination_keys);
+
+        for key in source_keys {
+            let expected = source.get(key.as_bytes()).await.unwrap().unwrap();
+            let actual = destination.get(key.as_bytes()).await.unwrap().unwrap();
+            assert_eq!(actual.value_type, expected.value_type, "{key}");
+            assert_eq!(actual.value, expected.value, "{key}");
+            assert!((actual.ttl - expected.ttl).abs() <= 1, "{key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_malformed_line() {
+        let input = "{\"key\":\"key1\",\"type\":\"String\",\"ttl\":-1,\"value\":\"6869\"}\n\
+                     {\"key\":\"key2\",\"type\":\"String\",\"ttl\":-1,\"value\":\"zz\"}\n";
+        let destination = Bredis::open();
+        let result = import(&destination, input.as_bytes(), false).await;
+        assert!(
+            matches!(&result, Err(DatabaseError::InvalidValueType(err)) if err.starts_with("Line 2")),
+            "{:?}",
+            result.err()
+        );
+        let value = destination.get(b"key1").await.unwrap().unwrap();
+        assert_eq!(value.value, b"hi");
+    }
+}
@@ -0,0 +1,186 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::errors::DatabaseError;
+
+use super::{
+    bredis::{Bredis, SNAPSHOT_INTERVAL},
+    rocksdb::Rocksdb,
+    storage::Storage,
+    surrealkv::SurrealKV,
+};
+
+/// The data directory used by `--persistent` when `--data-dir` is not given
+pub const DEFAULT_DATA_DIR: &str = "bredis-data";
+
+/// Storage backends supported by bredis
+///
+/// Without a data directory every backend is ephemeral: `bredis` and `surrealkv` keep the data
+/// in memory, `rocksdb` in a fresh directory under the system temporary directory, removed
+/// when the storage is dropped.
+/// With a data directory every backend keeps its data there across restarts.
+///
+/// # Example
+/// ```ignore
+/// let backend: Backend = "rocksdb".parse().unwrap();
+/// let db = backend.open(Some("/var/lib/bredis"), false, true).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Rocksdb,
+    Bredis,
+    SurrealKV,
+}
+
+impl Backend {
+    /// Parse a `backend[:path]` specification, e.g. `rocksdb:/var/lib/bredis`
+    ///
+    /// # Arguments
+    /// * `spec` - The backend name, optionally followed by a colon and its data directory
+    ///
+    /// # Returns
+    /// A Result containing the backend and its optional data directory or a `DatabaseError`
+    pub fn from_spec(spec: &str) -> Result<(Self, Option<&str>), DatabaseError> {
+        return match spec.split_once(':') {
+            Some((backend, path)) => Ok((backend.parse()?, Some(path))),
+            None => Ok((spec.parse()?, None)),
+        };
+    }
+
+    /// Open a storage of this backend
+    ///
+    /// # Arguments
+    /// * `path` - The data directory the data is kept in across restarts.
+    ///   The storage is ephemeral when it is omitted.
+    /// * `sync_writes` - Whether every write is fsync'd, only the `rocksdb` backend supports it
+    /// * `verify_checksums` - Whether reads verify block checksums, only the `rocksdb` backend
+    ///   supports turning it off
+    ///
+    /// # Returns
+    /// A Result containing the opened storage or a `DatabaseError`
+    pub fn open(
+        self,
+        path: Option<&str>,
+        sync_writes: bool,
+        verify_checksums: bool,
+    ) -> Result<Box<dyn Storage>, DatabaseError> {
+        return match self {
+            Self::Rocksdb => {
+                // Without a data directory nothing is meant to outlive the process
+                let db = match path {
+                    Some(path) => Rocksdb::open(path)?,
+                    None => Rocksdb::open_ephemeral()?,
+                };
+                log::debug!("Using database path: {}", db.path());
+                Ok(Box::new(
+                    db.with_sync_writes(sync_writes)
+                        .with_verify_checksums(verify_checksums),
+                ))
+            }
+            Self::Bredis => match path {
+                Some(path) => Ok(Box::new(Bredis::open_with_snapshot(
+                    path,
+                    SNAPSHOT_INTERVAL,
+                )?)),
+                None => Ok(Box::new(Bredis::open())),
+            },
+            Self::SurrealKV => match path {
+                Some(path) => Ok(Box::new(SurrealKV::open_persistent(path)?)),
+                None => Ok(Box::new(SurrealKV::open())),
+            },
+        };
+    }
+}
+
+/// Open a storage on the blocking thread pool, giving up once `timeout` has passed
+///
+/// Opening a store may hang on a held lock or a slow disk. The open itself can't be
+/// cancelled, so on timeout it is left running in the background and an error is returned.
+///
+/// # Arguments
+/// * `open` - The blocking function opening the storage
+/// * `timeout` - The longest the open may take, forever if `None`
+///
+/// # Returns
+/// A Result containing the opened storage or a `DatabaseError`
+///
+/// # Example
+/// ```ignore
+/// let db = open_with_timeout(
+///     move || Backend::Rocksdb.open(Some("/var/lib/bredis"), false, true),
+///     Some(Duration::from_secs(30)),
+/// )
+/// .await?;
+/// ```
+pub async fn open_with_timeout<F>(
+    open: F,
+    timeout: Option<Duration>,
+) -> Result<Box<dyn Storage>, DatabaseError>
+where
+    F: FnOnce() -> Result<Box<dyn Storage>, DatabaseError> + Send + 'static,
+{
+    let task = tokio::task::spawn_blocking(open);
+    let joined = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, task).await.map_err(|_| {
+            DatabaseError::InitialFailed(format!(
+                "The backend didn't open within {}s",
+                timeout.as_secs_f64()
+            ))
+        })?,
+        None => task.await,
+    };
+    return joined.map_err(|err| DatabaseError::InitialFailed(err.to_string()))?;
+}
+
+impl FromStr for Backend {
+    type Err = DatabaseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return match value {
+            "rocksdb" => Ok(Self::Rocksdb),
+            "bredis" => Ok(Self::Bredis),
+            "surrealkv" => Ok(Self::SurrealKV),
+            _ => Err(DatabaseError::InitialFailed(format!(
+                "Invalid backend: {value}"
+            ))),
+        };
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Rocksdb => write!(f, "rocksdb"),
+            Self::Bredis => write!(f, "bredis"),
+            Self::SurrealKV => write!(f, "surrealkv"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_open_with_timeout() {
+        let db = open_with_timeout(|| Backend::Bredis.open(None, false, true), None)
+            .await
+            .unwrap();
+        assert!(db.is_empty().await.unwrap());
+
+        // An open stuck on a lock or a slow disk is abandoned
+        let slow_open = || {
+            std::thread::sleep(Duration::from_millis(500));
+            return Backend::Bredis.open(None, false, true);
+        };
+        let result = open_with_timeout(slow_open, Some(Duration::from_millis(20))).await;
+        assert!(
+            matches!(result, Err(DatabaseError::InitialFailed(ref message)) if message.contains("didn't open within")),
+            "{:?}",
+            result.err()
+        );
+
+        let failed_open = || Err(DatabaseError::InitialFailed("Lock held".to_string()));
+        let result = open_with_timeout(failed_open, Some(Duration::from_secs(5))).await;
+        assert!(matches!(result, Err(DatabaseError::InitialFailed(_))));
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// The state of a `CircuitBreaker`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Operations reach the backend, `failures` counts the consecutive failures
+    Closed { failures: u32 },
+    /// Operations are rejected until the cooldown has passed
+    Open { since: Instant },
+    /// A single probe reaches the backend, the others are rejected until it completes
+    HalfOpen { since: Instant },
+}
+
+/// A storage wrapper that stops calling a failing backend for a while
+///
+/// After `threshold` consecutive backend failures the breaker opens and rejects every
+/// operation with a retryable `Conflict`, answered with 503, instead of piling requests
+/// onto the backend. Once the cooldown has passed a single operation probes the backend:
+/// if it succeeds the breaker closes, if it fails the breaker opens again.
+///
+/// Like `Monitored`, only backend failures count; client errors such as a missing key
+/// are successful operations.
+pub struct CircuitBreaker {
+    inner: Box<dyn Storage>,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    /// Wrap a storage in a closed circuit breaker
+    ///
+    /// # Arguments
+    /// * `inner` - The storage to protect
+    /// * `threshold` - The consecutive failures after which the breaker opens
+    /// * `cooldown` - How long the breaker stays open before it probes the backend
+    pub fn new(inner: Box<dyn Storage>, threshold: u32, cooldown: Duration) -> Self {
+        return Self {
+            inner,
+            threshold,
+            cooldown,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        };
+    }
+
+    /// Check whether an operation may reach the backend
+    ///
+    /// # Returns
+    /// A `Conflict` error if the breaker is open or a probe is already in flight.
+    fn admit(&self) -> Result<(), DatabaseError> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => return Ok(()),
+            // A probe whose request was dropped never completes, so it is replaced after a
+            // cooldown rather than holding the breaker half-open forever
+            State::Open { since } | State::HalfOpen { since }
+                if since.elapsed() >= self.cooldown =>
+            {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                return Ok(());
+            }
+            State::Open { .. } | State::HalfOpen { .. } => {
+                return Err(DatabaseError::Conflict(
+                    "The backend is failing, the circuit breaker is open".to_string(),
+                ));
+            }
+        }
+    }
+
+    fn track<T>(&self, result: Result<T, DatabaseError>) -> Result<T, DatabaseError> {
+        let failed = matches!(
+            result,
+            Err(DatabaseError::InternalError(_) | DatabaseError::InitialFailed(_))
+        );
+        let mut state = self.state.lock().unwrap();
+        *state = match (*state, failed) {
+            (_, false) => State::Closed { failures: 0 },
+            (State::Closed { failures }, true) if failures.saturating_add(1) < self.threshold => {
+                State::Closed {
+                    failures: failures.saturating_add(1),
+                }
+            }
+            (_, true) => {
+                log::warn!(
+                    "Opening the circuit breaker for {}s after a backend failure",
+                    self.cooldown.as_secs()
+                );
+                State::Open {
+                    since: Instant::now(),
+                }
+            }
+        };
+        return result;
+    }
+}
+
+#[async_trait]
+impl Storage for CircuitBreaker {
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get(key).await);
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_no_reap(key).await);
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_record(key).await);
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_many(keys).await);
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_all_keys(prefix).await);
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.keys_modified_since(since).await);
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.range(start, end, limit).await);
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.len().await);
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_ttl(key).await);
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_expire_at(key).await);
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.update_ttl(key, ttl).await);
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.adjust_ttl(key, delta).await);
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.get_ex(key, ttl).await);
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.set(key, value).await);
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.set_keep_ttl(key, value).await);
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.set_many(entries).await);
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.increment(key, value, default_value, base).await);
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.decrement(key, value, default_value, base).await);
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        self.admit()?;
+        return self.track(
+            self.inner
+                .increment_bounded(key, value, default_value, max, saturate, base)
+                .await,
+        );
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.increment_many(increments).await);
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.decrement_and_delete(key, value).await);
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.set_extreme(key, value, extreme).await);
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.compare_and_increment(key, expected, delta).await);
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.json_merge(key, patch).await);
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        self.admit()?;
+        return self.track(
+            self.inner
+                .list_push(key, values, maxlen, max_elements)
+                .await,
+        );
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.append(key, suffix, ttl).await);
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.hset_many(key, fields, max_elements).await);
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.rename_nx(key, new_key).await);
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.set_nx(key, value).await);
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.delete_if_equal(key, expected).await);
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.delete(key).await);
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.delete_prefix(prefix).await);
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.delete_expired().await);
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.sync().await);
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.flush_memtables().await);
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        self.admit()?;
+        return self.track(self.inner.checkpoint(dest).await);
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::mock::FailingStorage;
+
+    fn is_rejected<T>(result: &Result<T, DatabaseError>) -> bool {
+        return matches!(result, Err(DatabaseError::Conflict(_)));
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold() {
+        let breaker = CircuitBreaker::new(Box::new(FailingStorage), 3, Duration::from_mins(1));
+        for _ in 0..3 {
+            let result = breaker.get(b"key1").await;
+            assert!(matches!(result, Err(DatabaseError::InternalError(_))));
+        }
+
+        assert!(is_rejected(&breaker.get(b"key1").await));
+        assert!(is_rejected(
+            &breaker.set(b"key1", &StorageValue::string("value1")).await
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_half_opens_after_cooldown() {
+        let breaker = CircuitBreaker::new(Box::new(FailingStorage), 1, Duration::from_millis(100));
+        assert!(!is_rejected(&breaker.get(b"key1").await));
+        assert!(is_rejected(&breaker.get(b"key1").await));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        // The probe reaches the backend, fails and opens the breaker again
+        assert!(matches!(
+            breaker.get(b"key1").await,
+            Err(DatabaseError::InternalError(_))
+        ));
+        assert!(is_rejected(&breaker.get(b"key1").await));
+    }
+
+    #[tokio::test]
+    async fn test_closes_after_successful_probe() {
+        let breaker = CircuitBreaker::new(
+            Box::new(crate::storages::bredis::Bredis::open()),
+            1,
+            Duration::from_millis(100),
+        );
+        *breaker.state.lock().unwrap() = State::Open {
+            since: Instant::now(),
+        };
+        assert!(is_rejected(&breaker.get(b"key1").await));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(matches!(breaker.get(b"key1").await, Ok(None)));
+        assert_eq!(
+            *breaker.state.lock().unwrap(),
+            State::Closed { failures: 0 }
+        );
+        // Client errors are no backend failures
+        assert!(matches!(
+            breaker.update_ttl(b"key1", 10).await,
+            Err(DatabaseError::ValueNotFound(_))
+        ));
+        assert!(matches!(breaker.get(b"key1").await, Ok(None)));
+    }
+}
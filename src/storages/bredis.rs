@@ -1,20 +1,112 @@
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, HashMap},
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock, RwLockWriteGuard,
+    },
+    time::Duration,
 };
 
 use async_trait::async_trait;
+use tokio::{io::AsyncWriteExt, time::MissedTickBehavior};
 
 use crate::errors::DatabaseError;
 
 use super::{
-    storage::Storage,
-    value::{StorageValue, ValueType},
+    clock::{expire_at, Clock, SystemClock},
+    storage::{Storage, StorageCapabilities},
+    value::{format_integer, Extreme, StorageValue, ValueType},
 };
 
+/// The interval between background snapshot flushes
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The name of the snapshot file inside the data directory
+const SNAPSHOT_FILE: &str = "bredis.snapshot";
+
+type Store = RwLock<HashMap<String, StorageValue>>;
+
 #[derive(Clone)]
 pub struct Bredis {
-    store: Arc<RwLock<HashMap<String, StorageValue>>>,
+    store: Arc<Store>,
+    snapshot: Option<Arc<Snapshot>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// Persistence of the in-memory store into a snapshot file
+///
+/// Writes only raise the dirty flag. A background task serializes the store at most
+/// once per interval and only if the flag is set, so a burst of writes is coalesced
+/// into a single flush and never waits for the disk.
+struct Snapshot {
+    path: PathBuf,
+    dirty: AtomicBool,
+    flushes: AtomicUsize,
+    flush_lock: tokio::sync::Mutex<()>,
+}
+
+impl Snapshot {
+    fn new(path: PathBuf) -> Self {
+        return Self {
+            path,
+            dirty: AtomicBool::new(false),
+            flushes: AtomicUsize::new(0),
+            flush_lock: tokio::sync::Mutex::new(()),
+        };
+    }
+
+    /// Read the store from a snapshot file
+    ///
+    /// # Returns
+    /// The stored keys, none if the file doesn't exist yet, or a `DatabaseError` if it is corrupt
+    fn load(path: &Path) -> Result<HashMap<String, StorageValue>, DatabaseError> {
+        return match std::fs::read(path) {
+            Ok(data) => bincode::deserialize(&data)
+                .map_err(|err| DatabaseError::InitialFailed(format!("Corrupt snapshot: {err}"))),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        };
+    }
+
+    /// Write the store into the snapshot file if it changed since the last flush
+    ///
+    /// # Arguments
+    /// * `store` - The store to persist
+    async fn flush(&self, store: &Store) -> Result<(), DatabaseError> {
+        let _guard = self.flush_lock.lock().await;
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+
+        // Copy the store so writers are only blocked for the duration of the clone
+        let copy = store.read().unwrap().clone();
+        let result = Self::write(&self.path, &copy).await;
+        match result {
+            Ok(()) => {
+                self.flushes.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(_) => self.dirty.store(true, Ordering::Release),
+        }
+        return result;
+    }
+
+    async fn write(
+        path: &Path,
+        store: &HashMap<String, StorageValue>,
+    ) -> Result<(), DatabaseError> {
+        let data = bincode::serialize(store)
+            .map_err(|err| DatabaseError::InternalError(format!("Snapshot failed: {err}")))?;
+
+        // Replace the snapshot atomically so a crash never leaves a truncated file
+        let tmp_path = path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(&data).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        return Ok(());
+    }
 }
 
 impl Bredis {
@@ -22,6 +114,136 @@ impl Bredis {
     pub fn open() -> Self {
         Self {
             store: Arc::new(RwLock::new(HashMap::new())),
+            snapshot: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Read the current time from `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        return self;
+    }
+
+    /// Open a store persisted into a snapshot file in the specified directory
+    /// The existing snapshot is loaded and a background task flushes changes every `interval`
+    ///
+    /// # Arguments
+    /// * `path` - The data directory
+    /// * `interval` - The interval between background flushes
+    ///
+    /// # Returns
+    /// A Result containing the store or a `DatabaseError` if the snapshot can't be loaded
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Bredis::open_with_snapshot("/var/lib/bredis", SNAPSHOT_INTERVAL).unwrap();
+    /// ```
+    pub fn open_with_snapshot(path: &str, interval: Duration) -> Result<Self, DatabaseError> {
+        std::fs::create_dir_all(path)?;
+        let snapshot_path = Path::new(path).join(SNAPSHOT_FILE);
+
+        let store = Arc::new(RwLock::new(Snapshot::load(&snapshot_path)?));
+        let snapshot = Arc::new(Snapshot::new(snapshot_path));
+        tokio::spawn(Self::flush_periodically(
+            Arc::downgrade(&store),
+            snapshot.clone(),
+            interval,
+        ));
+
+        return Ok(Self {
+            store,
+            snapshot: Some(snapshot),
+            clock: Arc::new(SystemClock),
+        });
+    }
+
+    /// Open a store persisted into a snapshot file on a clean shutdown only
+    /// The existing snapshot is loaded, but there is no background flush: the changes are
+    /// written by `close` when the server shuts down, or by an explicit `sync`
+    ///
+    /// # Arguments
+    /// * `path` - The snapshot file
+    ///
+    /// # Returns
+    /// A Result containing the store or a `DatabaseError` if the snapshot can't be loaded
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Bredis::open_with_exit_snapshot("/var/lib/bredis/exit.snapshot").unwrap();
+    /// ```
+    pub fn open_with_exit_snapshot(path: &str) -> Result<Self, DatabaseError> {
+        let path = Path::new(path);
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        return Ok(Self {
+            store: Arc::new(RwLock::new(Snapshot::load(path)?)),
+            snapshot: Some(Arc::new(Snapshot::new(path.to_path_buf()))),
+            clock: Arc::new(SystemClock),
+        });
+    }
+
+    /// Flush the snapshot every `interval` until the store is dropped
+    async fn flush_periodically(
+        store: std::sync::Weak<Store>,
+        snapshot: Arc<Snapshot>,
+        interval: Duration,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let Some(store) = store.upgrade() else {
+                return;
+            };
+            if let Err(err) = snapshot.flush(&store).await {
+                log::error!("Error flushing snapshot: {err}");
+            }
+        }
+    }
+
+    /// Lock the store for a write, the snapshot is marked as dirty once the write is done
+    fn write_store(&self) -> StoreWriteGuard<'_> {
+        return StoreWriteGuard {
+            store: self.store.write().unwrap(),
+            snapshot: self.snapshot.as_deref(),
+        };
+    }
+}
+
+/// A write lock on the store that marks the snapshot as dirty when it is released
+///
+/// The flag is raised after the write and before the lock is released, so a flush that
+/// clears the flag either copies the write or leaves the flag set for the next flush.
+struct StoreWriteGuard<'a> {
+    store: RwLockWriteGuard<'a, HashMap<String, StorageValue>>,
+    snapshot: Option<&'a Snapshot>,
+}
+
+impl Deref for StoreWriteGuard<'_> {
+    type Target = HashMap<String, StorageValue>;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.store;
+    }
+}
+
+impl DerefMut for StoreWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.store;
+    }
+}
+
+impl Drop for StoreWriteGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot {
+            snapshot.dirty.store(true, Ordering::Release);
         }
     }
 }
@@ -31,12 +253,14 @@ impl Storage for Bredis {
     async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
         let key_str = String::from_utf8(key.to_vec()).unwrap();
         let mut store = self.store.write().unwrap();
-        if let Some(value) = store.get_mut(&key_str) {
+        if let Some(value) = store.get(&key_str) {
             if value.ttl < 0 {
                 return Ok(Some(value.clone()));
             }
 
-            value.ttl -= chrono::Utc::now().timestamp();
+            // The stored TTL is an absolute timestamp, so report the remaining time on a copy
+            let mut value = value.clone();
+            value.ttl -= self.clock.now();
             if value.ttl < 0 {
                 // Value is expired, remove it
                 store.remove(&key_str);
@@ -48,20 +272,102 @@ impl Storage for Bredis {
         Ok(None)
     }
 
-    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        let key_str = String::from_utf8(key.to_vec()).unwrap();
+        let store = self.store.read().unwrap();
+        let Some(value) = store.get(&key_str) else {
+            return Ok(None);
+        };
+
         let mut value = value.clone();
+        drop(store);
         if value.ttl < 0 {
-            value.ttl = -1;
-        } else {
-            value.ttl += chrono::Utc::now().timestamp();
+            return Ok(Some(value));
         }
-        self.store
-            .write()
-            .unwrap()
+        value.ttl -= self.clock.now();
+        if value.ttl < 0 {
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        let key_str = String::from_utf8(key.to_vec()).unwrap();
+        let store = self.store.read().unwrap();
+        // An expired value is reported as missing, but left for `get` to remove
+        let record = store
+            .get(&key_str)
+            .filter(|value| value.ttl < 0 || value.ttl >= self.clock.now())
+            .map(StorageValue::to_binary);
+        return Ok(record);
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        let now = self.clock.now();
+        // The read lock is held for the whole batch, so no write lands in between
+        let store = self.store.read().unwrap();
+        let values = keys
+            .iter()
+            .map(|key| {
+                let key = String::from_utf8(key.clone()).unwrap();
+                let mut value = store.get(&key)?.clone();
+                if value.ttl < 0 {
+                    return Some(value);
+                }
+                value.ttl -= now;
+                (value.ttl >= 0).then_some(value)
+            })
+            .collect();
+        drop(store);
+        Ok(values)
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        value.validate()?;
+
+        let mut value = value.clone();
+        value.ttl = expire_at(self.clock.now(), value.ttl)?;
+        value.touch(self.clock.now_millis());
+        self.write_store()
             .insert(String::from_utf8(key.to_vec()).unwrap(), value);
         Ok(())
     }
 
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        value.validate()?;
+
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        let mut value = value.clone();
+        value.ttl = match store.get(&key) {
+            Some(current) if !current.is_expired(now) => current.ttl,
+            _ => expire_at(now, value.ttl)?,
+        };
+        value.touch(self.clock.now_millis());
+        store.insert(key, value);
+        drop(store);
+        Ok(())
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        let now = self.clock.now();
+        let mut pending = Vec::with_capacity(entries.len());
+
+        // Every value is checked before the first write, so a failure leaves the store untouched
+        for (key, value) in entries {
+            value.validate()?;
+            let mut value = value.clone();
+            value.ttl = expire_at(now, value.ttl)?;
+            value.touch(self.clock.now_millis());
+            pending.push((String::from_utf8(key.clone()).unwrap(), value));
+        }
+
+        self.write_store().extend(pending);
+        Ok(())
+    }
+
     async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
         let keys: Vec<String> = self
             .store
@@ -74,6 +380,46 @@ impl Storage for Bredis {
         Ok(keys)
     }
 
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        let now = self.clock.now();
+        let keys: Vec<String> = self
+            .store
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, value)| !value.is_expired(now) && value.is_modified_since(since))
+            .map(|(key, _)| key.clone())
+            .collect();
+        Ok(keys)
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        let now = self.clock.now();
+        let mut keys: Vec<String> = self
+            .store
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(key, value)| (start..end).contains(&key.as_bytes()) && !value.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        // The map is unordered, so sort before applying the limit
+        keys.sort_unstable();
+        keys.truncate(limit.unwrap_or(usize::MAX));
+        Ok(keys)
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        let len = self.store.read().unwrap().len();
+        Ok(u64::try_from(len).unwrap_or(u64::MAX))
+    }
+
     async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
         let mut store = self.store.write().unwrap();
         match store.get(&String::from_utf8(key.to_vec()).unwrap()) {
@@ -82,7 +428,7 @@ impl Storage for Bredis {
                     return Ok(-1);
                 }
 
-                let ttl = value.ttl - chrono::Utc::now().timestamp();
+                let ttl = value.ttl - self.clock.now();
                 if ttl > 0 {
                     return Ok(ttl);
                 }
@@ -99,15 +445,21 @@ impl Storage for Bredis {
         }
     }
 
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+        match self.store.read().unwrap().get(&key) {
+            Some(value) if !value.is_expired(now) => Ok(value.ttl),
+            _ => Err(DatabaseError::ValueNotFound(key)),
+        }
+    }
+
     async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
-        let mut store = self.store.write().unwrap();
+        let mut store = self.write_store();
         match store.get_mut(&String::from_utf8(key.to_vec()).unwrap()) {
             Some(value) => {
-                if ttl < 0 {
-                    value.ttl = -1;
-                } else {
-                    value.ttl = chrono::Utc::now().timestamp() + ttl;
-                }
+                value.ttl = expire_at(self.clock.now(), ttl)?;
+                value.touch(self.clock.now_millis());
                 Ok(())
             }
             None => Err(DatabaseError::ValueNotFound(
@@ -116,35 +468,91 @@ impl Storage for Bredis {
         }
     }
 
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let Some(value) = store.get_mut(&key) else {
+            return Err(DatabaseError::ValueNotFound(key));
+        };
+
+        if value.ttl < 0 {
+            return Ok(-1);
+        }
+
+        let now = self.clock.now();
+        let remaining = value.ttl - now;
+        if remaining <= 0 {
+            store.remove(&key);
+            return Err(DatabaseError::ValueNotFound(key));
+        }
+
+        let ttl = remaining.saturating_add(delta).max(0);
+        if ttl == 0 {
+            store.remove(&key);
+        } else {
+            value.ttl = expire_at(now, ttl)?;
+            value.touch(self.clock.now_millis());
+        }
+        drop(store);
+        Ok(ttl)
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        let Some(ttl) = ttl else {
+            return self.get(key).await;
+        };
+
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let mut store = self.write_store();
+        let Some(value) = store.get_mut(&key) else {
+            return Ok(None);
+        };
+
+        let now = self.clock.now();
+        if value.ttl > -1 && value.ttl < now {
+            store.remove(&key);
+            return Ok(None);
+        }
+
+        value.ttl = expire_at(now, ttl)?;
+        value.touch(self.clock.now_millis());
+        let mut value = value.clone();
+        drop(store);
+        if value.ttl > -1 {
+            value.ttl = ttl;
+        }
+        Ok(Some(value))
+    }
+
     #[allow(clippy::significant_drop_tightening)]
     async fn increment(
         &self,
         key: &[u8],
         increment_value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, DatabaseError> {
-        let mut store = self.store.write().unwrap();
+        let default_text = format_integer(default_value.unwrap_or(0), base)?;
+        let mut store = self.write_store();
         let key = String::from_utf8(key.to_vec()).unwrap();
-        let value = store.entry(key).or_insert_with(|| StorageValue {
+        if !store.contains_key(&key) && default_value.is_none() {
+            return Err(DatabaseError::ValueNotFound(key));
+        }
+        // Computed on a copy, so an overflow leaves the key as it was
+        let mut value = store.get(&key).cloned().unwrap_or_else(|| StorageValue {
             value_type: ValueType::Integer,
             ttl: -1,
-            value: default_value.unwrap_or(0).to_string().into_bytes(),
+            value: default_text.into_bytes(),
+            mtime: 0,
         });
-        if value.value_type != ValueType::Integer {
-            return Err(DatabaseError::InvalidValueType(
-                "Value is not an integer".to_string(),
-            ));
-        }
-        let string_value = String::from_utf8(value.value.clone());
-        if string_value.is_err() {
-            return Err(DatabaseError::InternalError(
-                "Failed to parse integer value".to_string(),
-            ));
-        }
-        let current_value = string_value.unwrap().parse::<i64>().unwrap();
-        let new_value = current_value + increment_value;
-        value.value = new_value.to_string().into_bytes();
-        Ok(value.clone())
+        value.add_in_base(increment_value, base)?;
+        value.touch(self.clock.now_millis());
+        store.insert(key, value.clone());
+        Ok(value)
     }
 
     #[allow(clippy::significant_drop_tightening)]
@@ -153,41 +561,350 @@ impl Storage for Bredis {
         key: &[u8],
         decrement_value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, DatabaseError> {
-        let mut store = self.store.write().unwrap();
+        let default_text = format_integer(default_value.unwrap_or(0), base)?;
+        let mut store = self.write_store();
         let key = String::from_utf8(key.to_vec()).unwrap();
+        if !store.contains_key(&key) && default_value.is_none() {
+            return Err(DatabaseError::ValueNotFound(key));
+        }
         let value = store.entry(key).or_insert_with(|| StorageValue {
             value_type: ValueType::Integer,
             ttl: -1,
-            value: default_value.unwrap_or(0).to_string().into_bytes(),
+            value: default_text.into_bytes(),
+            mtime: 0,
         });
-        if value.value_type != ValueType::Integer {
-            return Err(DatabaseError::InvalidValueType(
-                "Value is not an integer".to_string(),
-            ));
-        }
-        let string_value = String::from_utf8(value.value.clone());
-        if string_value.is_err() {
-            return Err(DatabaseError::InternalError(
-                "Failed to parse integer value".to_string(),
-            ));
-        }
-        let current_value = string_value.unwrap().parse::<i64>().unwrap();
+        let current_value = value.get_integer_value_in_base(base)?;
         let new_value = current_value - decrement_value;
-        value.value = new_value.to_string().into_bytes();
+        value.value = format_integer(new_value, base)?.into_bytes();
+        value.touch(self.clock.now_millis());
         Ok(value.clone())
     }
 
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let mut storage_value = match (store.get(&key), default_value) {
+            (Some(storage_value), _) => storage_value.clone(),
+            (None, Some(default_value)) => StorageValue {
+                value_type: ValueType::Integer,
+                ttl: -1,
+                value: format_integer(default_value, base)?.into_bytes(),
+                mtime: 0,
+            },
+            (None, None) => return Err(DatabaseError::ValueNotFound(key)),
+        };
+
+        // The value is only written back once it is known to be in bounds
+        let capped = storage_value.add_bounded(value, max, saturate, base)?;
+        storage_value.touch(self.clock.now_millis());
+        store.insert(key, storage_value.clone());
+        drop(store);
+        Ok((storage_value, capped))
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let mut store = self.write_store();
+        let mut pending: HashMap<String, StorageValue> = HashMap::new();
+        let mut new_values = Vec::with_capacity(increments.len());
+
+        // Every new value is computed before the first write, so a failure leaves the store untouched
+        for (key, value) in increments {
+            let key = String::from_utf8(key.clone()).unwrap();
+            let mut storage_value = pending
+                .get(&key)
+                .or_else(|| store.get(&key))
+                .cloned()
+                .unwrap_or_else(|| StorageValue {
+                    value_type: ValueType::Integer,
+                    ttl: -1,
+                    value: b"0".to_vec(),
+                    mtime: 0,
+                });
+            let new_value = storage_value.add_in_base(*value, 10)?;
+            storage_value.touch(self.clock.now_millis());
+            pending.insert(key, storage_value);
+            new_values.push(new_value);
+        }
+
+        store.extend(pending);
+        drop(store);
+        Ok(new_values)
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match store.get(&key) {
+            Some(storage_value) if !storage_value.is_expired(now) => storage_value.clone(),
+            _ => return Err(DatabaseError::ValueNotFound(key)),
+        };
+
+        let new_value = storage_value.subtract(value)?;
+        let deleted = new_value <= 0;
+        if deleted {
+            store.remove(&key);
+        } else {
+            storage_value.touch(self.clock.now_millis());
+            store.insert(key, storage_value);
+        }
+        drop(store);
+        Ok((new_value, deleted))
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        let (mut storage_value, (result, changed)) = match store.get(&key) {
+            Some(storage_value) if !storage_value.is_expired(now) => {
+                let mut storage_value = storage_value.clone();
+                let kept = storage_value.keep_extreme(value, extreme)?;
+                (storage_value, kept)
+            }
+            _ => (
+                StorageValue {
+                    value_type: ValueType::Integer,
+                    ttl: -1,
+                    value: format_integer(value, 10)?.into_bytes(),
+                    mtime: 0,
+                },
+                (value, true),
+            ),
+        };
+
+        if changed {
+            storage_value.touch(self.clock.now_millis());
+            store.insert(key, storage_value);
+        }
+        drop(store);
+        Ok((result, changed))
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match store.get(&key) {
+            Some(storage_value) if !storage_value.is_expired(now) => storage_value.clone(),
+            _ => return Err(DatabaseError::ValueNotFound(key)),
+        };
+
+        let (value, swapped) = storage_value.compare_and_add(expected, delta)?;
+        if swapped {
+            storage_value.touch(self.clock.now_millis());
+            store.insert(key, storage_value);
+        }
+        drop(store);
+        Ok((value, swapped))
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if store.get(&key).is_some_and(|value| value.is_expired(now)) {
+            store.remove(&key);
+        }
+
+        let value = store.entry(key).or_insert_with(|| StorageValue {
+            value_type: ValueType::Json,
+            ttl: -1,
+            value: b"null".to_vec(),
+            mtime: 0,
+        });
+        value.merge_json(patch)?;
+        value.touch(self.clock.now_millis());
+        let value = value.clone();
+        drop(store);
+        Ok(value)
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if store.get(&key).is_some_and(|value| value.is_expired(now)) {
+            store.remove(&key);
+        }
+
+        // Push onto a copy, so a rejected push neither changes nor creates the list
+        let mut value = store
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| StorageValue::new_list(&[]));
+        let length = value.push_list(values, maxlen, max_elements)?;
+        value.touch(self.clock.now_millis());
+        store.insert(key, value);
+        drop(store);
+        Ok(length)
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if store.get(&key).is_some_and(|value| value.is_expired(now)) {
+            store.remove(&key);
+        }
+
+        // Append to a copy, so a rejected append neither changes nor creates the string
+        let mut value = store
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| StorageValue::string(""));
+        let length = value.append_string(suffix)?;
+        if let Some(ttl) = ttl {
+            value.ttl = expire_at(now, ttl)?;
+        }
+        value.touch(self.clock.now_millis());
+        store.insert(key, value);
+        drop(store);
+        Ok(length)
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if store.get(&key).is_some_and(|value| value.is_expired(now)) {
+            store.remove(&key);
+        }
+
+        // Set the fields on a copy, so a rejected write neither changes nor creates the hash
+        let mut value = store
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| StorageValue::new_hash(&BTreeMap::new()));
+        let counts = value.set_hash_fields(fields, max_elements)?;
+        value.touch(self.clock.now_millis());
+        store.insert(key, value);
+        drop(store);
+        Ok(counts)
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let new_key = String::from_utf8(new_key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if store.get(&key).is_none_or(|value| value.is_expired(now)) {
+            return Err(DatabaseError::ValueNotFound(key));
+        }
+
+        if store
+            .get(&new_key)
+            .is_some_and(|value| !value.is_expired(now))
+        {
+            return Ok(false);
+        }
+
+        let mut value = store.remove(&key).unwrap();
+        value.touch(self.clock.now_millis());
+        store.insert(new_key, value);
+        drop(store);
+        Ok(true)
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        value.validate()?;
+
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if store.get(&key).is_some_and(|value| !value.is_expired(now)) {
+            return Ok(false);
+        }
+
+        let mut value = value.clone();
+        value.ttl = expire_at(now, value.ttl)?;
+        value.touch(self.clock.now_millis());
+        store.insert(key, value);
+        drop(store);
+        Ok(true)
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        let mut store = self.write_store();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        let now = self.clock.now();
+
+        if !store
+            .get(&key)
+            .is_some_and(|value| !value.is_expired(now) && value.value == expected)
+        {
+            return Ok(false);
+        }
+
+        store.remove(&key);
+        drop(store);
+        Ok(true)
+    }
+
     async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
-        self.store
-            .write()
-            .unwrap()
+        self.write_store()
             .remove(&String::from_utf8(key.to_vec()).unwrap());
         Ok(())
     }
 
     async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
-        let mut store = self.store.write().unwrap();
+        let mut store = self.write_store();
 
         // Remove all keys that start with the prefix
         store.retain(|key, _| !key.starts_with(&String::from_utf8(prefix.to_vec()).unwrap()));
@@ -196,5 +913,187 @@ impl Storage for Bredis {
         Ok(())
     }
 
-    async fn close(&self) {}
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        let now = self.clock.now();
+        let mut expired = vec![];
+        self.write_store().retain(|key, value| {
+            if value.is_expired(now) {
+                expired.push(key.clone());
+                return false;
+            }
+            return true;
+        });
+        Ok(expired)
+    }
+
+    /// Flush the pending changes into the snapshot file, a no-op without a snapshot
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        match &self.snapshot {
+            Some(snapshot) => snapshot.flush(&self.store).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        Ok(())
+    }
+
+    async fn checkpoint(&self, _dest: &str) -> Result<(), DatabaseError> {
+        return self.capabilities().require_checkpoint();
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return StorageCapabilities {
+            backend: "bredis",
+            checkpoint: false,
+        };
+    }
+
+    /// Flush the pending changes into the snapshot file
+    async fn close(&self) {
+        if let Some(snapshot) = &self.snapshot {
+            if let Err(err) = snapshot.flush(&self.store).await {
+                log::error!("Error flushing snapshot: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::mock::{MockClock, TempPath};
+
+    #[tokio::test]
+    async fn test_get_no_reap_keeps_expired_record() {
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let db = Bredis::open().with_clock(clock.clone());
+        let value = StorageValue {
+            ttl: 10,
+            ..StorageValue::string("my_value")
+        };
+        db.set(b"my_key", &value).await.unwrap();
+
+        clock.advance(11);
+        assert!(db.get_no_reap(b"my_key").await.unwrap().is_none());
+        assert!(db.store.read().unwrap().contains_key("my_key"));
+
+        assert!(db.get(b"my_key").await.unwrap().is_none());
+        assert!(!db.store.read().unwrap().contains_key("my_key"));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_coalesces_writes() {
+        let path = TempPath::new("test_bredis");
+        let db = Bredis::open_with_snapshot(&path, Duration::from_millis(100)).unwrap();
+        let snapshot = db.snapshot.clone().unwrap();
+
+        for i in 0..1000 {
+            let value = StorageValue {
+                value_type: ValueType::String,
+                ttl: -1,
+                value: format!("value{i}").into_bytes(),
+                mtime: 0,
+            };
+            db.set(format!("key{i}").as_bytes(), &value).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        let flushes = snapshot.flushes.load(Ordering::Relaxed);
+        assert!((1..=2).contains(&flushes), "{flushes}");
+
+        // Writes after the last flush are persisted by close
+        db.delete(b"key0").await.unwrap();
+        db.close().await;
+        assert_eq!(snapshot.flushes.load(Ordering::Relaxed), flushes + 1);
+        drop(db);
+
+        let reopened = Bredis::open_with_snapshot(&path, SNAPSHOT_INTERVAL).unwrap();
+        assert!(reopened.get(b"key0").await.unwrap().is_none());
+        let value = reopened.get(b"key999").await.unwrap().unwrap();
+        assert_eq!(value.value, b"value999");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_writes_during_flush_are_persisted() {
+        let path = TempPath::new("test_bredis");
+        let db = Bredis::open_with_snapshot(&path, SNAPSHOT_INTERVAL).unwrap();
+        let snapshot = db.snapshot.clone().unwrap();
+
+        // The snapshot is only marked once the write is done
+        let store = db.write_store();
+        assert!(!snapshot.dirty.load(Ordering::Acquire));
+        drop(store);
+        assert!(snapshot.dirty.load(Ordering::Acquire));
+
+        let flushing = Arc::new(AtomicBool::new(true));
+        let flusher = tokio::spawn({
+            let (db, snapshot, flushing) = (db.clone(), snapshot.clone(), flushing.clone());
+            async move {
+                while flushing.load(Ordering::Acquire) {
+                    snapshot.flush(&db.store).await.unwrap();
+                }
+            }
+        });
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    for i in 0..250 {
+                        let key = format!("key{writer}_{i}");
+                        db.set(key.as_bytes(), &StorageValue::string("value"))
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+        flushing.store(false, Ordering::Release);
+        flusher.await.unwrap();
+
+        db.close().await;
+        drop(db);
+        let reopened = Bredis::open_with_snapshot(&path, SNAPSHOT_INTERVAL).unwrap();
+        assert_eq!(reopened.get_all_keys(b"key").await.unwrap().len(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_sync_flushes_snapshot() {
+        let path = TempPath::new("test_bredis");
+        let db = Bredis::open_with_snapshot(&path, Duration::from_hours(1)).unwrap();
+        db.set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+        db.sync().await.unwrap();
+
+        // The background flush is an hour away, only the sync can have written the key
+        let reopened = Bredis::open_with_snapshot(&path, SNAPSHOT_INTERVAL).unwrap();
+        assert!(reopened.get(b"key1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_exit_snapshot() {
+        let dir = TempPath::new("test_bredis");
+        let path = format!("{}/exit.snapshot", &*dir);
+        let db = Bredis::open_with_exit_snapshot(&path).unwrap();
+        db.set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+        db.list_push(b"events", &["login".to_string()], None, None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!Path::new(&path).exists(), "Written before the shutdown");
+
+        db.close().await;
+        drop(db);
+
+        let reopened = Bredis::open_with_exit_snapshot(&path).unwrap();
+        let value = reopened.get(b"key1").await.unwrap().unwrap();
+        assert_eq!(value.value, b"value1");
+        let events = reopened.get(b"events").await.unwrap().unwrap();
+        assert_eq!(events.get_list_value().unwrap(), vec!["login"]);
+    }
 }
@@ -0,0 +1,122 @@
+use crate::errors::DatabaseError;
+
+/// A source of the current time, injected into the backends so TTLs can be tested
+/// without sleeping
+pub trait Clock: Send + Sync {
+    /// The current time in seconds since the Unix epoch
+    fn now(&self) -> i64;
+
+    /// The current time in milliseconds since the Unix epoch
+    fn now_millis(&self) -> i64 {
+        return self.now().saturating_mul(1000);
+    }
+}
+
+/// The wall clock of the system
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        return chrono::Utc::now().timestamp();
+    }
+
+    fn now_millis(&self) -> i64 {
+        return chrono::Utc::now().timestamp_millis();
+    }
+}
+
+/// How a remaining TTL is rounded to whole seconds
+///
+/// Expiry times are stored in whole seconds, so a key set late in a second reports one
+/// second less than its TTL right away when the remaining time is floored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TtlRounding {
+    Floor,
+    /// Never reports less than the time the key actually has left
+    #[default]
+    Ceil,
+    Round,
+}
+
+impl TtlRounding {
+    /// The name of the policy, as passed to `--ttl-rounding`
+    pub const fn as_str(self) -> &'static str {
+        return match self {
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Round => "round",
+        };
+    }
+
+    /// The remaining TTL of a key, in whole seconds
+    ///
+    /// # Arguments
+    /// * `expire_at` - The expiry time in seconds since the Unix epoch, -1 if the key never expires
+    /// * `now_millis` - The current time in milliseconds since the Unix epoch
+    ///
+    /// # Returns
+    /// The remaining seconds, -1 for a key that never expires
+    pub const fn remaining(self, expire_at: i64, now_millis: i64) -> i64 {
+        if expire_at < 0 {
+            return -1;
+        }
+        let millis = expire_at.saturating_mul(1000).saturating_sub(now_millis);
+        if millis <= 0 {
+            return 0;
+        }
+        return match self {
+            Self::Floor => millis / 1000,
+            Self::Ceil => millis.saturating_add(999) / 1000,
+            Self::Round => millis.saturating_add(500) / 1000,
+        };
+    }
+}
+
+/// Turn a TTL into the time the key expires at
+///
+/// # Arguments
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `ttl` - The TTL in seconds, negative for a key that never expires
+///
+/// # Returns
+/// The expiry time in seconds since the Unix epoch, -1 for a key that never expires,
+/// or `DatabaseError::OutOfRange` if the expiry does not fit in an `i64`
+pub fn expire_at(now: i64, ttl: i64) -> Result<i64, DatabaseError> {
+    if ttl < 0 {
+        return Ok(-1);
+    }
+    return now
+        .checked_add(ttl)
+        .ok_or_else(|| DatabaseError::OutOfRange(format!("TTL {ttl} is too large")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expire_at() {
+        assert_eq!(expire_at(1000, 60).unwrap(), 1060);
+        assert_eq!(expire_at(1000, -1).unwrap(), -1);
+        assert_eq!(expire_at(1000, -100).unwrap(), -1);
+        assert!(matches!(
+            expire_at(1000, i64::MAX),
+            Err(DatabaseError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_ttl_rounding() {
+        // A 5s TTL set 900ms into a second expires at 1005, read 50ms later
+        let now_millis = 1_000_950;
+        assert_eq!(TtlRounding::Floor.remaining(1005, now_millis), 4);
+        assert_eq!(TtlRounding::Ceil.remaining(1005, now_millis), 5);
+        assert_eq!(TtlRounding::Round.remaining(1005, now_millis), 4);
+        assert_eq!(TtlRounding::Round.remaining(1005, 1_000_400), 5);
+
+        assert_eq!(TtlRounding::Ceil.remaining(1005, 1_000_000), 5);
+        assert_eq!(TtlRounding::Ceil.remaining(-1, now_millis), -1);
+        assert_eq!(TtlRounding::Floor.remaining(1000, now_millis), 0);
+    }
+}
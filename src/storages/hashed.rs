@@ -0,0 +1,666 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue, ValueType},
+};
+
+/// The prefix of the physical keys long keys are stored under
+const HASHED_KEY_PREFIX: &str = "__hashed__:";
+/// The prefix of the reverse index entries mapping a hash back to its original key
+const HASHED_INDEX_PREFIX: &str = "__hashed_index__:";
+
+/// A storage wrapper bounding the physical length of keys
+///
+/// Keys longer than the threshold are stored under `__hashed__:<sha256>` instead, so very
+/// long keys don't bloat the backend indexes. A reverse index entry `__hashed_index__:<sha256>`
+/// keeps the original key for enumeration.
+///
+/// The reverse index isn't ordered by the original keys, so every listing (`get_all_keys`,
+/// `range`, `delete_prefix`) scans the whole index and reads the value of each entry to skip
+/// expired keys. Its cost grows with the number of long keys, not with the size of the result.
+///
+/// Both prefixes are reserved: a key at most `threshold` bytes long that starts with one of
+/// them is rejected, since it would be taken for an entry of the hashing layer.
+///
+/// # Example
+/// ```ignore
+/// let db = HashedKeys::new(Box::new(Bredis::open()), 64);
+/// db.set(&[b'k'; 100], &value).await.unwrap();
+/// assert_eq!(db.get_all_keys(b"k").await.unwrap().len(), 1);
+/// ```
+pub struct HashedKeys {
+    inner: Box<dyn Storage>,
+    threshold: usize,
+}
+
+impl HashedKeys {
+    /// Wrap a storage so keys longer than `threshold` bytes are stored under their hash
+    ///
+    /// # Arguments
+    /// * `inner` - The storage to wrap
+    /// * `threshold` - The longest key length stored as is
+    pub fn new(inner: Box<dyn Storage>, threshold: usize) -> Self {
+        return Self { inner, threshold };
+    }
+
+    /// Hex-encoded SHA-256 of a key
+    fn hash(key: &[u8]) -> String {
+        let mut hash = String::with_capacity(64);
+        for byte in Sha256::digest(key) {
+            write!(hash, "{byte:02x}").unwrap();
+        }
+        return hash;
+    }
+
+    fn is_long(&self, key: &[u8]) -> bool {
+        return key.len() > self.threshold;
+    }
+
+    /// Whether a physical key belongs to the hashing layer rather than to a short user key
+    fn is_internal(key: &str) -> bool {
+        return key.starts_with(HASHED_KEY_PREFIX) || key.starts_with(HASHED_INDEX_PREFIX);
+    }
+
+    /// The key a user key is physically stored under
+    ///
+    /// # Errors
+    /// A short key under one of the reserved prefixes would be mistaken for an entry of the
+    /// hashing layer, so it is rejected with `DatabaseError::InvalidValueType`
+    fn physical_key(&self, key: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+        if !self.is_long(key) {
+            if Self::is_internal(&String::from_utf8_lossy(key)) {
+                return Err(DatabaseError::InvalidValueType(format!(
+                    "{} starts with a prefix reserved for hashed keys",
+                    String::from_utf8_lossy(key)
+                )));
+            }
+            return Ok(key.to_vec());
+        }
+        return Ok(format!("{HASHED_KEY_PREFIX}{}", Self::hash(key)).into_bytes());
+    }
+
+    /// The reverse index entry of a long key
+    fn index_key(key: &[u8]) -> Vec<u8> {
+        return format!("{HASHED_INDEX_PREFIX}{}", Self::hash(key)).into_bytes();
+    }
+
+    /// Record the original name of a long key in the reverse index
+    async fn index(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        if !self.is_long(key) {
+            return Ok(());
+        }
+
+        let entry = StorageValue {
+            value_type: ValueType::String,
+            ttl: -1,
+            value: key.to_vec(),
+            mtime: 0,
+        };
+        return self.inner.set(&Self::index_key(key), &entry).await;
+    }
+
+    /// Remove a long key from the reverse index
+    async fn unindex(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        if !self.is_long(key) {
+            return Ok(());
+        }
+        return self.inner.delete(&Self::index_key(key)).await;
+    }
+
+    /// Read the original names of all live long keys
+    /// Entries whose value is gone (deleted or expired) are dropped from the index
+    async fn long_keys(&self) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        let mut keys = vec![];
+        for index_key in self
+            .inner
+            .get_all_keys(HASHED_INDEX_PREFIX.as_bytes())
+            .await?
+        {
+            let Some(entry) = self.inner.get(index_key.as_bytes()).await? else {
+                continue;
+            };
+            let key = entry.value;
+            if self.inner.get(&self.physical_key(&key)?).await?.is_some() {
+                keys.push(key);
+            } else {
+                self.inner.delete(index_key.as_bytes()).await?;
+            }
+        }
+        return Ok(keys);
+    }
+
+    /// Map a physical key back to the user key it stores
+    async fn original_key(&self, physical_key: &str) -> Result<Option<String>, DatabaseError> {
+        if let Some(hash) = physical_key.strip_prefix(HASHED_KEY_PREFIX) {
+            let index_key = format!("{HASHED_INDEX_PREFIX}{hash}");
+            let entry = self.inner.get(index_key.as_bytes()).await?;
+            return Ok(entry.map(|entry| String::from_utf8_lossy(&entry.value).to_string()));
+        }
+        if physical_key.starts_with(HASHED_INDEX_PREFIX) {
+            return Ok(None);
+        }
+        return Ok(Some(physical_key.to_string()));
+    }
+}
+
+#[async_trait]
+impl Storage for HashedKeys {
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get(&self.physical_key(key)?).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get_no_reap(&self.physical_key(key)?).await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self.inner.get_record(&self.physical_key(key)?).await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        let physical = keys
+            .iter()
+            .map(|key| self.physical_key(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        return self.inner.get_many(&physical).await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        let mut keys: Vec<String> = self
+            .inner
+            .get_all_keys(prefix)
+            .await?
+            .into_iter()
+            .filter(|key| !Self::is_internal(key))
+            .collect();
+
+        for key in self.long_keys().await? {
+            if key.starts_with(prefix) {
+                keys.push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
+
+        keys.sort();
+        return Ok(keys);
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        let mut keys = vec![];
+        for physical_key in self.inner.keys_modified_since(since).await? {
+            if let Some(key) = self.original_key(&physical_key).await? {
+                keys.push(key);
+            }
+        }
+        return Ok(keys);
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        // Long keys are merged in afterwards, so the limit can't be pushed into the scan
+        let mut keys: Vec<String> = self
+            .inner
+            .range(start, end, None)
+            .await?
+            .into_iter()
+            .filter(|key| !Self::is_internal(key))
+            .collect();
+
+        for key in self.long_keys().await? {
+            if key.as_slice() >= start && key.as_slice() < end {
+                keys.push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
+
+        keys.sort();
+        keys.truncate(limit.unwrap_or(usize::MAX));
+        return Ok(keys);
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        // Each long key is stored with its reverse index entry, which is not a key of its own
+        let index_entries = self
+            .inner
+            .get_all_keys(HASHED_INDEX_PREFIX.as_bytes())
+            .await?;
+        let index_entries = u64::try_from(index_entries.len()).unwrap_or(u64::MAX);
+        return Ok(self.inner.len().await?.saturating_sub(index_entries));
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.inner.get_ttl(&self.physical_key(key)?).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.inner.get_expire_at(&self.physical_key(key)?).await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self.inner.update_ttl(&self.physical_key(key)?, ttl).await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self.inner.adjust_ttl(&self.physical_key(key)?, delta).await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get_ex(&self.physical_key(key)?, ttl).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.index(key).await?;
+        return self.inner.set(&self.physical_key(key)?, value).await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.index(key).await?;
+        return self
+            .inner
+            .set_keep_ttl(&self.physical_key(key)?, value)
+            .await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        let physical = entries
+            .iter()
+            .map(|(key, value)| Ok((self.physical_key(key)?, value.clone())))
+            .collect::<Result<Vec<_>, DatabaseError>>()?;
+        for (key, _) in entries {
+            self.index(key).await?;
+        }
+        return self.inner.set_many(&physical).await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        let result = self
+            .inner
+            .increment(&self.physical_key(key)?, value, default_value, base)
+            .await?;
+        self.index(key).await?;
+        return Ok(result);
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        let result = self
+            .inner
+            .decrement(&self.physical_key(key)?, value, default_value, base)
+            .await?;
+        self.index(key).await?;
+        return Ok(result);
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        let result = self
+            .inner
+            .increment_bounded(
+                &self.physical_key(key)?,
+                value,
+                default_value,
+                max,
+                saturate,
+                base,
+            )
+            .await?;
+        self.index(key).await?;
+        return Ok(result);
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let physical = increments
+            .iter()
+            .map(|(key, value)| Ok((self.physical_key(key)?, *value)))
+            .collect::<Result<Vec<_>, DatabaseError>>()?;
+        let result = self.inner.increment_many(&physical).await?;
+        for (key, _) in increments {
+            self.index(key).await?;
+        }
+        return Ok(result);
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let (new_value, deleted) = self
+            .inner
+            .decrement_and_delete(&self.physical_key(key)?, value)
+            .await?;
+        if deleted {
+            self.unindex(key).await?;
+        }
+        return Ok((new_value, deleted));
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let (result, changed) = self
+            .inner
+            .set_extreme(&self.physical_key(key)?, value, extreme)
+            .await?;
+        if changed {
+            self.index(key).await?;
+        }
+        return Ok((result, changed));
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .inner
+            .compare_and_increment(&self.physical_key(key)?, expected, delta)
+            .await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        let result = self
+            .inner
+            .json_merge(&self.physical_key(key)?, patch)
+            .await?;
+        self.index(key).await?;
+        return Ok(result);
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        let length = self
+            .inner
+            .list_push(&self.physical_key(key)?, values, maxlen, max_elements)
+            .await?;
+        self.index(key).await?;
+        return Ok(length);
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        let length = self
+            .inner
+            .append(&self.physical_key(key)?, suffix, ttl)
+            .await?;
+        self.index(key).await?;
+        return Ok(length);
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        let counts = self
+            .inner
+            .hset_many(&self.physical_key(key)?, fields, max_elements)
+            .await?;
+        self.index(key).await?;
+        return Ok(counts);
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        let renamed = self
+            .inner
+            .rename_nx(&self.physical_key(key)?, &self.physical_key(new_key)?)
+            .await?;
+        if renamed {
+            self.index(new_key).await?;
+            self.unindex(key).await?;
+        }
+        return Ok(renamed);
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        let set = self.inner.set_nx(&self.physical_key(key)?, value).await?;
+        if set {
+            self.index(key).await?;
+        }
+        return Ok(set);
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        let deleted = self
+            .inner
+            .delete_if_equal(&self.physical_key(key)?, expected)
+            .await?;
+        if deleted {
+            self.unindex(key).await?;
+        }
+        return Ok(deleted);
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        self.inner.delete(&self.physical_key(key)?).await?;
+        return self.unindex(key).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        for key in self.long_keys().await? {
+            if key.starts_with(prefix) {
+                self.delete(&key).await?;
+            }
+        }
+        return self.inner.delete_prefix(prefix).await;
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        let mut expired = vec![];
+        for physical_key in self.inner.delete_expired().await? {
+            let Some(key) = self.original_key(&physical_key).await? else {
+                continue;
+            };
+            self.unindex(key.as_bytes()).await?;
+            expired.push(key);
+        }
+        return Ok(expired);
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        return self.inner.sync().await;
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        return self.inner.flush_memtables().await;
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self.inner.checkpoint(dest).await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::bredis::Bredis;
+
+    fn string_value(value: &[u8]) -> StorageValue {
+        return StorageValue {
+            value_type: ValueType::String,
+            ttl: -1,
+            value: value.to_vec(),
+            mtime: 0,
+        };
+    }
+
+    #[tokio::test]
+    async fn test_long_key_roundtrip() {
+        let db = HashedKeys::new(Box::new(Bredis::open()), 16);
+        let long_key = format!("long_{}", "x".repeat(100));
+
+        db.set(long_key.as_bytes(), &string_value(b"long_value"))
+            .await
+            .unwrap();
+        db.set(b"long_short", &string_value(b"short_value"))
+            .await
+            .unwrap();
+
+        let value = db.get(long_key.as_bytes()).await.unwrap().unwrap();
+        assert_eq!(value.value, b"long_value");
+
+        let keys = db.get_all_keys(b"long_").await.unwrap();
+        assert_eq!(keys, vec!["long_short".to_string(), long_key.clone()]);
+
+        let keys = db.range(b"long_", b"long_z", None).await.unwrap();
+        assert_eq!(keys, vec!["long_short".to_string(), long_key.clone()]);
+
+        // Only the hash is stored as a data key
+        let physical_keys = db
+            .inner
+            .get_all_keys(HASHED_KEY_PREFIX.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(physical_keys.len(), 1);
+        assert!(physical_keys[0].len() < long_key.len());
+
+        db.delete(long_key.as_bytes()).await.unwrap();
+        assert!(db.get(long_key.as_bytes()).await.unwrap().is_none());
+        assert_eq!(db.get_all_keys(b"long_").await.unwrap(), vec!["long_short"]);
+        assert!(db
+            .inner
+            .get_all_keys(HASHED_INDEX_PREFIX.as_bytes())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_long_key_increment_and_rename() {
+        let db = HashedKeys::new(Box::new(Bredis::open()), 16);
+        let counter = "counter_".repeat(10);
+        let renamed = "renamed_".repeat(10);
+
+        db.increment(counter.as_bytes(), 1, Some(0), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_all_keys(b"counter_").await.unwrap(),
+            vec![counter.clone()]
+        );
+
+        assert!(db
+            .rename_nx(counter.as_bytes(), renamed.as_bytes())
+            .await
+            .unwrap());
+        assert!(db.get_all_keys(b"counter_").await.unwrap().is_empty());
+        assert_eq!(
+            db.get_all_keys(b"renamed_").await.unwrap(),
+            vec![renamed.clone()]
+        );
+
+        let value = db.get(renamed.as_bytes()).await.unwrap().unwrap();
+        assert_eq!(value.get_integer_value().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_listings_merge_long_keys_in_order() {
+        let db = HashedKeys::new(Box::new(Bredis::open()), 16);
+        let mut expected = vec![];
+        for i in 0..10 {
+            let short_key = format!("key_{i}");
+            let long_key = format!("key_{i}_{}", "x".repeat(100));
+            for key in [&short_key, &long_key] {
+                db.set(key.as_bytes(), &string_value(b"value"))
+                    .await
+                    .unwrap();
+            }
+            expected.extend([short_key, long_key]);
+        }
+
+        assert_eq!(db.get_all_keys(b"key_").await.unwrap(), expected);
+
+        // The limit keeps the first keys of the merged order, long or short
+        let keys = db.range(b"key_", b"key_z", Some(5)).await.unwrap();
+        assert_eq!(keys, expected[..5]);
+    }
+
+    #[tokio::test]
+    async fn test_reserved_prefixes_are_rejected() {
+        let db = HashedKeys::new(Box::new(Bredis::open()), 64);
+        for key in ["__hashed__:abc", "__hashed_index__:abc"] {
+            let result = db.set(key.as_bytes(), &string_value(b"value")).await;
+            assert!(
+                matches!(result, Err(DatabaseError::InvalidValueType(_))),
+                "{key}"
+            );
+            let result = db.get(key.as_bytes()).await;
+            assert!(
+                matches!(result, Err(DatabaseError::InvalidValueType(_))),
+                "{key}"
+            );
+        }
+        assert!(db.inner.get_all_keys(b"").await.unwrap().is_empty());
+
+        // A long key is stored under its hash, whatever it starts with
+        let long_key = format!("__hashed__:{}", "x".repeat(100));
+        db.set(long_key.as_bytes(), &string_value(b"value"))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_all_keys(b"__hashed__:").await.unwrap(),
+            vec![long_key]
+        );
+    }
+}
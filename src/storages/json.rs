@@ -0,0 +1,157 @@
+use serde_json::Value;
+
+use crate::errors::DatabaseError;
+
+/// A single step of a JSON path
+enum Segment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+/// Evaluate a JSON path against a document
+///
+/// Only the subset of `JSONPath` needed to address a single node is supported:
+/// the root `$`, dotted fields (`$.a.b`), quoted fields (`$['a']`) and array indices (`$.a[0]`).
+///
+/// # Arguments
+/// * `document` - The JSON document to query
+/// * `path` - The JSON path to evaluate
+///
+/// # Returns
+/// A Result containing the addressed node (None if it does not exist) or a `DatabaseError`
+/// if the path is malformed
+///
+/// # Example
+/// ```ignore
+/// let document = serde_json::json!({ "a": { "b": [1, 2] } });
+/// let value = query(&document, "$.a.b[1]").unwrap();
+/// ```
+pub fn query<'a>(document: &'a Value, path: &str) -> Result<Option<&'a Value>, DatabaseError> {
+    let Some(mut rest) = path.strip_prefix('$') else {
+        return Err(invalid_path(path));
+    };
+
+    let mut current = document;
+    while !rest.is_empty() {
+        let (segment, tail) = if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            if end == 0 {
+                return Err(invalid_path(path));
+            }
+            (Segment::Field(&tail[..end]), &tail[end..])
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let Some(end) = tail.find(']') else {
+                return Err(invalid_path(path));
+            };
+            let inner = &tail[..end];
+            let segment = match inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+                Some(name) => Segment::Field(name),
+                None => Segment::Index(inner.parse().map_err(|_| invalid_path(path))?),
+            };
+            (segment, &tail[end + 1..])
+        } else {
+            return Err(invalid_path(path));
+        };
+
+        let next = match segment {
+            Segment::Field(name) => current.get(name),
+            Segment::Index(index) => current.get(index),
+        };
+        match next {
+            Some(value) => current = value,
+            None => return Ok(None),
+        }
+        rest = tail;
+    }
+
+    return Ok(Some(current));
+}
+
+/// Apply an RFC 7386 JSON merge-patch to a document in place
+///
+/// Object members of the patch are merged recursively, `null` members remove the
+/// corresponding field and any other patch value replaces the target entirely.
+///
+/// # Arguments
+/// * `target` - The document to patch
+/// * `patch` - The merge-patch to apply
+///
+/// # Example
+/// ```ignore
+/// let mut document = serde_json::json!({ "a": 1, "b": 2 });
+/// merge_patch(&mut document, &serde_json::json!({ "b": null, "c": 3 }));
+/// ```
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+fn invalid_path(path: &str) -> DatabaseError {
+    return DatabaseError::InvalidJson(format!("invalid path `{path}`"));
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let document = json!({ "a": { "b": [1, { "c": "leaf" }] } });
+
+        assert_eq!(query(&document, "$").unwrap(), Some(&document));
+        assert_eq!(query(&document, "$.a.b[0]").unwrap(), Some(&json!(1)));
+        assert_eq!(
+            query(&document, "$.a.b[1].c").unwrap(),
+            Some(&json!("leaf"))
+        );
+        assert_eq!(
+            query(&document, "$['a']['b'][1]['c']").unwrap(),
+            Some(&json!("leaf"))
+        );
+        assert_eq!(query(&document, "$.a.missing").unwrap(), None);
+        assert_eq!(query(&document, "$.a.b[5]").unwrap(), None);
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut document = json!({ "a": "b", "c": { "d": "e", "f": "g" } });
+        merge_patch(&mut document, &json!({ "a": "z", "c": { "f": null } }));
+        assert_eq!(document, json!({ "a": "z", "c": { "d": "e" } }));
+
+        let mut document = json!([1, 2]);
+        merge_patch(&mut document, &json!({ "a": { "b": null, "c": 1 } }));
+        assert_eq!(document, json!({ "a": { "c": 1 } }));
+
+        let mut document = json!({ "a": 1 });
+        merge_patch(&mut document, &json!("replaced"));
+        assert_eq!(document, json!("replaced"));
+    }
+
+    #[test]
+    fn test_query_invalid_path() {
+        let document = json!({ "a": 1 });
+
+        assert!(query(&document, "a").is_err());
+        assert!(query(&document, "$..a").is_err());
+        assert!(query(&document, "$[x]").is_err());
+        assert!(query(&document, "$[0").is_err());
+    }
+}
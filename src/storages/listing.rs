@@ -0,0 +1,217 @@
+use std::{collections::BTreeSet, io};
+
+use serde::Serialize;
+
+/// Fold the keys under a prefix into its immediate children, like a directory listing
+///
+/// Keys with no `delimiter` after the prefix are kept as they are. The others are collapsed
+/// into their common prefix, which runs up to and including the first delimiter after the prefix.
+///
+/// # Arguments
+/// * `keys` - The keys starting with `prefix`
+/// * `prefix` - The prefix the keys were listed under
+/// * `delimiter` - The separator of the levels of the keys, e.g. `:`
+///
+/// # Returns
+/// The keys directly under the prefix, and the sorted common prefixes of the deeper ones
+///
+/// # Example
+/// ```
+/// # use bredis::storages::listing::fold_by_delimiter;
+/// let keys = vec!["a:b".to_string(), "a:c:d".to_string(), "a:c:e".to_string()];
+/// let (keys, common_prefixes) = fold_by_delimiter(keys, "a:", ":");
+/// assert_eq!(keys, vec!["a:b"]);
+/// assert_eq!(common_prefixes, vec!["a:c:"]);
+/// ```
+pub fn fold_by_delimiter(
+    keys: Vec<String>,
+    prefix: &str,
+    delimiter: &str,
+) -> (Vec<String>, Vec<String>) {
+    if delimiter.is_empty() {
+        return (keys, Vec::new());
+    }
+
+    let mut children = Vec::new();
+    let mut common_prefixes = BTreeSet::new();
+    for key in keys {
+        let rest = key.strip_prefix(prefix).unwrap_or(&key);
+        match rest.find(delimiter) {
+            Some(position) => {
+                let end = key.len() - rest.len() + position + delimiter.len();
+                common_prefixes.insert(key[..end].to_string());
+            }
+            None => children.push(key),
+        }
+    }
+    return (children, common_prefixes.into_iter().collect());
+}
+
+/// Cut one page out of a listing of keys
+///
+/// # Arguments
+/// * `keys` - Every key of the listing, in any order
+/// * `cursor` - The first key of the page, the `next_cursor` of the previous page
+/// * `limit` - The most keys of the page
+///
+/// # Returns
+/// The sorted keys of the page, and the first key of the next page if there is one
+///
+/// # Example
+/// ```
+/// # use bredis::storages::listing::paginate;
+/// let keys = vec!["c".to_string(), "a".to_string(), "b".to_string()];
+/// let (page, next_cursor) = paginate(keys, Some("b"), Some(1));
+/// assert_eq!(page, vec!["b"]);
+/// assert_eq!(next_cursor.as_deref(), Some("c"));
+/// ```
+pub fn paginate(
+    mut keys: Vec<String>,
+    cursor: Option<&str>,
+    limit: Option<usize>,
+) -> (Vec<String>, Option<String>) {
+    keys.sort_unstable();
+    if let Some(cursor) = cursor {
+        let start = keys.partition_point(|key| key.as_str() < cursor);
+        keys.drain(..start);
+    }
+
+    let next_cursor = match limit {
+        Some(limit) if keys.len() > limit => keys.split_off(limit).into_iter().next(),
+        _ => None,
+    };
+    return (keys, next_cursor);
+}
+
+/// Counts the bytes written to it, to size JSON without building it
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 = self.0.saturating_add(buf.len());
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+/// Cut a page short once its items would serialize to more than a byte budget
+///
+/// The first item is always kept, even when it alone is over the budget, so a listing
+/// resumed from the returned item always makes progress.
+///
+/// # Arguments
+/// * `items` - The items of the page, in order
+/// * `max_bytes` - The most bytes of JSON the items may take; `None` keeps every item
+///
+/// # Returns
+/// The first item that was cut off, if any
+///
+/// # Example
+/// ```
+/// # use bredis::storages::listing::fit_bytes;
+/// let mut keys = vec!["aaaa".to_string(), "bbbb".to_string(), "cccc".to_string()];
+/// let cut = fit_bytes(&mut keys, Some(14));
+/// assert_eq!(keys, vec!["aaaa", "bbbb"]);
+/// assert_eq!(cut.as_deref(), Some("cccc"));
+/// ```
+pub fn fit_bytes<T: Serialize>(items: &mut Vec<T>, max_bytes: Option<usize>) -> Option<T> {
+    let max_bytes = max_bytes?;
+    let mut counter = ByteCounter(0);
+    let mut fitting = items.len();
+    for (index, item) in items.iter().enumerate() {
+        // Every item but the first is preceded by a comma
+        if index > 0 {
+            counter.0 = counter.0.saturating_add(1);
+        }
+        if serde_json::to_writer(&mut counter, item).is_err() {
+            break;
+        }
+        if index > 0 && counter.0 > max_bytes {
+            fitting = index;
+            break;
+        }
+    }
+    return items.split_off(fitting).into_iter().next();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        return keys.iter().map(ToString::to_string).collect();
+    }
+
+    #[test]
+    fn test_fold_by_delimiter() {
+        let all = keys(&["a:b", "a:c:d", "a:c:e:f", "a:g::h", "a:"]);
+
+        let (children, common_prefixes) = fold_by_delimiter(all.clone(), "a:", ":");
+        assert_eq!(children, keys(&["a:b", "a:"]));
+        assert_eq!(common_prefixes, keys(&["a:c:", "a:g:"]));
+
+        let (children, common_prefixes) = fold_by_delimiter(keys(&["a:b", "ab", "b:c"]), "", ":");
+        assert_eq!(children, keys(&["ab"]));
+        assert_eq!(common_prefixes, keys(&["a:", "b:"]));
+
+        let (children, common_prefixes) = fold_by_delimiter(all.clone(), "a:", "");
+        assert_eq!(children, all);
+        assert!(common_prefixes.is_empty());
+    }
+
+    #[test]
+    fn test_paginate() {
+        let all = keys(&["d", "b", "a", "c", "e"]);
+
+        let (page, next_cursor) = paginate(all.clone(), None, Some(2));
+        assert_eq!(page, keys(&["a", "b"]));
+        assert_eq!(next_cursor.as_deref(), Some("c"));
+
+        let (page, next_cursor) = paginate(all.clone(), next_cursor.as_deref(), Some(2));
+        assert_eq!(page, keys(&["c", "d"]));
+        assert_eq!(next_cursor.as_deref(), Some("e"));
+
+        let (page, next_cursor) = paginate(all.clone(), next_cursor.as_deref(), Some(2));
+        assert_eq!(page, keys(&["e"]));
+        assert_eq!(next_cursor, None);
+
+        // A cursor between keys starts at the next one
+        let (page, next_cursor) = paginate(all, Some("bb"), None);
+        assert_eq!(page, keys(&["c", "d", "e"]));
+        assert_eq!(next_cursor, None);
+    }
+
+    #[test]
+    fn test_fit_bytes() {
+        // Each key takes 6 bytes as a JSON string, 7 with the comma before it
+        let all = keys(&["aaaa", "bbbb", "cccc", "dddd"]);
+
+        let mut page = all.clone();
+        assert_eq!(fit_bytes(&mut page, None), None);
+        assert_eq!(page, all);
+
+        let mut page = all.clone();
+        assert_eq!(fit_bytes(&mut page, Some(20)).as_deref(), Some("dddd"));
+        assert_eq!(page, keys(&["aaaa", "bbbb", "cccc"]));
+
+        let mut page = all.clone();
+        assert_eq!(fit_bytes(&mut page, Some(27)), None);
+        assert_eq!(page, all);
+
+        // The first item is kept even when it is over the budget
+        let mut page = all;
+        assert_eq!(fit_bytes(&mut page, Some(1)).as_deref(), Some("bbbb"));
+        assert_eq!(page, keys(&["aaaa"]));
+    }
+
+    #[test]
+    fn test_fold_by_multi_char_delimiter() {
+        let (children, common_prefixes) =
+            fold_by_delimiter(keys(&["x//y//z", "x//w", "x//y//v/u"]), "x//", "//");
+        assert_eq!(children, keys(&["x//w"]));
+        assert_eq!(common_prefixes, keys(&["x//y//"]));
+    }
+}
@@ -0,0 +1,622 @@
+//! Storage mocks used to exercise failure and latency paths in tests.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    clock::Clock,
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// A clock that only moves when a test advances it
+pub struct MockClock {
+    now_millis: AtomicI64,
+}
+
+impl MockClock {
+    /// A clock stopped at `now` seconds since the Unix epoch
+    pub const fn new(now: i64) -> Self {
+        return Self {
+            now_millis: AtomicI64::new(now.saturating_mul(1000)),
+        };
+    }
+
+    /// Move the clock forward by `seconds`
+    pub fn advance(&self, seconds: i64) {
+        self.advance_millis(seconds.saturating_mul(1000));
+    }
+
+    /// Move the clock forward by `millis`, e.g. into the middle of a second
+    pub fn advance_millis(&self, millis: i64) {
+        self.now_millis.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> i64 {
+        return self.now_millis().div_euclid(1000);
+    }
+
+    fn now_millis(&self) -> i64 {
+        return self.now_millis.load(Ordering::SeqCst);
+    }
+}
+
+/// A fresh path under the system temporary directory, removed with everything in it on drop
+pub struct TempPath(String);
+
+impl TempPath {
+    /// A path named `prefix` followed by a random number, nothing is created there yet
+    pub fn new(prefix: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{prefix}_{}", rand::random::<u32>()));
+        return Self(path.to_string_lossy().to_string());
+    }
+}
+
+impl Deref for TempPath {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        return Path::new(&self.0);
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let path = Path::new(&self.0);
+        // A database or a checkpoint is a directory, an export is a single file
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        if let Err(err) = removed {
+            log::debug!("Error removing {}: {err}", self.0);
+        }
+    }
+}
+
+/// A storage whose every operation fails with an internal error
+pub struct FailingStorage;
+
+impl FailingStorage {
+    fn fail<T>() -> Result<T, DatabaseError> {
+        return Err(DatabaseError::InternalError(
+            "Synthetic storage failure".to_string(),
+        ));
+    }
+}
+
+#[async_trait]
+impl Storage for FailingStorage {
+    async fn close(&self) {}
+
+    async fn get(&self, _key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_no_reap(&self, _key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_record(&self, _key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_many(
+        &self,
+        _keys: &[Vec<u8>],
+    ) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_all_keys(&self, _prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn keys_modified_since(&self, _since: i64) -> Result<Vec<String>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn range(
+        &self,
+        _start: &[u8],
+        _end: &[u8],
+        _limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_ttl(&self, _key: &[u8]) -> Result<i64, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_expire_at(&self, _key: &[u8]) -> Result<i64, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn update_ttl(&self, _key: &[u8], _ttl: i64) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn adjust_ttl(&self, _key: &[u8], _delta: i64) -> Result<i64, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn get_ex(
+        &self,
+        _key: &[u8],
+        _ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn set(&self, _key: &[u8], _value: &StorageValue) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn set_keep_ttl(&self, _key: &[u8], _value: &StorageValue) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn set_many(&self, _entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn increment(
+        &self,
+        _key: &[u8],
+        _value: i64,
+        _default_value: Option<i64>,
+        _base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn decrement(
+        &self,
+        _key: &[u8],
+        _value: i64,
+        _default_value: Option<i64>,
+        _base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn increment_bounded(
+        &self,
+        _key: &[u8],
+        _value: i64,
+        _default_value: Option<i64>,
+        _max: i64,
+        _saturate: bool,
+        _base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn increment_many(
+        &self,
+        _increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        _key: &[u8],
+        _value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn set_extreme(
+        &self,
+        _key: &[u8],
+        _value: i64,
+        _extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn compare_and_increment(
+        &self,
+        _key: &[u8],
+        _expected: i64,
+        _delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn json_merge(
+        &self,
+        _key: &[u8],
+        _patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn list_push(
+        &self,
+        _key: &[u8],
+        _values: &[String],
+        _maxlen: Option<usize>,
+        _max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn append(
+        &self,
+        _key: &[u8],
+        _suffix: &str,
+        _ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn hset_many(
+        &self,
+        _key: &[u8],
+        _fields: &HashMap<String, Vec<u8>>,
+        _max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn rename_nx(&self, _key: &[u8], _new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn set_nx(&self, _key: &[u8], _value: &StorageValue) -> Result<bool, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn delete_if_equal(&self, _key: &[u8], _expected: &[u8]) -> Result<bool, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn delete(&self, _key: &[u8]) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn delete_prefix(&self, _prefix: &[u8]) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    async fn checkpoint(&self, _dest: &str) -> Result<(), DatabaseError> {
+        return Self::fail();
+    }
+
+    /// Every operation is reported as supported, so each one fails with the configured error
+    fn capabilities(&self) -> StorageCapabilities {
+        return StorageCapabilities {
+            backend: "failing",
+            checkpoint: true,
+        };
+    }
+}
+
+/// A storage that delays every operation before handing it to the wrapped one
+///
+/// It also counts how many times it is closed, see `closes`.
+pub struct SlowStorage {
+    inner: Box<dyn Storage>,
+    delay: Duration,
+    closes: Arc<AtomicUsize>,
+}
+
+impl SlowStorage {
+    pub fn new(inner: Box<dyn Storage>, delay: Duration) -> Self {
+        return Self {
+            inner,
+            delay,
+            closes: Arc::new(AtomicUsize::new(0)),
+        };
+    }
+
+    /// The number of times the storage was closed, still readable after it is dropped
+    pub fn closes(&self) -> Arc<AtomicUsize> {
+        return self.closes.clone();
+    }
+
+    async fn stall(&self) {
+        tokio::time::sleep(self.delay).await;
+    }
+}
+
+#[async_trait]
+impl Storage for SlowStorage {
+    async fn close(&self) {
+        self.closes.fetch_add(1, Ordering::SeqCst);
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        self.stall().await;
+        return self.inner.get(key).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_no_reap(key).await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_record(key).await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_many(keys).await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_all_keys(prefix).await;
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        self.stall().await;
+        return self.inner.keys_modified_since(since).await;
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        self.stall().await;
+        return self.inner.range(start, end, limit).await;
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        self.stall().await;
+        return self.inner.len().await;
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_ttl(key).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_expire_at(key).await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.update_ttl(key, ttl).await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        self.stall().await;
+        return self.inner.adjust_ttl(key, delta).await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.stall().await;
+        return self.inner.get_ex(key, ttl).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.set(key, value).await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.set_keep_ttl(key, value).await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.set_many(entries).await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        self.stall().await;
+        return self.inner.increment(key, value, default_value, base).await;
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        self.stall().await;
+        return self.inner.decrement(key, value, default_value, base).await;
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        self.stall().await;
+        return self
+            .inner
+            .increment_bounded(key, value, default_value, max, saturate, base)
+            .await;
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        self.stall().await;
+        return self.inner.increment_many(increments).await;
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.stall().await;
+        return self.inner.decrement_and_delete(key, value).await;
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.stall().await;
+        return self.inner.set_extreme(key, value, extreme).await;
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        self.stall().await;
+        return self.inner.compare_and_increment(key, expected, delta).await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        self.stall().await;
+        return self.inner.json_merge(key, patch).await;
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        self.stall().await;
+        return self
+            .inner
+            .list_push(key, values, maxlen, max_elements)
+            .await;
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        self.stall().await;
+        return self.inner.append(key, suffix, ttl).await;
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        self.stall().await;
+        return self.inner.hset_many(key, fields, max_elements).await;
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        self.stall().await;
+        return self.inner.rename_nx(key, new_key).await;
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        self.stall().await;
+        return self.inner.set_nx(key, value).await;
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        self.stall().await;
+        return self.inner.delete_if_equal(key, expected).await;
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.delete(key).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.delete_prefix(prefix).await;
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        self.stall().await;
+        return self.inner.delete_expired().await;
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.sync().await;
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.flush_memtables().await;
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        self.stall().await;
+        return self.inner.checkpoint(dest).await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
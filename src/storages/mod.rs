@@ -1,7 +1,23 @@
+pub mod backend;
+pub mod breaker;
 pub mod bredis;
+pub mod clock;
+pub mod hashed;
+pub mod json;
+pub mod listing;
+/// Storages that fail or stall on purpose, for the tests of the server
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod mock;
+pub mod monitored;
+pub mod multi;
 pub mod rocksdb;
+pub mod slow_log;
 pub mod storage;
+pub mod strict_types;
 pub mod surrealkv;
+pub mod tee;
+pub mod tenant;
 pub mod value;
 
 #[cfg(test)]
@@ -0,0 +1,402 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// The width of a single bucket of the rolling error rate
+const BUCKET_WIDTH: Duration = Duration::from_secs(1);
+
+/// A rolling error rate over a time window
+///
+/// Operations are counted in one-second buckets, so memory use is bounded by the window
+/// length rather than by the request rate.
+///
+/// # Example
+/// ```ignore
+/// let error_rate = ErrorRate::new(Duration::from_secs(30), 0.5);
+/// error_rate.record(true);
+/// assert!(!error_rate.is_healthy());
+/// ```
+pub struct ErrorRate {
+    window: Duration,
+    threshold: f64,
+    buckets: Mutex<VecDeque<Bucket>>,
+}
+
+struct Bucket {
+    started_at: Instant,
+    total: u32,
+    failed: u32,
+}
+
+impl ErrorRate {
+    /// Create a new error rate tracker
+    ///
+    /// # Arguments
+    /// * `window` - How far back operations are taken into account
+    /// * `threshold` - The error rate (0.0 - 1.0) above which the backend is unhealthy
+    pub fn new(window: Duration, threshold: f64) -> Self {
+        return Self {
+            window,
+            threshold,
+            buckets: Mutex::new(VecDeque::new()),
+        };
+    }
+
+    /// Record the outcome of an operation
+    ///
+    /// # Arguments
+    /// * `failed` - Whether the operation failed
+    pub fn record(&self, failed: bool) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        self.evict(&mut buckets, now);
+
+        let is_current = buckets
+            .back()
+            .is_some_and(|bucket| now.duration_since(bucket.started_at) < BUCKET_WIDTH);
+        if !is_current {
+            buckets.push_back(Bucket {
+                started_at: now,
+                total: 0,
+                failed: 0,
+            });
+        }
+
+        let bucket = buckets.back_mut().unwrap();
+        bucket.total = bucket.total.saturating_add(1);
+        if failed {
+            bucket.failed = bucket.failed.saturating_add(1);
+        }
+    }
+
+    /// Get the error rate over the window
+    ///
+    /// # Returns
+    /// The share of failed operations (0.0 - 1.0), or 0.0 if there were no operations
+    pub fn rate(&self) -> f64 {
+        let mut buckets = self.buckets.lock().unwrap();
+        self.evict(&mut buckets, Instant::now());
+
+        let (total, failed) = buckets
+            .iter()
+            .fold((0u32, 0u32), |(total, failed), bucket| {
+                (
+                    total.saturating_add(bucket.total),
+                    failed.saturating_add(bucket.failed),
+                )
+            });
+        drop(buckets);
+
+        if total == 0 {
+            return 0.0;
+        }
+        return f64::from(failed) / f64::from(total);
+    }
+
+    /// Check whether the error rate is within the configured threshold
+    pub fn is_healthy(&self) -> bool {
+        return self.rate() <= self.threshold;
+    }
+
+    fn evict(&self, buckets: &mut VecDeque<Bucket>, now: Instant) {
+        while buckets
+            .front()
+            .is_some_and(|bucket| now.duration_since(bucket.started_at) >= self.window)
+        {
+            buckets.pop_front();
+        }
+    }
+}
+
+/// A storage wrapper that feeds the outcome of every operation into an `ErrorRate`
+///
+/// Only backend failures count as errors; client errors such as a missing key or
+/// an invalid value type are recorded as successful operations.
+pub struct Monitored {
+    inner: Box<dyn Storage>,
+    error_rate: Arc<ErrorRate>,
+}
+
+impl Monitored {
+    pub fn new(inner: Box<dyn Storage>, error_rate: Arc<ErrorRate>) -> Self {
+        return Self { inner, error_rate };
+    }
+
+    fn track<T>(&self, result: Result<T, DatabaseError>) -> Result<T, DatabaseError> {
+        let failed = matches!(
+            result,
+            Err(DatabaseError::InternalError(_) | DatabaseError::InitialFailed(_))
+        );
+        self.error_rate.record(failed);
+        return result;
+    }
+}
+
+#[async_trait]
+impl Storage for Monitored {
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.track(self.inner.get(key).await);
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.track(self.inner.get_no_reap(key).await);
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self.track(self.inner.get_record(key).await);
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        return self.track(self.inner.get_many(keys).await);
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        return self.track(self.inner.get_all_keys(prefix).await);
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        return self.track(self.inner.keys_modified_since(since).await);
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        return self.track(self.inner.range(start, end, limit).await);
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return self.track(self.inner.len().await);
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.track(self.inner.get_ttl(key).await);
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.track(self.inner.get_expire_at(key).await);
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self.track(self.inner.update_ttl(key, ttl).await);
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self.track(self.inner.adjust_ttl(key, delta).await);
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.track(self.inner.get_ex(key, ttl).await);
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self.track(self.inner.set(key, value).await);
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self.track(self.inner.set_keep_ttl(key, value).await);
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        return self.track(self.inner.set_many(entries).await);
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.track(self.inner.increment(key, value, default_value, base).await);
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.track(self.inner.decrement(key, value, default_value, base).await);
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        return self.track(
+            self.inner
+                .increment_bounded(key, value, default_value, max, saturate, base)
+                .await,
+        );
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        return self.track(self.inner.increment_many(increments).await);
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.track(self.inner.decrement_and_delete(key, value).await);
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.track(self.inner.set_extreme(key, value, extreme).await);
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.track(self.inner.compare_and_increment(key, expected, delta).await);
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.track(self.inner.json_merge(key, patch).await);
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        return self.track(
+            self.inner
+                .list_push(key, values, maxlen, max_elements)
+                .await,
+        );
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return self.track(self.inner.append(key, suffix, ttl).await);
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return self.track(self.inner.hset_many(key, fields, max_elements).await);
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return self.track(self.inner.rename_nx(key, new_key).await);
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        return self.track(self.inner.set_nx(key, value).await);
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        return self.track(self.inner.delete_if_equal(key, expected).await);
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        return self.track(self.inner.delete(key).await);
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        return self.track(self.inner.delete_prefix(prefix).await);
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        return self.track(self.inner.delete_expired().await);
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        return self.track(self.inner.sync().await);
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        return self.track(self.inner.flush_memtables().await);
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self.track(self.inner.checkpoint(dest).await);
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_rate() {
+        let error_rate = ErrorRate::new(Duration::from_mins(1), 0.5);
+        assert!(error_rate.is_healthy());
+
+        error_rate.record(false);
+        error_rate.record(true);
+        assert!((error_rate.rate() - 0.5).abs() < f64::EPSILON);
+        assert!(error_rate.is_healthy());
+
+        error_rate.record(true);
+        assert!(!error_rate.is_healthy());
+    }
+
+    #[test]
+    fn test_error_rate_recovers() {
+        let error_rate = ErrorRate::new(Duration::from_secs(1), 0.5);
+        error_rate.record(true);
+        assert!(!error_rate.is_healthy());
+
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(error_rate.is_healthy());
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+tokio::task_local! {
+    /// The name of the backend the operations of the current task go to
+    static SELECTED: String;
+}
+
+/// Named storages, one of which serves each operation
+///
+/// The operations of a future run with `MultiBackend::select` go to the backend it names;
+/// every other operation, e.g. of a background task, goes to the default backend. The HTTP
+/// server selects the backend of a request with the `X-Bredis-Backend` header.
+///
+/// `close`, `sync` and `delete_expired` apply to every backend.
+///
+/// # Example
+/// ```ignore
+/// let db = MultiBackend::new("durable", Box::new(Rocksdb::open("/var/lib/bredis")?))
+///     .with_backend("cache", Box::new(Bredis::open()));
+/// MultiBackend::select("cache".to_string(), db.set(b"key", &value)).await?;
+/// ```
+pub struct MultiBackend {
+    backends: BTreeMap<String, Box<dyn Storage>>,
+    default: String,
+}
+
+impl MultiBackend {
+    /// Create a multi-backend with a single, default backend
+    ///
+    /// # Arguments
+    /// * `name` - The name of the default backend
+    /// * `db` - The default backend
+    pub fn new(name: impl Into<String>, db: Box<dyn Storage>) -> Self {
+        let name = name.into();
+        return Self {
+            backends: BTreeMap::from([(name.clone(), db)]),
+            default: name,
+        };
+    }
+
+    /// Add a backend that requests can select by name
+    ///
+    /// # Arguments
+    /// * `name` - The name of the backend
+    /// * `db` - The backend
+    #[must_use]
+    pub fn with_backend(mut self, name: impl Into<String>, db: Box<dyn Storage>) -> Self {
+        self.backends.insert(name.into(), db);
+        return self;
+    }
+
+    /// The names of the backends, in order
+    pub fn names(&self) -> Vec<String> {
+        return self.backends.keys().cloned().collect();
+    }
+
+    /// Run a future with its storage operations going to the backend named `name`
+    ///
+    /// An unknown name selects the default backend, so callers should validate it first.
+    pub async fn select<F: Future>(name: String, future: F) -> F::Output {
+        return SELECTED.scope(name, future).await;
+    }
+
+    /// The backend selected by the current task, the default one without a selection
+    fn current(&self) -> &dyn Storage {
+        let selected = SELECTED
+            .try_with(|name| self.backends.get(name))
+            .ok()
+            .flatten();
+        return match selected {
+            Some(db) => db.as_ref(),
+            None => self.backends[&self.default].as_ref(),
+        };
+    }
+}
+
+#[async_trait]
+impl Storage for MultiBackend {
+    async fn close(&self) {
+        for db in self.backends.values() {
+            db.close().await;
+        }
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.current().get(key).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.current().get_no_reap(key).await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self.current().get_record(key).await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        return self.current().get_many(keys).await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        return self.current().get_all_keys(prefix).await;
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        return self.current().keys_modified_since(since).await;
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        return self.current().range(start, end, limit).await;
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return self.current().len().await;
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.current().get_ttl(key).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.current().get_expire_at(key).await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self.current().update_ttl(key, ttl).await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self.current().adjust_ttl(key, delta).await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.current().get_ex(key, ttl).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self.current().set(key, value).await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self.current().set_keep_ttl(key, value).await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        return self.current().set_many(entries).await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .current()
+            .increment(key, value, default_value, base)
+            .await;
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .current()
+            .decrement(key, value, default_value, base)
+            .await;
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        return self
+            .current()
+            .increment_bounded(key, value, default_value, max, saturate, base)
+            .await;
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        return self.current().increment_many(increments).await;
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.current().decrement_and_delete(key, value).await;
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.current().set_extreme(key, value, extreme).await;
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .current()
+            .compare_and_increment(key, expected, delta)
+            .await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.current().json_merge(key, patch).await;
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .current()
+            .list_push(key, values, maxlen, max_elements)
+            .await;
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return self.current().append(key, suffix, ttl).await;
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return self.current().hset_many(key, fields, max_elements).await;
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return self.current().rename_nx(key, new_key).await;
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        return self.current().set_nx(key, value).await;
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        return self.current().delete_if_equal(key, expected).await;
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        return self.current().delete(key).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        return self.current().delete_prefix(prefix).await;
+    }
+
+    /// Every backend is swept, so expired keys are deleted whatever backend they were set in
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        let mut expired = Vec::new();
+        for db in self.backends.values() {
+            expired.extend(db.delete_expired().await?);
+        }
+        return Ok(expired);
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        for db in self.backends.values() {
+            db.sync().await?;
+        }
+        return Ok(());
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        for db in self.backends.values() {
+            db.flush_memtables().await?;
+        }
+        return Ok(());
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self.current().checkpoint(dest).await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.current().capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::bredis::Bredis;
+
+    #[tokio::test]
+    async fn test_select() {
+        let db = MultiBackend::new("durable", Box::new(Bredis::open()))
+            .with_backend("cache", Box::new(Bredis::open()));
+        assert_eq!(db.names(), vec!["cache", "durable"]);
+
+        let value = StorageValue::string("value1");
+        MultiBackend::select("cache".to_string(), db.set(b"key1", &value))
+            .await
+            .unwrap();
+        db.set(b"key2", &value).await.unwrap();
+
+        let cached = MultiBackend::select("cache".to_string(), db.get(b"key1")).await;
+        assert!(cached.unwrap().is_some());
+        assert!(db.get(b"key1").await.unwrap().is_none());
+        let durable = MultiBackend::select("durable".to_string(), db.get(b"key2")).await;
+        assert!(durable.unwrap().is_some());
+    }
+}
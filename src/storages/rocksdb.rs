@@ -1,13 +1,21 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use rocksdb::{OptimisticTransactionDB, Options, Transaction, DB, DEFAULT_COLUMN_FAMILY_NAME};
+use rand::random;
+use rocksdb::{
+    checkpoint::Checkpoint, BoundColumnFamily, Direction, IteratorMode, OptimisticTransactionDB,
+    OptimisticTransactionOptions, Options, ReadOptions, Transaction, WriteOptions, DB,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
 
 use crate::errors::DatabaseError;
-use crate::storages::storage::Storage;
+use crate::storages::storage::{Storage, StorageCapabilities};
 
-use super::value::{StorageValue, ValueType};
+use super::clock::{expire_at, Clock, SystemClock};
+use super::value::{format_integer, Extreme, StorageValue, ValueType};
 
 /// The byte value to search for the end of a prefix
 const PREFIX_SEARCH_ENDING: u8 = 0xFF;
@@ -18,7 +26,7 @@ const PREFIX_SEARCH_ENDING: u8 = 0xFF;
 /// In the future, this struct can be extended to support multiple storage backends.
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// let db = Database::open("/dev/shm/my_storage").unwrap();
 /// db.set(b"my_key", b"my_value");
 /// let value = db.get(b"my_key").unwrap();
@@ -35,9 +43,41 @@ const PREFIX_SEARCH_ENDING: u8 = 0xFF;
 /// # Fields
 /// * `path` - The path to the database
 /// * `store` - The `RocksDB` instance
+/// * `sync_writes` - Whether every write is fsync'd before it is acknowledged
+/// * `verify_checksums` - Whether reads verify the checksums of the blocks they read
+/// * `closed` - Whether the database was closed, shared by all clones
+/// * `ephemeral` - The directory removed with the last handle, for a database opened by
+///   `open_ephemeral`
 pub struct Rocksdb {
     path: String,
     store: Arc<OptimisticTransactionDB>,
+    sync_writes: bool,
+    verify_checksums: bool,
+    clock: Arc<dyn Clock>,
+    closed: Arc<AtomicBool>,
+    // Declared after `store`, so the database is closed before its directory is removed
+    ephemeral: Option<Arc<EphemeralDir>>,
+}
+
+/// The directory of an ephemeral database, removed once no handle holds it anymore
+struct EphemeralDir(String);
+
+impl Drop for EphemeralDir {
+    fn drop(&mut self) {
+        if let Err(err) = DB::destroy(&Options::default(), &self.0) {
+            log::error!("Error removing the ephemeral database {}: {err}", self.0);
+        }
+    }
+}
+
+/// The outcome of an integrity check of a `RocksDB` store
+///
+/// # Fields
+/// * `checked` - The number of records checked
+/// * `corrupted` - The keys whose values failed to decode
+pub struct FsckReport {
+    pub checked: usize,
+    pub corrupted: Vec<String>,
 }
 
 impl Clone for Rocksdb {
@@ -45,18 +85,34 @@ impl Clone for Rocksdb {
         return Self {
             path: self.path.clone(),
             store: self.store.clone(),
+            sync_writes: self.sync_writes,
+            verify_checksums: self.verify_checksums,
+            clock: self.clock.clone(),
+            closed: self.closed.clone(),
+            ephemeral: self.ephemeral.clone(),
         };
     }
 }
 
+/// A best-effort fallback for a database dropped without `close`, e.g. on a panic
+///
+/// Only the last handle syncs the WAL. Nothing is removed, see `destroy`, except the directory
+/// of an ephemeral database.
 impl Drop for Rocksdb {
     fn drop(&mut self) {
-        futures::executor::block_on(self.close());
+        if Arc::strong_count(&self.store) > 1 || self.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Err(err) = self.store.flush_wal(true) {
+            log::error!("Error syncing the WAL of a database dropped without close: {err}");
+        }
     }
 }
 
 impl Rocksdb {
-    /// Open a new `RocksDB` database at the specified path
+    /// Open the `RocksDB` database at the specified path, creating it if it doesn't exist
+    ///
+    /// The data of an existing database is kept, see `open_fresh` to start from an empty one.
     ///
     /// # Arguments
     /// * `path` - The path to the database
@@ -65,7 +121,7 @@ impl Rocksdb {
     /// A Result containing the Database instance or a `RocksDB` error
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// ```
     pub fn open(path: &str) -> Result<Self, DatabaseError> {
@@ -78,27 +134,225 @@ impl Rocksdb {
         return Ok(Self {
             path: path.to_string(),
             store: Arc::new(store),
+            sync_writes: false,
+            verify_checksums: true,
+            clock: Arc::new(SystemClock),
+            closed: Arc::new(AtomicBool::new(false)),
+            ephemeral: None,
+        });
+    }
+
+    /// Open an empty `RocksDB` database at the specified path, removing any data kept there
+    ///
+    /// # Arguments
+    /// * `path` - The path to the database
+    ///
+    /// # Returns
+    /// A Result containing the Database instance or a `RocksDB` error
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open_fresh("/dev/shm/my_storage").unwrap();
+    /// ```
+    pub fn open_fresh(path: &str) -> Result<Self, DatabaseError> {
+        fs::remove_dir_all(path).unwrap_or_default();
+        return Self::open(path);
+    }
+
+    /// Open an empty `RocksDB` database in a fresh directory under the system temporary directory
+    ///
+    /// Nothing is meant to outlive the process: the directory is removed once the last handle
+    /// of the database is dropped, closed or not.
+    ///
+    /// # Returns
+    /// A Result containing the Database instance or a `RocksDB` error
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open_ephemeral().unwrap();
+    /// ```
+    pub fn open_ephemeral() -> Result<Self, DatabaseError> {
+        let path = std::env::temp_dir()
+            .join(format!("bredis_{}", random::<u32>()))
+            .to_string_lossy()
+            .to_string();
+        let mut db = Self::open_fresh(&path)?;
+        db.ephemeral = Some(Arc::new(EphemeralDir(path)));
+        return Ok(db);
+    }
+
+    /// The directory the database is kept in
+    #[must_use]
+    pub fn path(&self) -> &str {
+        return &self.path;
+    }
+
+    /// Remove the database and all of its data from disk
+    ///
+    /// Dropping or closing a `Rocksdb` only releases its handle, the data is only ever removed
+    /// here, or with the last handle of an ephemeral database. This handle is released first, so
+    /// the call fails while a clone keeps the database open.
+    ///
+    /// # Returns
+    /// A Result containing `()` or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.destroy().unwrap();
+    /// ```
+    pub fn destroy(self) -> Result<(), DatabaseError> {
+        let path = self.path.clone();
+        drop(self);
+        DB::destroy(&Options::default(), &path)?;
+        return Ok(());
+    }
+
+    /// Fsync every write before acknowledging it
+    ///
+    /// Without it, acknowledged writes still in the OS page cache are lost if the machine
+    /// crashes. Syncing each write costs a disk flush, which cuts write throughput by
+    /// an order of magnitude on most disks.
+    ///
+    /// # Arguments
+    /// * `sync_writes` - Whether to fsync every write
+    #[must_use]
+    pub fn with_sync_writes(mut self, sync_writes: bool) -> Self {
+        self.sync_writes = sync_writes;
+        return self;
+    }
+
+    /// Verify the checksums of the blocks every read loads from disk
+    ///
+    /// Verification is the `RocksDB` default and catches corrupted files on the read that hits
+    /// them. Turning it off saves some CPU on read-heavy workloads.
+    ///
+    /// # Arguments
+    /// * `verify_checksums` - Whether reads verify checksums
+    #[must_use]
+    pub fn with_verify_checksums(mut self, verify_checksums: bool) -> Self {
+        self.verify_checksums = verify_checksums;
+        return self;
+    }
+
+    /// Read the current time from `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        return self;
+    }
+
+    /// The write options of every write, following the sync policy
+    fn write_options(&self) -> WriteOptions {
+        let mut write_options = WriteOptions::default();
+        write_options.set_sync(self.sync_writes);
+        return write_options;
+    }
+
+    /// The read options of every read, following the checksum policy
+    fn read_options(&self) -> ReadOptions {
+        let mut read_options = ReadOptions::default();
+        read_options.set_verify_checksums(self.verify_checksums);
+        return read_options;
+    }
+
+    /// Begin a transaction whose commit follows the sync policy
+    fn transaction(&self) -> Transaction<'_, OptimisticTransactionDB> {
+        return self.store.transaction_opt(
+            &self.write_options(),
+            &OptimisticTransactionOptions::default(),
+        );
+    }
+
+    /// Look up a column family of the store
+    ///
+    /// # Arguments
+    /// * `name` - The name of the column family
+    ///
+    /// # Returns
+    /// The column family, or a `DatabaseError::InternalError` naming it if the store has none
+    fn column_family(&self, name: &str) -> Result<Arc<BoundColumnFamily<'_>>, DatabaseError> {
+        return self.store.cf_handle(name).ok_or_else(|| {
+            DatabaseError::InternalError(format!("The column family {name} doesn't exist"))
         });
     }
 
+    /// Delete the keys starting with a prefix from a column family
+    ///
+    /// # Arguments
+    /// * `cf_name` - The name of the column family
+    /// * `prefix` - The prefix of the keys to delete
+    fn delete_prefix_in(&self, cf_name: &str, prefix: &[u8]) -> Result<(), DatabaseError> {
+        let mut end_prefix = prefix.to_vec();
+        end_prefix.push(PREFIX_SEARCH_ENDING);
+        let cf = self.column_family(cf_name)?;
+
+        let del_result = self.store.delete_range_cf_opt(
+            &cf,
+            prefix,
+            end_prefix.as_slice(),
+            &self.write_options(),
+        );
+
+        match del_result {
+            Ok(()) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    /// Check that every record of a `RocksDB` store decodes to a `StorageValue`
+    /// The store is opened read-only and left untouched
+    ///
+    /// # Arguments
+    /// * `path` - The path to the database
+    ///
+    /// # Returns
+    /// A Result containing the check report or a `DatabaseError` if the store can't be read
+    ///
+    /// # Example
+    /// ```ignore
+    /// let report = Rocksdb::fsck("/var/lib/bredis").unwrap();
+    /// println!("{} corrupted records", report.corrupted.len());
+    /// ```
+    pub fn fsck(path: &str) -> Result<FsckReport, DatabaseError> {
+        let store = DB::open_for_read_only(&Options::default(), path, false)?;
+
+        let mut report = FsckReport {
+            checked: 0,
+            corrupted: Vec::new(),
+        };
+        for record in store.iterator(IteratorMode::Start) {
+            let (key, value) = record?;
+            report.checked += 1;
+            if StorageValue::try_from_binary(&value).is_err() {
+                report
+                    .corrupted
+                    .push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
+        return Ok(report);
+    }
+
     /// Delete a key-value pair from the database if the TTL has expired
     /// # Arguments
-    /// * `txn` - The transaction to use
+    /// * `txn` - The transaction to use, the caller commits it
     /// * `key` - The key to delete
+    /// * `value` - The value of the key, with its remaining TTL
     /// # Returns
     /// A Result containing a boolean indicating if the key was deleted or a `RocksDB` error
     fn delete_on_ttl(
         txn: &Transaction<OptimisticTransactionDB>,
-        key: &StorageValue,
+        key: &[u8],
+        value: &StorageValue,
     ) -> Result<bool, DatabaseError> {
-        if key.ttl <= 0 {
-            txn.delete(key.value.as_slice())?;
+        if value.ttl <= 0 {
+            txn.delete(key)?;
             return Ok(true);
         }
         return Ok(false);
     }
 
-    /// Prepare the storage location by removing the directory and creating a new one
+    /// Prepare the storage location by creating the directory if it doesn't exist
     ///
     /// # Arguments
     /// * `path` - The path to the storage location
@@ -107,13 +361,11 @@ impl Rocksdb {
     /// A Result containing `()` or a `DatabaseError`
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let result = Database::prepare_store_location("/dev/shm/my_storage");
     /// result.unwrap();
     /// ```
     fn prepare_store_location(path: &str) -> Result<(), DatabaseError> {
-        fs::remove_dir_all(path).unwrap_or_default();
-
         match fs::create_dir_all(path) {
             Ok(()) => return Ok(()),
             Err(err) => return Err(DatabaseError::InitialFailed(err.to_string())),
@@ -122,9 +374,18 @@ impl Rocksdb {
 }
 #[async_trait]
 impl Storage for Rocksdb {
-    /// Close the database and remove the storage directory
+    /// Close the database, syncing the WAL so no acknowledged write is lost
+    ///
+    /// The data is kept, see `destroy` to remove it. An ephemeral database is removed once its
+    /// last handle is dropped.
     async fn close(&self) {
-        DB::destroy(&Options::default(), &self.path).unwrap_or_default();
+        // Every clone shares the database, so it is only synced by the first close
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Err(err) = self.sync().await {
+            log::error!("Error syncing the WAL on close: {err}");
+        }
     }
 
     /// Get the value for a key from the database
@@ -136,7 +397,7 @@ impl Storage for Rocksdb {
     /// An Option containing the value or None if the key is not found
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// let value = db.get(b"my_key").uimpl fmt::Display for StorageValue {
     ///       println!("Value: {}", String::from_utf8(value).unwrap());
@@ -146,16 +407,17 @@ impl Storage for Rocksdb {
     /// }
     /// ```
     async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
-        let txn = self.store.transaction();
-        let raw_value = txn.get(key);
+        let txn = self.transaction();
+        let raw_value = txn.get_opt(key, &self.read_options());
         match raw_value {
             Ok(value) => match value {
                 Some(value) => {
                     let mut storage_value = StorageValue::from_binary(value.as_slice());
                     if storage_value.ttl > -1 {
-                        let now = chrono::Utc::now().timestamp();
+                        let now = self.clock.now();
                         storage_value.ttl -= now;
-                        if Self::delete_on_ttl(&txn, &storage_value)? {
+                        if Self::delete_on_ttl(&txn, key, &storage_value)? {
+                            txn.commit()?;
                             return Ok(None);
                         }
                     }
@@ -168,6 +430,79 @@ impl Storage for Rocksdb {
         }
     }
 
+    /// Get the value for a key without deleting it if it has expired
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the value for
+    ///
+    /// # Returns
+    /// An Option containing the value or None if the key is not found or has expired
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        let Some(value) = self.store.get_opt(key, &self.read_options())? else {
+            return Ok(None);
+        };
+
+        let mut storage_value = StorageValue::from_binary(value.as_slice());
+        if storage_value.ttl > -1 {
+            storage_value.ttl -= self.clock.now();
+            if storage_value.ttl <= 0 {
+                return Ok(None);
+            }
+        }
+        return Ok(Some(storage_value));
+    }
+
+    /// Get the stored record of a key as it is written on disk
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the record for
+    ///
+    /// # Returns
+    /// A Result containing the record, or None if the key is missing or expired
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        let Some(record) = self.store.get_opt(key, &self.read_options())? else {
+            return Ok(None);
+        };
+        if StorageValue::from_binary(&record).is_expired(self.clock.now()) {
+            return Ok(None);
+        }
+        return Ok(Some(record));
+    }
+
+    /// Get the values of several keys from a single snapshot
+    ///
+    /// Every key is read from the same snapshot of the store, so a transaction committed while
+    /// the batch is read is seen for all of its keys or for none of them. Expired values are
+    /// reported as missing and left in place.
+    ///
+    /// # Arguments
+    /// * `keys` - The keys to get the values for
+    ///
+    /// # Returns
+    /// A Result containing the value of every key, in the order of `keys`, or a `DatabaseError`
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        // One snapshot for the whole batch, instead of one transaction per key
+        let snapshot = self.store.snapshot();
+        let mut read_options = self.read_options();
+        read_options.set_snapshot(&snapshot);
+        let now = self.clock.now();
+
+        let mut values = Vec::with_capacity(keys.len());
+        for raw_value in self.store.multi_get_opt(keys, &read_options) {
+            let value = raw_value?
+                .map(|raw_value| StorageValue::from_binary(&raw_value))
+                .filter(|value| !value.is_expired(now))
+                .map(|mut value| {
+                    if value.ttl > -1 {
+                        value.ttl -= now;
+                    }
+                    value
+                });
+            values.push(value);
+        }
+        return Ok(values);
+    }
+
     /// Get all keys in the database
     ///
     /// # Arguments
@@ -177,8 +512,14 @@ impl Storage for Rocksdb {
     /// A Result containing a vector of keys or a `RocksDB` error
     async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
         let mut keys = Vec::new();
-        let txn = self.store.transaction();
-        let iter = txn.prefix_iterator(prefix);
+        let mut reaped = false;
+        let txn = self.transaction();
+        // `prefix_iterator` is only exact with a prefix extractor, which the store doesn't set up,
+        // so seek to the prefix and walk the keys in order instead
+        let iter = txn.iterator_opt(
+            IteratorMode::From(prefix, Direction::Forward),
+            self.read_options(),
+        );
         for result in iter {
             match result {
                 Ok((key, raw_value)) => {
@@ -190,8 +531,9 @@ impl Storage for Rocksdb {
 
                     let mut storage_value = StorageValue::from_binary(&raw_value);
                     if storage_value.ttl > -1 {
-                        storage_value.ttl -= chrono::Utc::now().timestamp();
-                        if Self::delete_on_ttl(&txn, &storage_value)? {
+                        storage_value.ttl -= self.clock.now();
+                        if Self::delete_on_ttl(&txn, &key, &storage_value)? {
+                            reaped = true;
                             continue;
                         }
                     }
@@ -202,9 +544,82 @@ impl Storage for Rocksdb {
                 Err(err) => return Err(err.into()),
             }
         }
+        if reaped {
+            txn.commit()?;
+        }
+        return Ok(keys);
+    }
+
+    /// Get all keys written after the specified time
+    /// Expired keys are skipped but left for the regular TTL cleanup
+    ///
+    /// # Arguments
+    /// * `since` - The time in milliseconds since the Unix epoch
+    ///
+    /// # Returns
+    /// A Result containing a vector of keys or a `DatabaseError`
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        let now = self.clock.now();
+        let mut keys = Vec::new();
+        for result in self.store.iterator(IteratorMode::Start) {
+            let (key, raw_value) = result?;
+            let storage_value = StorageValue::from_binary(&raw_value);
+            if !storage_value.is_expired(now) && storage_value.is_modified_since(since) {
+                keys.push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
+        return Ok(keys);
+    }
+
+    /// Get the keys in the half-open range `[start, end)` in lexicographic order
+    /// Expired keys are skipped but left for the regular TTL cleanup
+    ///
+    /// # Arguments
+    /// * `start` - The first key of the range, inclusive
+    /// * `end` - The end of the range, exclusive
+    /// * `limit` - The maximum number of keys to return
+    ///
+    /// # Returns
+    /// A Result containing a sorted vector of keys or a `DatabaseError`
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        let now = self.clock.now();
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut keys = Vec::new();
+        for result in self.store.iterator_opt(
+            IteratorMode::From(start, Direction::Forward),
+            self.read_options(),
+        ) {
+            let (key, raw_value) = result?;
+            // The iterator is sorted, so the first key past the end finishes the range
+            if keys.len() >= limit || key.as_ref() >= end {
+                break;
+            }
+            if !StorageValue::from_binary(&raw_value).is_expired(now) {
+                keys.push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
         return Ok(keys);
     }
 
+    /// Count the keys of the database from the estimate `RocksDB` keeps
+    ///
+    /// The estimate is exact until keys are overwritten or deleted, and approaches the exact
+    /// count again as compactions run.
+    ///
+    /// # Returns
+    /// A Result containing the estimated number of keys or a `DatabaseError`
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return Ok(self
+            .store
+            .property_int_value("rocksdb.estimate-num-keys")?
+            .unwrap_or(0));
+    }
+
     /// Get the time-to-live (TTL) for a key
     ///
     /// # Arguments
@@ -214,7 +629,7 @@ impl Storage for Rocksdb {
     /// A Result containing the TTL or a `RocksDB` error
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// let ttl = db.get_ttl(b"my_key").unwrap();
     /// ```
@@ -223,25 +638,24 @@ impl Storage for Rocksdb {
     /// If the key is not found, a `DatabaseError::ValueNotFound` error is returned
     /// If there is an error getting the value, a `DatabaseError` is returned
     async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
-        let txn = self.store.transaction();
+        let txn = self.transaction();
         let raw_value = txn.get(key);
         match raw_value {
             Ok(value) => match value {
                 Some(value) => {
-                    let storage_value = StorageValue::from_binary(value.as_slice());
+                    let mut storage_value = StorageValue::from_binary(value.as_slice());
                     if storage_value.ttl <= 0 {
                         return Ok(storage_value.ttl);
                     }
 
-                    let ttl = storage_value.ttl - chrono::Utc::now().timestamp();
-                    if ttl > 0 {
-                        return Ok(ttl);
+                    storage_value.ttl -= self.clock.now();
+                    if Self::delete_on_ttl(&txn, key, &storage_value)? {
+                        txn.commit()?;
+                        return Err(DatabaseError::ValueNotFound(
+                            String::from_utf8_lossy(key).to_string(),
+                        ));
                     }
-
-                    txn.delete(key)?;
-                    return Err(DatabaseError::ValueNotFound(
-                        String::from_utf8_lossy(key).to_string(),
-                    ));
+                    return Ok(storage_value.ttl);
                 }
                 None => {
                     return Err(DatabaseError::ValueNotFound(
@@ -253,6 +667,28 @@ impl Storage for Rocksdb {
         }
     }
 
+    /// Get the absolute expiry time of a key
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the expiry time for
+    ///
+    /// # Returns
+    /// A Result containing the expiry time in seconds since the Unix epoch, -1 if the key
+    /// never expires, or a `DatabaseError`
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        let now = self.clock.now();
+        match self.store.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                return Ok(StorageValue::from_binary(&raw_value).ttl);
+            }
+            _ => {
+                return Err(DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        }
+    }
+
     /// Update the time-to-live (TTL) for a key
     /// If the TTL is set to a negative value, the key will not expire
     ///
@@ -264,20 +700,17 @@ impl Storage for Rocksdb {
     /// A Result containing `()` or a `DatabaseError`
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.update_ttl(b"my_key", 1000);
     /// ```
     async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
-        let txn = self.store.transaction();
+        let txn = self.transaction();
         let raw_value = txn.get(key)?;
         if let Some(value) = raw_value {
             let mut storage_value = StorageValue::from_binary(value.as_slice());
-            if ttl < 0 {
-                storage_value.ttl = -1;
-            } else {
-                storage_value.ttl = ttl + chrono::Utc::now().timestamp();
-            };
+            storage_value.ttl = expire_at(self.clock.now(), ttl)?;
+            storage_value.touch(self.clock.now_millis());
             txn.put(key, storage_value.to_binary())?;
             txn.commit()?;
             Ok(())
@@ -288,6 +721,94 @@ impl Storage for Rocksdb {
         }
     }
 
+    /// Adjust the remaining time-to-live (TTL) for a key by a relative delta
+    /// The remaining TTL is clamped at 0, which expires the key immediately
+    /// Keys without a TTL are left untouched
+    ///
+    /// # Arguments
+    /// * `key` - The key to adjust the TTL for
+    /// * `delta` - The number of seconds to extend (positive) or shrink (negative) the TTL by
+    ///
+    /// # Returns
+    /// A Result containing the new remaining TTL (-1 if the key does not expire) or a `DatabaseError`
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        let txn = self.transaction();
+        let mut storage_value = match txn.get(key)? {
+            Some(value) => StorageValue::from_binary(value.as_slice()),
+            None => {
+                return Err(DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        if storage_value.ttl < 0 {
+            return Ok(-1);
+        }
+
+        let now = self.clock.now();
+        let remaining = storage_value.ttl - now;
+        if remaining <= 0 {
+            txn.delete(key)?;
+            txn.commit()?;
+            return Err(DatabaseError::ValueNotFound(
+                String::from_utf8_lossy(key).to_string(),
+            ));
+        }
+
+        let ttl = remaining.saturating_add(delta).max(0);
+        if ttl == 0 {
+            txn.delete(key)?;
+        } else {
+            storage_value.ttl = expire_at(now, ttl)?;
+            storage_value.touch(self.clock.now_millis());
+            txn.put(key, storage_value.to_binary())?;
+        }
+        txn.commit()?;
+        return Ok(ttl);
+    }
+
+    /// Get the value for a key and replace its time-to-live (TTL) in one transaction
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the value for
+    /// * `ttl` - The new TTL in seconds, negative to never expire, None to leave it unchanged
+    ///
+    /// # Returns
+    /// A Result containing the value with its new remaining TTL, None if the key is not found
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        let Some(ttl) = ttl else {
+            return self.get(key).await;
+        };
+
+        let txn = self.transaction();
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(value) => StorageValue::from_binary(value.as_slice()),
+            None => return Ok(None),
+        };
+
+        let now = self.clock.now();
+        if storage_value.ttl > -1 && storage_value.ttl <= now {
+            txn.delete(key)?;
+            txn.commit()?;
+            return Ok(None);
+        }
+
+        storage_value.ttl = expire_at(now, ttl)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.put(key, storage_value.to_binary())?;
+        txn.commit()?;
+
+        if storage_value.ttl > -1 {
+            storage_value.ttl = ttl;
+        }
+        return Ok(Some(storage_value));
+    }
+
     /// Set the value for a key in the database
     ///
     /// # Arguments
@@ -295,24 +816,71 @@ impl Storage for Rocksdb {
     /// * `value` - The value to set
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.set(b"my_key", b"my_value");
     /// ```
     async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
-        let mut value = value.clone();
-        if value.ttl < 0 {
-            value.ttl = -1;
-        } else {
-            value.ttl += chrono::Utc::now().timestamp();
-        }
+        value.validate()?;
 
-        match self.store.put(key, value.to_binary()) {
+        let mut value = value.clone();
+        value.ttl = expire_at(self.clock.now(), value.ttl)?;
+        value.touch(self.clock.now_millis());
+        match self
+            .store
+            .put_opt(key, value.to_binary(), &self.write_options())
+        {
             Ok(()) => return Ok(()),
             Err(err) => return Err(err.into()),
         }
     }
 
+    /// Set the value for a key, keeping the expiry of the value it replaces
+    /// The key is read for update, so a concurrent TTL change can't be lost
+    ///
+    /// # Arguments
+    /// * `key` - The key to set the value for
+    /// * `value` - The value to set; its TTL only applies if the key doesn't exist
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        value.validate()?;
+
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut value = value.clone();
+        value.ttl = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value).ttl
+            }
+            _ => expire_at(now, value.ttl)?,
+        };
+        value.touch(self.clock.now_millis());
+        txn.put(key, value.to_binary())?;
+        txn.commit()?;
+        return Ok(());
+    }
+
+    /// Set the values of several keys in one transaction
+    /// Nothing is written if any of the values is invalid
+    ///
+    /// # Arguments
+    /// * `entries` - The keys and the values to set, each value with its own TTL
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        for (key, value) in entries {
+            value.validate()?;
+            let mut value = value.clone();
+            value.ttl = expire_at(now, value.ttl)?;
+            value.touch(self.clock.now_millis());
+            txn.put(key, value.to_binary())?;
+        }
+
+        txn.commit()?;
+        return Ok(());
+    }
+
     /// Increment the value for a key in the database
     /// If the key does not exist, it will be created with the default value
     ///
@@ -320,47 +888,46 @@ impl Storage for Rocksdb {
     /// * `key` - The key to increment
     /// * `value` - The value to increment by
     /// * `default_value` - The default value to use if the key does not exist
+    /// * `base` - The base the integer is written in
     ///
     /// # Returns
     /// A Result containing the new value or a `DatabaseError`
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
-    /// db.increment(b"my_key", 1, None);
+    /// db.increment(b"my_key", 1, None, 10);
     /// ```
     async fn increment(
         &self,
         key: &[u8],
         value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, DatabaseError> {
-        let txn = self.store.transaction();
-        let raw_value = txn.get(key);
-
-        if raw_value.is_err() {
-            return Err(DatabaseError::InternalError(format!(
-                "Failed to get value: {err}",
-                err = raw_value.unwrap_err()
-            )));
-        }
+        let txn = self.transaction();
+        let raw_value = match txn.get(key) {
+            Ok(raw_value) => raw_value,
+            Err(err) => {
+                return Err(DatabaseError::InternalError(format!(
+                    "Failed to get value: {err}"
+                )))
+            }
+        };
 
         let mut storage_value: StorageValue;
 
-        match raw_value.unwrap() {
+        match raw_value {
             Some(raw_value) => {
                 storage_value = StorageValue::from_binary(raw_value.as_slice());
-
-                let current_value = storage_value.get_integer_value()?;
-                let new_value = current_value + value;
-                storage_value.value = new_value.to_string().as_bytes().to_vec();
             }
             None => match default_value {
                 Some(default_value) => {
                     storage_value = StorageValue {
                         value_type: ValueType::Integer,
                         ttl: -1,
-                        value: (default_value + value).to_string().as_bytes().to_vec(),
+                        value: format_integer(default_value, base)?.into_bytes(),
+                        mtime: 0,
                     };
                 }
                 None => {
@@ -371,6 +938,8 @@ impl Storage for Rocksdb {
             },
         }
 
+        storage_value.add_in_base(value, base)?;
+        storage_value.touch(self.clock.now_millis());
         txn.put(key, storage_value.to_binary())?;
         txn.commit()?;
         return Ok(storage_value);
@@ -383,47 +952,50 @@ impl Storage for Rocksdb {
     /// * `key` - The key to decrement
     /// * `value` - The value to decrement by
     /// * `default_value` - The default value to use if the key does not exist
+    /// * `base` - The base the integer is written in
     ///
     /// # Returns
     /// A Result containing the new value or a `DatabaseError`
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
-    /// db.decrement(b"my_key", 1, None);
+    /// db.decrement(b"my_key", 1, None, 10);
     /// ```
     async fn decrement(
         &self,
         key: &[u8],
         value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, DatabaseError> {
-        let txn = self.store.transaction();
-        let raw_value = txn.get(key);
-
-        if raw_value.is_err() {
-            return Err(DatabaseError::InternalError(format!(
-                "Failed to get value: {err}",
-                err = raw_value.unwrap_err()
-            )));
-        }
+        let txn = self.transaction();
+        let raw_value = match txn.get(key) {
+            Ok(raw_value) => raw_value,
+            Err(err) => {
+                return Err(DatabaseError::InternalError(format!(
+                    "Failed to get value: {err}"
+                )))
+            }
+        };
 
         let mut storage_value: StorageValue;
 
-        match raw_value.unwrap() {
+        match raw_value {
             Some(raw_value) => {
                 storage_value = StorageValue::from_binary(raw_value.as_slice());
 
-                let current_value = storage_value.get_integer_value()?;
+                let current_value = storage_value.get_integer_value_in_base(base)?;
                 let new_value = current_value - value;
-                storage_value.value = new_value.to_string().as_bytes().to_vec();
+                storage_value.value = format_integer(new_value, base)?.into_bytes();
             }
             None => match default_value {
                 Some(default_value) => {
                     storage_value = StorageValue {
                         value_type: ValueType::Integer,
                         ttl: -1,
-                        value: (default_value - value).to_string().as_bytes().to_vec(),
+                        value: format_integer(default_value - value, base)?.into_bytes(),
+                        mtime: 0,
                     };
                 }
                 None => {
@@ -434,23 +1006,471 @@ impl Storage for Rocksdb {
             },
         }
 
+        storage_value.touch(self.clock.now_millis());
+        txn.put(key, storage_value.to_binary())?;
+        txn.commit()?;
+        return Ok(storage_value);
+    }
+
+    /// Increment the value for a key without going over `max`
+    /// The read is validated at commit, so of two concurrent increments the later one fails
+    /// with a retryable `DatabaseError::Conflict` instead of overshooting `max`
+    ///
+    /// # Arguments
+    /// * `key` - The key to increment
+    /// * `value` - The amount to add
+    /// * `default_value` - The value a missing key starts from, the key must exist if `None`
+    /// * `max` - The largest value allowed
+    /// * `saturate` - Cap the result at `max` instead of failing
+    /// * `base` - The base the integer is written in
+    ///
+    /// # Returns
+    /// A Result containing the new value and whether it was capped at `max`, or a `DatabaseError`
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        let txn = self.transaction();
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) => StorageValue::from_binary(raw_value.as_slice()),
+            None => match default_value {
+                Some(default_value) => StorageValue {
+                    value_type: ValueType::Integer,
+                    ttl: -1,
+                    value: format_integer(default_value, base)?.into_bytes(),
+                    mtime: 0,
+                },
+                None => {
+                    return Err(DatabaseError::ValueNotFound(
+                        String::from_utf8_lossy(key).to_string(),
+                    ));
+                }
+            },
+        };
+
+        let capped = storage_value.add_bounded(value, max, saturate, base)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.put(key, storage_value.to_binary())?;
+        txn.commit()?;
+        return Ok((storage_value, capped));
+    }
+
+    /// Increment several base-10 integers in one transaction
+    /// Nothing is written if any of the values is not an integer
+    ///
+    /// # Arguments
+    /// * `increments` - The keys and the amounts to add to them
+    ///
+    /// # Returns
+    /// A Result containing the new values in the order of `increments` or a `DatabaseError`
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let txn = self.transaction();
+        let mut new_values = Vec::with_capacity(increments.len());
+
+        for (key, value) in increments {
+            let mut storage_value = match txn.get_for_update(key, true)? {
+                Some(raw_value) => StorageValue::from_binary(raw_value.as_slice()),
+                None => StorageValue {
+                    value_type: ValueType::Integer,
+                    ttl: -1,
+                    value: b"0".to_vec(),
+                    mtime: 0,
+                },
+            };
+
+            let new_value = storage_value.add_in_base(*value, 10)?;
+            storage_value.touch(self.clock.now_millis());
+            txn.put(key, storage_value.to_binary())?;
+            new_values.push(new_value);
+        }
+
+        txn.commit()?;
+        return Ok(new_values);
+    }
+
+    /// Decrement a base-10 integer and delete the key once it drops to 0 or below
+    /// The read is validated at commit, so of two decrements that both see the last reference
+    /// the later one fails with a retryable `DatabaseError::Conflict`
+    ///
+    /// # Arguments
+    /// * `key` - The key of the counter
+    /// * `value` - The value to decrement by
+    ///
+    /// # Returns
+    /// A Result containing the new value and whether the key was deleted, or a `DatabaseError`
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => {
+                return Err(DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        let new_value = storage_value.subtract(value)?;
+        let deleted = new_value <= 0;
+        if deleted {
+            txn.delete(key)?;
+        } else {
+            storage_value.touch(self.clock.now_millis());
+            txn.put(key, storage_value.to_binary())?;
+        }
+        txn.commit()?;
+        return Ok((new_value, deleted));
+    }
+
+    /// Set a base-10 integer only if the new value is larger, or smaller, than the stored one
+    /// The read is validated at commit, so if a concurrent write slips in between, the commit
+    /// fails with a retryable `DatabaseError::Conflict`
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `value` - The candidate value
+    /// * `extreme` - Whether the larger or the smaller value is kept
+    ///
+    /// # Returns
+    /// A Result containing the resulting value and whether it changed, or a `DatabaseError`
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let (mut storage_value, (result, changed)) = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                let mut storage_value = StorageValue::from_binary(&raw_value);
+                let kept = storage_value.keep_extreme(value, extreme)?;
+                (storage_value, kept)
+            }
+            _ => (
+                StorageValue {
+                    value_type: ValueType::Integer,
+                    ttl: -1,
+                    value: format_integer(value, 10)?.into_bytes(),
+                    mtime: 0,
+                },
+                (value, true),
+            ),
+        };
+
+        if changed {
+            storage_value.touch(self.clock.now_millis());
+            txn.put(key, storage_value.to_binary())?;
+            txn.commit()?;
+        }
+        return Ok((result, changed));
+    }
+
+    /// Add to an integer value if it equals an expected value
+    ///
+    /// The key is read with `get_for_update`, so a concurrent write to it makes the commit fail
+    /// instead of being overwritten.
+    ///
+    /// # Arguments
+    /// * `key` - The key holding the integer
+    /// * `expected` - The value the key must hold for `delta` to be added
+    /// * `delta` - The amount to add
+    ///
+    /// # Returns
+    /// A Result containing the resulting value and whether `delta` was added, or a
+    /// `DatabaseError`; `DatabaseError::ValueNotFound` if the key is missing or expired
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => {
+                return Err(DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        let (value, swapped) = storage_value.compare_and_add(expected, delta)?;
+        if swapped {
+            storage_value.touch(self.clock.now_millis());
+            txn.put(key, storage_value.to_binary())?;
+            txn.commit()?;
+        }
+        return Ok((value, swapped));
+    }
+
+    /// Merge a JSON merge-patch (RFC 7386) into a stored JSON document atomically
+    /// If the key does not exist, it will be created from the patch
+    ///
+    /// # Arguments
+    /// * `key` - The key holding the JSON document
+    /// * `patch` - The merge-patch to apply
+    ///
+    /// # Returns
+    /// A Result containing the patched value or a `DatabaseError`
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue {
+                value_type: ValueType::Json,
+                ttl: -1,
+                value: b"null".to_vec(),
+                mtime: 0,
+            },
+        };
+
+        storage_value.merge_json(patch)?;
+        storage_value.touch(self.clock.now_millis());
         txn.put(key, storage_value.to_binary())?;
         txn.commit()?;
         return Ok(storage_value);
     }
 
+    /// Append elements to the end of a list, creating the list if the key doesn't exist
+    ///
+    /// # Arguments
+    /// * `key` - The key of the list
+    /// * `values` - The elements to append
+    /// * `maxlen` - Keep only the last `maxlen` elements after appending
+    /// * `max_elements` - Reject the append if the list would hold more elements than this
+    ///
+    /// # Returns
+    /// A Result containing the new length of the list or a `DatabaseError`
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue::new_list(&[]),
+        };
+
+        let length = storage_value.push_list(values, maxlen, max_elements)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.put(key, storage_value.to_binary())?;
+        txn.commit()?;
+        return Ok(length);
+    }
+
+    /// Append text to a string, creating the string if the key doesn't exist
+    /// The string is read for update, so the append and the TTL update land in one transaction
+    ///
+    /// # Arguments
+    /// * `key` - The key of the string
+    /// * `suffix` - The text to append
+    /// * `ttl` - The new TTL of the key, the TTL is unchanged without it
+    ///
+    /// # Returns
+    /// A Result containing the new length of the string in bytes or a `DatabaseError`
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue::string(""),
+        };
+
+        let length = storage_value.append_string(suffix)?;
+        if let Some(ttl) = ttl {
+            storage_value.ttl = expire_at(now, ttl)?;
+        }
+        storage_value.touch(self.clock.now_millis());
+        txn.put(key, storage_value.to_binary())?;
+        txn.commit()?;
+        return Ok(length);
+    }
+
+    /// Set several fields of a hash, creating the hash if the key doesn't exist
+    /// The hash is read for update and rewritten once, so the fields are set atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key of the hash
+    /// * `fields` - The fields to set and their new values
+    ///
+    /// # Returns
+    /// A Result containing the numbers of added and of updated fields or a `DatabaseError`
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue::new_hash(&BTreeMap::new()),
+        };
+
+        let counts = storage_value.set_hash_fields(fields, max_elements)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.put(key, storage_value.to_binary())?;
+        txn.commit()?;
+        return Ok(counts);
+    }
+
+    /// Rename a key only if the new key does not already exist
+    /// The existence check and the rename are performed atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key to rename
+    /// * `new_key` - The new name for the key
+    ///
+    /// # Returns
+    /// A Result containing `true` if the key was renamed, `false` if the new key already exists,
+    /// or a `DatabaseError`
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        // Both keys are read for update, so a concurrent rename to the same target
+        // makes one of the commits fail
+        let raw_value = match txn.get_for_update(key, true)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => raw_value,
+            _ => {
+                return Err(DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        if let Some(raw_target) = txn.get_for_update(new_key, true)? {
+            if !StorageValue::from_binary(&raw_target).is_expired(now) {
+                return Ok(false);
+            }
+        }
+
+        let mut value = StorageValue::from_binary(&raw_value);
+        value.touch(self.clock.now_millis());
+        txn.put(new_key, value.to_binary())?;
+        txn.delete(key)?;
+        txn.commit()?;
+        return Ok(true);
+    }
+
+    /// Set a key only if it doesn't exist or has expired
+    ///
+    /// # Arguments
+    /// * `key` - The key to set
+    /// * `value` - The value to set, with its TTL
+    ///
+    /// # Returns
+    /// A Result containing `true` if the key was set, `false` if it already exists,
+    /// or a `DatabaseError`
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        value.validate()?;
+
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        // The key is read for update, so of two concurrent writers only one commits
+        if let Some(raw_value) = txn.get_for_update(key, true)? {
+            if !StorageValue::from_binary(&raw_value).is_expired(now) {
+                return Ok(false);
+            }
+        }
+
+        let mut value = value.clone();
+        value.ttl = expire_at(now, value.ttl)?;
+        value.touch(self.clock.now_millis());
+        txn.put(key, value.to_binary())?;
+        txn.commit()?;
+        return Ok(true);
+    }
+
+    /// Delete a key only if its value equals the expected bytes
+    ///
+    /// # Arguments
+    /// * `key` - The key to delete
+    /// * `expected` - The bytes the value must hold
+    ///
+    /// # Returns
+    /// A Result containing `true` if the key was deleted, `false` if it is missing, expired
+    /// or holds another value, or a `DatabaseError`
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        let txn = self.transaction();
+        let now = self.clock.now();
+
+        match txn.get_for_update(key, true)? {
+            Some(raw_value) => {
+                let value = StorageValue::from_binary(&raw_value);
+                if value.is_expired(now) || value.value != expected {
+                    return Ok(false);
+                }
+            }
+            None => return Ok(false),
+        }
+
+        txn.delete(key)?;
+        txn.commit()?;
+        return Ok(true);
+    }
+
     /// Delete a key-value pair from the database
     ///
     /// # Arguments
     /// * `key` - The key to delete
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.delete(b"my_key");
     /// ```
     async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
-        match self.store.delete(key) {
+        match self.store.delete_opt(key, &self.write_options()) {
             Ok(()) => return Ok(()),
             Err(err) => return Err(err.into()),
         }
@@ -462,23 +1482,246 @@ impl Storage for Rocksdb {
     /// * `prefix` - The prefix to filter keys by
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.delete_prefix(b"my_prefix");
     /// ```
     async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
-        let mut end_prefix = prefix.to_vec();
-        end_prefix.push(PREFIX_SEARCH_ENDING);
-        let cf = self.store.cf_handle(DEFAULT_COLUMN_FAMILY_NAME);
-        let cf = cf.unwrap();
+        return self.delete_prefix_in(DEFAULT_COLUMN_FAMILY_NAME, prefix);
+    }
 
-        let del_result = self
-            .store
-            .delete_range_cf(&cf, prefix, end_prefix.as_slice());
+    /// Delete every key whose TTL has passed
+    /// Each expired key is checked again inside the transaction, so a key written
+    /// again since the scan is kept
+    ///
+    /// # Returns
+    /// A Result containing the deleted keys or a `DatabaseError`
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        let now = self.clock.now();
+        let mut candidates = vec![];
+        for result in self.store.iterator(IteratorMode::Start) {
+            let (key, raw_value) = result?;
+            if StorageValue::from_binary(&raw_value).is_expired(now) {
+                candidates.push(key);
+            }
+        }
 
-        match del_result {
-            Ok(()) => return Ok(()),
-            Err(err) => return Err(err.into()),
+        let txn = self.transaction();
+        let mut expired = vec![];
+        for key in candidates {
+            let Some(raw_value) = txn.get_for_update(&key, true)? else {
+                continue;
+            };
+            if StorageValue::from_binary(&raw_value).is_expired(now) {
+                txn.delete(&key)?;
+                expired.push(String::from_utf8_lossy(&key).to_string());
+            }
         }
+
+        txn.commit()?;
+        return Ok(expired);
+    }
+
+    /// Flush the write-ahead log and fsync it
+    /// Writes in the log are recovered on restart, so the memtables don't need to be flushed
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        self.store.flush_wal(true)?;
+        return Ok(());
+    }
+
+    /// Flush the memtables into SST files, so the writes no longer depend on the WAL
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.flush_memtables().await.unwrap();
+    /// ```
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        self.store.flush()?;
+        return Ok(());
+    }
+
+    /// Write a consistent point-in-time copy of the database into a directory
+    /// Files are hard-linked when `dest` is on the same filesystem, so the copy is cheap
+    ///
+    /// # Arguments
+    /// * `dest` - The directory to write the copy into, it must not exist yet
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.checkpoint("/var/backups/bredis").await.unwrap();
+    /// ```
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        let checkpoint = Checkpoint::new(self.store.as_ref())?;
+        checkpoint.create_checkpoint(dest)?;
+        return Ok(());
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return StorageCapabilities {
+            backend: "rocksdb",
+            checkpoint: true,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::mock::{MockClock, TempPath};
+
+    #[tokio::test]
+    async fn test_delete_prefix_in_missing_column_family() {
+        let db = Rocksdb::open_ephemeral().unwrap();
+        db.set(b"ns:key", &StorageValue::string("value"))
+            .await
+            .unwrap();
+
+        let result = db.delete_prefix_in("namespace", b"ns:");
+        match result {
+            Err(DatabaseError::InternalError(message)) => {
+                assert!(message.contains("namespace"), "{message}");
+            }
+            other => panic!("Unexpected result: {other:?}"),
+        }
+        assert!(db.get(b"ns:key").await.unwrap().is_some());
+
+        db.delete_prefix(b"ns:").await.unwrap();
+        assert!(db.get(b"ns:key").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reads_reap_expired_keys() {
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let db = Rocksdb::open_ephemeral().unwrap().with_clock(clock.clone());
+        let expiring = StorageValue {
+            ttl: 1,
+            ..StorageValue::string("other")
+        };
+        db.set(b"session", &expiring).await.unwrap();
+        db.set(b"session:listed", &expiring).await.unwrap();
+        db.set(b"session:ttl", &expiring).await.unwrap();
+        // A key named after the value of the expired one is left alone
+        db.set(b"other", &StorageValue::string("value"))
+            .await
+            .unwrap();
+        clock.advance(2);
+
+        assert!(db.get(b"session").await.unwrap().is_none());
+        assert!(db.store.get(b"session").unwrap().is_none());
+
+        let keys = db.get_all_keys(b"session").await.unwrap();
+        assert!(keys.is_empty(), "{keys:?}");
+        assert!(db.store.get(b"session:listed").unwrap().is_none());
+
+        let result = db.get_ttl(b"session:ttl").await;
+        assert!(matches!(result, Err(DatabaseError::ValueNotFound(_))));
+        assert!(db.store.get(b"session:ttl").unwrap().is_none());
+
+        assert!(db.get(b"other").await.unwrap().is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_many_reads_a_snapshot() {
+        let db = Arc::new(Rocksdb::open_ephemeral().unwrap());
+        let pair = |i: i64| {
+            return vec![
+                (b"left".to_vec(), StorageValue::integer(i)),
+                (b"right".to_vec(), StorageValue::integer(i)),
+            ];
+        };
+        db.set_many(&pair(0)).await.unwrap();
+
+        let writer = {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for i in 1..=2000 {
+                    db.set_many(&pair(i)).await.unwrap();
+                }
+            })
+        };
+        let keys = [b"left".to_vec(), b"right".to_vec()];
+        while !writer.is_finished() {
+            let values = db.get_many(&keys).await.unwrap();
+            let left = values[0].as_ref().unwrap().get_integer_value().unwrap();
+            let right = values[1].as_ref().unwrap().get_integer_value().unwrap();
+            assert_eq!(left, right, "A batch saw a write for one key only");
+        }
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reopen_keeps_data() {
+        let db_path = TempPath::new("test_db");
+        let db = Rocksdb::open(&db_path).unwrap();
+        db.set(b"kept", &StorageValue::string("value"))
+            .await
+            .unwrap();
+        drop(db);
+
+        let db = Rocksdb::open(&db_path).unwrap();
+        let value = db.get(b"kept").await.unwrap().unwrap();
+        assert_eq!(value.value, b"value");
+        drop(db);
+
+        let db = Rocksdb::open_fresh(&db_path).unwrap();
+        assert!(db.get(b"kept").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_drop_keeps_data() {
+        let db_path = TempPath::new("test_db");
+        let db = Rocksdb::open(&db_path).unwrap();
+        db.set(b"kept", &StorageValue::string("value"))
+            .await
+            .unwrap();
+        let clone = db.clone();
+        drop(db);
+        let value = clone.get(b"kept").await.unwrap().unwrap();
+        assert_eq!(value.value, b"value");
+
+        // The database is only removed once every handle is released
+        let open_clone = clone.clone();
+        assert!(clone.destroy().is_err());
+        open_clone.destroy().unwrap();
+        assert!(!std::path::Path::new(&*db_path).join("CURRENT").exists());
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_is_removed_with_the_last_handle() {
+        let db = Rocksdb::open_ephemeral().unwrap();
+        let path = std::path::PathBuf::from(db.path());
+        db.set(b"key", &StorageValue::string("value"))
+            .await
+            .unwrap();
+        let clone = db.clone();
+        db.close().await;
+        drop(db);
+        // A clone still holds the database
+        assert!(path.exists());
+
+        drop(clone);
+        assert!(!path.exists(), "{} was left behind", path.display());
+    }
+
+    #[tokio::test]
+    async fn test_close_is_idempotent() {
+        let db_path = TempPath::new("test_db");
+        let db = Rocksdb::open(&db_path).unwrap();
+        db.set(b"kept", &StorageValue::string("value"))
+            .await
+            .unwrap();
+        let clone = db.clone();
+        db.close().await;
+        assert!(clone.closed.load(Ordering::SeqCst));
+        // A second close, from any clone, is a no-op that keeps the data
+        clone.close().await;
+        drop(db);
+        drop(clone);
+
+        let db = Rocksdb::open(&db_path).unwrap();
+        let value = db.get(b"kept").await.unwrap().unwrap();
+        assert_eq!(value.value, b"value");
     }
 }
@@ -0,0 +1,448 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// A storage wrapper that logs every operation slower than a threshold
+///
+/// Slow operations are logged at warn level with the key they touched and their duration.
+pub struct SlowOpLog {
+    inner: Box<dyn Storage>,
+    threshold: Duration,
+}
+
+impl SlowOpLog {
+    pub fn new(inner: Box<dyn Storage>, threshold: Duration) -> Self {
+        return Self { inner, threshold };
+    }
+
+    async fn timed<T>(&self, op: &str, key: &[u8], operation: impl Future<Output = T>) -> T {
+        let started_at = Instant::now();
+        let result = operation.await;
+        let elapsed = started_at.elapsed();
+        if elapsed >= self.threshold && key.is_empty() {
+            log::warn!("Slow {op} took {}ms", elapsed.as_millis());
+        } else if elapsed >= self.threshold {
+            log::warn!(
+                "Slow {op} of {} took {}ms",
+                String::from_utf8_lossy(key),
+                elapsed.as_millis()
+            );
+        }
+        return result;
+    }
+}
+
+#[async_trait]
+impl Storage for SlowOpLog {
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.timed("get", key, self.inner.get(key)).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self
+            .timed("get_no_reap", key, self.inner.get_no_reap(key))
+            .await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self
+            .timed("get_record", key, self.inner.get_record(key))
+            .await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        let names: Vec<_> = keys
+            .iter()
+            .map(|key| String::from_utf8_lossy(key))
+            .collect();
+        return self
+            .timed(
+                "get_many",
+                names.join(",").as_bytes(),
+                self.inner.get_many(keys),
+            )
+            .await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        return self
+            .timed("get_all_keys", prefix, self.inner.get_all_keys(prefix))
+            .await;
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        return self
+            .timed(
+                "keys_modified_since",
+                b"",
+                self.inner.keys_modified_since(since),
+            )
+            .await;
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        return self
+            .timed("range", start, self.inner.range(start, end, limit))
+            .await;
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return self.timed("len", b"", self.inner.len()).await;
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.timed("get_ttl", key, self.inner.get_ttl(key)).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self
+            .timed("get_expire_at", key, self.inner.get_expire_at(key))
+            .await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self
+            .timed("update_ttl", key, self.inner.update_ttl(key, ttl))
+            .await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self
+            .timed("adjust_ttl", key, self.inner.adjust_ttl(key, delta))
+            .await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.timed("get_ex", key, self.inner.get_ex(key, ttl)).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self.timed("set", key, self.inner.set(key, value)).await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self
+            .timed("set_keep_ttl", key, self.inner.set_keep_ttl(key, value))
+            .await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        let keys: Vec<_> = entries
+            .iter()
+            .map(|(key, _)| String::from_utf8_lossy(key))
+            .collect();
+        return self
+            .timed(
+                "set_many",
+                keys.join(",").as_bytes(),
+                self.inner.set_many(entries),
+            )
+            .await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .timed(
+                "increment",
+                key,
+                self.inner.increment(key, value, default_value, base),
+            )
+            .await;
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .timed(
+                "decrement",
+                key,
+                self.inner.decrement(key, value, default_value, base),
+            )
+            .await;
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        return self
+            .timed(
+                "increment_bounded",
+                key,
+                self.inner
+                    .increment_bounded(key, value, default_value, max, saturate, base),
+            )
+            .await;
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let keys: Vec<_> = increments
+            .iter()
+            .map(|(key, _)| String::from_utf8_lossy(key))
+            .collect();
+        return self
+            .timed(
+                "increment_many",
+                keys.join(",").as_bytes(),
+                self.inner.increment_many(increments),
+            )
+            .await;
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .timed(
+                "decrement_and_delete",
+                key,
+                self.inner.decrement_and_delete(key, value),
+            )
+            .await;
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .timed(
+                "set_extreme",
+                key,
+                self.inner.set_extreme(key, value, extreme),
+            )
+            .await;
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .timed(
+                "compare_and_increment",
+                key,
+                self.inner.compare_and_increment(key, expected, delta),
+            )
+            .await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .timed("json_merge", key, self.inner.json_merge(key, patch))
+            .await;
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .timed(
+                "list_push",
+                key,
+                self.inner.list_push(key, values, maxlen, max_elements),
+            )
+            .await;
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .timed("append", key, self.inner.append(key, suffix, ttl))
+            .await;
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return self
+            .timed(
+                "hset_many",
+                key,
+                self.inner.hset_many(key, fields, max_elements),
+            )
+            .await;
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return self
+            .timed("rename_nx", key, self.inner.rename_nx(key, new_key))
+            .await;
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        return self
+            .timed("set_nx", key, self.inner.set_nx(key, value))
+            .await;
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        return self
+            .timed(
+                "delete_if_equal",
+                key,
+                self.inner.delete_if_equal(key, expected),
+            )
+            .await;
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        return self.timed("delete", key, self.inner.delete(key)).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        return self
+            .timed("delete_prefix", prefix, self.inner.delete_prefix(prefix))
+            .await;
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        return self
+            .timed("delete_expired", b"", self.inner.delete_expired())
+            .await;
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        return self.timed("sync", b"", self.inner.sync()).await;
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        return self
+            .timed("flush_memtables", b"", self.inner.flush_memtables())
+            .await;
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self
+            .timed("checkpoint", dest.as_bytes(), self.inner.checkpoint(dest))
+            .await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::storages::{bredis::Bredis, mock::SlowStorage};
+
+    /// Keeps the warnings logged by the tests of this module
+    struct CapturingLogger {
+        records: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            return metadata.level() <= log::Level::Warn;
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.records.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    #[tokio::test]
+    async fn test_slow_op_warning() {
+        // Another test may have installed the logger already
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let slow = Box::new(SlowStorage::new(
+            Box::new(Bredis::open()),
+            Duration::from_millis(50),
+        ));
+        let db = SlowOpLog::new(slow, Duration::from_millis(10));
+        db.get(b"slow_key").await.unwrap();
+
+        let fast = SlowOpLog::new(Box::new(Bredis::open()), Duration::from_secs(10));
+        fast.get(b"fast_key").await.unwrap();
+
+        let records = LOGGER.records.lock().unwrap();
+        assert!(
+            records
+                .iter()
+                .any(|record| record.starts_with("Slow get of slow_key took")),
+            "{records:?}"
+        );
+        assert!(
+            !records.iter().any(|record| record.contains("fast_key")),
+            "{records:?}"
+        );
+    }
+}
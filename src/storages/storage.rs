@@ -1,12 +1,43 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 
 use crate::errors::DatabaseError;
 
-use super::value::StorageValue;
+use super::value::{Extreme, StorageValue};
+
+/// The optional operations a storage supports
+///
+/// Every storage supports the operations of `Storage` that are not listed here
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageCapabilities {
+    /// The name of the backend, used in the messages of unsupported operations
+    pub backend: &'static str,
+    /// Whether `checkpoint` can write a point-in-time copy of the database
+    pub checkpoint: bool,
+}
+
+impl StorageCapabilities {
+    /// Check that the storage can write checkpoints
+    ///
+    /// # Errors
+    /// A `DatabaseError::Unsupported` error naming the backend if it can't
+    pub fn require_checkpoint(&self) -> Result<(), DatabaseError> {
+        if self.checkpoint {
+            return Ok(());
+        }
+        return Err(DatabaseError::Unsupported(format!(
+            "checkpoints are not supported by the {} backend",
+            self.backend
+        )));
+    }
+}
 
 #[async_trait]
 pub trait Storage: Sync + Send {
-    /// Close the database and remove the storage directory
+    /// Close the database, flushing whatever the backend still buffers
+    ///
+    /// Closing never removes data, the storage can be opened again with its data intact.
     async fn close(&self);
 
     /// Get the value for a key from the database
@@ -18,7 +49,7 @@ pub trait Storage: Sync + Send {
     /// An Option containing the value or None if the key is not found
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// let value = db.get(b"my_key").uimpl fmt::Display for StorageValue {
     ///       println!("Value: {}", String::from_utf8(value).unwrap());
@@ -29,6 +60,61 @@ pub trait Storage: Sync + Send {
     /// ```
     async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError>;
 
+    /// Get the value for a key without deleting it if it has expired
+    ///
+    /// Unlike `get`, an expired value is only reported as missing and left for `get` or
+    /// `delete_expired` to reap, so the read never writes.
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the value for
+    ///
+    /// # Returns
+    /// An Option containing the value or None if the key is not found or has expired
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let value = db.get_no_reap(b"my_key").await.unwrap();
+    /// ```
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError>;
+
+    /// Get the record of a key as the database stores it, in a single read
+    ///
+    /// The record is the binary encoding of the value, holding its absolute expiry time, see
+    /// `StorageValue::from_binary`. A backend keeping its values in memory returns the encoding
+    /// it persists them with. An expired record is reported as missing and left in place.
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the record for
+    ///
+    /// # Returns
+    /// An Option containing the encoded record or None if the key is not found or has expired
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let record = db.get_record(b"my_key").await.unwrap();
+    /// ```
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError>;
+
+    /// Get the values of several keys as of a single point in time
+    ///
+    /// A write committed while the batch is read is seen for all of its keys or for none.
+    /// Like `get_no_reap`, expired values are reported as missing and left in place.
+    ///
+    /// # Arguments
+    /// * `keys` - The keys to get the values for
+    ///
+    /// # Returns
+    /// A Result containing the value of every key, in the order of `keys`, or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let values = db.get_many(&[b"a".to_vec(), b"b".to_vec()]).await.unwrap();
+    /// ```
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError>;
+
     /// Get all keys in the database
     ///
     /// # Arguments
@@ -38,6 +124,60 @@ pub trait Storage: Sync + Send {
     /// A Result containing a vector of keys or a `RocksDB` error
     async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError>;
 
+    /// Get all keys written after the specified time
+    ///
+    /// # Arguments
+    /// * `since` - The time in milliseconds since the Unix epoch
+    ///
+    /// # Returns
+    /// A Result containing a vector of keys or a `DatabaseError`
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError>;
+
+    /// Get the keys in the half-open range `[start, end)` in lexicographic order
+    ///
+    /// # Arguments
+    /// * `start` - The first key of the range, inclusive
+    /// * `end` - The end of the range, exclusive
+    /// * `limit` - The maximum number of keys to return
+    ///
+    /// # Returns
+    /// A Result containing a sorted vector of keys or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let keys = db.range(b"user:a", b"user:n", Some(100)).await.unwrap();
+    /// ```
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError>;
+
+    /// Count the keys of the database
+    ///
+    /// Expired keys that haven't been reaped yet are counted, and a backend may estimate the
+    /// count instead of scanning its keys.
+    ///
+    /// # Returns
+    /// A Result containing the number of keys or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let keys = db.len().await.unwrap();
+    /// ```
+    async fn len(&self) -> Result<u64, DatabaseError>;
+
+    /// Check whether the database holds no keys
+    ///
+    /// # Returns
+    /// A Result containing `true` if `len` is zero or a `DatabaseError`
+    async fn is_empty(&self) -> Result<bool, DatabaseError> {
+        return Ok(self.len().await? == 0);
+    }
+
     /// Get the time-to-live (TTL) for a key
     ///
     /// # Arguments
@@ -47,7 +187,7 @@ pub trait Storage: Sync + Send {
     /// A Result containing the TTL or a `RocksDB` error
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// let ttl = db.get_ttl(b"my_key").unwrap();
     /// ```
@@ -57,6 +197,25 @@ pub trait Storage: Sync + Send {
     /// If there is an error getting the value, a `DatabaseError` is returned
     async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError>;
 
+    /// Get the absolute expiry time of a key, as stored by the database
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the expiry time for
+    ///
+    /// # Returns
+    /// A Result containing the expiry time in seconds since the Unix epoch, -1 if the key
+    /// never expires, or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let expire_at = db.get_expire_at(b"my_key").await.unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// If the key is not found or has expired, a `DatabaseError::ValueNotFound` error is returned
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError>;
+
     /// Update the time-to-live (TTL) for a key
     /// If the TTL is set to a negative value, the key will not expire
     ///
@@ -68,12 +227,50 @@ pub trait Storage: Sync + Send {
     /// A Result containing `()` or a `DatabaseError`
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.update_ttl(b"my_key", 1000);
     /// ```
     async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError>;
 
+    /// Adjust the remaining time-to-live (TTL) for a key by a relative delta
+    /// The remaining TTL is clamped at 0, which expires the key immediately
+    /// Keys without a TTL are left untouched
+    ///
+    /// # Arguments
+    /// * `key` - The key to adjust the TTL for
+    /// * `delta` - The number of seconds to extend (positive) or shrink (negative) the TTL by
+    ///
+    /// # Returns
+    /// A Result containing the new remaining TTL (-1 if the key does not expire) or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.adjust_ttl(b"my_key", 60);
+    /// ```
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError>;
+
+    /// Get the value for a key and replace its time-to-live (TTL) in the same operation
+    ///
+    /// # Arguments
+    /// * `key` - The key to get the value for
+    /// * `ttl` - The new TTL in seconds, negative to never expire, None to leave it unchanged
+    ///
+    /// # Returns
+    /// A Result containing the value with its new remaining TTL, None if the key is not found
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let value = db.get_ex(b"my_key", Some(60)).await.unwrap();
+    /// ```
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError>;
+
     /// Set the value for a key in the database
     ///
     /// # Arguments
@@ -81,33 +278,376 @@ pub trait Storage: Sync + Send {
     /// * `value` - The value to set
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.set(b"my_key", b"my_value");
     /// ```
     async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError>;
 
+    /// Set the value for a key, keeping the expiry of the value it replaces
+    /// The current expiry is read in the same transaction as the write, like `SET ... KEEPTTL`
+    ///
+    /// # Arguments
+    /// * `key` - The key to set the value for
+    /// * `value` - The value to set; its TTL only applies if the key doesn't exist
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.set_keep_ttl(b"session", &StorageValue::string("refreshed")).await?;
+    /// ```
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError>;
+
+    /// Set the values of several keys atomically: either every value is written or none
+    ///
+    /// # Arguments
+    /// * `entries` - The keys and the values to set, each value with its own TTL
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let value = StorageValue { ttl: 300, ..StorageValue::string("cached") };
+    /// db.set_many(&[(b"k1".to_vec(), value.clone()), (b"k2".to_vec(), value)]).await.unwrap();
+    /// ```
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError>;
+
+    /// Increment an integer value, which is stored as text in the specified base
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `value` - The amount to add
+    /// * `default_value` - The value a missing key starts from, the key must exist if `None`
+    /// * `base` - The base the integer is written in, 10 or 16
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.increment(b"my_counter", 1, Some(0), 16);
+    /// ```
     async fn increment(
         &self,
         key: &[u8],
         value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, DatabaseError>;
 
+    /// Decrement an integer value, which is stored as text in the specified base
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `value` - The amount to subtract
+    /// * `default_value` - The value a missing key starts from, the key must exist if `None`
+    /// * `base` - The base the integer is written in, 10 or 16
     async fn decrement(
         &self,
         key: &[u8],
         value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, DatabaseError>;
 
-    /// Delete a key-value pair from the database
+    /// Increment an integer value without going over `max`, atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `value` - The amount to add
+    /// * `default_value` - The value a missing key starts from, the key must exist if `None`
+    /// * `max` - The largest value allowed
+    /// * `saturate` - Cap the result at `max` instead of failing
+    /// * `base` - The base the integer is written in, 10 or 16
+    ///
+    /// # Returns
+    /// A Result containing the new value and whether it was capped at `max`, or a `DatabaseError`
+    ///
+    /// # Errors
+    /// If the result would exceed `max` and `saturate` is false, nothing is written and a
+    /// `DatabaseError::OutOfRange` error is returned
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let (value, capped) = db.increment_bounded(b"requests", 1, Some(0), 100, true, 10).await.unwrap();
+    /// ```
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError>;
+
+    /// Increment several base-10 integers atomically: either every increment is applied or none
+    /// Missing keys start from 0
+    ///
+    /// # Arguments
+    /// * `increments` - The keys and the amounts to add to them
+    ///
+    /// # Returns
+    /// A Result containing the new values in the order of `increments` or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.increment_many(&[(b"hits".to_vec(), 1), (b"bytes".to_vec(), 512)]);
+    /// ```
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError>;
+
+    /// Decrement a base-10 integer and delete the key once it drops to 0 or below, atomically
+    /// Meant for reference counts, whose key must go away with the last reference
+    ///
+    /// # Arguments
+    /// * `key` - The key of the counter
+    /// * `value` - The value to decrement by
+    ///
+    /// # Returns
+    /// A Result containing the new value and whether the key was deleted, or a `DatabaseError`
+    ///
+    /// # Errors
+    /// If the key doesn't exist, a `DatabaseError::ValueNotFound` error is returned
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let (refs, deleted) = db.decrement_and_delete(b"refs", 1).await.unwrap();
+    /// ```
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError>;
+
+    /// Set a base-10 integer only if the new value is larger, or smaller, than the stored one
+    /// The comparison and the write are performed atomically; a missing key is set to the value
+    ///
+    /// # Arguments
+    /// * `key` - The key of the integer
+    /// * `value` - The candidate value
+    /// * `extreme` - `Extreme::Max` to keep the larger value, `Extreme::Min` the smaller one
+    ///
+    /// # Returns
+    /// A Result containing the resulting value and whether it changed, or a `DatabaseError`
+    ///
+    /// # Errors
+    /// If the key holds a value of another type, a `DatabaseError::InvalidValueType` error is returned
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let (peak, changed) = db.set_extreme(b"peak_latency", 250, Extreme::Max).await.unwrap();
+    /// ```
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError>;
+
+    /// Add to an integer value atomically if it equals an expected value
+    ///
+    /// # Arguments
+    /// * `key` - The key holding the integer
+    /// * `expected` - The value the key must hold for `delta` to be added
+    /// * `delta` - The amount to add
+    ///
+    /// # Returns
+    /// A Result containing the resulting value and whether `delta` was added, or a
+    /// `DatabaseError`; on a mismatch the current value is returned unchanged
+    ///
+    /// # Errors
+    /// If the key does not exist, a `DatabaseError::ValueNotFound` error is returned
+    /// If the key holds a value of another type, a `DatabaseError::InvalidValueType` error is returned
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let (version, swapped) = db.compare_and_increment(b"version", 5, 1).await.unwrap();
+    /// ```
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError>;
+
+    /// Merge a JSON merge-patch (RFC 7386) into a stored JSON document atomically
+    /// If the key does not exist, it will be created from the patch
+    ///
+    /// # Arguments
+    /// * `key` - The key holding the JSON document
+    /// * `patch` - The merge-patch to apply
+    ///
+    /// # Returns
+    /// A Result containing the patched value or a `DatabaseError`
+    ///
+    /// # Errors
+    /// If the key holds a non-JSON value, a `DatabaseError::InvalidValueType` error is returned
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.json_merge(b"my_key", &serde_json::json!({ "a": 1 }));
+    /// ```
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError>;
+
+    /// Append elements to the end of a list, creating the list if the key doesn't exist
+    /// The append and the trim are applied atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key of the list
+    /// * `values` - The elements to append
+    /// * `maxlen` - Keep only the last `maxlen` elements after appending
+    /// * `max_elements` - Reject the append if the list would hold more elements than this
+    ///
+    /// # Returns
+    /// A Result containing the new length of the list or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let length = db
+    ///     .list_push(b"events", &["login".to_string()], Some(100), None)
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// If the list would exceed `max_elements`, a `DatabaseError::OutOfRange` error is returned
+    /// and the list is left unchanged
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError>;
+
+    /// Append text to a string, creating the string if the key doesn't exist
+    /// The append and the TTL update are applied atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key of the string
+    /// * `suffix` - The text to append
+    /// * `ttl` - The new TTL of the key, -1 to remove it, the TTL is unchanged without it
+    ///
+    /// # Returns
+    /// A Result containing the new length of the string in bytes or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let length = db.append(b"log", "login\n", Some(3600)).await.unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// If the key holds another type, a `DatabaseError::InvalidValueType` error is returned
+    /// and neither the value nor the TTL is changed
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError>;
+
+    /// Set several fields of a hash, creating the hash if the key doesn't exist
+    /// The whole hash is rewritten once, so the fields are set atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key of the hash
+    /// * `fields` - The fields to set and their new values
+    /// * `max_elements` - Reject the write if the hash would hold more fields than this
+    ///
+    /// # Returns
+    /// A Result containing the numbers of added and of updated fields or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let fields = HashMap::from([("name".to_string(), b"bredis".to_vec())]);
+    /// let (added, updated) = db.hset_many(b"user:1", &fields, None).await.unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// If the key holds another type, a `DatabaseError::InvalidValueType` error is returned.
+    /// If the hash would exceed `max_elements`, a `DatabaseError::OutOfRange` error is returned
+    /// and the hash is left unchanged
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError>;
+
+    /// Rename a key only if the new key does not already exist
+    /// The existence check and the rename are performed atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key to rename
+    /// * `new_key` - The new name for the key
+    ///
+    /// # Returns
+    /// A Result containing `true` if the key was renamed, `false` if the new key already exists,
+    /// or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.rename_nx(b"my_key", b"my_new_key");
+    /// ```
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError>;
+
+    /// Set a key only if it doesn't exist or has expired
+    /// The existence check and the write are performed atomically
+    ///
+    /// # Arguments
+    /// * `key` - The key to set
+    /// * `value` - The value to set, with its TTL
+    ///
+    /// # Returns
+    /// A Result containing `true` if the key was set, `false` if it already exists,
+    /// or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.set_nx(b"my_lock", &StorageValue::string("token"));
+    /// ```
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError>;
+
+    /// Delete a key only if its value equals the expected bytes
+    /// The comparison and the delete are performed atomically
     ///
     /// # Arguments
     /// * `key` - The key to delete
+    /// * `expected` - The bytes the value must hold
+    ///
+    /// # Returns
+    /// A Result containing `true` if the key was deleted, `false` if it is missing, expired
+    /// or holds another value, or a `DatabaseError`
     ///
     /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.delete_if_equal(b"my_lock", b"token");
     /// ```
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError>;
+
+    /// Delete a key-value pair from the database
+    ///
+    /// # Arguments
+    /// * `key` - The key to delete
+    ///
+    /// # Example
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.delete(b"my_key");
     /// ```
@@ -119,9 +659,67 @@ pub trait Storage: Sync + Send {
     /// * `prefix` - The prefix to filter keys by
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let db = Database::open("/dev/shm/my_storage").unwrap();
     /// db.delete_prefix(b"my_prefix");
     /// ```
     async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError>;
+
+    /// Delete every key whose TTL has passed
+    /// A key written again with a new TTL between the scan and the delete is kept
+    ///
+    /// # Returns
+    /// A Result containing the deleted keys or a `DatabaseError`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// let expired = db.delete_expired().await.unwrap();
+    /// ```
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError>;
+
+    /// Persist every acknowledged write durably before returning
+    /// A durability barrier for writes made without `--sync-writes`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.set(b"my_key", &value).await.unwrap();
+    /// db.sync().await.unwrap();
+    /// ```
+    async fn sync(&self) -> Result<(), DatabaseError>;
+
+    /// Write the buffered writes out of memory into the files of the backend
+    /// A no-op for backends without an in-memory write buffer
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.flush_memtables().await.unwrap();
+    /// ```
+    async fn flush_memtables(&self) -> Result<(), DatabaseError>;
+
+    /// Write a consistent point-in-time copy of the database into a directory
+    ///
+    /// # Arguments
+    /// * `dest` - The directory to write the copy into, it must not exist yet
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// db.checkpoint("/var/backups/bredis").await.unwrap();
+    /// ```
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError>;
+
+    /// Describe the optional operations the storage supports
+    /// Wrapping storages report the capabilities of the storage they wrap
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = Database::open("/dev/shm/my_storage").unwrap();
+    /// if db.capabilities().checkpoint {
+    ///     db.checkpoint("/var/backups/bredis").await.unwrap();
+    /// }
+    /// ```
+    fn capabilities(&self) -> StorageCapabilities;
 }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// A storage wrapper rejecting writes that would change the type of an existing key
+///
+/// A `set` on a key holding a value of another type fails with `InvalidValueType`, so a
+/// counter can't silently turn into a string. Deleting the key and writing it again is allowed.
+///
+/// The type check and the write are separate operations, so two concurrent writes of
+/// different types to a missing key may both succeed.
+pub struct StrictTypes {
+    inner: Box<dyn Storage>,
+}
+
+impl StrictTypes {
+    pub fn new(inner: Box<dyn Storage>) -> Self {
+        return Self { inner };
+    }
+
+    /// Fail with `InvalidValueType` if the key holds a value of another type than `value`
+    async fn check_type(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        if let Some(current) = self.inner.get(key).await? {
+            if current.value_type != value.value_type {
+                return Err(DatabaseError::InvalidValueType(format!(
+                    "{} holds {}, not {}",
+                    String::from_utf8_lossy(key),
+                    current.value_type,
+                    value.value_type
+                )));
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[async_trait]
+impl Storage for StrictTypes {
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get(key).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get_no_reap(key).await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self.inner.get_record(key).await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        return self.inner.get_many(keys).await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        return self.inner.get_all_keys(prefix).await;
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        return self.inner.keys_modified_since(since).await;
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        return self.inner.range(start, end, limit).await;
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return self.inner.len().await;
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.inner.get_ttl(key).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.inner.get_expire_at(key).await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self.inner.update_ttl(key, ttl).await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self.inner.adjust_ttl(key, delta).await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get_ex(key, ttl).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.check_type(key, value).await?;
+        return self.inner.set(key, value).await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        self.check_type(key, value).await?;
+        return self.inner.set_keep_ttl(key, value).await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        for (key, value) in entries {
+            self.check_type(key, value).await?;
+        }
+        return self.inner.set_many(entries).await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.inner.increment(key, value, default_value, base).await;
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.inner.decrement(key, value, default_value, base).await;
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        return self
+            .inner
+            .increment_bounded(key, value, default_value, max, saturate, base)
+            .await;
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        return self.inner.increment_many(increments).await;
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.inner.decrement_and_delete(key, value).await;
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.inner.set_extreme(key, value, extreme).await;
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self.inner.compare_and_increment(key, expected, delta).await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.inner.json_merge(key, patch).await;
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .inner
+            .list_push(key, values, maxlen, max_elements)
+            .await;
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return self.inner.append(key, suffix, ttl).await;
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return self.inner.hset_many(key, fields, max_elements).await;
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return self.inner.rename_nx(key, new_key).await;
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        return self.inner.set_nx(key, value).await;
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        return self.inner.delete_if_equal(key, expected).await;
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        return self.inner.delete(key).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        return self.inner.delete_prefix(prefix).await;
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        return self.inner.delete_expired().await;
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        return self.inner.sync().await;
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        return self.inner.flush_memtables().await;
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self.inner.checkpoint(dest).await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::bredis::Bredis;
+
+    #[tokio::test]
+    async fn test_type_change_is_rejected() {
+        let db = StrictTypes::new(Box::new(Bredis::open()));
+        db.set(b"name", &StorageValue::string("alice"))
+            .await
+            .unwrap();
+        db.set(b"name", &StorageValue::string("bob")).await.unwrap();
+
+        let result = db.set(b"name", &StorageValue::integer(42)).await;
+        assert!(
+            matches!(result, Err(DatabaseError::InvalidValueType(_))),
+            "{result:?}"
+        );
+        assert_eq!(db.get(b"name").await.unwrap().unwrap().value, b"bob");
+
+        // A deleted key can be written again with another type
+        db.delete(b"name").await.unwrap();
+        db.set(b"name", &StorageValue::integer(42)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_type_change_is_allowed_by_default() {
+        let db = Bredis::open();
+        db.set(b"name", &StorageValue::string("alice"))
+            .await
+            .unwrap();
+        db.set(b"name", &StorageValue::integer(42)).await.unwrap();
+    }
+}
@@ -1,14 +1,22 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use surrealkv::{Options, Store};
 
 use crate::errors;
 
-use super::{storage::Storage, value::StorageValue};
+use super::{
+    clock::{expire_at, Clock, SystemClock},
+    storage::{Storage, StorageCapabilities},
+    value::{format_integer, Extreme, StorageValue, ValueType},
+};
 
 const PREFIX_SEARCH_ENDING: u8 = 0xFF;
 
 pub struct SurrealKV {
     store: Store,
+    clock: Arc<dyn Clock>,
 }
 
 impl SurrealKV {
@@ -19,7 +27,38 @@ impl SurrealKV {
         };
 
         let store = Store::new(options).expect("Failed to create store");
-        Self { store }
+        Self {
+            store,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Open a store that keeps its data on disk in `path` and recovers it on the next open
+    ///
+    /// # Arguments
+    /// * `path` - The data directory of the store, created if it does not exist
+    ///
+    /// # Returns
+    /// A Result containing the opened store or a `DatabaseError`
+    pub fn open_persistent(path: &str) -> Result<Self, errors::DatabaseError> {
+        let options = Options {
+            dir: path.into(),
+            disk_persistence: true,
+            ..Default::default()
+        };
+
+        let store = Store::new(options)?;
+        return Ok(Self {
+            store,
+            clock: Arc::new(SystemClock),
+        });
+    }
+
+    /// Read the current time from `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        return self;
     }
 }
 
@@ -44,7 +83,7 @@ impl Storage for SurrealKV {
         }
 
         // TTL is set, check if the value is expired
-        value.ttl -= chrono::Utc::now().timestamp();
+        value.ttl -= self.clock.now();
         if value.ttl <= 0 {
             txn.delete(key).unwrap();
             return Ok(None);
@@ -54,6 +93,60 @@ impl Storage for SurrealKV {
         return Ok(Some(value));
     }
 
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, errors::DatabaseError> {
+        // The transaction is dropped without a commit, so nothing is written
+        let mut txn = self.store.begin().unwrap();
+        let mut value = match txn.get(key) {
+            Ok(Some(value)) => super::value::StorageValue::from_binary(&value),
+            Ok(None) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        if value.ttl < 0 {
+            return Ok(Some(value));
+        }
+        value.ttl -= self.clock.now();
+        if value.ttl <= 0 {
+            return Ok(None);
+        }
+        return Ok(Some(value));
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, errors::DatabaseError> {
+        // The transaction is dropped without a commit, so nothing is written
+        let mut txn = self.store.begin().unwrap();
+        let Some(record) = txn.get(key)? else {
+            return Ok(None);
+        };
+        if super::value::StorageValue::from_binary(&record).is_expired(self.clock.now()) {
+            return Ok(None);
+        }
+        return Ok(Some(record));
+    }
+
+    async fn get_many(
+        &self,
+        keys: &[Vec<u8>],
+    ) -> Result<Vec<Option<StorageValue>>, errors::DatabaseError> {
+        // A single transaction reads the whole batch from its snapshot, nothing is written
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = txn.get(key)?.and_then(|raw_value| {
+                let mut value = super::value::StorageValue::from_binary(&raw_value);
+                if value.ttl < 0 {
+                    return Some(value);
+                }
+                value.ttl -= now;
+                (value.ttl > 0).then_some(value)
+            });
+            values.push(value);
+        }
+        return Ok(values);
+    }
+
     async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, errors::DatabaseError> {
         let mut end_prefix = prefix.to_vec();
         end_prefix.push(PREFIX_SEARCH_ENDING);
@@ -67,7 +160,7 @@ impl Storage for SurrealKV {
             let value = super::value::StorageValue::from_binary(&raw_value);
 
             if value.ttl > -1 {
-                let ttl = value.ttl - chrono::Utc::now().timestamp();
+                let ttl = value.ttl - self.clock.now();
                 if ttl <= 0 {
                     txn.delete(&key).unwrap();
                     continue;
@@ -80,6 +173,57 @@ impl Storage for SurrealKV {
         return Ok(keys);
     }
 
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, errors::DatabaseError> {
+        let end_key = [PREFIX_SEARCH_ENDING];
+        let keys_range = b"".as_slice()..end_key.as_slice();
+
+        let mut txn = self.store.begin().unwrap();
+        let key_val_res = txn.scan(keys_range, None)?;
+
+        let now = self.clock.now();
+        let mut keys: Vec<String> = vec![];
+        for (key, raw_value, _) in key_val_res {
+            let value = StorageValue::from_binary(&raw_value);
+            if !value.is_expired(now) && value.is_modified_since(since) {
+                keys.push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
+        return Ok(keys);
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, errors::DatabaseError> {
+        if start >= end {
+            return Ok(vec![]);
+        }
+
+        let mut txn = self.store.begin().unwrap();
+        // Expired keys are filtered out afterwards, so the limit can't be pushed into the scan
+        let key_val_res = txn.scan(start..end, None)?;
+
+        let now = self.clock.now();
+        let keys = key_val_res
+            .into_iter()
+            .filter(|(_, raw_value, _)| !StorageValue::from_binary(raw_value).is_expired(now))
+            .map(|(key, _, _)| String::from_utf8_lossy(&key).to_string())
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        return Ok(keys);
+    }
+
+    async fn len(&self) -> Result<u64, errors::DatabaseError> {
+        let end_key = [PREFIX_SEARCH_ENDING];
+        let keys_range = b"".as_slice()..end_key.as_slice();
+
+        let mut txn = self.store.begin().unwrap();
+        let len = txn.scan(keys_range, None)?.len();
+        return Ok(u64::try_from(len).unwrap_or(u64::MAX));
+    }
+
     async fn get_ttl(&self, key: &[u8]) -> Result<i64, errors::DatabaseError> {
         let mut txn = self.store.begin().unwrap();
         let raw_value = txn.get(key)?;
@@ -96,7 +240,7 @@ impl Storage for SurrealKV {
             return Ok(-1);
         }
 
-        let ttl = value.ttl - chrono::Utc::now().timestamp();
+        let ttl = value.ttl - self.clock.now();
         if ttl <= 0 {
             txn.delete(key)?;
             return Err(errors::DatabaseError::ValueNotFound(
@@ -108,6 +252,21 @@ impl Storage for SurrealKV {
         return Ok(ttl);
     }
 
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+        match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                return Ok(StorageValue::from_binary(&raw_value).ttl);
+            }
+            _ => {
+                return Err(errors::DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        }
+    }
+
     async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), errors::DatabaseError> {
         let mut txn = self.store.begin().unwrap();
         let raw_value = txn.get(key)?;
@@ -120,56 +279,164 @@ impl Storage for SurrealKV {
             }
         };
 
-        if ttl < 0 {
-            value.ttl = -1;
-        } else {
-            value.ttl = ttl + chrono::Utc::now().timestamp();
-        }
+        value.ttl = expire_at(self.clock.now(), ttl)?;
 
+        value.touch(self.clock.now_millis());
         txn.set(key, &value.to_binary())?;
 
         txn.commit().await.unwrap();
         return Ok(());
     }
 
-    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), errors::DatabaseError> {
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, errors::DatabaseError> {
         let mut txn = self.store.begin().unwrap();
-        let mut value = value.clone();
+        let raw_value = txn.get(key)?;
+        let mut value = match raw_value {
+            Some(value) => super::value::StorageValue::from_binary(&value),
+            None => {
+                return Err(errors::DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
 
-        if value.ttl >= 0 {
-            value.ttl += chrono::Utc::now().timestamp();
+        if value.ttl < 0 {
+            return Ok(-1);
+        }
+
+        let now = self.clock.now();
+        let remaining = value.ttl - now;
+        if remaining <= 0 {
+            txn.delete(key)?;
+            txn.commit().await.unwrap();
+            return Err(errors::DatabaseError::ValueNotFound(
+                String::from_utf8_lossy(key).to_string(),
+            ));
+        }
+
+        let ttl = remaining.saturating_add(delta).max(0);
+        if ttl == 0 {
+            txn.delete(key)?;
         } else {
-            value.ttl = -1;
+            value.ttl = expire_at(now, ttl)?;
+            value.touch(self.clock.now_millis());
+            txn.set(key, &value.to_binary())?;
+        }
+
+        txn.commit().await.unwrap();
+        return Ok(ttl);
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, errors::DatabaseError> {
+        let Some(ttl) = ttl else {
+            return self.get(key).await;
+        };
+
+        let mut txn = self.store.begin().unwrap();
+        let mut value = match txn.get(key)? {
+            Some(value) => super::value::StorageValue::from_binary(&value),
+            None => return Ok(None),
+        };
+
+        let now = self.clock.now();
+        if value.ttl > -1 && value.ttl <= now {
+            txn.delete(key)?;
+            txn.commit().await.unwrap();
+            return Ok(None);
         }
 
+        value.ttl = expire_at(now, ttl)?;
+        value.touch(self.clock.now_millis());
+        txn.set(key, &value.to_binary())?;
+        txn.commit().await.unwrap();
+
+        if value.ttl > -1 {
+            value.ttl = ttl;
+        }
+        return Ok(Some(value));
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), errors::DatabaseError> {
+        value.validate()?;
+
+        let mut txn = self.store.begin().unwrap();
+        let mut value = value.clone();
+
+        value.ttl = expire_at(self.clock.now(), value.ttl)?;
+
+        value.touch(self.clock.now_millis());
         txn.set(key, &value.to_binary())?;
         txn.commit().await.unwrap();
 
         return Ok(());
     }
 
+    async fn set_keep_ttl(
+        &self,
+        key: &[u8],
+        value: &StorageValue,
+    ) -> Result<(), errors::DatabaseError> {
+        value.validate()?;
+
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut value = value.clone();
+        value.ttl = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value).ttl
+            }
+            _ => expire_at(now, value.ttl)?,
+        };
+        value.touch(self.clock.now_millis());
+        txn.set(key, &value.to_binary())?;
+
+        // A conflicting transaction touching the same key makes the commit fail
+        txn.commit().await?;
+        return Ok(());
+    }
+
+    async fn set_many(
+        &self,
+        entries: &[(Vec<u8>, StorageValue)],
+    ) -> Result<(), errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        for (key, value) in entries {
+            value.validate()?;
+            let mut value = value.clone();
+            value.ttl = expire_at(now, value.ttl)?;
+            value.touch(self.clock.now_millis());
+            txn.set(key, &value.to_binary())?;
+        }
+
+        txn.commit().await?;
+        return Ok(());
+    }
+
     async fn increment(
         &self,
         key: &[u8],
         value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, errors::DatabaseError> {
         let mut txn = self.store.begin().unwrap();
         let raw_value = txn.get(key)?;
 
-        let storage_value = match raw_value {
-            Some(raw_value) => {
-                let mut storage_value = StorageValue::from_binary(&raw_value);
-                let current_value = storage_value.get_integer_value()?;
-                let new_value = current_value + value;
-                storage_value.value = new_value.to_string().as_bytes().to_vec();
-                storage_value
-            }
+        let mut storage_value = match raw_value {
+            Some(raw_value) => StorageValue::from_binary(&raw_value),
             None => match default_value {
                 Some(default_value) => StorageValue {
                     value_type: super::value::ValueType::Integer,
                     ttl: -1,
-                    value: (default_value + value).to_string().as_bytes().to_vec(),
+                    value: format_integer(default_value, base)?.into_bytes(),
+                    mtime: 0,
                 },
                 None => {
                     return Err(errors::DatabaseError::ValueNotFound(
@@ -179,6 +446,8 @@ impl Storage for SurrealKV {
             },
         };
 
+        storage_value.add_in_base(value, base)?;
+        storage_value.touch(self.clock.now_millis());
         txn.set(key, &storage_value.to_binary())?;
 
         txn.commit().await.unwrap();
@@ -190,23 +459,25 @@ impl Storage for SurrealKV {
         key: &[u8],
         value: i64,
         default_value: Option<i64>,
+        base: u32,
     ) -> Result<StorageValue, errors::DatabaseError> {
         let mut txn = self.store.begin().unwrap();
         let raw_value = txn.get(key)?;
 
-        let storage_value = match raw_value {
+        let mut storage_value = match raw_value {
             Some(raw_value) => {
                 let mut storage_value = StorageValue::from_binary(&raw_value);
-                let current_value = storage_value.get_integer_value()?;
+                let current_value = storage_value.get_integer_value_in_base(base)?;
                 let new_value = current_value - value;
-                storage_value.value = new_value.to_string().as_bytes().to_vec();
+                storage_value.value = format_integer(new_value, base)?.into_bytes();
                 storage_value
             }
             None => match default_value {
                 Some(default_value) => StorageValue {
                     value_type: super::value::ValueType::Integer,
                     ttl: -1,
-                    value: (default_value - value).to_string().as_bytes().to_vec(),
+                    value: format_integer(default_value - value, base)?.into_bytes(),
+                    mtime: 0,
                 },
                 None => {
                     return Err(errors::DatabaseError::ValueNotFound(
@@ -216,12 +487,352 @@ impl Storage for SurrealKV {
             },
         };
 
+        storage_value.touch(self.clock.now_millis());
         txn.set(key, &storage_value.to_binary())?;
 
         txn.commit().await.unwrap();
         Ok(storage_value)
     }
 
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) => StorageValue::from_binary(&raw_value),
+            None => match default_value {
+                Some(default_value) => StorageValue {
+                    value_type: super::value::ValueType::Integer,
+                    ttl: -1,
+                    value: format_integer(default_value, base)?.into_bytes(),
+                    mtime: 0,
+                },
+                None => {
+                    return Err(errors::DatabaseError::ValueNotFound(
+                        String::from_utf8_lossy(key).to_string(),
+                    ));
+                }
+            },
+        };
+
+        let capped = storage_value.add_bounded(value, max, saturate, base)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.set(key, &storage_value.to_binary())?;
+        txn.commit().await?;
+        return Ok((storage_value, capped));
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let mut new_values = Vec::with_capacity(increments.len());
+
+        for (key, value) in increments {
+            let mut storage_value = match txn.get(key)? {
+                Some(raw_value) => StorageValue::from_binary(&raw_value),
+                None => StorageValue {
+                    value_type: super::value::ValueType::Integer,
+                    ttl: -1,
+                    value: b"0".to_vec(),
+                    mtime: 0,
+                },
+            };
+
+            let new_value = storage_value.add_in_base(*value, 10)?;
+            storage_value.touch(self.clock.now_millis());
+            txn.set(key, &storage_value.to_binary())?;
+            new_values.push(new_value);
+        }
+
+        txn.commit().await?;
+        return Ok(new_values);
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => {
+                return Err(errors::DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        let new_value = storage_value.subtract(value)?;
+        let deleted = new_value <= 0;
+        if deleted {
+            txn.delete(key)?;
+        } else {
+            storage_value.touch(self.clock.now_millis());
+            txn.set(key, &storage_value.to_binary())?;
+        }
+
+        txn.commit().await?;
+        return Ok((new_value, deleted));
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let (mut storage_value, (result, changed)) = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                let mut storage_value = StorageValue::from_binary(&raw_value);
+                let kept = storage_value.keep_extreme(value, extreme)?;
+                (storage_value, kept)
+            }
+            _ => (
+                StorageValue {
+                    value_type: ValueType::Integer,
+                    ttl: -1,
+                    value: format_integer(value, 10)?.into_bytes(),
+                    mtime: 0,
+                },
+                (value, true),
+            ),
+        };
+
+        if changed {
+            storage_value.touch(self.clock.now_millis());
+            txn.set(key, &storage_value.to_binary())?;
+            txn.commit().await?;
+        }
+        return Ok((result, changed));
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => {
+                return Err(errors::DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        let (value, swapped) = storage_value.compare_and_add(expected, delta)?;
+        if swapped {
+            storage_value.touch(self.clock.now_millis());
+            txn.set(key, &storage_value.to_binary())?;
+            txn.commit().await?;
+        }
+        return Ok((value, swapped));
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue {
+                value_type: super::value::ValueType::Json,
+                ttl: -1,
+                value: b"null".to_vec(),
+                mtime: 0,
+            },
+        };
+
+        storage_value.merge_json(patch)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.set(key, &storage_value.to_binary())?;
+
+        txn.commit().await?;
+        return Ok(storage_value);
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue::new_list(&[]),
+        };
+
+        let length = storage_value.push_list(values, maxlen, max_elements)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.set(key, &storage_value.to_binary())?;
+
+        txn.commit().await?;
+        return Ok(length);
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue::string(""),
+        };
+
+        let length = storage_value.append_string(suffix)?;
+        if let Some(ttl) = ttl {
+            storage_value.ttl = expire_at(now, ttl)?;
+        }
+        storage_value.touch(self.clock.now_millis());
+        txn.set(key, &storage_value.to_binary())?;
+
+        txn.commit().await?;
+        return Ok(length);
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let mut storage_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => {
+                StorageValue::from_binary(&raw_value)
+            }
+            _ => StorageValue::new_hash(&BTreeMap::new()),
+        };
+
+        let counts = storage_value.set_hash_fields(fields, max_elements)?;
+        storage_value.touch(self.clock.now_millis());
+        txn.set(key, &storage_value.to_binary())?;
+
+        txn.commit().await?;
+        return Ok(counts);
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        let raw_value = match txn.get(key)? {
+            Some(raw_value) if !StorageValue::from_binary(&raw_value).is_expired(now) => raw_value,
+            _ => {
+                return Err(errors::DatabaseError::ValueNotFound(
+                    String::from_utf8_lossy(key).to_string(),
+                ))
+            }
+        };
+
+        if let Some(raw_target) = txn.get(new_key)? {
+            if !StorageValue::from_binary(&raw_target).is_expired(now) {
+                return Ok(false);
+            }
+        }
+
+        let mut value = StorageValue::from_binary(&raw_value);
+        value.touch(self.clock.now_millis());
+        txn.set(new_key, &value.to_binary())?;
+        txn.delete(key)?;
+
+        // A conflicting transaction touching the same keys makes the commit fail
+        txn.commit().await?;
+        return Ok(true);
+    }
+
+    async fn set_nx(
+        &self,
+        key: &[u8],
+        value: &StorageValue,
+    ) -> Result<bool, errors::DatabaseError> {
+        value.validate()?;
+
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        if let Some(raw_value) = txn.get(key)? {
+            if !StorageValue::from_binary(&raw_value).is_expired(now) {
+                return Ok(false);
+            }
+        }
+
+        let mut value = value.clone();
+        value.ttl = expire_at(now, value.ttl)?;
+        value.touch(self.clock.now_millis());
+        txn.set(key, &value.to_binary())?;
+
+        // A conflicting transaction touching the same key makes the commit fail
+        txn.commit().await?;
+        return Ok(true);
+    }
+
+    async fn delete_if_equal(
+        &self,
+        key: &[u8],
+        expected: &[u8],
+    ) -> Result<bool, errors::DatabaseError> {
+        let mut txn = self.store.begin().unwrap();
+        let now = self.clock.now();
+
+        match txn.get(key)? {
+            Some(raw_value) => {
+                let value = StorageValue::from_binary(&raw_value);
+                if value.is_expired(now) || value.value != expected {
+                    return Ok(false);
+                }
+            }
+            None => return Ok(false),
+        }
+
+        txn.delete(key)?;
+        txn.commit().await?;
+        return Ok(true);
+    }
+
     async fn delete(&self, key: &[u8]) -> Result<(), errors::DatabaseError> {
         let mut txn = self.store.begin().unwrap();
         txn.delete(key)?;
@@ -245,10 +856,53 @@ impl Storage for SurrealKV {
         txn.commit().await.unwrap();
         return Ok(());
     }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, errors::DatabaseError> {
+        let end_key = [PREFIX_SEARCH_ENDING];
+        let keys_range = b"".as_slice()..end_key.as_slice();
+
+        let mut txn = self.store.begin().unwrap();
+        let key_val_res = txn.scan(keys_range, None)?;
+
+        let now = self.clock.now();
+        let mut expired: Vec<String> = vec![];
+        for (key, raw_value, _) in key_val_res {
+            if StorageValue::from_binary(&raw_value).is_expired(now) {
+                txn.delete(&key)?;
+                expired.push(String::from_utf8_lossy(&key).to_string());
+            }
+        }
+
+        txn.commit().await?;
+        return Ok(expired);
+    }
+
+    /// The store has no flush of its own, a persistent one writes every commit to its log
+    async fn sync(&self) -> Result<(), errors::DatabaseError> {
+        return Ok(());
+    }
+
+    async fn flush_memtables(&self) -> Result<(), errors::DatabaseError> {
+        return Ok(());
+    }
+
+    async fn checkpoint(&self, _dest: &str) -> Result<(), errors::DatabaseError> {
+        return self.capabilities().require_checkpoint();
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return StorageCapabilities {
+            backend: "surrealkv",
+            checkpoint: false,
+        };
+    }
 }
 
 impl From<surrealkv::Error> for errors::DatabaseError {
     fn from(err: surrealkv::Error) -> Self {
-        Self::InternalError(err.to_string())
+        match err {
+            surrealkv::Error::TransactionWriteConflict => Self::Conflict(err.to_string()),
+            _ => Self::InternalError(err.to_string()),
+        }
     }
 }
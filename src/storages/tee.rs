@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// A storage wrapper that mirrors every write to a secondary storage, e.g. during a migration
+///
+/// Reads are served by the primary. After a write succeeds on the primary, the records it
+/// touched are copied to the secondary as they are stored in the primary, rather than the
+/// operation being replayed, so increments and merges can't drift when the secondary starts
+/// out with other data. Keys that were never written are not copied; use `migrate` for them.
+///
+/// A failed write to the secondary is logged, and only fails the operation when the
+/// secondary is `fatal`.
+pub struct TeeStorage {
+    primary: Box<dyn Storage>,
+    secondary: Box<dyn Storage>,
+    fatal: bool,
+}
+
+impl TeeStorage {
+    /// Mirror the writes of a storage to another one
+    ///
+    /// # Arguments
+    /// * `primary` - The storage reads are served by
+    /// * `secondary` - The storage writes are mirrored to
+    /// * `fatal` - Whether a failed write to the secondary fails the operation
+    pub fn new(primary: Box<dyn Storage>, secondary: Box<dyn Storage>, fatal: bool) -> Self {
+        return Self {
+            primary,
+            secondary,
+            fatal,
+        };
+    }
+
+    /// Handle the outcome of a write to the secondary
+    fn secondary_result(&self, result: Result<(), DatabaseError>) -> Result<(), DatabaseError> {
+        return match result {
+            Ok(()) => Ok(()),
+            Err(err) if self.fatal => Err(err),
+            Err(err) => {
+                log::warn!("Failed to mirror a write to the secondary storage: {err}");
+                Ok(())
+            }
+        };
+    }
+
+    /// Copy the record of a key from the primary to the secondary, or delete it there if it's gone
+    async fn mirror(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        let result = match self.primary.get_no_reap(key).await {
+            Ok(Some(value)) => self.secondary.set(key, &value).await,
+            Ok(None) => self.secondary.delete(key).await,
+            Err(err) => Err(err),
+        };
+        return self.secondary_result(result);
+    }
+
+    /// Mirror the keys touched by a write, if it succeeded on the primary
+    async fn mirrored<T>(
+        &self,
+        result: Result<T, DatabaseError>,
+        keys: &[&[u8]],
+    ) -> Result<T, DatabaseError> {
+        let value = result?;
+        for key in keys {
+            self.mirror(key).await?;
+        }
+        return Ok(value);
+    }
+}
+
+#[async_trait]
+impl Storage for TeeStorage {
+    async fn close(&self) {
+        self.primary.close().await;
+        self.secondary.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.primary.get(key).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.primary.get_no_reap(key).await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self.primary.get_record(key).await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        return self.primary.get_many(keys).await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        return self.primary.get_all_keys(prefix).await;
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        return self.primary.keys_modified_since(since).await;
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        return self.primary.range(start, end, limit).await;
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        return self.primary.len().await;
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.primary.get_ttl(key).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.primary.get_expire_at(key).await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self
+            .mirrored(self.primary.update_ttl(key, ttl).await, &[key])
+            .await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self
+            .mirrored(self.primary.adjust_ttl(key, delta).await, &[key])
+            .await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        let result = self.primary.get_ex(key, ttl).await;
+        if ttl.is_none() {
+            return result;
+        }
+        return self.mirrored(result, &[key]).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self
+            .mirrored(self.primary.set(key, value).await, &[key])
+            .await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self
+            .mirrored(self.primary.set_keep_ttl(key, value).await, &[key])
+            .await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        let keys: Vec<&[u8]> = entries.iter().map(|(key, _)| key.as_slice()).collect();
+        return self
+            .mirrored(self.primary.set_many(entries).await, &keys)
+            .await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        let result = self
+            .primary
+            .increment(key, value, default_value, base)
+            .await;
+        return self.mirrored(result, &[key]).await;
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        let result = self
+            .primary
+            .decrement(key, value, default_value, base)
+            .await;
+        return self.mirrored(result, &[key]).await;
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        let result = self
+            .primary
+            .increment_bounded(key, value, default_value, max, saturate, base)
+            .await;
+        return self.mirrored(result, &[key]).await;
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let keys: Vec<&[u8]> = increments.iter().map(|(key, _)| key.as_slice()).collect();
+        return self
+            .mirrored(self.primary.increment_many(increments).await, &keys)
+            .await;
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .mirrored(self.primary.decrement_and_delete(key, value).await, &[key])
+            .await;
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .mirrored(self.primary.set_extreme(key, value, extreme).await, &[key])
+            .await;
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .mirrored(
+                self.primary
+                    .compare_and_increment(key, expected, delta)
+                    .await,
+                &[key],
+            )
+            .await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .mirrored(self.primary.json_merge(key, patch).await, &[key])
+            .await;
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        let result = self
+            .primary
+            .list_push(key, values, maxlen, max_elements)
+            .await;
+        return self.mirrored(result, &[key]).await;
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .mirrored(self.primary.append(key, suffix, ttl).await, &[key])
+            .await;
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return self
+            .mirrored(
+                self.primary.hset_many(key, fields, max_elements).await,
+                &[key],
+            )
+            .await;
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return self
+            .mirrored(self.primary.rename_nx(key, new_key).await, &[key, new_key])
+            .await;
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        return self
+            .mirrored(self.primary.set_nx(key, value).await, &[key])
+            .await;
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        return self
+            .mirrored(self.primary.delete_if_equal(key, expected).await, &[key])
+            .await;
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        return self.mirrored(self.primary.delete(key).await, &[key]).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        self.primary.delete_prefix(prefix).await?;
+        return self.secondary_result(self.secondary.delete_prefix(prefix).await);
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        let expired = self.primary.delete_expired().await?;
+        self.secondary_result(self.secondary.delete_expired().await.map(|_| ()))?;
+        return Ok(expired);
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        self.primary.sync().await?;
+        return self.secondary_result(self.secondary.sync().await);
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        self.primary.flush_memtables().await?;
+        return self.secondary_result(self.secondary.flush_memtables().await);
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self.primary.checkpoint(dest).await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.primary.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::{bredis::Bredis, mock::FailingStorage};
+
+    #[tokio::test]
+    async fn test_set_lands_in_both() {
+        let tee = TeeStorage::new(Box::new(Bredis::open()), Box::new(Bredis::open()), true);
+        tee.set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+        tee.increment(b"counter", 5, Some(0), 10).await.unwrap();
+
+        for db in [&tee.primary, &tee.secondary] {
+            let value = db.get(b"key1").await.unwrap().unwrap();
+            assert_eq!(value.value, b"value1");
+            let counter = db.get(b"counter").await.unwrap().unwrap();
+            assert_eq!(counter.get_integer_value().unwrap(), 5);
+        }
+
+        tee.delete(b"key1").await.unwrap();
+        assert!(tee.secondary.get(b"key1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_secondary_failure() {
+        let tee = TeeStorage::new(Box::new(Bredis::open()), Box::new(FailingStorage), false);
+        tee.set(b"key1", &StorageValue::string("value1"))
+            .await
+            .unwrap();
+        assert!(tee.get(b"key1").await.unwrap().is_some());
+
+        let tee = TeeStorage::new(Box::new(Bredis::open()), Box::new(FailingStorage), true);
+        let result = tee.set(b"key1", &StorageValue::string("value1")).await;
+        assert!(matches!(result, Err(DatabaseError::InternalError(_))));
+    }
+}
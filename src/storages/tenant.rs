@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use async_trait::async_trait;
+
+use crate::errors::DatabaseError;
+
+use super::{
+    storage::{Storage, StorageCapabilities},
+    value::{Extreme, StorageValue},
+};
+
+/// The prefix of the keys of every tenant, followed by the tenant and a colon
+const TENANT_KEY_PREFIX: &str = "tenant:";
+
+tokio::task_local! {
+    /// The tenant whose keys the operations of the current task read and write
+    static TENANT: String;
+}
+
+/// A storage wrapper keeping the keys of each tenant apart
+///
+/// The operations of a future run with `TenantKeys::scope` store their keys under
+/// `tenant:{id}:`, and listings only return the keys of the tenant, without the prefix.
+/// Every other operation, e.g. of a background task, sees the keys as they are stored.
+/// The HTTP server selects the tenant of a request with the `--tenant-header` header.
+///
+/// `delete_expired` reaps the expired keys of every tenant and reports them as stored.
+///
+/// # Example
+/// ```ignore
+/// let db = TenantKeys::new(Box::new(Bredis::open()));
+/// TenantKeys::scope("acme".to_string(), db.set(b"key", &value)).await?;
+/// assert!(db.get(b"tenant:acme:key").await?.is_some());
+/// ```
+pub struct TenantKeys {
+    inner: Box<dyn Storage>,
+}
+
+impl TenantKeys {
+    pub fn new(inner: Box<dyn Storage>) -> Self {
+        return Self { inner };
+    }
+
+    /// Run a future with its storage operations confined to the keys of a tenant
+    ///
+    /// # Arguments
+    /// * `tenant` - The tenant, it must not contain a colon, see `is_valid_tenant`
+    /// * `future` - The future to run
+    pub async fn scope<F: Future>(tenant: String, future: F) -> F::Output {
+        return TENANT.scope(tenant, future).await;
+    }
+
+    /// Whether a tenant can be told apart from the others in the stored keys
+    ///
+    /// A colon in a tenant would let `a:b` read the keys of `a` starting with `b:`.
+    ///
+    /// # Arguments
+    /// * `tenant` - The tenant to check
+    pub fn is_valid_tenant(tenant: &str) -> bool {
+        return !tenant.is_empty() && !tenant.contains(':');
+    }
+
+    /// The prefix of the keys of the current tenant, empty outside of a tenant scope
+    fn prefix() -> String {
+        return TENANT
+            .try_with(|tenant| format!("{TENANT_KEY_PREFIX}{tenant}:"))
+            .unwrap_or_default();
+    }
+
+    /// The key a key of the current tenant is stored under
+    fn physical_key(key: &[u8]) -> Vec<u8> {
+        let mut physical_key = Self::prefix().into_bytes();
+        physical_key.extend_from_slice(key);
+        return physical_key;
+    }
+
+    /// The keys of the current tenant among stored keys, without the tenant prefix
+    fn logical_keys(physical_keys: Vec<String>) -> Vec<String> {
+        let prefix = Self::prefix();
+        return physical_keys
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&prefix).map(ToString::to_string))
+            .collect();
+    }
+}
+
+#[async_trait]
+impl Storage for TenantKeys {
+    async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get(&Self::physical_key(key)).await;
+    }
+
+    async fn get_no_reap(&self, key: &[u8]) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get_no_reap(&Self::physical_key(key)).await;
+    }
+
+    async fn get_record(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        return self.inner.get_record(&Self::physical_key(key)).await;
+    }
+
+    async fn get_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<StorageValue>>, DatabaseError> {
+        let physical: Vec<Vec<u8>> = keys.iter().map(|key| Self::physical_key(key)).collect();
+        return self.inner.get_many(&physical).await;
+    }
+
+    async fn get_all_keys(&self, prefix: &[u8]) -> Result<Vec<String>, DatabaseError> {
+        let keys = self.inner.get_all_keys(&Self::physical_key(prefix)).await?;
+        return Ok(Self::logical_keys(keys));
+    }
+
+    async fn keys_modified_since(&self, since: i64) -> Result<Vec<String>, DatabaseError> {
+        let keys = self.inner.keys_modified_since(since).await?;
+        return Ok(Self::logical_keys(keys));
+    }
+
+    async fn range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: Option<usize>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        let keys = self
+            .inner
+            .range(&Self::physical_key(start), &Self::physical_key(end), limit)
+            .await?;
+        return Ok(Self::logical_keys(keys));
+    }
+
+    async fn len(&self) -> Result<u64, DatabaseError> {
+        if Self::prefix().is_empty() {
+            return self.inner.len().await;
+        }
+        let keys = self.get_all_keys(b"").await?;
+        return Ok(u64::try_from(keys.len()).unwrap_or(u64::MAX));
+    }
+
+    async fn get_ttl(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.inner.get_ttl(&Self::physical_key(key)).await;
+    }
+
+    async fn get_expire_at(&self, key: &[u8]) -> Result<i64, DatabaseError> {
+        return self.inner.get_expire_at(&Self::physical_key(key)).await;
+    }
+
+    async fn update_ttl(&self, key: &[u8], ttl: i64) -> Result<(), DatabaseError> {
+        return self.inner.update_ttl(&Self::physical_key(key), ttl).await;
+    }
+
+    async fn adjust_ttl(&self, key: &[u8], delta: i64) -> Result<i64, DatabaseError> {
+        return self.inner.adjust_ttl(&Self::physical_key(key), delta).await;
+    }
+
+    async fn get_ex(
+        &self,
+        key: &[u8],
+        ttl: Option<i64>,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        return self.inner.get_ex(&Self::physical_key(key), ttl).await;
+    }
+
+    async fn set(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self.inner.set(&Self::physical_key(key), value).await;
+    }
+
+    async fn set_keep_ttl(&self, key: &[u8], value: &StorageValue) -> Result<(), DatabaseError> {
+        return self
+            .inner
+            .set_keep_ttl(&Self::physical_key(key), value)
+            .await;
+    }
+
+    async fn set_many(&self, entries: &[(Vec<u8>, StorageValue)]) -> Result<(), DatabaseError> {
+        let physical: Vec<(Vec<u8>, StorageValue)> = entries
+            .iter()
+            .map(|(key, value)| (Self::physical_key(key), value.clone()))
+            .collect();
+        return self.inner.set_many(&physical).await;
+    }
+
+    async fn increment(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .inner
+            .increment(&Self::physical_key(key), value, default_value, base)
+            .await;
+    }
+
+    async fn decrement(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        base: u32,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self
+            .inner
+            .decrement(&Self::physical_key(key), value, default_value, base)
+            .await;
+    }
+
+    async fn increment_bounded(
+        &self,
+        key: &[u8],
+        value: i64,
+        default_value: Option<i64>,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<(StorageValue, bool), DatabaseError> {
+        return self
+            .inner
+            .increment_bounded(
+                &Self::physical_key(key),
+                value,
+                default_value,
+                max,
+                saturate,
+                base,
+            )
+            .await;
+    }
+
+    async fn increment_many(
+        &self,
+        increments: &[(Vec<u8>, i64)],
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let physical: Vec<(Vec<u8>, i64)> = increments
+            .iter()
+            .map(|(key, value)| (Self::physical_key(key), *value))
+            .collect();
+        return self.inner.increment_many(&physical).await;
+    }
+
+    async fn decrement_and_delete(
+        &self,
+        key: &[u8],
+        value: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .inner
+            .decrement_and_delete(&Self::physical_key(key), value)
+            .await;
+    }
+
+    async fn set_extreme(
+        &self,
+        key: &[u8],
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .inner
+            .set_extreme(&Self::physical_key(key), value, extreme)
+            .await;
+    }
+
+    async fn compare_and_increment(
+        &self,
+        key: &[u8],
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        return self
+            .inner
+            .compare_and_increment(&Self::physical_key(key), expected, delta)
+            .await;
+    }
+
+    async fn json_merge(
+        &self,
+        key: &[u8],
+        patch: &serde_json::Value,
+    ) -> Result<StorageValue, DatabaseError> {
+        return self.inner.json_merge(&Self::physical_key(key), patch).await;
+    }
+
+    async fn list_push(
+        &self,
+        key: &[u8],
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .inner
+            .list_push(&Self::physical_key(key), values, maxlen, max_elements)
+            .await;
+    }
+
+    async fn append(
+        &self,
+        key: &[u8],
+        suffix: &str,
+        ttl: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        return self
+            .inner
+            .append(&Self::physical_key(key), suffix, ttl)
+            .await;
+    }
+
+    async fn hset_many(
+        &self,
+        key: &[u8],
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        return self
+            .inner
+            .hset_many(&Self::physical_key(key), fields, max_elements)
+            .await;
+    }
+
+    async fn rename_nx(&self, key: &[u8], new_key: &[u8]) -> Result<bool, DatabaseError> {
+        return self
+            .inner
+            .rename_nx(&Self::physical_key(key), &Self::physical_key(new_key))
+            .await;
+    }
+
+    async fn set_nx(&self, key: &[u8], value: &StorageValue) -> Result<bool, DatabaseError> {
+        return self.inner.set_nx(&Self::physical_key(key), value).await;
+    }
+
+    async fn delete_if_equal(&self, key: &[u8], expected: &[u8]) -> Result<bool, DatabaseError> {
+        return self
+            .inner
+            .delete_if_equal(&Self::physical_key(key), expected)
+            .await;
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), DatabaseError> {
+        return self.inner.delete(&Self::physical_key(key)).await;
+    }
+
+    async fn delete_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        return self.inner.delete_prefix(&Self::physical_key(prefix)).await;
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<String>, DatabaseError> {
+        return self.inner.delete_expired().await;
+    }
+
+    async fn sync(&self) -> Result<(), DatabaseError> {
+        return self.inner.sync().await;
+    }
+
+    async fn flush_memtables(&self) -> Result<(), DatabaseError> {
+        return self.inner.flush_memtables().await;
+    }
+
+    async fn checkpoint(&self, dest: &str) -> Result<(), DatabaseError> {
+        return self.inner.checkpoint(dest).await;
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        return self.inner.capabilities();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::bredis::Bredis;
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated() {
+        let db = TenantKeys::new(Box::new(Bredis::open()));
+        for tenant in ["acme", "globex"] {
+            let value = StorageValue::string(tenant);
+            TenantKeys::scope(tenant.to_string(), db.set(b"user:1", &value))
+                .await
+                .unwrap();
+        }
+
+        let value = TenantKeys::scope("acme".to_string(), db.get(b"user:1"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(value.value, b"acme");
+
+        let keys = TenantKeys::scope("globex".to_string(), db.get_all_keys(b"user:"))
+            .await
+            .unwrap();
+        assert_eq!(keys, vec!["user:1"]);
+        let keys = TenantKeys::scope("globex".to_string(), db.range(b"a", b"z", None))
+            .await
+            .unwrap();
+        assert_eq!(keys, vec!["user:1"]);
+        let len = TenantKeys::scope("globex".to_string(), db.len()).await;
+        assert_eq!(len.unwrap(), 1);
+
+        // Outside of a tenant scope the keys are seen as stored
+        let mut keys = db.get_all_keys(b"").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["tenant:acme:user:1", "tenant:globex:user:1"]);
+    }
+
+    #[test]
+    fn test_is_valid_tenant() {
+        assert!(TenantKeys::is_valid_tenant("acme"));
+        assert!(!TenantKeys::is_valid_tenant(""));
+        assert!(!TenantKeys::is_valid_tenant("acme:eu"));
+    }
+}
@@ -1,8 +1,18 @@
-use crate::storages::value::{StorageValue, ValueType};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::errors::DatabaseError;
+use crate::storages::value::{Extreme, StorageValue, ValueType};
 use rstest::*;
 use rstest_reuse::{self, *};
 
-use super::{bredis::Bredis, rocksdb::Rocksdb, storage::Storage, surrealkv::SurrealKV};
+use super::{
+    bredis::Bredis,
+    clock::Clock,
+    mock::{MockClock, TempPath},
+    rocksdb::Rocksdb,
+    storage::Storage,
+    surrealkv::SurrealKV,
+};
 
 #[template]
 #[rstest]
@@ -30,6 +40,58 @@ async fn test_get_all_keys(
     assert!(keys.contains(&String::from("prefix_key2")));
 }
 
+#[apply(test_cases)]
+async fn test_get_all_keys_exact_prefix(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    let value = StorageValue::string("value");
+    for key in ["user:1", "user:10", "user:2", "user", "user2", "usr:1", "a"] {
+        db.set(key.as_bytes(), &value).await.unwrap();
+    }
+
+    let mut keys = db.get_all_keys(b"user:").await.unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["user:1", "user:10", "user:2"]);
+
+    let mut keys = db.get_all_keys(b"user").await.unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["user", "user2", "user:1", "user:10", "user:2"]);
+
+    assert!(db.get_all_keys(b"zzz").await.unwrap().is_empty());
+}
+
+#[apply(test_cases)]
+async fn test_len(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    assert_eq!(db.len().await.unwrap(), 5);
+    assert!(!db.is_empty().await.unwrap());
+
+    db.set(b"key6", &StorageValue::string("value6"))
+        .await
+        .unwrap();
+    assert_eq!(db.len().await.unwrap(), 6);
+}
+
+#[tokio::test]
+async fn test_is_empty_on_fresh_store() {
+    let backends: Vec<Box<dyn Storage>> = vec![
+        Box::new(Rocksdb::open_ephemeral().unwrap()),
+        Box::new(Bredis::open()),
+        Box::new(SurrealKV::open()),
+    ];
+    for db in &backends {
+        assert_eq!(db.len().await.unwrap(), 0);
+        assert!(db.is_empty().await.unwrap());
+    }
+}
+
 #[apply(test_cases)]
 async fn test_get_ttl(
     #[future]
@@ -38,9 +100,8 @@ async fn test_get_ttl(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
     let value = &StorageValue {
-        value_type: ValueType::String,
         ttl: 1000,
-        value: b"my_value".to_vec(),
+        ..StorageValue::string("my_value")
     };
     db.set(b"my_key", value).await.unwrap();
 
@@ -58,11 +119,7 @@ async fn test_get_ttl_no_ttl(
     db: Box<impl Storage>,
 ) {
     let db = db.await; // Await the future to get the actual storage instance
-    let value = &StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"my_value".to_vec(),
-    };
+    let value = &StorageValue::string("my_value");
     db.set(b"my_key", value).await.unwrap();
 
     let ttl = db.get_ttl(b"my_key").await.unwrap();
@@ -78,9 +135,8 @@ async fn test_get_ttl_expired(
     let db = db.await; // Await the future to get the actual storage instance
 
     let value = &StorageValue {
-        value_type: ValueType::String,
         ttl: 1,
-        value: b"my_value".to_vec(),
+        ..StorageValue::string("my_value")
     };
     db.set(b"my_key", value).await.unwrap();
 
@@ -89,6 +145,37 @@ async fn test_get_ttl_expired(
     assert!(ttl.is_err(), "Expected error for expired key");
 }
 
+#[tokio::test]
+async fn test_ttl_with_mock_clock() {
+    let clock = std::sync::Arc::new(MockClock::new(1_700_000_000));
+    let backends: Vec<Box<dyn Storage>> = vec![
+        Box::new(Rocksdb::open_ephemeral().unwrap().with_clock(clock.clone())),
+        Box::new(Bredis::open().with_clock(clock.clone())),
+        Box::new(SurrealKV::open().with_clock(clock.clone())),
+    ];
+
+    let value = &StorageValue {
+        ttl: 10,
+        ..StorageValue::string("my_value")
+    };
+    for db in &backends {
+        db.set(b"my_key", value).await.unwrap();
+        assert_eq!(db.get_expire_at(b"my_key").await.unwrap(), 1_700_000_010);
+    }
+
+    clock.advance(9);
+    for db in &backends {
+        assert_eq!(db.get_ttl(b"my_key").await.unwrap(), 1);
+        assert!(db.get(b"my_key").await.unwrap().is_some());
+    }
+
+    clock.advance(2);
+    for db in &backends {
+        assert!(db.get(b"my_key").await.unwrap().is_none());
+        assert!(db.get_ttl(b"my_key").await.is_err());
+    }
+}
+
 #[apply(test_cases)]
 async fn test_update_ttl(
     #[future]
@@ -98,9 +185,8 @@ async fn test_update_ttl(
     let db = db.await; // Await the future to get the actual storage instance
 
     let value = &StorageValue {
-        value_type: ValueType::String,
         ttl: 1000,
-        value: b"my_value".to_vec(),
+        ..StorageValue::string("my_value")
     };
     db.set(b"my_key", value).await.unwrap();
 
@@ -117,7 +203,7 @@ async fn test_update_ttl(
 }
 
 #[apply(test_cases)]
-async fn test_set(
+async fn test_ttl_overflow(
     #[future]
     #[case]
     db: Box<impl Storage>,
@@ -125,12 +211,138 @@ async fn test_set(
     let db = db.await; // Await the future to get the actual storage instance
 
     let value = &StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"my_value".to_vec(),
+        ttl: i64::MAX,
+        ..StorageValue::string("my_value")
+    };
+    let result = db.set(b"my_key", value).await;
+    assert!(
+        matches!(result, Err(DatabaseError::OutOfRange(_))),
+        "{result:?}"
+    );
+    assert!(db.get(b"my_key").await.unwrap().is_none());
+
+    db.set(b"my_key", &StorageValue::string("my_value"))
+        .await
+        .unwrap();
+    let result = db.update_ttl(b"my_key", i64::MAX).await;
+    assert!(
+        matches!(result, Err(DatabaseError::OutOfRange(_))),
+        "{result:?}"
+    );
+    let result = db.get_ex(b"my_key", Some(i64::MAX)).await;
+    assert!(
+        matches!(result, Err(DatabaseError::OutOfRange(_))),
+        "{:?}",
+        result.as_ref().err()
+    );
+    assert_eq!(db.get_ttl(b"my_key").await.unwrap(), -1);
+}
+
+#[apply(test_cases)]
+async fn test_get_ex(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        ttl: 1000,
+        ..StorageValue::string("my_value")
+    };
+    db.set(b"my_key", value).await.unwrap();
+
+    let value = db.get_ex(b"my_key", None).await.unwrap().unwrap();
+    assert_eq!(value.value, b"my_value");
+    let ttl = db.get_ttl(b"my_key").await.unwrap();
+    assert_eq!(ttl, 1000, "TTL is incorrect");
+
+    let value = db.get_ex(b"my_key", Some(2000)).await.unwrap().unwrap();
+    assert_eq!(value.value, b"my_value");
+    assert_eq!(value.ttl, 2000, "TTL is incorrect");
+    let ttl = db.get_ttl(b"my_key").await.unwrap();
+    assert_eq!(ttl, 2000, "TTL is incorrect");
+
+    let value = db.get_ex(b"my_key", Some(-1)).await.unwrap().unwrap();
+    assert_eq!(value.ttl, -1, "TTL is incorrect");
+    let ttl = db.get_ttl(b"my_key").await.unwrap();
+    assert_eq!(ttl, -1, "TTL is incorrect");
+
+    assert!(db.get_ex(b"missing_key", Some(60)).await.unwrap().is_none());
+}
+
+#[apply(test_cases)]
+async fn test_adjust_ttl(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        ttl: 1000,
+        ..StorageValue::string("my_value")
     };
     db.set(b"my_key", value).await.unwrap();
 
+    let ttl = db.adjust_ttl(b"my_key", 60).await.unwrap();
+    assert_eq!(ttl, 1060, "TTL is incorrect");
+    let ttl = db.get_ttl(b"my_key").await.unwrap();
+    assert_eq!(ttl, 1060, "TTL is incorrect");
+
+    let ttl = db.adjust_ttl(b"my_key", -500).await.unwrap();
+    assert_eq!(ttl, 560, "TTL is incorrect");
+    let ttl = db.get_ttl(b"my_key").await.unwrap();
+    assert_eq!(ttl, 560, "TTL is incorrect");
+}
+
+#[apply(test_cases)]
+async fn test_adjust_ttl_past_zero(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        ttl: 100,
+        ..StorageValue::string("my_value")
+    };
+    db.set(b"my_key", value).await.unwrap();
+
+    let ttl = db.adjust_ttl(b"my_key", -200).await.unwrap();
+    assert_eq!(ttl, 0, "TTL is incorrect");
+
+    let value = db.get(b"my_key").await.unwrap();
+    assert!(value.is_none(), "Expected the key to be expired");
+}
+
+#[apply(test_cases)]
+async fn test_adjust_ttl_no_ttl(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let ttl = db.adjust_ttl(b"key1", 60).await.unwrap();
+    assert_eq!(ttl, -1, "TTL is incorrect");
+
+    let ttl = db.adjust_ttl(b"non_existent_key", 60).await;
+    assert!(ttl.is_err(), "Expected error for non-existent key");
+}
+
+#[apply(test_cases)]
+async fn test_set(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue::string("my_value");
+    db.set(b"my_key", value).await.unwrap();
+
     let storage_value = db.get(b"my_key").await.unwrap().unwrap();
     assert_eq!(
         storage_value.value_type,
@@ -149,11 +361,7 @@ async fn test_delete(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = &StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"my_value".to_vec(),
-    };
+    let value = &StorageValue::string("my_value");
     db.set(b"my_key", value).await.unwrap();
     db.delete(b"my_key").await.unwrap();
 
@@ -161,6 +369,127 @@ async fn test_delete(
     assert!(value.is_none());
 }
 
+#[apply(test_cases)]
+async fn test_rename_nx(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let renamed = db.rename_nx(b"key1", b"key3").await.unwrap();
+    assert!(renamed, "Expected the key to be renamed");
+
+    assert!(db.get(b"key1").await.unwrap().is_none());
+    let value = db.get(b"key3").await.unwrap().unwrap();
+    assert_eq!(value.value, b"value1", "Value is incorrect");
+}
+
+#[apply(test_cases)]
+async fn test_rename_nx_existing_target(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let renamed = db.rename_nx(b"key1", b"key2").await.unwrap();
+    assert!(!renamed, "Expected the rename to be refused");
+
+    let value = db.get(b"key1").await.unwrap().unwrap();
+    assert_eq!(value.value, b"value1", "Value is incorrect");
+    let value = db.get(b"key2").await.unwrap().unwrap();
+    assert_eq!(value.value, b"value2", "Value is incorrect");
+
+    let renamed = db.rename_nx(b"non_existent_key", b"key3").await;
+    assert!(renamed.is_err(), "Expected error for non-existent key");
+}
+
+#[apply(test_cases)]
+async fn test_set_nx(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let set = db
+        .set_nx(b"key1", &StorageValue::string("other"))
+        .await
+        .unwrap();
+    assert!(!set, "Expected an existing key to be kept");
+    let value = db.get(b"key1").await.unwrap().unwrap();
+    assert_eq!(value.value, b"value1", "Value is incorrect");
+
+    let set = db
+        .set_nx(b"lock_key", &StorageValue::string("token"))
+        .await
+        .unwrap();
+    assert!(set, "Expected a missing key to be set");
+    let value = db.get(b"lock_key").await.unwrap().unwrap();
+    assert_eq!(value.value, b"token", "Value is incorrect");
+}
+
+#[apply(test_cases)]
+async fn test_set_keep_ttl(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    let expiring = StorageValue {
+        ttl: 100,
+        ..StorageValue::string("first")
+    };
+    db.set(b"session", &expiring).await.unwrap();
+
+    db.set_keep_ttl(b"session", &StorageValue::string("second"))
+        .await
+        .unwrap();
+    let value = db.get(b"session").await.unwrap().unwrap();
+    assert_eq!(value.value, b"second");
+    let ttl = db.get_ttl(b"session").await.unwrap();
+    assert!((99..=100).contains(&ttl), "The expiry was not kept: {ttl}");
+
+    // A plain set replaces the expiry
+    db.set(b"session", &StorageValue::string("third"))
+        .await
+        .unwrap();
+    assert_eq!(db.get_ttl(b"session").await.unwrap(), -1);
+
+    // A missing key takes the TTL of the value
+    let fresh = StorageValue {
+        ttl: 50,
+        ..StorageValue::string("fresh")
+    };
+    db.set_keep_ttl(b"fresh", &fresh).await.unwrap();
+    let ttl = db.get_ttl(b"fresh").await.unwrap();
+    assert!((49..=50).contains(&ttl), "{ttl}");
+}
+
+#[apply(test_cases)]
+async fn test_delete_if_equal(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let deleted = db.delete_if_equal(b"key1", b"value2").await.unwrap();
+    assert!(!deleted, "Expected a different value to be kept");
+    assert!(db.get(b"key1").await.unwrap().is_some());
+
+    let deleted = db.delete_if_equal(b"key1", b"value1").await.unwrap();
+    assert!(deleted, "Expected a matching value to be deleted");
+    assert!(db.get(b"key1").await.unwrap().is_none());
+
+    let deleted = db
+        .delete_if_equal(b"non_existent_key", b"value1")
+        .await
+        .unwrap();
+    assert!(!deleted, "Expected nothing to delete");
+}
+
 #[apply(test_cases)]
 async fn test_delete_prefix(
     #[future]
@@ -177,6 +506,27 @@ async fn test_delete_prefix(
     assert!(keys.contains(&String::from("key2")));
 }
 
+#[apply(test_cases)]
+async fn test_delete_expired(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        ttl: 1,
+        ..StorageValue::string("my_value")
+    };
+    db.set(b"short_lived", value).await.unwrap();
+    assert!(db.delete_expired().await.unwrap().is_empty());
+
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert_eq!(db.delete_expired().await.unwrap(), vec!["short_lived"]);
+    assert!(db.delete_expired().await.unwrap().is_empty());
+    assert_eq!(db.get_all_keys(b"").await.unwrap().len(), 5);
+}
+
 #[apply(test_cases)]
 async fn test_ttl(
     #[future]
@@ -187,9 +537,8 @@ async fn test_ttl(
 
     let ttl = 1;
     let value = &StorageValue {
-        value_type: ValueType::String,
         ttl,
-        value: b"my_value".to_vec(),
+        ..StorageValue::string("my_value")
     };
     db.set(b"my_key", value).await.unwrap();
 
@@ -219,6 +568,7 @@ async fn test_integer_value(
         value_type: ValueType::Integer,
         ttl: -1,
         value: b"123".to_vec(),
+        mtime: 0,
     };
     db.set(b"my_key", value).await.unwrap();
 
@@ -232,6 +582,719 @@ async fn test_integer_value(
     assert_eq!(value.ttl, -1, "TTL is incorrect");
 }
 
+#[apply(test_cases)]
+async fn test_json_value(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        value_type: ValueType::Json,
+        ttl: -1,
+        value: br#"{"a":{"b":1}}"#.to_vec(),
+        mtime: 0,
+    };
+    db.set(b"my_key", value).await.unwrap();
+
+    let value = db.get(b"my_key").await.unwrap().unwrap();
+    assert_eq!(value.value_type, ValueType::Json, "Value type is incorrect");
+    assert_eq!(
+        value.get_json_value().unwrap(),
+        serde_json::json!({ "a": { "b": 1 } }),
+        "Value is incorrect"
+    );
+
+    let value = &StorageValue {
+        value_type: ValueType::Json,
+        ttl: -1,
+        value: b"{not json".to_vec(),
+        mtime: 0,
+    };
+    let result = db.set(b"invalid_json", value).await;
+    assert!(result.is_err(), "Expected error for invalid JSON");
+    assert!(db.get(b"invalid_json").await.unwrap().is_none());
+}
+
+#[apply(test_cases)]
+async fn test_json_merge(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        value_type: ValueType::Json,
+        ttl: -1,
+        value: br#"{"a":1,"b":{"c":2,"d":3}}"#.to_vec(),
+        mtime: 0,
+    };
+    db.set(b"my_key", value).await.unwrap();
+
+    db.json_merge(
+        b"my_key",
+        &serde_json::json!({ "b": { "d": null, "e": 4 } }),
+    )
+    .await
+    .unwrap();
+    let value = db.get(b"my_key").await.unwrap().unwrap();
+    assert_eq!(
+        value.get_json_value().unwrap(),
+        serde_json::json!({ "a": 1, "b": { "c": 2, "e": 4 } }),
+        "Value is incorrect"
+    );
+
+    let value = db
+        .json_merge(b"new_key", &serde_json::json!({ "a": 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        value.get_json_value().unwrap(),
+        serde_json::json!({ "a": 1 })
+    );
+
+    let result = db.json_merge(b"key1", &serde_json::json!({ "a": 1 })).await;
+    assert!(result.is_err(), "Expected error for non-JSON value");
+}
+
+#[tokio::test]
+async fn test_keys_modified_since() {
+    let clock = std::sync::Arc::new(MockClock::new(1_700_000_000));
+    let backends: Vec<Box<dyn Storage>> = vec![
+        Box::new(Rocksdb::open_ephemeral().unwrap().with_clock(clock.clone())),
+        Box::new(Bredis::open().with_clock(clock.clone())),
+        Box::new(SurrealKV::open().with_clock(clock.clone())),
+    ];
+
+    let value = StorageValue::string("early");
+    for db in &backends {
+        db.set(b"early_key", &value).await.unwrap();
+        db.increment(b"value_num", 1, Some(0), 10).await.unwrap();
+    }
+
+    let since = clock.now_millis();
+    clock.advance_millis(1);
+    for db in &backends {
+        db.set(b"late_key", &value).await.unwrap();
+        db.increment(b"value_num", 1, None, 10).await.unwrap();
+
+        let mut keys = db.keys_modified_since(since).await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["late_key", "value_num"]);
+
+        let value = db.get(b"late_key").await.unwrap().unwrap();
+        assert_eq!(value.mtime, since + 1);
+    }
+}
+
+#[tokio::test]
+async fn test_get_record() {
+    let clock = std::sync::Arc::new(MockClock::new(1_700_000_000));
+    let backends: Vec<Box<dyn Storage>> = vec![
+        Box::new(Rocksdb::open_ephemeral().unwrap().with_clock(clock.clone())),
+        Box::new(Bredis::open().with_clock(clock.clone())),
+        Box::new(SurrealKV::open().with_clock(clock.clone())),
+    ];
+
+    let value = &StorageValue {
+        ttl: 10,
+        ..StorageValue::string("my_value")
+    };
+    for db in &backends {
+        db.set(b"my_key", value).await.unwrap();
+        // The record keeps the absolute expiry
+        let record = db.get_record(b"my_key").await.unwrap().unwrap();
+        let stored = StorageValue::from_binary(&record);
+        assert_eq!(stored.ttl, 1_700_000_010);
+        assert_eq!(stored.value, b"my_value");
+        assert!(db.get_record(b"missing").await.unwrap().is_none());
+    }
+
+    clock.advance(11);
+    for db in &backends {
+        assert!(db.get_record(b"my_key").await.unwrap().is_none());
+    }
+}
+
+#[apply(test_cases)]
+async fn test_range(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    // The fixture holds key1, key2, prefix_key1, prefix_key2 and value_num
+    let keys = db.range(b"key1", b"prefix_key2", None).await.unwrap();
+    assert_eq!(keys, vec!["key1", "key2", "prefix_key1"]);
+
+    let keys = db.range(b"key1", b"z", Some(2)).await.unwrap();
+    assert_eq!(keys, vec!["key1", "key2"]);
+
+    let keys = db.range(b"key2", b"key1", None).await.unwrap();
+    assert!(keys.is_empty());
+
+    let value = StorageValue {
+        ttl: 0,
+        ..StorageValue::string("expired")
+    };
+    db.set(b"key3", &value).await.unwrap();
+    let keys = db.range(b"key", b"kez", None).await.unwrap();
+    assert_eq!(keys, vec!["key1", "key2"]);
+}
+
+#[apply(test_cases)]
+async fn test_list_push(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let values: Vec<String> = (1..=5).map(|i| format!("event{i}")).collect();
+    let length = db
+        .list_push(b"events", &values[..2], Some(3), None)
+        .await
+        .unwrap();
+    assert_eq!(length, 2);
+    let length = db
+        .list_push(b"events", &values[2..], Some(3), None)
+        .await
+        .unwrap();
+    assert_eq!(length, 3);
+
+    let list = db.get(b"events").await.unwrap().unwrap();
+    assert_eq!(list.get_list_value().unwrap(), values[2..]);
+
+    let length = db
+        .list_push(b"events", &values[..1], None, None)
+        .await
+        .unwrap();
+    assert_eq!(length, 4);
+
+    let result = db.list_push(b"key1", &values, None, None).await;
+    assert!(result.is_err(), "Pushing onto a string must fail");
+}
+
+#[apply(test_cases)]
+async fn test_list_push_max_elements(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+
+    let values: Vec<String> = (1..=4).map(|i| format!("event{i}")).collect();
+    let length = db
+        .list_push(b"events", &values[..2], None, Some(3))
+        .await
+        .unwrap();
+    assert_eq!(length, 2);
+
+    // The push is rejected as a whole and the list is left unchanged
+    let result = db.list_push(b"events", &values[2..], None, Some(3)).await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    let list = db.get(b"events").await.unwrap().unwrap();
+    assert_eq!(list.get_list_value().unwrap(), values[..2]);
+
+    // Trimming to `maxlen` happens first, so a capped stream stays within the limit
+    let length = db
+        .list_push(b"events", &values[2..], Some(3), Some(3))
+        .await
+        .unwrap();
+    assert_eq!(length, 3);
+
+    // A rejected push doesn't create the list
+    let result = db.list_push(b"new_events", &values, None, Some(3)).await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    assert!(db.get(b"new_events").await.unwrap().is_none());
+}
+
+#[apply(test_cases)]
+async fn test_set_many(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+
+    let entries = vec![
+        (
+            b"batch1".to_vec(),
+            StorageValue {
+                ttl: 100,
+                ..StorageValue::string("one")
+            },
+        ),
+        (b"batch2".to_vec(), StorageValue::integer(2)),
+    ];
+    db.set_many(&entries).await.unwrap();
+
+    let value = db.get(b"batch1").await.unwrap().unwrap();
+    assert_eq!(value.value, b"one");
+    let ttl = db.get_ttl(b"batch1").await.unwrap();
+    assert!((99..=100).contains(&ttl), "{ttl}");
+    let value = db.get(b"batch2").await.unwrap().unwrap();
+    assert_eq!(value.value_type, ValueType::Integer);
+    assert_eq!(value.value, StorageValue::integer(2).value);
+    assert_eq!(db.get_ttl(b"batch2").await.unwrap(), -1);
+
+    // An invalid value rejects the whole batch
+    let entries = vec![
+        (b"batch3".to_vec(), StorageValue::string("three")),
+        (
+            b"batch4".to_vec(),
+            StorageValue {
+                value_type: ValueType::Json,
+                ttl: -1,
+                value: b"{not json".to_vec(),
+                mtime: 0,
+            },
+        ),
+    ];
+    let result = db.set_many(&entries).await;
+    assert!(matches!(result, Err(DatabaseError::InvalidJson(_))));
+    assert!(db.get(b"batch3").await.unwrap().is_none());
+}
+
+#[apply(test_cases)]
+async fn test_append(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+
+    let length = db.append(b"log", "login;", None).await.unwrap();
+    assert_eq!(length, 6);
+    assert_eq!(db.get_ttl(b"log").await.unwrap(), -1);
+
+    let length = db.append(b"log", "logout;", Some(100)).await.unwrap();
+    assert_eq!(length, 13);
+    let log = db.get(b"log").await.unwrap().unwrap();
+    assert_eq!(log.value, b"login;logout;");
+    let ttl = db.get_ttl(b"log").await.unwrap();
+    assert!((99..=100).contains(&ttl), "{ttl}");
+
+    // Without a TTL the expiry is kept
+    db.append(b"log", "login;", None).await.unwrap();
+    let ttl = db.get_ttl(b"log").await.unwrap();
+    assert!((99..=100).contains(&ttl), "{ttl}");
+
+    // Appending to another type changes neither the value nor its TTL
+    db.list_push(b"events", &["a".to_string()], None, None)
+        .await
+        .unwrap();
+    let result = db.append(b"events", "b", Some(100)).await;
+    assert!(matches!(result, Err(DatabaseError::InvalidValueType(_))));
+    assert_eq!(db.get_ttl(b"events").await.unwrap(), -1);
+}
+
+#[apply(test_cases)]
+async fn test_hset_many(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+
+    let fields = HashMap::from([
+        ("name".to_string(), b"bredis".to_vec()),
+        ("lang".to_string(), b"rust".to_vec()),
+    ]);
+    assert_eq!(
+        db.hset_many(b"user:1", &fields, None).await.unwrap(),
+        (2, 0)
+    );
+
+    let fields = HashMap::from([
+        ("lang".to_string(), b"Rust".to_vec()),
+        ("stars".to_string(), b"42".to_vec()),
+    ]);
+    assert_eq!(
+        db.hset_many(b"user:1", &fields, None).await.unwrap(),
+        (1, 1)
+    );
+
+    let hash = db.get(b"user:1").await.unwrap().unwrap();
+    assert_eq!(hash.value_type, ValueType::Hash);
+    assert_eq!(
+        hash.get_hash_value().unwrap(),
+        BTreeMap::from([
+            ("lang".to_string(), b"Rust".to_vec()),
+            ("name".to_string(), b"bredis".to_vec()),
+            ("stars".to_string(), b"42".to_vec()),
+        ])
+    );
+
+    // A key of another type is left unchanged
+    db.set(b"name", &StorageValue::string("value"))
+        .await
+        .unwrap();
+    let result = db.hset_many(b"name", &fields, None).await;
+    assert!(matches!(result, Err(DatabaseError::InvalidValueType(_))));
+    assert_eq!(db.get(b"name").await.unwrap().unwrap().value, b"value");
+}
+
+#[apply(test_cases)]
+async fn test_hset_many_max_elements(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+
+    let fields = HashMap::from([
+        ("name".to_string(), b"bredis".to_vec()),
+        ("lang".to_string(), b"rust".to_vec()),
+    ]);
+    assert_eq!(
+        db.hset_many(b"user:1", &fields, Some(3)).await.unwrap(),
+        (2, 0)
+    );
+
+    // The write is rejected as a whole and the hash is left unchanged
+    let more_fields = HashMap::from([
+        ("stars".to_string(), b"42".to_vec()),
+        ("forks".to_string(), b"7".to_vec()),
+    ]);
+    let result = db.hset_many(b"user:1", &more_fields, Some(3)).await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    let hash = db.get(b"user:1").await.unwrap().unwrap();
+    assert_eq!(
+        hash.get_hash_value().unwrap(),
+        fields.into_iter().collect::<BTreeMap<_, _>>()
+    );
+
+    // Updating existing fields doesn't grow the hash
+    let updated_fields = HashMap::from([("lang".to_string(), b"Rust".to_vec())]);
+    assert_eq!(
+        db.hset_many(b"user:1", &updated_fields, Some(2))
+            .await
+            .unwrap(),
+        (0, 1)
+    );
+
+    // A rejected write doesn't create the hash
+    let result = db.hset_many(b"user:2", &more_fields, Some(1)).await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    assert!(db.get(b"user:2").await.unwrap().is_none());
+}
+
+#[apply(test_cases)]
+async fn test_increment_many(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    let new_values = db
+        .increment_many(&[(b"value_num".to_vec(), 2), (b"new_num".to_vec(), 5)])
+        .await
+        .unwrap();
+    assert_eq!(new_values, vec![3, 5]);
+
+    // A string in the batch rejects it as a whole
+    let result = db
+        .increment_many(&[(b"value_num".to_vec(), 1), (b"key1".to_vec(), 1)])
+        .await;
+    assert!(matches!(result, Err(DatabaseError::InvalidValueType(_))));
+    let value = db.get(b"value_num").await.unwrap().unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 3);
+
+    // An overflow rejects the batch instead of wrapping around
+    db.increment(b"max_num", i64::MAX, Some(0), 10)
+        .await
+        .unwrap();
+    let result = db
+        .increment_many(&[(b"value_num".to_vec(), 1), (b"max_num".to_vec(), 1)])
+        .await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    let result = db.increment(b"max_num", 1, Some(0), 10).await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    let value = db.get(b"max_num").await.unwrap().unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), i64::MAX);
+    let value = db.get(b"value_num").await.unwrap().unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 3);
+}
+
+#[apply(test_cases)]
+async fn test_decrement_and_delete(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    db.increment(b"refs", 2, Some(0), 10).await.unwrap();
+    assert_eq!(
+        db.decrement_and_delete(b"refs", 1).await.unwrap(),
+        (1, false)
+    );
+    assert_eq!(
+        db.get(b"refs")
+            .await
+            .unwrap()
+            .unwrap()
+            .get_integer_value()
+            .unwrap(),
+        1
+    );
+
+    assert_eq!(
+        db.decrement_and_delete(b"refs", 1).await.unwrap(),
+        (0, true)
+    );
+    assert!(db.get(b"refs").await.unwrap().is_none());
+
+    let result = db.decrement_and_delete(b"refs", 1).await;
+    assert!(matches!(result, Err(DatabaseError::ValueNotFound(_))));
+    let result = db.decrement_and_delete(b"key1", 1).await;
+    assert!(result.is_err(), "Decrementing a string must fail");
+    assert!(db.get(b"key1").await.unwrap().is_some());
+}
+
+#[apply(test_cases)]
+async fn test_set_extreme(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+
+    // A missing key is set to the value
+    assert_eq!(
+        db.set_extreme(b"peak", 100, Extreme::Max).await.unwrap(),
+        (100, true)
+    );
+    assert_eq!(
+        db.set_extreme(b"peak", 250, Extreme::Max).await.unwrap(),
+        (250, true)
+    );
+    let peak = db.get(b"peak").await.unwrap().unwrap();
+    assert_eq!(peak.get_integer_value().unwrap(), 250);
+    assert_eq!(
+        db.set_extreme(b"peak", 120, Extreme::Max).await.unwrap(),
+        (250, false)
+    );
+    let peak = db.get(b"peak").await.unwrap().unwrap();
+    assert_eq!(peak.get_integer_value().unwrap(), 250);
+
+    assert_eq!(
+        db.set_extreme(b"peak", 300, Extreme::Min).await.unwrap(),
+        (250, false)
+    );
+    assert_eq!(
+        db.set_extreme(b"peak", -5, Extreme::Min).await.unwrap(),
+        (-5, true)
+    );
+    let peak = db.get(b"peak").await.unwrap().unwrap();
+    assert_eq!(peak.get_integer_value().unwrap(), -5);
+
+    let result = db.set_extreme(b"key1", 1, Extreme::Max).await;
+    assert!(matches!(result, Err(DatabaseError::InvalidValueType(_))));
+}
+
+#[tokio::test]
+async fn test_get_many() {
+    let clock = std::sync::Arc::new(MockClock::new(1_700_000_000));
+    let backends: Vec<Box<dyn Storage>> = vec![
+        Box::new(Rocksdb::open_ephemeral().unwrap().with_clock(clock.clone())),
+        Box::new(Bredis::open().with_clock(clock.clone())),
+        Box::new(SurrealKV::open().with_clock(clock.clone())),
+    ];
+
+    let expiring = StorageValue {
+        ttl: 1,
+        ..StorageValue::string("gone")
+    };
+    let kept = StorageValue {
+        ttl: 100,
+        ..StorageValue::string("kept")
+    };
+    for db in &backends {
+        db.set(b"key2", &StorageValue::string("value2"))
+            .await
+            .unwrap();
+        db.set(b"expiring", &expiring).await.unwrap();
+        db.set(b"kept", &kept).await.unwrap();
+    }
+    clock.advance(2);
+
+    let keys = [
+        b"key2".to_vec(),
+        b"missing".to_vec(),
+        b"expiring".to_vec(),
+        b"kept".to_vec(),
+    ];
+    for db in &backends {
+        let values = db.get_many(&keys).await.unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[0].as_ref().unwrap().value, b"value2");
+        assert!(values[1].is_none());
+        assert!(values[2].is_none());
+        let kept = values[3].as_ref().unwrap();
+        assert_eq!(kept.value, b"kept");
+        assert_eq!(kept.ttl, 98);
+
+        // Expired values are left for the TTL cleanup
+        assert_eq!(db.delete_expired().await.unwrap(), vec!["expiring"]);
+    }
+}
+
+#[apply(test_cases)]
+async fn test_compare_and_increment(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    db.set(b"version", &StorageValue::integer(5)).await.unwrap();
+
+    assert_eq!(
+        db.compare_and_increment(b"version", 5, 1).await.unwrap(),
+        (6, true)
+    );
+    // A stale expected value leaves the key alone
+    assert_eq!(
+        db.compare_and_increment(b"version", 5, 1).await.unwrap(),
+        (6, false)
+    );
+    let version = db.get(b"version").await.unwrap().unwrap();
+    assert_eq!(version.get_integer_value().unwrap(), 6);
+
+    let result = db.compare_and_increment(b"version", 6, i64::MAX).await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    let result = db.compare_and_increment(b"missing", 0, 1).await;
+    assert!(matches!(result, Err(DatabaseError::ValueNotFound(_))));
+    let result = db.compare_and_increment(b"key1", 0, 1).await;
+    assert!(matches!(result, Err(DatabaseError::InvalidValueType(_))));
+}
+
+#[apply(test_cases)]
+async fn test_increment_bounded(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    let (value, capped) = db
+        .increment_bounded(b"value_num", 5, None, 10, false, 10)
+        .await
+        .unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 6);
+    assert!(!capped);
+
+    // Without saturation the increment is rejected and nothing is written
+    let result = db
+        .increment_bounded(b"value_num", 5, None, 10, false, 10)
+        .await;
+    assert!(matches!(result, Err(DatabaseError::OutOfRange(_))));
+    let value = db.get(b"value_num").await.unwrap().unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 6);
+
+    let (value, capped) = db
+        .increment_bounded(b"value_num", 5, None, 10, true, 10)
+        .await
+        .unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 10);
+    assert!(capped);
+
+    let (value, capped) = db
+        .increment_bounded(b"new_num", 20, Some(0), 10, true, 10)
+        .await
+        .unwrap();
+    assert_eq!(value.get_integer_value().unwrap(), 10);
+    assert!(capped);
+}
+
+#[apply(test_cases)]
+async fn test_sync(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    db.set(b"durable", &StorageValue::string("value"))
+        .await
+        .unwrap();
+    db.sync().await.unwrap();
+    assert!(db.get(b"durable").await.unwrap().is_some());
+}
+
+#[apply(test_cases)]
+async fn test_flush_memtables(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    db.set(b"flushed", &StorageValue::string("value"))
+        .await
+        .unwrap();
+    db.flush_memtables().await.unwrap();
+    let value = db.get(b"flushed").await.unwrap().unwrap();
+    assert_eq!(value.value, b"value");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_rocksdb_checkpoint(
+    #[future]
+    #[from(rocksdb)]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    let checkpoint_path = TempPath::new("test_checkpoint");
+    db.checkpoint(&checkpoint_path).await.unwrap();
+
+    // Written after the checkpoint, so it must not be in the copy
+    let value = StorageValue::string("late");
+    db.set(b"late_key", &value).await.unwrap();
+
+    let copy = Rocksdb::open(&checkpoint_path).unwrap();
+    let value = copy.get(b"key1").await.unwrap().unwrap();
+    assert_eq!(value.value, b"value1");
+    assert!(copy.get(b"prefix_key2").await.unwrap().is_some());
+    assert!(copy.get(b"late_key").await.unwrap().is_none());
+
+    copy.destroy().unwrap();
+}
+
+#[tokio::test]
+async fn test_rocksdb_sync_writes() {
+    let db = Rocksdb::open_ephemeral().unwrap().with_sync_writes(true);
+
+    let value = StorageValue::string("durable");
+    db.set(b"synced", &value).await.unwrap();
+    db.increment_many(&[(b"synced_num".to_vec(), 2)])
+        .await
+        .unwrap();
+    db.delete(b"synced").await.unwrap();
+    db.delete_prefix(b"synced_").await.unwrap();
+
+    assert!(db.get(b"synced").await.unwrap().is_none());
+    assert!(db.get(b"synced_num").await.unwrap().is_none());
+}
+
+#[rstest]
+#[case::bredis(async { bredis().await })]
+#[case::surrealkv(async { surrealkv().await })]
+#[tokio::test]
+async fn test_checkpoint_unsupported(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await;
+    assert!(!db.capabilities().checkpoint);
+    let result = db
+        .checkpoint(&TempPath::new("test_checkpoint_unsupported"))
+        .await;
+    assert!(matches!(result, Err(DatabaseError::Unsupported(_))));
+}
+
 #[apply(test_cases)]
 async fn test_get_integer_value(
     #[future]
@@ -244,6 +1307,7 @@ async fn test_get_integer_value(
         value_type: ValueType::Integer,
         ttl: -1,
         value: b"123".to_vec(),
+        mtime: 0,
     };
     db.set(b"my_key", value).await.unwrap();
 
@@ -260,13 +1324,58 @@ async fn test_increment(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = db.increment(b"value_num", 1, None).await.unwrap();
+    let value = db.increment(b"value_num", 1, None, 10).await.unwrap();
     assert_eq!(value.value, b"2", "Value is incorrect");
 
-    let value = db.increment(b"value_num", 2, None).await.unwrap();
+    let value = db.increment(b"value_num", 2, None, 10).await.unwrap();
     assert_eq!(value.value, b"4", "Value is incorrect");
 }
 
+#[apply(test_cases)]
+async fn test_increment_missing_key(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let result = db.increment(b"missing_num", 1, None, 10).await;
+    assert!(result.is_err(), "Expected error for missing key");
+
+    let result = db.decrement(b"missing_num", 1, None, 10).await;
+    assert!(result.is_err(), "Expected error for missing key");
+}
+
+#[apply(test_cases)]
+async fn test_increment_hex(
+    #[future]
+    #[case]
+    db: Box<impl Storage>,
+) {
+    let db = db.await; // Await the future to get the actual storage instance
+
+    let value = &StorageValue {
+        value_type: ValueType::Integer,
+        ttl: -1,
+        value: b"ff".to_vec(),
+        mtime: 0,
+    };
+    db.set(b"hex_num", value).await.unwrap();
+
+    let value = db.increment(b"hex_num", 1, None, 16).await.unwrap();
+    assert_eq!(value.value, b"100", "Value is incorrect");
+
+    let value = db.get(b"hex_num").await.unwrap().unwrap();
+    assert_eq!(value.value, b"100");
+    assert_eq!(value.get_integer_value_in_base(16).unwrap(), 256);
+
+    let value = db.decrement(b"new_hex_num", 1, Some(0), 16).await.unwrap();
+    assert_eq!(value.value, b"-1", "Value is incorrect");
+
+    let result = db.increment(b"hex_num", 1, None, 8).await;
+    assert!(result.is_err(), "Expected error for unsupported base");
+}
+
 #[apply(test_cases)]
 async fn test_default_increment(
     #[future]
@@ -275,10 +1384,10 @@ async fn test_default_increment(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = db.increment(b"value_num", 1, Some(10)).await.unwrap();
+    let value = db.increment(b"value_num", 1, Some(10), 10).await.unwrap();
     assert_eq!(value.value, b"2", "Value is incorrect");
 
-    let value = db.increment(b"value_num", 2, Some(10)).await.unwrap();
+    let value = db.increment(b"value_num", 2, Some(10), 10).await.unwrap();
     assert_eq!(value.value, b"4", "Value is incorrect");
 }
 
@@ -290,10 +1399,10 @@ async fn test_default_exist_increment(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = db.increment(b"value_num", 1, Some(10)).await.unwrap();
+    let value = db.increment(b"value_num", 1, Some(10), 10).await.unwrap();
     assert_eq!(value.value, b"2", "Value is incorrect");
 
-    let value = db.increment(b"value_num", 2, Some(10)).await.unwrap();
+    let value = db.increment(b"value_num", 2, Some(10), 10).await.unwrap();
     assert_eq!(value.value, b"4", "Value is incorrect");
 }
 
@@ -305,10 +1414,10 @@ async fn test_decrement(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = db.decrement(b"value_num", 1, None).await.unwrap();
+    let value = db.decrement(b"value_num", 1, None, 10).await.unwrap();
     assert_eq!(value.value, b"0", "Value is incorrect");
 
-    let value = db.decrement(b"value_num", 2, None).await.unwrap();
+    let value = db.decrement(b"value_num", 2, None, 10).await.unwrap();
     assert_eq!(value.value, b"-2", "Value is incorrect");
 }
 
@@ -320,10 +1429,16 @@ async fn test_default_decrement(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = db.decrement(b"new_value_num", 1, Some(10)).await.unwrap();
+    let value = db
+        .decrement(b"new_value_num", 1, Some(10), 10)
+        .await
+        .unwrap();
     assert_eq!(value.value, b"9", "Value is incorrect");
 
-    let value = db.decrement(b"new_value_num", 2, Some(10)).await.unwrap();
+    let value = db
+        .decrement(b"new_value_num", 2, Some(10), 10)
+        .await
+        .unwrap();
     assert_eq!(value.value, b"7", "Value is incorrect");
 }
 
@@ -335,10 +1450,10 @@ async fn test_default_exist_decrement(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = db.decrement(b"value_num", 1, Some(10)).await.unwrap();
+    let value = db.decrement(b"value_num", 1, Some(10), 10).await.unwrap();
     assert_eq!(value.value, b"0", "Value is incorrect");
 
-    let value = db.decrement(b"value_num", 2, Some(10)).await.unwrap();
+    let value = db.decrement(b"value_num", 2, Some(10), 10).await.unwrap();
     assert_eq!(value.value, b"-2", "Value is incorrect");
 }
 
@@ -350,11 +1465,7 @@ async fn test_string_value(
 ) {
     let db = db.await; // Await the future to get the actual storage instance
 
-    let value = &StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"my_value".to_vec(),
-    };
+    let value = &StorageValue::string("my_value");
     db.set(b"my_key", value).await.unwrap();
 
     let value = db.get(b"my_key").await.unwrap().unwrap();
@@ -369,14 +1480,9 @@ async fn test_string_value(
 
 #[fixture]
 async fn rocksdb() -> Box<impl Storage> {
-    let db_path = format!("/dev/shm/test_db_{}", rand::random::<i32>());
-    let db = Rocksdb::open(db_path.as_str()).unwrap();
+    let db = Rocksdb::open_ephemeral().unwrap();
 
-    let value = &mut StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"value1".to_vec(),
-    };
+    let value = &mut StorageValue::string("value1");
     db.set(b"key1", value).await.unwrap();
 
     value.value = b"value2".to_vec();
@@ -392,6 +1498,7 @@ async fn rocksdb() -> Box<impl Storage> {
         value_type: ValueType::Integer,
         ttl: -1,
         value: b"1".to_vec(),
+        mtime: 0,
     };
     db.set(b"value_num", value).await.unwrap();
 
@@ -401,11 +1508,7 @@ async fn rocksdb() -> Box<impl Storage> {
 #[fixture]
 async fn bredis() -> Box<impl Storage> {
     let db = Bredis::open();
-    let value = &mut StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"value1".to_vec(),
-    };
+    let value = &mut StorageValue::string("value1");
     db.set(b"key1", value).await.unwrap();
 
     value.value = b"value2".to_vec();
@@ -421,6 +1524,7 @@ async fn bredis() -> Box<impl Storage> {
         value_type: ValueType::Integer,
         ttl: -1,
         value: b"1".to_vec(),
+        mtime: 0,
     };
     db.set(b"value_num", value).await.unwrap();
 
@@ -430,11 +1534,7 @@ async fn bredis() -> Box<impl Storage> {
 #[fixture]
 async fn surrealkv() -> Box<impl Storage> {
     let db = SurrealKV::open();
-    let value = &mut StorageValue {
-        value_type: ValueType::String,
-        ttl: -1,
-        value: b"value1".to_vec(),
-    };
+    let value = &mut StorageValue::string("value1");
     db.set(b"key1", value).await.unwrap();
 
     value.value = b"value2".to_vec();
@@ -450,8 +1550,41 @@ async fn surrealkv() -> Box<impl Storage> {
         value_type: ValueType::Integer,
         ttl: -1,
         value: b"1".to_vec(),
+        mtime: 0,
     };
     db.set(b"value_num", value).await.unwrap();
 
     return Box::new(db);
 }
+
+#[tokio::test]
+async fn test_get_no_reap() {
+    let clock = std::sync::Arc::new(MockClock::new(1_700_000_000));
+    let backends: Vec<Box<dyn Storage>> = vec![
+        Box::new(Rocksdb::open_ephemeral().unwrap().with_clock(clock.clone())),
+        Box::new(Bredis::open().with_clock(clock.clone())),
+        Box::new(SurrealKV::open().with_clock(clock.clone())),
+    ];
+
+    let value = &StorageValue {
+        ttl: 10,
+        ..StorageValue::string("my_value")
+    };
+    for db in &backends {
+        db.set(b"my_key", value).await.unwrap();
+        let read = db.get_no_reap(b"my_key").await.unwrap().unwrap();
+        assert_eq!(read.ttl, 10);
+    }
+
+    clock.advance(11);
+    for db in &backends {
+        assert!(db.get_no_reap(b"my_key").await.unwrap().is_none());
+    }
+
+    // The expired record is still stored: it is readable again once the clock goes back
+    clock.advance(-2);
+    for db in &backends {
+        assert!(db.get_no_reap(b"my_key").await.unwrap().is_some());
+        assert!(db.get(b"my_key").await.unwrap().is_some());
+    }
+}
@@ -1,7 +1,109 @@
-use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::Future,
+    str::FromStr,
+};
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use utoipa::ToSchema;
 
 use crate::errors::DatabaseError;
 
+use super::json;
+
+/// A value as clients send and receive it: an integer, a string or a JSON document
+///
+/// Only integers, strings, objects and arrays are read; booleans, nulls and floats are
+/// rejected with `INVALID_VALUE` rather than stored as one-value JSON documents.
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(untagged)]
+pub enum IntOrString {
+    Int(i64),
+    String(String),
+    #[schema(value_type = Object)]
+    Json(serde_json::Value),
+}
+
+/// The error of a value that is not an integer, a string or a JSON document
+pub const INVALID_VALUE: &str = "value must be an integer or string, or a JSON object or array";
+
+impl<'de> Deserialize<'de> for IntOrString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        return deserializer.deserialize_any(IntOrStringVisitor);
+    }
+}
+
+/// Reads an `IntOrString` from the type the body names, in any of the request formats
+struct IntOrStringVisitor;
+
+impl<'de> Visitor<'de> for IntOrStringVisitor {
+    type Value = IntOrString;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return formatter.write_str(INVALID_VALUE);
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        return Ok(IntOrString::Int(value));
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        return i64::try_from(value)
+            .map(IntOrString::Int)
+            .map_err(|_| E::custom(format!("integer {value} is out of range")));
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<Self::Value, E> {
+        return i64::try_from(value)
+            .map(IntOrString::Int)
+            .map_err(|_| E::custom(format!("integer {value} is out of range")));
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Self::Value, E> {
+        return i64::try_from(value)
+            .map(IntOrString::Int)
+            .map_err(|_| E::custom(format!("integer {value} is out of range")));
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        return Ok(IntOrString::String(value.to_string()));
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        return Ok(IntOrString::String(value));
+    }
+
+    fn visit_bool<E: de::Error>(self, _value: bool) -> Result<Self::Value, E> {
+        return Err(E::custom(INVALID_VALUE));
+    }
+
+    fn visit_f64<E: de::Error>(self, _value: f64) -> Result<Self::Value, E> {
+        return Err(E::custom(INVALID_VALUE));
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        return Err(E::custom(INVALID_VALUE));
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        return Err(E::custom(INVALID_VALUE));
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        return serde_json::Value::deserialize(de::value::MapAccessDeserializer::new(map))
+            .map(IntOrString::Json);
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        return serde_json::Value::deserialize(de::value::SeqAccessDeserializer::new(seq))
+            .map(IntOrString::Json);
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 /// A struct to represent a value in the database
 /// This struct is used to store the value type and the time-to-live (TTL) for the value
@@ -9,11 +111,12 @@ use crate::errors::DatabaseError;
 /// The struct can be serialized and deserialized to/from a binary representation
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// let storage_value = StorageValue {
 ///   value_type: ValueType::String,
 ///   ttl: 1000,
 ///   value: b"my_value".to_vec(),
+///   mtime: 0,
 /// };
 /// let binary = storage_value.to_binary();
 /// let storage_value = StorageValue::from_binary(&binary);
@@ -23,11 +126,22 @@ use crate::errors::DatabaseError;
 /// * `value_type` - The type of the value
 /// * `ttl` - The time-to-live (TTL) for the value
 /// * `value` - The value as a byte array
+/// * `mtime` - The time of the last write in milliseconds since the Unix epoch,
+///   stamped by the storage on every write
 #[derive(Clone, Serialize, Deserialize)]
 pub struct StorageValue {
     pub value_type: ValueType,
     pub ttl: i64,
     pub value: Vec<u8>,
+    pub mtime: i64,
+}
+
+/// The binary layout of `StorageValue` written before `mtime` was added
+#[derive(Deserialize)]
+struct LegacyStorageValue {
+    value_type: ValueType,
+    ttl: i64,
+    value: Vec<u8>,
 }
 
 impl StorageValue {
@@ -44,7 +158,321 @@ impl StorageValue {
     /// # Returns
     /// The `StorageValue` instance
     pub fn from_binary(data: &[u8]) -> Self {
-        return bincode::deserialize(data).unwrap();
+        return Self::try_from_binary(data).unwrap();
+    }
+
+    /// Create a new `StorageValue` instance from a binary representation without panicking
+    /// # Arguments
+    /// * `data` - The binary representation of the `StorageValue`
+    /// # Returns
+    /// Result containing the `StorageValue` instance or an error if the data is corrupt
+    pub fn try_from_binary(data: &[u8]) -> Result<Self, DatabaseError> {
+        let value = bincode::deserialize(data).or_else(|err| {
+            // Records written before `mtime` was added have never been modified since
+            let legacy: LegacyStorageValue = bincode::deserialize(data).map_err(|_| err)?;
+            Ok::<_, bincode::Error>(Self {
+                value_type: legacy.value_type,
+                ttl: legacy.ttl,
+                value: legacy.value,
+                mtime: 0,
+            })
+        });
+        return value.map_err(|err| DatabaseError::InternalError(format!("Corrupt value: {err}")));
+    }
+
+    /// Stamp the current time as the time of the last write
+    ///
+    /// # Arguments
+    /// * `now_millis` - The current time of the backend clock, in milliseconds since the Unix epoch
+    pub const fn touch(&mut self, now_millis: i64) {
+        self.mtime = now_millis;
+    }
+
+    /// Check whether the value was written after the specified time
+    /// # Arguments
+    /// * `since` - The time in milliseconds since the Unix epoch
+    pub const fn is_modified_since(&self, since: i64) -> bool {
+        return self.mtime > since;
+    }
+
+    /// Check whether a stored value has expired
+    ///
+    /// # Arguments
+    /// * `now` - The current unix timestamp
+    ///
+    /// # Returns
+    /// `true` if the value has a TTL and its expiry timestamp is not in the future
+    pub const fn is_expired(&self, now: i64) -> bool {
+        return self.ttl >= 0 && self.ttl <= now;
+    }
+
+    /// Validate the value against its declared type
+    /// JSON values must hold a well-formed JSON document
+    ///
+    /// # Returns
+    /// Result containing `()` or a `DatabaseError` if the value is malformed
+    pub fn validate(&self) -> Result<(), DatabaseError> {
+        if self.value_type == ValueType::Json {
+            if let Err(err) = serde_json::from_slice::<serde_json::Value>(&self.value) {
+                return Err(DatabaseError::InvalidJson(err.to_string()));
+            }
+        }
+        if self.value_type == ValueType::List {
+            self.get_list_value()?;
+        }
+        if self.value_type == ValueType::Hash {
+            self.get_hash_value()?;
+        }
+        return Ok(());
+    }
+
+    /// Check that the value has the expected type
+    ///
+    /// # Returns
+    /// Result containing `()` or an `InvalidValueType` error naming both types
+    fn expect_type(&self, expected: &ValueType) -> Result<(), DatabaseError> {
+        if self.value_type != *expected {
+            return Err(DatabaseError::InvalidValueType(format!(
+                "expected {expected}, found {}",
+                self.value_type
+            )));
+        }
+        return Ok(());
+    }
+
+    /// Get the value as a JSON document
+    ///
+    /// # Returns
+    /// Result containing the parsed JSON document or an error
+    pub fn get_json_value(&self) -> Result<serde_json::Value, DatabaseError> {
+        self.expect_type(&ValueType::Json)?;
+
+        return serde_json::from_slice(&self.value)
+            .map_err(|err| DatabaseError::InvalidJson(err.to_string()));
+    }
+
+    /// Apply a JSON merge-patch to the stored JSON document
+    ///
+    /// # Arguments
+    /// * `patch` - The RFC 7386 merge-patch to apply
+    ///
+    /// # Returns
+    /// Result containing `()` or an error if the value is not a JSON document
+    pub fn merge_json(&mut self, patch: &serde_json::Value) -> Result<(), DatabaseError> {
+        let mut document = self.get_json_value()?;
+        json::merge_patch(&mut document, patch);
+        self.value = document.to_string().into_bytes();
+        return Ok(());
+    }
+
+    /// Create a string value without a TTL
+    ///
+    /// # Arguments
+    /// * `value` - The string to store
+    pub fn string(value: &str) -> Self {
+        return Self {
+            value_type: ValueType::String,
+            ttl: -1,
+            value: value.as_bytes().to_vec(),
+            mtime: 0,
+        };
+    }
+
+    /// Create a blob value from arbitrary bytes
+    ///
+    /// # Arguments
+    /// * `value` - The bytes to store
+    /// * `ttl` - The TTL of the value, -1 to never expire
+    pub fn blob(value: &[u8], ttl: i64) -> Self {
+        return Self {
+            value_type: ValueType::Blob,
+            ttl,
+            value: value.to_vec(),
+            mtime: 0,
+        };
+    }
+
+    /// Create an integer value without a TTL, stored as its decimal text like the counters
+    ///
+    /// # Arguments
+    /// * `value` - The integer to store
+    pub fn integer(value: i64) -> Self {
+        return Self {
+            value_type: ValueType::Integer,
+            ttl: -1,
+            value: value.to_string().into_bytes(),
+            mtime: 0,
+        };
+    }
+
+    /// Create a value from the value of a set request
+    ///
+    /// # Arguments
+    /// * `value` - The integer, string or JSON document to store
+    /// * `ttl` - The TTL of the value, -1 to never expire
+    pub fn from_int_or_string(value: &IntOrString, ttl: i64) -> Self {
+        let storage_value = match value {
+            IntOrString::Int(value) => Self::integer(*value),
+            IntOrString::String(value) => Self::string(value),
+            IntOrString::Json(document) => Self {
+                value_type: ValueType::Json,
+                ttl: -1,
+                value: document.to_string().into_bytes(),
+                mtime: 0,
+            },
+        };
+        return Self {
+            ttl,
+            ..storage_value
+        };
+    }
+
+    /// Create a list value without a TTL
+    ///
+    /// # Arguments
+    /// * `values` - The elements of the list
+    pub fn new_list(values: &[String]) -> Self {
+        return Self {
+            value_type: ValueType::List,
+            ttl: -1,
+            value: bincode::serialize(values).unwrap(),
+            mtime: 0,
+        };
+    }
+
+    /// Get the elements of a list value
+    ///
+    /// # Returns
+    /// Result containing the elements or an error if the value is not a list
+    pub fn get_list_value(&self) -> Result<Vec<String>, DatabaseError> {
+        self.expect_type(&ValueType::List)?;
+
+        return bincode::deserialize(&self.value)
+            .map_err(|err| DatabaseError::InternalError(format!("Corrupt list value: {err}")));
+    }
+
+    /// Append text to the end of a string value
+    ///
+    /// # Arguments
+    /// * `suffix` - The text to append
+    ///
+    /// # Returns
+    /// Result containing the new length of the string in bytes or an error if the value is not a string
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut line = StorageValue::string("GET /");
+    /// let length = line.append_string(" 200").unwrap();
+    /// assert_eq!(length, 9);
+    /// ```
+    pub fn append_string(&mut self, suffix: &str) -> Result<usize, DatabaseError> {
+        self.expect_type(&ValueType::String)?;
+        self.value.extend_from_slice(suffix.as_bytes());
+        return Ok(self.value.len());
+    }
+
+    /// Append elements to the end of a list value
+    ///
+    /// # Arguments
+    /// * `values` - The elements to append
+    /// * `maxlen` - Keep only the last `maxlen` elements after appending
+    /// * `max_elements` - Fail without changing the list if it would hold more elements than this
+    ///
+    /// # Returns
+    /// Result containing the new length of the list or an error if the value is not a list
+    /// or would exceed `max_elements`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut list = StorageValue::new_list(&["a".to_string()]);
+    /// let length = list.push_list(&["b".to_string(), "c".to_string()], Some(2), None).unwrap();
+    /// assert_eq!(length, 2);
+    /// ```
+    pub fn push_list(
+        &mut self,
+        values: &[String],
+        maxlen: Option<usize>,
+        max_elements: Option<usize>,
+    ) -> Result<usize, DatabaseError> {
+        let mut list = self.get_list_value()?;
+        list.extend_from_slice(values);
+        if let Some(maxlen) = maxlen {
+            list.drain(..list.len().saturating_sub(maxlen));
+        }
+        if let Some(max_elements) = max_elements.filter(|max| list.len() > *max) {
+            return Err(DatabaseError::OutOfRange(format!(
+                "The list would hold {} elements, more than the limit of {max_elements}",
+                list.len()
+            )));
+        }
+
+        self.value = bincode::serialize(&list).unwrap();
+        return Ok(list.len());
+    }
+
+    /// Create a hash value without a TTL
+    ///
+    /// # Arguments
+    /// * `fields` - The fields of the hash and their values
+    pub fn new_hash(fields: &BTreeMap<String, Vec<u8>>) -> Self {
+        return Self {
+            value_type: ValueType::Hash,
+            ttl: -1,
+            value: bincode::serialize(fields).unwrap(),
+            mtime: 0,
+        };
+    }
+
+    /// Get the fields of a hash value
+    ///
+    /// # Returns
+    /// Result containing the fields or an error if the value is not a hash
+    pub fn get_hash_value(&self) -> Result<BTreeMap<String, Vec<u8>>, DatabaseError> {
+        self.expect_type(&ValueType::Hash)?;
+
+        return bincode::deserialize(&self.value)
+            .map_err(|err| DatabaseError::InternalError(format!("Corrupt hash value: {err}")));
+    }
+
+    /// Set several fields of a hash value, rewriting the stored hash once
+    ///
+    /// # Arguments
+    /// * `fields` - The fields to set and their new values
+    /// * `max_elements` - Fail without changing the hash if it would hold more fields than this
+    ///
+    /// # Returns
+    /// Result containing the numbers of added and of updated fields, or an error if the value
+    /// is not a hash or would exceed `max_elements`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut hash = StorageValue::new_hash(&BTreeMap::new());
+    /// let fields = HashMap::from([("name".to_string(), b"bredis".to_vec())]);
+    /// assert_eq!(hash.set_hash_fields(&fields, None).unwrap(), (1, 0));
+    /// ```
+    pub fn set_hash_fields(
+        &mut self,
+        fields: &HashMap<String, Vec<u8>>,
+        max_elements: Option<usize>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        let mut hash = self.get_hash_value()?;
+        let mut added = 0;
+        let mut updated = 0;
+        for (field, value) in fields {
+            match hash.insert(field.clone(), value.clone()) {
+                Some(_) => updated += 1,
+                None => added += 1,
+            }
+        }
+        if let Some(max_elements) = max_elements.filter(|max| hash.len() > *max) {
+            return Err(DatabaseError::OutOfRange(format!(
+                "The hash would hold {} fields, more than the limit of {max_elements}",
+                hash.len()
+            )));
+        }
+
+        self.value = bincode::serialize(&hash).unwrap();
+        return Ok((added, updated));
     }
 
     /// Get the value as a Integer
@@ -53,7 +481,7 @@ impl StorageValue {
     /// Result containing the integer value or an error
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let storage_value = StorageValue {
     ///  value_type: ValueType::Integer,
     ///  ttl: 1000,
@@ -62,11 +490,26 @@ impl StorageValue {
     /// let value = storage_value.get_integer_value().unwrap();
     /// ```
     pub fn get_integer_value(&self) -> Result<i64, DatabaseError> {
-        if self.value_type != ValueType::Integer {
-            return Err(DatabaseError::InvalidValueType(
-                "Value is not an integer".to_string(),
-            ));
-        }
+        return self.get_integer_value_in_base(10);
+    }
+
+    /// Get the integer value of the storage value written in the specified base
+    /// # Arguments
+    /// * `base` - The base of the stored text, one of `INTEGER_BASES`
+    /// # Returns
+    /// Result containing the integer value or an error if the value is not an integer
+    /// # Example
+    /// ```ignore
+    /// let storage_value = StorageValue {
+    ///  value_type: ValueType::Integer,
+    ///  ttl: -1,
+    ///  value: b"ff".to_vec(),
+    /// };
+    /// let value = storage_value.get_integer_value_in_base(16).unwrap();
+    /// ```
+    pub fn get_integer_value_in_base(&self, base: u32) -> Result<i64, DatabaseError> {
+        check_integer_base(base)?;
+        self.expect_type(&ValueType::Integer)?;
 
         let string_value = String::from_utf8(self.value.clone());
         if string_value.is_err() {
@@ -75,7 +518,7 @@ impl StorageValue {
             ));
         }
 
-        let value = string_value.unwrap().parse();
+        let value = i64::from_str_radix(&string_value.unwrap(), base);
         match value {
             Ok(value) => return Ok(value),
             Err(err) => {
@@ -85,6 +528,165 @@ impl StorageValue {
             }
         }
     }
+
+    /// Add to an integer value without going over a maximum
+    /// # Arguments
+    /// * `value` - The amount to add
+    /// * `max` - The largest value allowed
+    /// * `saturate` - Cap the result at `max` instead of failing
+    /// * `base` - The base of the stored text, one of `INTEGER_BASES`
+    /// # Returns
+    /// Whether the result was capped at `max`; a `DatabaseError::OutOfRange` error
+    /// if it would exceed `max` and `saturate` is false, in which case nothing is changed
+    pub fn add_bounded(
+        &mut self,
+        value: i64,
+        max: i64,
+        saturate: bool,
+        base: u32,
+    ) -> Result<bool, DatabaseError> {
+        let current_value = self.get_integer_value_in_base(base)?;
+        let new_value = current_value.saturating_add(value);
+        let capped = new_value > max;
+        if capped && !saturate {
+            return Err(DatabaseError::OutOfRange(format!(
+                "{current_value} + {value} exceeds the maximum {max}"
+            )));
+        }
+
+        self.value = format_integer(new_value.min(max), base)?.into_bytes();
+        return Ok(capped);
+    }
+
+    /// Replace a base-10 integer value if another value goes past it
+    /// # Arguments
+    /// * `value` - The candidate value
+    /// * `extreme` - Whether the larger or the smaller of the two values is kept
+    /// # Returns
+    /// The kept value and whether it is `value`
+    pub fn keep_extreme(
+        &mut self,
+        value: i64,
+        extreme: Extreme,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let current_value = self.get_integer_value()?;
+        let replace = match extreme {
+            Extreme::Max => value > current_value,
+            Extreme::Min => value < current_value,
+        };
+        if !replace {
+            return Ok((current_value, false));
+        }
+
+        self.value = format_integer(value, 10)?.into_bytes();
+        return Ok((value, true));
+    }
+
+    /// Add to a base-10 integer value if it equals an expected value
+    /// # Arguments
+    /// * `expected` - The value the integer must hold
+    /// * `delta` - The amount to add
+    /// # Returns
+    /// The resulting value and whether `delta` was added; a `DatabaseError::OutOfRange` error
+    /// if the sum would overflow, in which case nothing is changed
+    pub fn compare_and_add(
+        &mut self,
+        expected: i64,
+        delta: i64,
+    ) -> Result<(i64, bool), DatabaseError> {
+        let current_value = self.get_integer_value()?;
+        if current_value != expected {
+            return Ok((current_value, false));
+        }
+        let Some(new_value) = current_value.checked_add(delta) else {
+            return Err(DatabaseError::OutOfRange(format!(
+                "{current_value} + {delta} overflows a 64-bit integer"
+            )));
+        };
+
+        self.value = format_integer(new_value, 10)?.into_bytes();
+        return Ok((new_value, true));
+    }
+
+    /// Add to an integer value written in the specified base
+    /// # Arguments
+    /// * `value` - The amount to add
+    /// * `base` - The base of the stored text, one of `INTEGER_BASES`
+    /// # Returns
+    /// The new value; a `DatabaseError::OutOfRange` error if it would overflow, in which case
+    /// nothing is changed
+    pub fn add_in_base(&mut self, value: i64, base: u32) -> Result<i64, DatabaseError> {
+        let current_value = self.get_integer_value_in_base(base)?;
+        let Some(new_value) = current_value.checked_add(value) else {
+            return Err(DatabaseError::OutOfRange(format!(
+                "{current_value} + {value} overflows a 64-bit integer"
+            )));
+        };
+
+        self.value = format_integer(new_value, base)?.into_bytes();
+        return Ok(new_value);
+    }
+
+    /// Subtract from a base-10 integer value
+    /// # Arguments
+    /// * `value` - The amount to subtract
+    /// # Returns
+    /// The new value; a `DatabaseError::OutOfRange` error if it would overflow, in which case
+    /// nothing is changed
+    pub fn subtract(&mut self, value: i64) -> Result<i64, DatabaseError> {
+        let current_value = self.get_integer_value()?;
+        let Some(new_value) = current_value.checked_sub(value) else {
+            return Err(DatabaseError::OutOfRange(format!(
+                "{current_value} - {value} overflows a 64-bit integer"
+            )));
+        };
+
+        self.value = format_integer(new_value, 10)?.into_bytes();
+        return Ok(new_value);
+    }
+}
+
+/// Which of two integers a high-water mark keeps, see `StorageValue::keep_extreme`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extreme {
+    Max,
+    Min,
+}
+
+/// The bases integer values can be written in
+pub const INTEGER_BASES: [u32; 2] = [10, 16];
+
+/// Check that integer values can be written in the specified base
+/// # Arguments
+/// * `base` - The base to check
+/// # Returns
+/// An error if the base is not one of `INTEGER_BASES`
+pub fn check_integer_base(base: u32) -> Result<(), DatabaseError> {
+    if INTEGER_BASES.contains(&base) {
+        return Ok(());
+    }
+    return Err(DatabaseError::InvalidValueType(format!(
+        "Unsupported integer base: {base}"
+    )));
+}
+
+/// Write an integer in the specified base
+/// # Arguments
+/// * `value` - The integer to write
+/// * `base` - The base to write the integer in, one of `INTEGER_BASES`
+/// # Returns
+/// Result containing the text of the integer or an error if the base is not supported
+/// # Example
+/// ```ignore
+/// assert_eq!(format_integer(-255, 16).unwrap(), "-ff");
+/// ```
+pub fn format_integer(value: i64, base: u32) -> Result<String, DatabaseError> {
+    check_integer_base(base)?;
+    if base == 16 {
+        let sign = if value < 0 { "-" } else { "" };
+        return Ok(format!("{sign}{:x}", value.unsigned_abs()));
+    }
+    return Ok(value.to_string());
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -93,13 +695,241 @@ impl StorageValue {
 pub enum ValueType {
     String,
     Integer,
+    Json,
+    /// A list of strings, stored as one serialized blob
+    List,
+    /// Arbitrary bytes, uploaded through the raw route
+    Blob,
+    /// Fields with byte values, stored as one serialized blob
+    Hash,
 }
 
-impl From<ValueType> for String {
-    fn from(value: ValueType) -> Self {
+/// The case value type names are written in, e.g. `Integer` or `integer`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TypeCase {
+    #[default]
+    Title,
+    Lower,
+}
+
+tokio::task_local! {
+    /// The case the value type names of the current task are written in
+    static TYPE_CASE: TypeCase;
+}
+
+impl TypeCase {
+    /// The name of the case, as passed to `--type-case`
+    pub const fn as_str(self) -> &'static str {
+        return match self {
+            Self::Title => "title",
+            Self::Lower => "lower",
+        };
+    }
+
+    /// Run a future with the value type names it writes in this case
+    ///
+    /// Outside of such a future, e.g. in a background task, names are written in title case.
+    ///
+    /// # Arguments
+    /// * `future` - The future to run
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        return TYPE_CASE.scope(self, future).await;
+    }
+}
+
+impl ValueType {
+    /// The name of the type, in a given case
+    ///
+    /// # Arguments
+    /// * `case` - The case to write the name in
+    pub const fn name(&self, case: TypeCase) -> &'static str {
+        return match (self, case) {
+            (Self::String, TypeCase::Title) => "String",
+            (Self::String, TypeCase::Lower) => "string",
+            (Self::Integer, TypeCase::Title) => "Integer",
+            (Self::Integer, TypeCase::Lower) => "integer",
+            (Self::Json, TypeCase::Title) => "Json",
+            (Self::Json, TypeCase::Lower) => "json",
+            (Self::List, TypeCase::Title) => "List",
+            (Self::List, TypeCase::Lower) => "list",
+            (Self::Blob, TypeCase::Title) => "Blob",
+            (Self::Blob, TypeCase::Lower) => "blob",
+            (Self::Hash, TypeCase::Title) => "Hash",
+            (Self::Hash, TypeCase::Lower) => "hash",
+        };
+    }
+}
+
+/// Writes the name in the case of the current task, see `TypeCase::scope`
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let case = TYPE_CASE.try_with(|case| *case).unwrap_or_default();
+        return f.write_str(self.name(case));
+    }
+}
+
+/// Reads names in either case
+impl FromStr for ValueType {
+    type Err = DatabaseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         return match value {
-            ValueType::String => Self::from("String"),
-            ValueType::Integer => Self::from("Integer"),
+            "String" | "string" => Ok(Self::String),
+            "Integer" | "integer" => Ok(Self::Integer),
+            "Json" | "json" => Ok(Self::Json),
+            "List" | "list" => Ok(Self::List),
+            "Blob" | "blob" => Ok(Self::Blob),
+            "Hash" | "hash" => Ok(Self::Hash),
+            _ => Err(DatabaseError::InvalidValueType(format!(
+                "Unknown value type: {value}"
+            ))),
         };
     }
 }
+
+impl From<ValueType> for String {
+    fn from(value: ValueType) -> Self {
+        return value.to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct LegacyLayout {
+        value_type: ValueType,
+        ttl: i64,
+        value: Vec<u8>,
+    }
+
+    #[test]
+    fn test_decode_legacy_layout() {
+        let data = bincode::serialize(&LegacyLayout {
+            value_type: ValueType::String,
+            ttl: -1,
+            value: b"value".to_vec(),
+        })
+        .unwrap();
+
+        let value = StorageValue::try_from_binary(&data).unwrap();
+        assert_eq!(value.value, b"value");
+        assert_eq!(value.mtime, 0);
+        assert!(StorageValue::try_from_binary(b"\xff\xff").is_err());
+    }
+
+    #[test]
+    fn test_value_type_round_trip() {
+        assert_eq!(ValueType::Integer.to_string(), "Integer");
+        assert_eq!("Integer".parse::<ValueType>().unwrap(), ValueType::Integer);
+        for value_type in [
+            ValueType::String,
+            ValueType::Integer,
+            ValueType::Json,
+            ValueType::List,
+            ValueType::Blob,
+            ValueType::Hash,
+        ] {
+            assert_eq!(
+                value_type.to_string().parse::<ValueType>().unwrap(),
+                value_type
+            );
+        }
+        assert!("Set".parse::<ValueType>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_type_case() {
+        assert_eq!(ValueType::Integer.to_string(), "Integer");
+        let lower = TypeCase::Lower
+            .scope(async { ValueType::Integer.to_string() })
+            .await;
+        assert_eq!(lower, "integer");
+        assert_eq!("integer".parse::<ValueType>().unwrap(), ValueType::Integer);
+
+        let error = TypeCase::Lower
+            .scope(async { StorageValue::integer(1).get_list_value().unwrap_err() })
+            .await;
+        assert!(
+            error.to_string().contains("expected list, found integer"),
+            "{error}"
+        );
+        let error = StorageValue::integer(1).get_list_value().unwrap_err();
+        assert!(
+            error.to_string().contains("expected List, found Integer"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch_error() {
+        let err = StorageValue::string("value")
+            .get_integer_value()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value type: expected Integer, found String"
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        let value = StorageValue::string("value");
+        assert_eq!(value.value_type, ValueType::String);
+        assert_eq!(value.ttl, -1);
+        assert_eq!(value.value, b"value");
+    }
+
+    #[test]
+    fn test_integer() {
+        let value = StorageValue::integer(42);
+        assert_eq!(value.value_type, ValueType::Integer);
+        assert_eq!(value.ttl, -1);
+        assert_eq!(value.value, b"42");
+        assert_eq!(StorageValue::integer(-7).get_integer_value().unwrap(), -7);
+    }
+
+    #[test]
+    fn test_from_int_or_string() {
+        let value = StorageValue::from_int_or_string(&IntOrString::Int(42), 100);
+        assert_eq!(value.value_type, ValueType::Integer);
+        assert_eq!(value.ttl, 100);
+        assert_eq!(value.value, b"42");
+
+        let value = StorageValue::from_int_or_string(&IntOrString::String("42".to_string()), -1);
+        assert_eq!(value.value_type, ValueType::String);
+        assert_eq!(value.value, b"42");
+
+        let document = serde_json::json!({ "a": 1 });
+        let value = StorageValue::from_int_or_string(&IntOrString::Json(document.clone()), -1);
+        assert_eq!(value.get_json_value().unwrap(), document);
+    }
+
+    #[test]
+    fn test_deserialize_int_or_string() {
+        let read = |json: &str| serde_json::from_str::<IntOrString>(json);
+
+        assert!(matches!(read("42"), Ok(IntOrString::Int(42))));
+        assert!(matches!(read("-7"), Ok(IntOrString::Int(-7))));
+        assert!(matches!(read("\"42\""), Ok(IntOrString::String(value)) if value == "42"));
+        assert!(matches!(read("{\"a\":1}"), Ok(IntOrString::Json(_))));
+        assert!(matches!(read("[1,2]"), Ok(IntOrString::Json(_))));
+
+        for json in ["true", "null", "1.5"] {
+            let err = read(json).unwrap_err();
+            assert!(err.to_string().starts_with(INVALID_VALUE), "{json}: {err}");
+        }
+        let err = read("18446744073709551615").unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+
+        // The binary formats name the same types
+        let msgpack = rmp_serde::to_vec(&true).unwrap();
+        assert!(rmp_serde::from_slice::<IntOrString>(&msgpack).is_err());
+        let msgpack = rmp_serde::to_vec(&7u8).unwrap();
+        assert!(matches!(
+            rmp_serde::from_slice::<IntOrString>(&msgpack),
+            Ok(IntOrString::Int(7))
+        ));
+    }
+}